@@ -0,0 +1,60 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use plasma_store::{ObjectId, PlasmaClient};
+use std::time::Duration;
+
+const PLASMA_SOCKET: &str = "/tmp/plasma";
+const NUM_OBJECTS: usize = 1_000;
+const DATA_SIZE: usize = 4096;
+const TIMEOUT: Option<Duration> = Some(Duration::from_millis(5));
+
+// BENCHMARKS
+// ================================================================================================
+
+/// Compares retrieving a list of objects with a single request to the store against retrieving
+/// them one at a time; the latter is how the server used to read objects before sending them to
+/// a peer. Requires a plasma store listening at `PLASMA_SOCKET`.
+fn get(c: &mut Criterion) {
+    let pc = PlasmaClient::new(PLASMA_SOCKET, 0).unwrap();
+    let object_ids = create_objects(&pc, NUM_OBJECTS);
+    let mut group = c.benchmark_group("get");
+
+    group.bench_function(BenchmarkId::new("serial", NUM_OBJECTS), |b| {
+        b.iter(|| {
+            let mut objects = Vec::with_capacity(object_ids.len());
+            for oid in object_ids.iter() {
+                objects.push(pc.get(oid.clone(), TIMEOUT).unwrap().unwrap());
+            }
+            black_box(objects)
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("get_many", NUM_OBJECTS), |b| {
+        b.iter(|| black_box(pc.get_many(&object_ids, TIMEOUT).unwrap()))
+    });
+
+    group.finish();
+    pc.delete_many(&object_ids).unwrap();
+}
+
+criterion_group!(get_group, get);
+criterion_main!(get_group);
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Creates the specified number of objects in the store and returns their IDs.
+fn create_objects(pc: &PlasmaClient, n: usize) -> Vec<ObjectId> {
+    let data = vec![1u8; DATA_SIZE];
+    (0..n)
+        .map(|_| {
+            let oid = ObjectId::rand();
+            pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+            oid
+        })
+        .collect()
+}
@@ -14,6 +14,18 @@ fn main() {
 
     let out_dir = env::var("OUT_DIR").unwrap();
     let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+
+    // objects can be allocated in GPU memory only if the store client is built with CUDA
+    // support; this requires CUDA toolkit to be installed on the build machine
+    if env::var("CARGO_FEATURE_CUDA").is_ok() {
+        cxx.define("PLASMA_CUDA", None).files(&[
+            "arrow/cpp/src/arrow/gpu/cuda_context.cc",
+            "arrow/cpp/src/arrow/gpu/cuda_memory.cc",
+        ]);
+        println!("cargo:rustc-link-lib=cuda");
+        println!("cargo:rustc-link-lib=cudart");
+    }
+
     cxx.files(&[
         "arrow/cpp/src/arrow/util/future.cc",
         "arrow/cpp/src/arrow/util/string.cc",
@@ -41,7 +53,6 @@ fn main() {
     .include("arrow/cpp/src/")
     .include("arrow/cpp/thirdparty/flatbuffers/include")
     .opt_level(3)
-    // TODO: CUDA support
     .flag_if_supported("-fwrapv")
     .flag_if_supported("-fomit-frame-pointer")
     .flag_if_supported("-funroll-loops")
@@ -0,0 +1,244 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::{ObjectBuffer, ObjectId, ObjectInfo, PlasmaClient, PlasmaError, StatusCode};
+use std::{ops::Range, time::Duration};
+
+// ASYNC PLASMA CLIENT
+// ================================================================================================
+
+/// Asynchronous wrapper around `PlasmaClient`. Every call to the store is executed on the
+/// blocking thread pool of the current tokio runtime via `tokio::task::spawn_blocking`, so
+/// that waiting on the store does not stall other tasks running on the runtime.
+///
+/// Object buffers borrow the client they were retrieved by and cannot be moved out of the
+/// blocking task; so, `get` copies object data and metadata out of the store. Use `get_with`
+/// to process an object in place without copying it.
+#[derive(Clone, Debug)]
+pub struct AsyncPlasmaClient {
+    client: PlasmaClient,
+}
+
+impl AsyncPlasmaClient {
+    /// Creates a new client and connects it to the local plasma store.
+    /// * `store_socket_name` The name of the UNIX domain socket to use to connect
+    ///   to the Plasma store.
+    /// * `num_retries` number of attempts to connect to IPC socket, default 50
+    pub async fn new(store_socket_name: &str, num_retries: u32) -> Result<Self, PlasmaError> {
+        let store_socket_name = String::from(store_socket_name);
+        let client =
+            run_blocking(move || PlasmaClient::new(&store_socket_name, num_retries)).await?;
+        Ok(AsyncPlasmaClient { client })
+    }
+
+    /// Returns the underlying blocking client; the returned client shares the connection to
+    /// the store with this client.
+    pub fn blocking(&self) -> &PlasmaClient {
+        &self.client
+    }
+
+    /// Retrieves an object with the specified ID from the store and returns copies of its
+    /// metadata and data, in that order. Resolves once the object has been created and sealed
+    /// in the Plasma store or the timeout expires.
+    /// * `oid` The ID of the object to get.
+    /// * `timeout` The amount of time to wait before this request times out. If this value is
+    ///   `None`, then no timeout is set.
+    pub async fn get(
+        &self,
+        oid: ObjectId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, PlasmaError> {
+        self.get_with(oid, timeout, |ob| {
+            ob.map(|ob| (ob.meta().to_vec(), ob.data().to_vec()))
+        })
+        .await
+    }
+
+    /// Retrieves an object with the specified ID from the store and passes it to `f` on the
+    /// blocking thread pool; the object is released once `f` returns. Resolves to the value
+    /// returned by `f`, which receives `None` if the object was not retrieved before the
+    /// timeout expired.
+    pub async fn get_with<F, R>(
+        &self,
+        oid: ObjectId,
+        timeout: Option<Duration>,
+        f: F,
+    ) -> Result<R, PlasmaError>
+    where
+        F: FnOnce(Option<ObjectBuffer>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let client = self.client.clone();
+        run_blocking(move || client.get(oid, timeout).map(f)).await
+    }
+
+    /// Retrieves metadata of an object with the specified ID from the store. Resolves once the
+    /// object has been created and sealed in the Plasma store or the timeout expires.
+    pub async fn get_metadata(
+        &self,
+        oid: ObjectId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, PlasmaError> {
+        let client = self.client.clone();
+        run_blocking(move || client.get_metadata(oid, timeout)).await
+    }
+
+    /// Retrieves a copy of the specified byte range of data of an object with the specified ID
+    /// from the store. Resolves once the object has been created and sealed in the Plasma store
+    /// or the timeout expires.
+    pub async fn get_range(
+        &self,
+        oid: ObjectId,
+        range: Range<usize>,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, PlasmaError> {
+        let client = self.client.clone();
+        run_blocking(move || client.get_range(oid, range, timeout)).await
+    }
+
+    /// Creates and seals an object in the object store.
+    /// * `oid` The ID for the object to create.
+    /// * `data` The data for the object to create.
+    /// * `meta` The metadata for the object to create.
+    pub async fn create_and_seal(
+        &self,
+        oid: ObjectId,
+        data: Vec<u8>,
+        meta: Vec<u8>,
+    ) -> Result<(), PlasmaError> {
+        let client = self.client.clone();
+        run_blocking(move || client.create_and_seal(oid, &data, &meta)).await
+    }
+
+    /// Stores the specified data and metadata as an object whose ID is derived from the data.
+    /// See `PlasmaClient::put` for details.
+    pub async fn put(&self, data: Vec<u8>, meta: Vec<u8>) -> Result<ObjectId, PlasmaError> {
+        let client = self.client.clone();
+        run_blocking(move || client.put(&data, &meta)).await
+    }
+
+    /// Creates and seals multiple objects in the object store; each entry in `objects` consists
+    /// of object ID, data, and metadata. See `PlasmaClient::create_and_seal_many` for details.
+    pub async fn create_and_seal_many(
+        &self,
+        objects: Vec<(ObjectId, Vec<u8>, Vec<u8>)>,
+    ) -> Result<Vec<Result<(), PlasmaError>>, PlasmaError> {
+        let client = self.client.clone();
+        run_blocking(move || {
+            let objects: Vec<_> = objects
+                .iter()
+                .map(|(oid, data, meta)| (oid.clone(), data.as_slice(), meta.as_slice()))
+                .collect();
+            client.create_and_seal_many(&objects)
+        })
+        .await
+    }
+
+    /// Deletes an object from the object store. See `PlasmaClient::delete` for details.
+    pub async fn delete(&self, oid: &ObjectId) -> Result<(), PlasmaError> {
+        let client = self.client.clone();
+        let oid = oid.clone();
+        run_blocking(move || client.delete(&oid)).await
+    }
+
+    /// Deletes all objects specified by `object_ids` list from the object store. See
+    /// `PlasmaClient::delete_many` for details.
+    pub async fn delete_many(&self, object_ids: &[ObjectId]) -> Result<(), PlasmaError> {
+        let client = self.client.clone();
+        let object_ids = object_ids.to_vec();
+        run_blocking(move || client.delete_many(&object_ids)).await
+    }
+
+    /// Bumps the specified objects to the most recently used position in the store's LRU
+    /// cache.
+    pub async fn refresh(&self, object_ids: &[ObjectId]) -> Result<(), PlasmaError> {
+        let client = self.client.clone();
+        let object_ids = object_ids.to_vec();
+        run_blocking(move || client.refresh(&object_ids)).await
+    }
+
+    /// Checks if the object store contains a particular object and the object has been sealed.
+    pub async fn contains(&self, oid: &ObjectId) -> Result<bool, PlasmaError> {
+        let client = self.client.clone();
+        let oid = oid.clone();
+        run_blocking(move || client.contains(&oid)).await
+    }
+
+    /// Waits until the specified object is sealed in the store or the timeout expires. Resolves
+    /// to false if the timeout expired first; the object is not mapped into memory.
+    pub async fn wait_for(&self, oid: &ObjectId, timeout: Duration) -> Result<bool, PlasmaError> {
+        let client = self.client.clone();
+        let oid = oid.clone();
+        run_blocking(move || client.wait_for(&oid, timeout)).await
+    }
+
+    /// Returns a list of IDs for objects contained in the object store.
+    pub async fn contains_many(
+        &self,
+        object_ids: &[ObjectId],
+    ) -> Result<Vec<ObjectId>, PlasmaError> {
+        let client = self.client.clone();
+        let object_ids = object_ids.to_vec();
+        run_blocking(move || client.contains_many(&object_ids)).await
+    }
+
+    /// Asks the store to evict objects until at least `num_bytes` bytes are freed. Resolves to
+    /// the number of bytes which were actually evicted.
+    pub async fn evict(&self, num_bytes: usize) -> Result<usize, PlasmaError> {
+        let client = self.client.clone();
+        run_blocking(move || client.evict(num_bytes)).await
+    }
+
+    /// Returns information about all objects currently held by the store, including objects
+    /// which have been created but not yet sealed.
+    pub async fn list(&self) -> Result<Vec<ObjectInfo>, PlasmaError> {
+        let client = self.client.clone();
+        run_blocking(move || client.list()).await
+    }
+
+    /// Returns sizes of data and metadata of the specified sealed object as
+    /// `(data_size, metadata_size)` without mapping the object into memory.
+    pub async fn object_size(&self, oid: &ObjectId) -> Result<Option<(usize, usize)>, PlasmaError> {
+        let client = self.client.clone();
+        let oid = oid.clone();
+        run_blocking(move || client.object_size(&oid)).await
+    }
+
+    /// Returns memory capacity of the store in bytes.
+    pub async fn store_capacity(&self) -> usize {
+        let client = self.client.clone();
+        match tokio::task::spawn_blocking(move || client.store_capacity()).await {
+            Ok(capacity) => capacity,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+impl From<PlasmaClient> for AsyncPlasmaClient {
+    fn from(client: PlasmaClient) -> Self {
+        AsyncPlasmaClient { client }
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Runs `f` on the blocking thread pool of the current runtime. Panics in `f` are propagated
+/// to the caller, as they would be for the blocking client; if the task is cancelled because
+/// the runtime is shutting down, an error is returned instead.
+async fn run_blocking<F, T>(f: F) -> Result<T, PlasmaError>
+where
+    F: FnOnce() -> Result<T, PlasmaError> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(PlasmaError::UnknownError(
+            StatusCode::UnknownError,
+            format!("blocking task did not complete: {}", err),
+        )),
+    }
+}
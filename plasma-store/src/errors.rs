@@ -3,6 +3,7 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
+use crate::StatusCode;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,10 +12,24 @@ pub enum PlasmaError {
     ConnectError(String),
     #[error("the object already exists in the Plasma Store")]
     AlreadyExists,
+    #[error("not enough memory in the Plasma Store: {0}")]
+    OutOfMemory(String),
     #[error("the object has already been sealed")]
     AlreadySealed,
     #[error("the object is not mutable")]
     NotMutable,
-    #[error("unknown error: {0}")]
-    UnknownError(String),
+    #[error("the object has not been sealed")]
+    NotSealed,
+    #[error("digest of the sealed object does not match the expected digest (actual: {0})")]
+    HashMismatch(String),
+    #[error("the object is allocated on device {0} and cannot be accessed from CPU")]
+    NotOnCpu(i32),
+    #[error("invalid object ID: {0}")]
+    InvalidObjectId(String),
+    #[error("connection to Plasma Store lost: {0}")]
+    StoreDisconnected(String),
+    /// An error not covered by other variants; carries the status code reported by the
+    /// underlying client (or the closest matching code for errors raised by this crate).
+    #[error("unknown error: {1}")]
+    UnknownError(StatusCode, String),
 }
@@ -31,6 +31,14 @@ namespace plasma {
     return oid1 == oid2;
   }
 
+  std::unique_ptr<std::vector<ObjectID>> new_oid_vector() {
+    return std::make_unique<std::vector<ObjectID>>();
+  }
+
+  void push_oid(std::vector<ObjectID>& oids, const ObjectID& oid) {
+    oids.push_back(oid);
+  }
+
   ////////////
   // Buffer //
   ////////////
@@ -41,12 +49,25 @@ namespace plasma {
     return std::make_unique<ObjectBuffer>(ObjectBuffer{data_ptr, metadata_ptr, 0});
   }
 
+  std::unique_ptr<std::vector<ObjectBuffer>> new_obj_buffer_vector() {
+    return std::make_unique<std::vector<ObjectBuffer>>();
+  }
+
+  std::unique_ptr<ObjectBuffer> take_obj_buffer(std::vector<ObjectBuffer>& obs, size_t index) {
+    // buffers are moved out of the vector so that each object can be released independently
+    return std::make_unique<ObjectBuffer>(std::move(obs.at(index)));
+  }
+
   rust::Slice<const unsigned char> get_buffer_data(const std::shared_ptr<Buffer>& buffer) {
     const uint8_t *c = buffer->data();
     int64_t len = buffer->size();
     return rust::Slice<const unsigned char>(c, len);
   }
 
+  int64_t get_buffer_size(const std::shared_ptr<Buffer>& buffer) {
+    return buffer->size();
+  }
+
   rust::Slice<unsigned char> get_buffer_data_mut(const std::shared_ptr<Buffer>& buffer) {
     uint8_t *c = buffer->mutable_data();
     int64_t len = buffer->size();
@@ -74,11 +95,12 @@ namespace plasma {
     return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
   }
 
-  ArrowStatus create(PlasmaClient const& pc, ObjectBuffer& ob, const ObjectID& oid, int64_t data_size, rust::Slice<const uint8_t> metadata) {
+  ArrowStatus create(PlasmaClient const& pc, ObjectBuffer& ob, const ObjectID& oid, int64_t data_size, rust::Slice<const uint8_t> metadata, int32_t device_num) {
     auto pc_mut = const_cast<PlasmaClient&>(pc);
     std::shared_ptr<Buffer>* data_ptr = &ob.data;
-    Status client_status = pc_mut.Create(oid, data_size, metadata.data(), metadata.size(), data_ptr, 0, true);
+    Status client_status = pc_mut.Create(oid, data_size, metadata.data(), metadata.size(), data_ptr, device_num, true);
     ob.metadata = std::make_shared<Buffer>(metadata.data(), metadata.size());
+    ob.device_num = device_num;
     return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
   }
 
@@ -91,6 +113,43 @@ namespace plasma {
     return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
   }
 
+  ArrowStatus create_and_seal_batch(PlasmaClient const& pc, const std::vector<ObjectID>& oids, rust::Slice<const uint8_t> data, rust::Slice<const uint64_t> data_sizes, rust::Slice<const uint8_t> metadata, rust::Slice<const uint64_t> metadata_sizes, rust::Vec<ArrowStatus>& results) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+
+    // split concatenated data and metadata buffers into individual objects
+    std::vector<std::string> bin_data;
+    std::vector<std::string> bin_metadata;
+    size_t data_offset = 0;
+    size_t metadata_offset = 0;
+    for (size_t i = 0; i < oids.size(); i++) {
+      bin_data.emplace_back(reinterpret_cast<const char*>(data.data()) + data_offset, data_sizes[i]);
+      bin_metadata.emplace_back(reinterpret_cast<const char*>(metadata.data()) + metadata_offset, metadata_sizes[i]);
+      data_offset += data_sizes[i];
+      metadata_offset += metadata_sizes[i];
+    }
+
+    // try to create all objects with a single request to the store
+    Status client_status = pc_mut.CreateAndSealBatch(oids, bin_data, bin_metadata, true);
+    if (client_status.ok() || client_status.IsIOError()) {
+      for (size_t i = 0; client_status.ok() && i < oids.size(); i++) {
+        results.push_back(ArrowStatus{StatusCode::OK, ""});
+      }
+      return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
+    }
+
+    // the store rejects the batch as a whole if any of the objects cannot be created, and
+    // none of the objects are created in this case; so, fall back to creating objects one by
+    // one to determine the result for each object
+    for (size_t i = 0; i < oids.size(); i++) {
+      Status object_status = pc_mut.CreateAndSeal(oids[i], bin_data[i], bin_metadata[i], true);
+      if (object_status.IsIOError()) {
+        return ArrowStatus{make_plasma_error(object_status.code()), object_status.message()};
+      }
+      results.push_back(ArrowStatus{make_plasma_error(object_status.code()), object_status.message()});
+    }
+    return ArrowStatus{StatusCode::OK, ""};
+  }
+
   ArrowStatus get(PlasmaClient const& pc, const ObjectID& oid, int64_t timeout_ms, ObjectBuffer& ob) {
     auto pc_mut = const_cast<PlasmaClient&>(pc);
     const ObjectID* oidp = &oid;
@@ -119,12 +178,38 @@ namespace plasma {
     return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
   }
 
+  ArrowStatus multi_contains(PlasmaClient const& pc, const std::vector<ObjectID>& oids, rust::Vec<bool>& has_objects) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+
+    // the store has no batched contains request, but checking all objects in a single call
+    // avoids crossing the FFI boundary and reallocating results for every object
+    has_objects.reserve(oids.size());
+    for (const auto& oid : oids) {
+      bool has_object = false;
+      Status client_status = pc_mut.Contains(oid, &has_object);
+      if (!client_status.ok()) {
+        return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
+      }
+      has_objects.push_back(has_object);
+    }
+    return ArrowStatus{StatusCode::OK, ""};
+  }
+
   ArrowStatus abort(PlasmaClient const& pc, const ObjectID& oid) {
     auto pc_mut = const_cast<PlasmaClient&>(pc);
     Status client_status = pc_mut.Abort(oid);
     return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
   }
 
+  ArrowStatus hash(PlasmaClient const& pc, const ObjectID& oid, rust::Slice<uint8_t> digest) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+    if (digest.size() < kDigestSize) {
+      return ArrowStatus{StatusCode::Invalid, "digest buffer is too small"};
+    }
+    Status client_status = pc_mut.Hash(oid, digest.data());
+    return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
+  }
+
   ArrowStatus seal(PlasmaClient const& pc, const ObjectID& oid) {
     auto pc_mut = const_cast<PlasmaClient&>(pc);
     Status client_status = pc_mut.Seal(oid);
@@ -149,6 +234,63 @@ namespace plasma {
     return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
   }
 
+  ArrowStatus evict(PlasmaClient const& pc, int64_t num_bytes, int64_t& num_bytes_evicted) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+    Status client_status = pc_mut.Evict(num_bytes, num_bytes_evicted);
+    return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
+  }
+
+  ArrowStatus list(PlasmaClient const& pc, rust::Vec<ObjectEntry>& objects) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+    ObjectTable object_table;
+    Status client_status = pc_mut.List(&object_table);
+    for (const auto& item : object_table) {
+      ObjectEntry entry;
+      std::copy(item.first.data(), item.first.data() + kUniqueIDSize, entry.object_id.begin());
+      entry.data_size = item.second->data_size;
+      entry.metadata_size = item.second->metadata_size;
+      entry.state = static_cast<int32_t>(item.second->state);
+      std::copy(item.second->digest, item.second->digest + kDigestSize, entry.digest.begin());
+      objects.push_back(entry);
+    }
+    return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
+  }
+
+  ArrowStatus object_info(PlasmaClient const& pc, const ObjectID& oid, rust::Vec<ObjectEntry>& objects) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+
+    // the object table holds sizes of all objects in the store, so the object does not need
+    // to be mapped into the client's memory to determine its size
+    ObjectTable object_table;
+    Status client_status = pc_mut.List(&object_table);
+    auto item = object_table.find(oid);
+    if (client_status.ok() && item != object_table.end()) {
+      ObjectEntry entry;
+      std::copy(item->first.data(), item->first.data() + kUniqueIDSize, entry.object_id.begin());
+      entry.data_size = item->second->data_size;
+      entry.metadata_size = item->second->metadata_size;
+      entry.state = static_cast<int32_t>(item->second->state);
+      std::copy(item->second->digest, item->second->digest + kDigestSize, entry.digest.begin());
+      objects.push_back(entry);
+    }
+    return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
+  }
+
+  ArrowStatus subscribe(PlasmaClient const& pc, int32_t& fd) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+    int* fdp = &fd;
+    Status client_status = pc_mut.Subscribe(fdp);
+    return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
+  }
+
+  ArrowStatus get_notification(PlasmaClient const& pc, int32_t fd, ObjectNotification& notification) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+    ObjectID oid;
+    Status client_status = pc_mut.GetNotification(fd, &oid, &notification.data_size, &notification.metadata_size);
+    std::copy(oid.data(), oid.data() + kUniqueIDSize, notification.object_id.begin());
+    return ArrowStatus{make_plasma_error(client_status.code()), client_status.message()};
+  }
+
   ArrowStatus disconnect(PlasmaClient const& pc) {
     auto pc_mut = const_cast<PlasmaClient&>(pc);
     Status client_status = pc_mut.Disconnect();
@@ -160,6 +302,18 @@ namespace plasma {
     return pc_mut.store_capacity();
   }
 
+  ArrowStatus debug_string(PlasmaClient const& pc, rust::String& result) {
+    auto pc_mut = const_cast<PlasmaClient&>(pc);
+    std::string debug = pc_mut.DebugString();
+    // the client does not return a status for this request; instead, communication failures
+    // are reported via the returned string
+    if (debug.rfind("error ", 0) == 0) {
+      return ArrowStatus{StatusCode::IOError, debug};
+    }
+    result = rust::String(debug);
+    return ArrowStatus{StatusCode::OK, ""};
+  }
+
   ///////////
   // utils //
   ///////////
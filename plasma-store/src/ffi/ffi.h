@@ -23,13 +23,22 @@ namespace plasma {
 
   bool oid_equals(const ObjectID& oid1, const ObjectID& oid2);
 
+  std::unique_ptr<std::vector<ObjectID>> new_oid_vector();
+
+  void push_oid(std::vector<ObjectID>& oids, const ObjectID& oid);
+
   ////////////
   // Buffer //
   ////////////
 
   std::unique_ptr<ObjectBuffer> new_obj_buffer();
 
+  std::unique_ptr<std::vector<ObjectBuffer>> new_obj_buffer_vector();
+
+  std::unique_ptr<ObjectBuffer> take_obj_buffer(std::vector<ObjectBuffer>& obs, size_t index);
+
   rust::Slice<const unsigned char> get_buffer_data(const std::shared_ptr<Buffer>& buffer);
+  int64_t get_buffer_size(const std::shared_ptr<Buffer>& buffer);
   
   rust::Slice<unsigned char> get_buffer_data_mut(const std::shared_ptr<Buffer>& buffer);
 
@@ -43,10 +52,12 @@ namespace plasma {
 
   ArrowStatus set_client_options(PlasmaClient const& pc, rust::Str client_name, int64_t output_memory_quota);
 
-  ArrowStatus create(PlasmaClient const& pc, ObjectBuffer& ob, const ObjectID& oid, int64_t data_size, rust::Slice<const uint8_t> metadata);
+  ArrowStatus create(PlasmaClient const& pc, ObjectBuffer& ob, const ObjectID& oid, int64_t data_size, rust::Slice<const uint8_t> metadata, int32_t device_num);
 
   ArrowStatus create_and_seal(PlasmaClient const& pc, const ObjectID& oid, rust::Slice<const uint8_t> data, rust::Slice<const uint8_t> metadata);
 
+  ArrowStatus create_and_seal_batch(PlasmaClient const& pc, const std::vector<ObjectID>& oids, rust::Slice<const uint8_t> data, rust::Slice<const uint64_t> data_sizes, rust::Slice<const uint8_t> metadata, rust::Slice<const uint64_t> metadata_sizes, rust::Vec<ArrowStatus>& results);
+
   ArrowStatus get(PlasmaClient const& pc, const ObjectID& oid, int64_t timeout_ms, ObjectBuffer& ob);
 
   ArrowStatus multi_get(PlasmaClient const& pc, const std::vector<ObjectID>& oids, int64_t timeout_ms, std::vector<ObjectBuffer>& obs);
@@ -55,8 +66,12 @@ namespace plasma {
 
   ArrowStatus contains(PlasmaClient const& pc, const ObjectID& oid, bool& has_object);
 
+  ArrowStatus multi_contains(PlasmaClient const& pc, const std::vector<ObjectID>& oids, rust::Vec<bool>& has_objects);
+
   ArrowStatus abort(PlasmaClient const& pc, const ObjectID& oid);
 
+  ArrowStatus hash(PlasmaClient const& pc, const ObjectID& oid, rust::Slice<uint8_t> digest);
+
   ArrowStatus seal(PlasmaClient const& pc, const ObjectID& oid);
 
   ArrowStatus single_delete(PlasmaClient const& pc, const ObjectID& oid);
@@ -65,10 +80,22 @@ namespace plasma {
 
   ArrowStatus refresh(PlasmaClient const& pc, const std::vector<ObjectID>& oids);
 
+  ArrowStatus evict(PlasmaClient const& pc, int64_t num_bytes, int64_t& num_bytes_evicted);
+
+  ArrowStatus list(PlasmaClient const& pc, rust::Vec<ObjectEntry>& objects);
+
+  ArrowStatus object_info(PlasmaClient const& pc, const ObjectID& oid, rust::Vec<ObjectEntry>& objects);
+
+  ArrowStatus subscribe(PlasmaClient const& pc, int32_t& fd);
+
+  ArrowStatus get_notification(PlasmaClient const& pc, int32_t fd, ObjectNotification& notification);
+
   ArrowStatus disconnect(PlasmaClient const& pc);
 
   int64_t store_capacity_bytes(PlasmaClient const& pc);
 
+  ArrowStatus debug_string(PlasmaClient const& pc, rust::String& result);
+
   ///////////
   // utils //
   ///////////
@@ -20,6 +20,30 @@ pub(crate) mod ffi {
         device_num: i32,
     }
 
+    /// Information about a single object in the store.
+    struct ObjectEntry {
+        /// Binary representation of the object ID.
+        object_id: [u8; 20],
+        /// The size of the object's data in bytes.
+        data_size: i64,
+        /// The size of the object's metadata in bytes.
+        metadata_size: i64,
+        /// The state of the object: 1 = created, 2 = sealed, 3 = evicted.
+        state: i32,
+        /// Digest of the object computed by the store client when the object was sealed.
+        digest: [u8; 8],
+    }
+
+    /// Notification about an object which was sealed or deleted in the store.
+    struct ObjectNotification {
+        /// Binary representation of the object ID.
+        object_id: [u8; 20],
+        /// The size of the object's data in bytes; -1 if the object was deleted.
+        data_size: i64,
+        /// The size of the object's metadata in bytes; -1 if the object was deleted.
+        metadata_size: i64,
+    }
+
     #[derive(Debug)]
     pub struct ArrowStatus {
         code: StatusCode,
@@ -56,10 +80,14 @@ pub(crate) mod ffi {
         fn oid_to_hex(oid: &ObjectID) -> String;
         fn oid_equals(oid1: &ObjectID, oid2: &ObjectID) -> bool;
 
+        fn new_oid_vector() -> UniquePtr<CxxVector<ObjectID>>;
+        fn push_oid(oids: Pin<&mut CxxVector<ObjectID>>, oid: &ObjectID);
+
         #[namespace = "arrow"]
         type Buffer;
 
         fn get_buffer_data(buffer: &SharedPtr<Buffer>) -> &[u8];
+        fn get_buffer_size(buffer: &SharedPtr<Buffer>) -> i64;
 
         // Safety:
         //   - buffer must be a mutable CPU buffer
@@ -71,6 +99,11 @@ pub(crate) mod ffi {
 
         type ObjectBuffer;
         fn new_obj_buffer() -> UniquePtr<ObjectBuffer>;
+        fn new_obj_buffer_vector() -> UniquePtr<CxxVector<ObjectBuffer>>;
+        fn take_obj_buffer(
+            obs: Pin<&mut CxxVector<ObjectBuffer>>,
+            index: usize,
+        ) -> UniquePtr<ObjectBuffer>;
 
         type PlasmaClient;
 
@@ -89,6 +122,7 @@ pub(crate) mod ffi {
             oid: &ObjectID,
             data_size: i64,
             metadata: &[u8],
+            device_num: i32,
         ) -> ArrowStatus;
 
         fn create_and_seal(
@@ -98,6 +132,16 @@ pub(crate) mod ffi {
             metadata: &[u8],
         ) -> ArrowStatus;
 
+        fn create_and_seal_batch(
+            pc: &PlasmaClient,
+            oids: &CxxVector<ObjectID>,
+            data: &[u8],
+            data_sizes: &[u64],
+            metadata: &[u8],
+            metadata_sizes: &[u64],
+            results: &mut Vec<ArrowStatus>,
+        ) -> ArrowStatus;
+
         fn get(
             pc: &PlasmaClient,
             oid: &ObjectID,
@@ -105,8 +149,6 @@ pub(crate) mod ffi {
             ob: Pin<&mut ObjectBuffer>,
         ) -> ArrowStatus;
 
-        // TODO: implement multi_get abstraction
-        #[allow(dead_code)]
         fn multi_get(
             pc: &PlasmaClient,
             oids: &CxxVector<ObjectID>,
@@ -118,23 +160,47 @@ pub(crate) mod ffi {
 
         fn contains(pc: &PlasmaClient, oid: &ObjectID, has_object: &mut bool) -> ArrowStatus;
 
+        fn multi_contains(
+            pc: &PlasmaClient,
+            oids: &CxxVector<ObjectID>,
+            has_objects: &mut Vec<bool>,
+        ) -> ArrowStatus;
+
         fn abort(pc: &PlasmaClient, oid: &ObjectID) -> ArrowStatus;
 
+        fn hash(pc: &PlasmaClient, oid: &ObjectID, digest: &mut [u8]) -> ArrowStatus;
+
         fn seal(pc: &PlasmaClient, oid: &ObjectID) -> ArrowStatus;
 
         #[cxx_name = "single_delete"]
         fn delete(pc: &PlasmaClient, oid: &ObjectID) -> ArrowStatus;
 
-        // TODO: implement multi_delete abstraction
-        #[allow(dead_code)]
         fn multi_delete(pc: &PlasmaClient, oid: &CxxVector<ObjectID>) -> ArrowStatus;
 
-        // TODO: implement refresh abstraction
-        #[allow(dead_code)]
         fn refresh(pc: &PlasmaClient, oid: &CxxVector<ObjectID>) -> ArrowStatus;
 
+        fn evict(pc: &PlasmaClient, num_bytes: i64, num_bytes_evicted: &mut i64) -> ArrowStatus;
+
+        fn list(pc: &PlasmaClient, objects: &mut Vec<ObjectEntry>) -> ArrowStatus;
+
+        fn object_info(
+            pc: &PlasmaClient,
+            oid: &ObjectID,
+            objects: &mut Vec<ObjectEntry>,
+        ) -> ArrowStatus;
+
+        fn subscribe(pc: &PlasmaClient, fd: &mut i32) -> ArrowStatus;
+
+        fn get_notification(
+            pc: &PlasmaClient,
+            fd: i32,
+            notification: &mut ObjectNotification,
+        ) -> ArrowStatus;
+
         fn disconnect(pc: &PlasmaClient) -> ArrowStatus;
 
         fn store_capacity_bytes(pc: &PlasmaClient) -> i64;
+
+        fn debug_string(pc: &PlasmaClient, result: &mut String) -> ArrowStatus;
     }
 }
@@ -89,7 +89,7 @@ fn plasma_ffi_create() {
         let mut ob = ffi::new_obj_buffer();
         let oid = get_random_oid();
         let meta = vec![1, 3, 5, 7];
-        let res2 = ffi::create(pc, ob.pin_mut(), &oid, 8, &meta);
+        let res2 = ffi::create(pc, ob.pin_mut(), &oid, 8, &meta, 0);
 
         let data_mut = unsafe { ffi::get_buffer_data_mut(&ob.data) };
         for i in 0..8 {
@@ -148,6 +148,28 @@ fn plasma_ffi_contains() {
     })
 }
 
+#[test]
+#[ignore]
+fn plasma_ffi_multi_contains() {
+    run_test(|pc| {
+        // put one of two objects into the store
+        let oid1 = get_random_oid();
+        let oid2 = get_random_oid();
+        let data = [1u8; 32];
+        let meta = vec![];
+        let _ = ffi::create_and_seal(pc, &oid1, &data, &meta);
+
+        // check which of the objects are in the store
+        let mut oids = ffi::new_oid_vector();
+        ffi::push_oid(oids.pin_mut(), &oid1);
+        ffi::push_oid(oids.pin_mut(), &oid2);
+        let mut contained = Vec::new();
+        let res = ffi::multi_contains(pc, &oids, &mut contained);
+        assert_eq!(res.code, ffi::StatusCode::OK);
+        assert_eq!(contained, vec![true, false]);
+    })
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
 
@@ -3,19 +3,42 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
-use cxx::UniquePtr;
+use cxx::{CxxVector, UniquePtr};
 use rand::Rng;
-use std::fmt::{self, Debug, Display, Formatter};
+use sha2::{Digest, Sha256};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt::{self, Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    ops::Range,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::error;
 
 mod ffi;
 use ffi::ffi as plasma;
+pub use ffi::ffi::StatusCode;
 
 mod errors;
 pub use errors::PlasmaError;
 
+#[cfg(feature = "async")]
+mod async_client;
+#[cfg(feature = "async")]
+pub use async_client::AsyncPlasmaClient;
+
 #[cfg(test)]
 mod tests;
 
+// CONSTANTS
+// ================================================================================================
+
+/// Size of an object digest (in bytes) computed by the store client when an object is sealed.
+pub const DIGEST_SIZE: usize = 8;
+
 // OBJECT ID
 // ================================================================================================
 
@@ -31,9 +54,33 @@ impl ObjectId {
         ObjectId(plasma::oid_from_binary(&bytes))
     }
 
+    /// Returns a new object ID instantiated from a 40-character hexadecimal string.
+    pub fn from_hex(hex_str: &str) -> Result<Self, PlasmaError> {
+        let mut bytes = [0u8; 20];
+        hex::decode_to_slice(hex_str, &mut bytes)
+            .map_err(|err| PlasmaError::InvalidObjectId(format!("'{}' {}", hex_str, err)))?;
+        Ok(Self::new(bytes))
+    }
+
+    /// Returns a new object ID derived from the contents of an object; the ID consists of the
+    /// first 20 bytes of the SHA-256 digest of the specified data.
+    pub fn from_content(data: &[u8]) -> Self {
+        let digest = Sha256::digest(data);
+        let mut bytes = [0u8; 20];
+        bytes.copy_from_slice(&digest[..20]);
+        Self::new(bytes)
+    }
+
     /// Returns a new object ID instantiated from a random sequence of 20 bytes.
     pub fn rand() -> Self {
-        Self::new(rand::thread_rng().gen())
+        Self::rand_with(&mut rand::thread_rng())
+    }
+
+    /// Returns a new object ID instantiated from a sequence of 20 bytes generated by the
+    /// specified random number generator; passing a seeded generator makes the generated IDs
+    /// reproducible.
+    pub fn rand_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::new(rng.gen())
     }
 
     /// Returns binary representation of the object ID.
@@ -57,6 +104,25 @@ impl Clone for ObjectId {
     }
 }
 
+impl From<[u8; 20]> for ObjectId {
+    fn from(bytes: [u8; 20]) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for ObjectId {
+    type Error = PlasmaError;
+
+    /// Returns a new object ID instantiated from the specified bytes; returns an error if
+    /// the slice is not exactly 20 bytes long.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let bytes: [u8; 20] = TryFrom::try_from(bytes).map_err(|_| {
+            PlasmaError::InvalidObjectId(format!("expected 20 bytes, but received {}", bytes.len()))
+        })?;
+        Ok(Self::new(bytes))
+    }
+}
+
 impl Debug for ObjectId {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.to_hex())
@@ -75,6 +141,29 @@ impl PartialEq for ObjectId {
     }
 }
 
+impl Eq for ObjectId {}
+
+impl PartialOrd for ObjectId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ObjectId {
+    /// Compares binary representations of object IDs lexicographically.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_bytes().cmp(other.to_bytes())
+    }
+}
+
+impl Hash for ObjectId {
+    /// Hashes binary representation of the object ID; this is consistent with equality because
+    /// two object IDs are equal only if their binary representations are equal.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
 // OBJECT BUFFER
 // ================================================================================================
 
@@ -83,23 +172,23 @@ impl PartialEq for ObjectId {
 // * PlasmaClient is thread-safe on the C++ side
 // * Object buffer on the C++ side can be mutated only once, right after it is crated; so, there
 //   should never be two mutable references to an object buffer
-// * is_mutable and is_aborted can be updated only via mutable references to ObjectBuffer, and
+// * is_mutable and is_released can be updated only via mutable references to ObjectBuffer, and
 //   thus cannot be done simultaneously from different threads.
 unsafe impl<'a> Send for ObjectBuffer<'a> {}
 unsafe impl<'a> Sync for ObjectBuffer<'a> {}
 
 pub struct ObjectBuffer<'a> {
     id: ObjectId,
-    pc: &'a UniquePtr<plasma::PlasmaClient>,
+    pc: &'a PlasmaClient,
     buf: UniquePtr<plasma::ObjectBuffer>,
     is_mutable: bool,
-    is_aborted: bool,
+    is_released: bool,
 }
 
 impl<'a> ObjectBuffer<'a> {
     fn new(
         id: ObjectId,
-        pc: &'a UniquePtr<plasma::PlasmaClient>,
+        pc: &'a PlasmaClient,
         buf: UniquePtr<plasma::ObjectBuffer>,
         is_mutable: bool,
     ) -> Self {
@@ -108,7 +197,7 @@ impl<'a> ObjectBuffer<'a> {
             pc,
             buf,
             is_mutable,
-            is_aborted: false,
+            is_released: false,
         }
     }
 
@@ -117,15 +206,46 @@ impl<'a> ObjectBuffer<'a> {
         &self.id
     }
 
-    /// Returns read-only data buffer of this object buffer.
+    /// Returns read-only data buffer of this object buffer. Data of objects allocated on a GPU
+    /// device cannot be accessed from CPU; for such objects an empty slice is returned.
     pub fn data(&self) -> &[u8] {
+        if !self.is_on_cpu() {
+            return &[];
+        }
         plasma::get_buffer_data(&self.buf.data)
     }
 
-    /// Returns mutable data buffer of this object buffer.
-    pub fn data_mut(&mut self) -> &mut [u8] {
-        assert!(self.is_mutable, "object buffer is not mutable");
-        unsafe { plasma::get_buffer_data_mut(&self.buf.data) }
+    /// Returns mutable data buffer of this object buffer. Mutable buffers can be obtained only
+    /// for objects which have been created but not yet sealed; for all other objects (e.g.
+    /// objects retrieved via `get()`), `PlasmaError::NotMutable` is returned.
+    ///
+    /// For objects allocated on a GPU device, `PlasmaError::NotOnCpu` is returned.
+    pub fn data_mut(&mut self) -> Result<&mut [u8], PlasmaError> {
+        if !self.is_mutable {
+            return Err(PlasmaError::NotMutable);
+        }
+        if !self.is_on_cpu() {
+            return Err(PlasmaError::NotOnCpu(self.device_num()));
+        }
+        Ok(unsafe { plasma::get_buffer_data_mut(&self.buf.data) })
+    }
+
+    /// Returns `len` bytes of the read-only data buffer of this object buffer starting at
+    /// `start`; no data is copied.
+    ///
+    /// # Panics
+    /// Panics if the range does not fit into the data buffer of this object buffer.
+    pub fn data_range(&self, start: usize, len: usize) -> &[u8] {
+        let data = self.data();
+        match start.checked_add(len) {
+            Some(end) if end <= data.len() => &data[start..end],
+            _ => panic!(
+                "range of {} bytes starting at {} is out of bounds for object data of {} bytes",
+                len,
+                start,
+                data.len()
+            ),
+        }
     }
 
     /// Returns metadata buffer of this object buffer.
@@ -133,12 +253,45 @@ impl<'a> ObjectBuffer<'a> {
         plasma::get_buffer_data(&self.buf.metadata)
     }
 
+    /// Returns metadata and read-only data buffers of this object buffer as `(meta, data)`;
+    /// this is equivalent to calling `meta()` and `data()`.
+    pub fn parts(&self) -> (&[u8], &[u8]) {
+        (self.meta(), self.data())
+    }
+
+    /// Appends data of this object buffer to the end of `dst`. Data of objects allocated on a
+    /// GPU device cannot be accessed from CPU; for such objects nothing is appended.
+    pub fn copy_data_into(&self, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(self.data());
+    }
+
+    /// Appends metadata of this object buffer to the end of `dst`.
+    pub fn copy_meta_into(&self, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(self.meta());
+    }
+
+    /// Copies data and metadata of this object buffer into owned vectors and returns them as
+    /// `(data, meta)`; the buffer can then be released without losing access to the contents.
+    pub fn to_owned(&self) -> (Vec<u8>, Vec<u8>) {
+        (self.data().to_vec(), self.meta().to_vec())
+    }
+
     /// Returns the size of this object buffer in bytes; this includes size of data and
     /// metadata.
     pub fn size(&self) -> usize {
-        let meta_size = plasma::get_buffer_data(&self.buf.metadata).len();
-        let data_size = plasma::get_buffer_data(&self.buf.data).len();
-        meta_size + data_size
+        let meta_size = plasma::get_buffer_size(&self.buf.metadata);
+        let data_size = plasma::get_buffer_size(&self.buf.data);
+        (meta_size + data_size) as usize
+    }
+
+    /// Returns the number of the device on which data of this object buffer is allocated;
+    /// 0 means that the data is in CPU memory, and 1 or greater refers to a GPU device.
+    pub fn device_num(&self) -> i32 {
+        self.buf.device_num
+    }
+
+    fn is_on_cpu(&self) -> bool {
+        self.device_num() == 0
     }
 
     /// Returns true if data of this object buffer is mutable.
@@ -148,42 +301,115 @@ impl<'a> ObjectBuffer<'a> {
 
     /// Seals an object in the object store. The object will be immutable after this call.
     pub fn seal(&mut self) -> Result<(), PlasmaError> {
-        let status = plasma::seal(self.pc.as_ref().unwrap(), self.id.inner());
+        let status = plasma::seal(self.pc.client(), self.id.inner());
         match status.code {
             plasma::StatusCode::OK => {
                 self.is_mutable = false;
                 Ok(())
             }
             plasma::StatusCode::TypeError => Err(PlasmaError::AlreadySealed),
-            _ => Err(PlasmaError::UnknownError(status.msg)),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Seals the object and makes sure that the digest computed for the object when sealing it
+    /// matches `expected` (e.g. a digest of the original object reported by `list()` on the
+    /// store the object was copied from). Plasma always computes the digest itself, so the
+    /// object is sealed even if the digests do not match; in this case, `HashMismatch` error is
+    /// returned, and it is up to the caller to delete the object.
+    pub fn seal_with_hash(&mut self, expected: &[u8]) -> Result<(), PlasmaError> {
+        self.seal()?;
+        let digest = self.pc.hash(&self.id)?;
+        if digest[..] != *expected {
+            return Err(PlasmaError::HashMismatch(hex::encode(digest)));
+        }
+        Ok(())
+    }
+
+    /// Converts this object buffer into an Arrow buffer over object data without copying the
+    /// data. The object is released once the returned buffer (and all buffers sliced from it)
+    /// are dropped; object metadata is not accessible via the returned buffer.
+    ///
+    /// Only sealed objects can be converted; for other objects `PlasmaError::NotSealed` is
+    /// returned. Objects allocated on a GPU device cannot be converted either.
+    #[cfg(feature = "arrow")]
+    pub fn into_arrow_buffer(mut self) -> Result<arrow::buffer::Buffer, PlasmaError> {
+        if self.is_mutable {
+            return Err(PlasmaError::NotSealed);
+        }
+        if !self.is_on_cpu() {
+            return Err(PlasmaError::NotOnCpu(self.device_num()));
+        }
+
+        let data = self.data();
+        let len = data.len();
+        let ptr = std::ptr::NonNull::new(data.as_ptr() as *mut u8)
+            .unwrap_or_else(std::ptr::NonNull::dangling);
+
+        // ownership of the plasma buffer moves to the allocation, which releases the object
+        // when dropped; so, the object must not be released when this buffer is dropped
+        let allocation = ArrowAllocation {
+            id: self.id.clone(),
+            pc: self.pc.clone(),
+            buf: std::mem::replace(&mut self.buf, UniquePtr::null()),
+        };
+        self.is_released = true;
+
+        // this is safe because the data stays mapped until the allocation releases the object
+        Ok(
+            unsafe {
+                arrow::buffer::Buffer::from_custom_allocation(ptr, len, Arc::new(allocation))
+            },
+        )
+    }
+
+    /// Releases this object buffer; once released, the object can be evicted from the store if
+    /// it is not in use by other clients. Object buffers are released automatically when they
+    /// are dropped, but errors are only logged in that case; this method can be used to handle
+    /// the errors instead.
+    pub fn release(mut self) -> Result<(), PlasmaError> {
+        let status = plasma::release(self.pc.client(), self.id.inner());
+        // the object should not be released again when the buffer is dropped, even if the
+        // release failed
+        self.is_released = true;
+        match status.code {
+            plasma::StatusCode::OK => Ok(()),
+            _ => Err(status_error(status)),
         }
     }
 
     /// Aborts an unsealed object in the object store. If the abort succeeds, then
     /// it will be as if the object was never created at all.
     pub fn abort(mut self) -> Result<(), PlasmaError> {
+        self.abort_in_place()
+    }
+
+    /// Same as `abort()`, but does not consume the object buffer. Once the object has been
+    /// released, the buffer is left in an aborted state: it is not released again when dropped,
+    /// and its data must not be accessed anymore.
+    pub fn abort_in_place(&mut self) -> Result<(), PlasmaError> {
         if !self.is_mutable {
             return Err(PlasmaError::NotMutable);
         }
 
         // release the object before it is aborted
-        let status = plasma::release(self.pc.as_ref().unwrap(), self.id.inner());
+        let status = plasma::release(self.pc.client(), self.id.inner());
         match status.code {
             plasma::StatusCode::OK => {
-                // once the object has been released, call abort
-                let status = plasma::abort(self.pc.as_ref().unwrap(), self.id.inner());
+                // once the object has been released, call abort; the buffer cannot be aborted
+                // or sealed again even if the abort fails
+                self.is_released = true;
+                self.is_mutable = false;
+                let status = plasma::abort(self.pc.client(), self.id.inner());
                 match status.code {
-                    plasma::StatusCode::OK => {
-                        self.is_aborted = true;
-                        Ok(())
-                    }
-                    _ => Err(PlasmaError::UnknownError(status.msg)),
+                    plasma::StatusCode::OK => Ok(()),
+                    _ => Err(status_error(status)),
                 }
             }
-            _ => Err(PlasmaError::UnknownError(format!(
-                "release failed: {}",
-                status.msg
-            ))),
+            _ => Err(PlasmaError::UnknownError(
+                status.code,
+                format!("release failed: {}", status.msg),
+            )),
         }
     }
 }
@@ -201,17 +427,219 @@ impl<'a> Display for ObjectBuffer<'a> {
 }
 
 impl<'a> Drop for ObjectBuffer<'a> {
+    /// Releases the object buffer unless it has already been released (or aborted). Drop may run
+    /// inside async tasks or while unwinding, so a failed release is logged rather than turned
+    /// into a panic.
+    ///
+    /// Buffers of unsealed objects are not released; instead, the object is recorded as
+    /// abandoned by the client, so that it can still be aborted via `abort_many()`. The client
+    /// keeps its reference to the object until the object is aborted or the client disconnects.
+    fn drop(&mut self) {
+        if self.is_released {
+            return;
+        }
+        if self.is_mutable {
+            self.pc.inner.abandon(&self.id);
+        } else {
+            let status = plasma::release(self.pc.client(), self.id().inner());
+            log_release_status(&self.id, status);
+        }
+    }
+}
+
+// ARROW ALLOCATION
+// ================================================================================================
+
+/// Keeps an object retrieved from the store alive for as long as Arrow buffers built over its
+/// data are in use; the object is released when the last of the buffers is dropped.
+#[cfg(feature = "arrow")]
+struct ArrowAllocation {
+    id: ObjectId,
+    pc: PlasmaClient,
+    buf: UniquePtr<plasma::ObjectBuffer>,
+}
+
+// this should be OK for the same reasons as for ObjectBuffer; the allocation never mutates
+// the buffer, and panics cannot leave it in an inconsistent state
+#[cfg(feature = "arrow")]
+unsafe impl Send for ArrowAllocation {}
+#[cfg(feature = "arrow")]
+unsafe impl Sync for ArrowAllocation {}
+#[cfg(feature = "arrow")]
+impl std::panic::RefUnwindSafe for ArrowAllocation {}
+
+#[cfg(feature = "arrow")]
+impl Drop for ArrowAllocation {
     fn drop(&mut self) {
-        if !self.is_aborted {
-            let status = plasma::release(self.pc.as_ref().unwrap(), self.id().inner());
-            if let plasma::StatusCode::OK = status.code {
-            } else {
-                panic!("failed to release object buffer: {}", status.msg);
+        let status = plasma::release(self.pc.client(), self.id.inner());
+        log_release_status(&self.id, status);
+        // the data buffer must stay mapped until the object is released
+        drop(std::mem::replace(&mut self.buf, UniquePtr::null()));
+    }
+}
+
+// OBJECT INFO
+// ================================================================================================
+
+/// State of an object in the store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectState {
+    /// Object was created but not sealed in the local Plasma Store.
+    Created,
+    /// Object is sealed and stored in the local Plasma Store.
+    Sealed,
+    /// Object was evicted from the local Plasma Store.
+    Evicted,
+}
+
+/// Describes an object stored in the store.
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    /// ID of the object.
+    pub id: ObjectId,
+    /// Size of the object's data in bytes.
+    pub data_size: usize,
+    /// Size of the object's metadata in bytes.
+    pub metadata_size: usize,
+    /// Current state of the object.
+    pub state: ObjectState,
+    /// Digest of the object computed when the object was sealed; all zeros for objects which
+    /// have not been sealed yet.
+    pub digest: [u8; DIGEST_SIZE],
+}
+
+impl TryFrom<plasma::ObjectEntry> for ObjectInfo {
+    type Error = PlasmaError;
+
+    fn try_from(entry: plasma::ObjectEntry) -> Result<Self, Self::Error> {
+        let state = match entry.state {
+            1 => ObjectState::Created,
+            2 => ObjectState::Sealed,
+            3 => ObjectState::Evicted,
+            _ => {
+                return Err(PlasmaError::UnknownError(
+                    StatusCode::Invalid,
+                    format!(
+                        "invalid state {} for object {}",
+                        entry.state,
+                        hex::encode(entry.object_id)
+                    ),
+                ))
+            }
+        };
+        Ok(ObjectInfo {
+            id: ObjectId::new(entry.object_id),
+            data_size: entry.data_size as usize,
+            metadata_size: entry.metadata_size as usize,
+            state,
+            digest: entry.digest,
+        })
+    }
+}
+
+// MEMORY USAGE
+// ================================================================================================
+
+/// Describes memory usage of the store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Memory capacity of the store in bytes.
+    pub capacity_bytes: usize,
+    /// Number of bytes currently allocated for objects in the store.
+    pub used_bytes: usize,
+    /// Number of objects currently held by the store.
+    pub num_objects: usize,
+}
+
+// SUBSCRIPTION
+// ================================================================================================
+
+/// Notification about an object which was sealed or deleted in the store.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    /// ID of the object.
+    pub object_id: ObjectId,
+    /// Size of the object's data in bytes; this is negative if the object was deleted.
+    pub data_size: i64,
+    /// Size of the object's metadata in bytes; this is negative if the object was deleted.
+    pub metadata_size: i64,
+}
+
+impl Notification {
+    /// Returns true if this notification reports deletion of an object.
+    pub fn is_deletion(&self) -> bool {
+        self.data_size < 0
+    }
+}
+
+/// Subscription to notifications about objects sealed or deleted in the store. The
+/// subscription is closed when it is dropped.
+pub struct Subscription<'a> {
+    pc: &'a UniquePtr<plasma::PlasmaClient>,
+    fd: i32,
+}
+
+impl<'a> Subscription<'a> {
+    /// Blocks until the next notification is received from the store and returns it.
+    pub fn next(&self) -> Result<Notification, PlasmaError> {
+        let mut notification = plasma::ObjectNotification {
+            object_id: [0u8; 20],
+            data_size: 0,
+            metadata_size: 0,
+        };
+        let status =
+            plasma::get_notification(self.pc.as_ref().unwrap(), self.fd, &mut notification);
+        match status.code {
+            plasma::StatusCode::OK => Ok(Notification {
+                object_id: ObjectId::new(notification.object_id),
+                data_size: notification.data_size,
+                metadata_size: notification.metadata_size,
+            }),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Blocks until a notification is available or the timeout expires; returns false if the
+    /// timeout expired before a notification arrived.
+    fn poll(&self, timeout: Duration) -> Result<bool, PlasmaError> {
+        let mut pollfd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+        loop {
+            match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+                -1 => {
+                    let err = std::io::Error::last_os_error();
+                    if err.kind() != std::io::ErrorKind::Interrupted {
+                        return Err(PlasmaError::UnknownError(
+                            StatusCode::IOError,
+                            err.to_string(),
+                        ));
+                    }
+                }
+                0 => return Ok(false),
+                _ => return Ok(true),
             }
         }
     }
 }
 
+impl<'a> Debug for Subscription<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Subscription {{ fd: {} }}", self.fd)
+    }
+}
+
+impl<'a> Drop for Subscription<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
 // PLASMA CLIENT
 // ================================================================================================
 
@@ -219,9 +647,37 @@ impl<'a> Drop for ObjectBuffer<'a> {
 unsafe impl Send for plasma::PlasmaClient {}
 unsafe impl Sync for plasma::PlasmaClient {}
 
+/// Client for the local plasma store. Cloning the client is cheap: all clones share the same
+/// connection to the store, and the connection is closed when the last clone is dropped.
+#[derive(Clone)]
 pub struct PlasmaClient {
+    inner: Arc<PlasmaClientInner>,
+}
+
+/// Connection to the plasma store shared by all clones of a `PlasmaClient`.
+struct PlasmaClientInner {
     socket_name: String,
+    num_retries: u32,
     client_ptr: UniquePtr<plasma::PlasmaClient>,
+    /// IDs of unsealed objects created over this connection whose buffers were dropped without
+    /// sealing or aborting them; the client still holds a reference to each of these objects,
+    /// which is what the underlying client requires to abort them.
+    abandoned: Mutex<HashSet<ObjectId>>,
+}
+
+impl PlasmaClientInner {
+    /// Records the specified unsealed object as abandoned by its buffer.
+    fn abandon(&self, oid: &ObjectId) {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        self.abandoned.lock().unwrap().insert(oid.clone());
+    }
+
+    /// Stops tracking the specified object as abandoned; returns false if the object was not
+    /// abandoned by a buffer of this client.
+    fn reclaim(&self, oid: &ObjectId) -> bool {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        self.abandoned.lock().unwrap().remove(oid)
+    }
 }
 
 impl PlasmaClient {
@@ -234,13 +690,30 @@ impl PlasmaClient {
         let status = plasma::connect(client_ptr.as_ref().unwrap(), store_socket_name, num_retries);
         match status.code {
             plasma::StatusCode::OK => Ok(PlasmaClient {
-                socket_name: String::from(store_socket_name),
-                client_ptr,
+                inner: Arc::new(PlasmaClientInner {
+                    socket_name: String::from(store_socket_name),
+                    num_retries,
+                    client_ptr,
+                    abandoned: Mutex::new(HashSet::new()),
+                }),
             }),
             _ => Err(PlasmaError::ConnectError(status.msg)),
         }
     }
 
+    /// Re-establishes connection to the plasma store using the socket name and the number of
+    /// retries this client was created with; this can be used to recover after the store has
+    /// been restarted. Options previously set via `set_options()` must be set again.
+    ///
+    /// Only this client is moved to the new connection; other clones of this client keep using
+    /// the old connection, which is closed once the last of them is dropped. If the new
+    /// connection cannot be established, this client keeps using the old connection.
+    pub fn reconnect(&mut self) -> Result<(), PlasmaError> {
+        let client = Self::new(&self.inner.socket_name, self.inner.num_retries)?;
+        self.inner = client.inner;
+        Ok(())
+    }
+
     /// Sets runtime options for this client.
     /// * The name of the client, used in debug messages.
     /// * The memory quota in bytes for objects created by this client.
@@ -250,27 +723,31 @@ impl PlasmaClient {
         output_memory_quota: usize,
     ) -> Result<(), PlasmaError> {
         let status = plasma::set_client_options(
-            self.client_ptr.as_ref().unwrap(),
+            self.inner.client_ptr.as_ref().unwrap(),
             client_name,
             output_memory_quota as i64,
         );
         match status.code {
             plasma::StatusCode::OK => Ok(()),
-            _ => Err(PlasmaError::UnknownError(status.msg)),
+            _ => Err(status_error(status)),
         }
     }
 
     /// Retrieves an object with the specified ID from the store. This function will block until
     /// the object has been created and sealed in the Plasma store or the timeout expires.
     /// * `oid` The ID of the object to get.
-    /// * `timeout_ms` The amount of time in milliseconds to wait before this request times out.
-    ///    If this value is -1, then no timeout is set.
-    pub fn get(&self, oid: ObjectId, timeout_ms: i64) -> Result<Option<ObjectBuffer>, PlasmaError> {
+    /// * `timeout` The amount of time to wait before this request times out. If this value is
+    ///   `None`, then no timeout is set.
+    pub fn get(
+        &self,
+        oid: ObjectId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<ObjectBuffer>, PlasmaError> {
         let mut ob = plasma::new_obj_buffer();
         let status = plasma::get(
-            self.client_ptr.as_ref().unwrap(),
+            self.inner.client_ptr.as_ref().unwrap(),
             oid.inner(),
-            timeout_ms,
+            timeout_to_ms(timeout),
             ob.pin_mut(),
         );
         match status.code {
@@ -278,29 +755,99 @@ impl PlasmaClient {
                 if ob.data.is_null() {
                     Ok(None)
                 } else {
-                    Ok(Some(ObjectBuffer::new(oid, &self.client_ptr, ob, false)))
+                    Ok(Some(ObjectBuffer::new(oid, self, ob, false)))
                 }
             }
-            _ => Err(PlasmaError::UnknownError(status.msg)),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Retrieves metadata of an object with the specified ID from the store. This function will
+    /// block until the object has been created and sealed in the Plasma store or the timeout
+    /// expires. The metadata is copied out and the object is released immediately.
+    ///
+    /// Plasma store does not provide a way to retrieve only object metadata; so, the data
+    /// buffer of the object is still mapped into the client's memory, but it is never read.
+    /// * `oid` The ID of the object to get.
+    /// * `timeout` The amount of time to wait before this request times out. If this value is
+    ///   `None`, then no timeout is set.
+    pub fn get_metadata(
+        &self,
+        oid: ObjectId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, PlasmaError> {
+        let ob = self.get(oid, timeout)?;
+        Ok(ob.map(|ob| ob.meta().to_vec()))
+    }
+
+    /// Retrieves a copy of the specified byte range of data of an object with the specified ID
+    /// from the store. This function will block until the object has been created and sealed in
+    /// the Plasma store or the timeout expires. Only the requested range is copied out, and the
+    /// object is released immediately.
+    /// * `oid` The ID of the object to get.
+    /// * `range` The range of bytes of object data to copy; if the range does not fit into the
+    ///   object data, `StatusCode::Invalid` error is returned.
+    /// * `timeout` The amount of time to wait before this request times out. If this value is
+    ///   `None`, then no timeout is set.
+    pub fn get_range(
+        &self,
+        oid: ObjectId,
+        range: Range<usize>,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, PlasmaError> {
+        let ob = match self.get(oid, timeout)? {
+            Some(ob) => ob,
+            None => return Ok(None),
+        };
+        let data_size = ob.data().len();
+        if range.start > range.end || range.end > data_size {
+            return Err(PlasmaError::UnknownError(
+                StatusCode::Invalid,
+                format!(
+                    "range {}..{} is out of bounds for object data of {} bytes",
+                    range.start, range.end, data_size
+                ),
+            ));
         }
+        Ok(Some(ob.data_range(range.start, range.len()).to_vec()))
     }
 
-    /// Retrieves a list of specified objects from the store.This function will block until
+    /// Retrieves a list of specified objects from the store. This function will block until
     /// all objects have been created and sealed in the Plasma store or the timeout expires.
+    /// All objects are retrieved with a single request to the store; objects which were not
+    /// retrieved before the timeout expired are returned as `None`.
     /// * `object_ids` The list of IDs for objects to get.
-    /// * `timeout_ms` The amount of time in milliseconds to wait before this request times out.
-    ///    If this value is -1, then no timeout is set.
+    /// * `timeout` The amount of time to wait before this request times out. If this value is
+    ///   `None`, then no timeout is set.
     pub fn get_many(
         &self,
         object_ids: &[ObjectId],
-        timeout_ms: i64,
+        timeout: Option<Duration>,
     ) -> Result<Vec<Option<ObjectBuffer>>, PlasmaError> {
-        // TODO: use native C++ function to retrieve all objects at once
-        let mut result = Vec::with_capacity(object_ids.len());
-        for oid in object_ids {
-            result.push(self.get(oid.clone(), timeout_ms)?);
+        let oids = build_oid_vector(object_ids);
+        let mut obs = plasma::new_obj_buffer_vector();
+        let status = plasma::multi_get(
+            self.inner.client_ptr.as_ref().unwrap(),
+            &oids,
+            timeout_to_ms(timeout),
+            obs.pin_mut(),
+        );
+        match status.code {
+            plasma::StatusCode::OK => {
+                // wrap every retrieved buffer, so that it is released when no longer needed
+                let mut result = Vec::with_capacity(object_ids.len());
+                for (i, oid) in object_ids.iter().enumerate() {
+                    let ob = plasma::take_obj_buffer(obs.pin_mut(), i);
+                    if ob.data.is_null() {
+                        result.push(None);
+                    } else {
+                        result.push(Some(ObjectBuffer::new(oid.clone(), self, ob, false)));
+                    }
+                }
+                Ok(result)
+            }
+            _ => Err(status_error(status)),
         }
-        Ok(result)
     }
 
     /// Creates an object in the Plasma Store. Any metadata for this object must be
@@ -317,18 +864,36 @@ impl PlasmaClient {
         data_size: usize,
         meta: &[u8],
     ) -> Result<ObjectBuffer, PlasmaError> {
+        self.create_on_device(oid, data_size, meta, 0)
+    }
+
+    /// Creates an object in the Plasma Store with data allocated on the specified device.
+    /// * `device_num` The number of the device on which to allocate object data; 0 means CPU
+    ///   memory, and 1 or greater refers to a GPU device. Allocating objects on GPU devices
+    ///   requires the store and this crate (via the `cuda` feature) to be built with CUDA
+    ///   support.
+    ///
+    /// See `create()` for the description of other parameters. Data of objects allocated on
+    /// GPU devices cannot be accessed via `data()` or `data_mut()`.
+    pub fn create_on_device(
+        &self,
+        oid: ObjectId,
+        data_size: usize,
+        meta: &[u8],
+        device_num: i32,
+    ) -> Result<ObjectBuffer<'_>, PlasmaError> {
         let mut ob = plasma::new_obj_buffer();
         let status = plasma::create(
-            self.client_ptr.as_ref().unwrap(),
+            self.inner.client_ptr.as_ref().unwrap(),
             ob.pin_mut(),
             oid.inner(),
             data_size as i64,
             meta,
+            device_num,
         );
         match status.code {
-            plasma::StatusCode::OK => Ok(ObjectBuffer::new(oid, &self.client_ptr, ob, true)),
-            plasma::StatusCode::AlreadyExists => Err(PlasmaError::AlreadyExists),
-            _ => Err(PlasmaError::UnknownError(status.msg)),
+            plasma::StatusCode::OK => Ok(ObjectBuffer::new(oid, self, ob, true)),
+            _ => Err(create_error(status)),
         }
     }
 
@@ -343,12 +908,76 @@ impl PlasmaClient {
         data: &[u8],
         meta: &[u8],
     ) -> Result<(), PlasmaError> {
-        let status =
-            plasma::create_and_seal(self.client_ptr.as_ref().unwrap(), oid.inner(), data, meta);
+        let status = plasma::create_and_seal(
+            self.inner.client_ptr.as_ref().unwrap(),
+            oid.inner(),
+            data,
+            meta,
+        );
         match status.code {
             plasma::StatusCode::OK => Ok(()),
-            plasma::StatusCode::AlreadyExists => Err(PlasmaError::AlreadyExists),
-            _ => Err(PlasmaError::UnknownError(status.msg)),
+            _ => Err(create_error(status)),
+        }
+    }
+
+    /// Stores the specified data and metadata as an object whose ID is derived from the data
+    /// (see `ObjectId::from_content`) and returns the ID of the object.
+    ///
+    /// If an object with the same ID is already in the store, the data is assumed to be stored
+    /// already and the ID is returned without an error; metadata of the existing object is not
+    /// compared against `meta`.
+    pub fn put(&self, data: &[u8], meta: &[u8]) -> Result<ObjectId, PlasmaError> {
+        let oid = ObjectId::from_content(data);
+        match self.create_and_seal(oid.clone(), data, meta) {
+            Ok(()) | Err(PlasmaError::AlreadyExists) => Ok(oid),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates and seals multiple objects in the object store with as few requests to the store
+    /// as possible; each entry in `objects` consists of object ID, data, and metadata.
+    ///
+    /// Returns a result for each of the objects in the same order as the objects were specified;
+    /// e.g. objects which already existed in the store are reported as
+    /// `PlasmaError::AlreadyExists`, while other objects are still created. An error is returned
+    /// only if the request could not be completed (e.g. connection to the store was lost).
+    pub fn create_and_seal_many(
+        &self,
+        objects: &[(ObjectId, &[u8], &[u8])],
+    ) -> Result<Vec<Result<(), PlasmaError>>, PlasmaError> {
+        // object data and metadata are passed to the store in contiguous buffers
+        let mut oids = plasma::new_oid_vector();
+        let mut data = Vec::new();
+        let mut data_sizes = Vec::with_capacity(objects.len());
+        let mut metadata = Vec::new();
+        let mut metadata_sizes = Vec::with_capacity(objects.len());
+        for (oid, ob_data, ob_meta) in objects {
+            plasma::push_oid(oids.pin_mut(), oid.inner());
+            data.extend_from_slice(ob_data);
+            data_sizes.push(ob_data.len() as u64);
+            metadata.extend_from_slice(ob_meta);
+            metadata_sizes.push(ob_meta.len() as u64);
+        }
+
+        let mut results = Vec::with_capacity(objects.len());
+        let status = plasma::create_and_seal_batch(
+            self.inner.client_ptr.as_ref().unwrap(),
+            &oids,
+            &data,
+            &data_sizes,
+            &metadata,
+            &metadata_sizes,
+            &mut results,
+        );
+        match status.code {
+            plasma::StatusCode::OK => Ok(results
+                .into_iter()
+                .map(|status| match status.code {
+                    plasma::StatusCode::OK => Ok(()),
+                    _ => Err(create_error(status)),
+                })
+                .collect()),
+            _ => Err(status_error(status)),
         }
     }
 
@@ -356,64 +985,386 @@ impl PlasmaClient {
     /// object is present, has been sealed and not used by another client. Otherwise,
     /// it is a no operation.
     pub fn delete(&self, oid: &ObjectId) -> Result<(), PlasmaError> {
-        let status = plasma::delete(self.client_ptr.as_ref().unwrap(), oid.inner());
+        let status = plasma::delete(self.inner.client_ptr.as_ref().unwrap(), oid.inner());
         match status.code {
             plasma::StatusCode::OK => Ok(()),
-            _ => Err(PlasmaError::UnknownError(status.msg)),
+            _ => Err(status_error(status)),
         }
     }
 
     /// Deletes all objects specified by `object_ids` list from the object store. This
     /// currently assumes that the objects are present, haven been sealed and are not
     /// used by another client. Otherwise it is a no operation.
+    ///
+    /// All objects are deleted with a single request to the store; objects which are not
+    /// present, are not sealed, or are in use by other clients are silently skipped.
     pub fn delete_many(&self, object_ids: &[ObjectId]) -> Result<(), PlasmaError> {
-        // TODO: use native C++ function to retrieve all objects at once
-        for oid in object_ids {
-            self.delete(oid)?;
+        let oids = build_oid_vector(object_ids);
+        let status = plasma::multi_delete(self.inner.client_ptr.as_ref().unwrap(), &oids);
+        match status.code {
+            plasma::StatusCode::OK => Ok(()),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Aborts the specified unsealed objects, removing them from the store as if they were
+    /// never created; this can be used to roll back a batch of objects which a producer failed
+    /// to complete. Only objects created by this client whose buffers have been dropped without
+    /// sealing them can be aborted; every such object is released and then aborted.
+    ///
+    /// Returns a result for each of the objects in the same order as the objects were specified.
+    /// Other objects are left untouched and reported as errors: sealed objects as
+    /// `PlasmaError::AlreadySealed`, objects which are not in the store with
+    /// `StatusCode::KeyError`, and unsealed objects created by other clients (or whose buffers
+    /// are still in use) with `StatusCode::Invalid`. The object table of the store is retrieved
+    /// at most once per call to tell these cases apart.
+    pub fn abort_many(&self, object_ids: &[ObjectId]) -> Vec<Result<(), PlasmaError>> {
+        let mut states: Option<Result<HashMap<ObjectId, ObjectState>, String>> = None;
+        object_ids
+            .iter()
+            .map(|oid| {
+                if self.inner.reclaim(oid) {
+                    return self.abort_abandoned(oid);
+                }
+                let states = states.get_or_insert_with(|| {
+                    self.list()
+                        .map(|objects| {
+                            objects
+                                .into_iter()
+                                .map(|info| (info.id, info.state))
+                                .collect()
+                        })
+                        .map_err(|err| err.to_string())
+                });
+                match states {
+                    Ok(states) => Err(not_abandoned_error(oid, states.get(oid))),
+                    Err(err) => Err(PlasmaError::UnknownError(
+                        StatusCode::IOError,
+                        format!("failed to list objects: {}", err),
+                    )),
+                }
+            })
+            .collect()
+    }
+
+    /// Bumps the specified objects to the most recently used position in the store's LRU
+    /// cache. This can be used to keep objects from being evicted while the client holds
+    /// on to their IDs in between `get` calls.
+    pub fn refresh(&self, object_ids: &[ObjectId]) -> Result<(), PlasmaError> {
+        let oids = build_oid_vector(object_ids);
+        let status = plasma::refresh(self.inner.client_ptr.as_ref().unwrap(), &oids);
+        match status.code {
+            plasma::StatusCode::OK => Ok(()),
+            _ => Err(status_error(status)),
         }
-        Ok(())
     }
 
     /// Checks if the object store contains a particular object and the object has been sealed.
     pub fn contains(&self, oid: &ObjectId) -> Result<bool, PlasmaError> {
         let mut has_object = false;
         let status = plasma::contains(
-            self.client_ptr.as_ref().unwrap(),
+            self.inner.client_ptr.as_ref().unwrap(),
             oid.inner(),
             &mut has_object,
         );
         match status.code {
             plasma::StatusCode::OK => Ok(has_object),
-            _ => Err(PlasmaError::UnknownError(status.msg)),
+            _ => Err(status_error(status)),
         }
     }
 
-    /// Returns a list of IDs for objects contained in the object store.
-    pub fn contains_many(&self, object_ids: &[ObjectId]) -> Result<Vec<ObjectId>, PlasmaError> {
-        let mut found_objects = Vec::new();
-        // TODO: move this to C++ side to make it more efficient?
-        for oid in object_ids.iter() {
-            if self.contains(oid)? {
-                found_objects.push(oid.clone());
+    /// Blocks until the specified object is sealed in the store or the timeout expires. Returns
+    /// true if the object is available and false if the timeout expired first. Unlike `get()`,
+    /// the object is not mapped into memory of this process.
+    pub fn wait_for(&self, oid: &ObjectId, timeout: Duration) -> Result<bool, PlasmaError> {
+        // subscribe before checking the store so that an object sealed in between is not missed
+        let subscription = self.subscribe()?;
+        if self.contains(oid)? {
+            return Ok(true);
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let now = Instant::now();
+            if now >= deadline || !subscription.poll(deadline - now)? {
+                return Ok(false);
+            }
+            let notification = subscription.next()?;
+            if notification.object_id == *oid && !notification.is_deletion() {
+                return Ok(true);
             }
         }
-        Ok(found_objects)
+    }
+
+    /// Returns a list of IDs for objects contained in the object store; the IDs are returned in
+    /// the same order as in `object_ids`. All objects are checked in a single call to the
+    /// underlying client.
+    pub fn contains_many(&self, object_ids: &[ObjectId]) -> Result<Vec<ObjectId>, PlasmaError> {
+        let oids = build_oid_vector(object_ids);
+        let mut has_objects = Vec::with_capacity(object_ids.len());
+        let status = plasma::multi_contains(
+            self.inner.client_ptr.as_ref().unwrap(),
+            &oids,
+            &mut has_objects,
+        );
+        match status.code {
+            plasma::StatusCode::OK => Ok(object_ids
+                .iter()
+                .zip(has_objects)
+                .filter(|(_, has_object)| *has_object)
+                .map(|(oid, _)| oid.clone())
+                .collect()),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Asks the store to evict objects until at least `num_bytes` bytes are freed; only objects
+    /// which are not in use by any client can be evicted. Returns the number of bytes which
+    /// were actually evicted; this may be less than `num_bytes`.
+    pub fn evict(&self, num_bytes: usize) -> Result<usize, PlasmaError> {
+        let mut num_bytes_evicted = 0;
+        let status = plasma::evict(
+            self.inner.client_ptr.as_ref().unwrap(),
+            num_bytes as i64,
+            &mut num_bytes_evicted,
+        );
+        match status.code {
+            plasma::StatusCode::OK => Ok(num_bytes_evicted as usize),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Returns information about all objects currently held by the store, including objects
+    /// which have been created but not yet sealed.
+    pub fn list(&self) -> Result<Vec<ObjectInfo>, PlasmaError> {
+        let mut entries = Vec::new();
+        let status = plasma::list(self.inner.client_ptr.as_ref().unwrap(), &mut entries);
+        match status.code {
+            plasma::StatusCode::OK => entries.into_iter().map(ObjectInfo::try_from).collect(),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Computes the digest of the specified sealed object; this is the same digest as the one
+    /// recorded by the store when the object was sealed.
+    pub fn hash(&self, oid: &ObjectId) -> Result<[u8; DIGEST_SIZE], PlasmaError> {
+        let mut digest = [0u8; DIGEST_SIZE];
+        let status = plasma::hash(self.client(), oid.inner(), &mut digest);
+        match status.code {
+            plasma::StatusCode::OK => Ok(digest),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Returns sizes of data and metadata of the specified object as `(data_size, metadata_size)`
+    /// without mapping the object into memory of this client. Returns `None` if the object is
+    /// not in the store or has not been sealed yet.
+    pub fn object_size(&self, oid: &ObjectId) -> Result<Option<(usize, usize)>, PlasmaError> {
+        let mut entries = Vec::new();
+        let status = plasma::object_info(
+            self.inner.client_ptr.as_ref().unwrap(),
+            oid.inner(),
+            &mut entries,
+        );
+        match status.code {
+            plasma::StatusCode::OK => match entries.pop().map(ObjectInfo::try_from).transpose()? {
+                Some(info) if info.state == ObjectState::Sealed => {
+                    Ok(Some((info.data_size, info.metadata_size)))
+                }
+                _ => Ok(None),
+            },
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Subscribes to notifications about objects sealed or deleted in the store. Notifications
+    /// can then be retrieved by calling `next()` on the returned subscription.
+    pub fn subscribe(&self) -> Result<Subscription, PlasmaError> {
+        let mut fd = -1;
+        let status = plasma::subscribe(self.inner.client_ptr.as_ref().unwrap(), &mut fd);
+        match status.code {
+            plasma::StatusCode::OK => Ok(Subscription {
+                pc: &self.inner.client_ptr,
+                fd,
+            }),
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Returns the name of the UNIX domain socket this client connects to.
+    pub fn socket_name(&self) -> &str {
+        &self.inner.socket_name
+    }
+
+    /// Returns true if the connection to the store is still usable. This sends a single
+    /// `contains()` request for a placeholder object to the store, so it is cheap enough to be
+    /// called periodically, e.g. to decide when to `reconnect()`. `store_capacity()` cannot be
+    /// used for this, because the capacity is reported by the store only when connecting.
+    pub fn is_connected(&self) -> bool {
+        self.contains(&ObjectId::new([0; 20])).is_ok()
     }
 
     /// Returns memory capacity of the store in bytes.
     pub fn store_capacity(&self) -> usize {
-        plasma::store_capacity_bytes(self.client_ptr.as_ref().unwrap()) as usize
+        plasma::store_capacity_bytes(self.inner.client_ptr.as_ref().unwrap()) as usize
+    }
+
+    /// Returns current memory usage of the store. Plasma store reports memory usage only as a
+    /// part of its debug string; so, the usage is parsed out of this string.
+    pub fn memory_usage(&self) -> Result<MemoryUsage, PlasmaError> {
+        let mut debug = String::new();
+        let status = plasma::debug_string(self.inner.client_ptr.as_ref().unwrap(), &mut debug);
+        match status.code {
+            plasma::StatusCode::OK => parse_memory_usage(&debug, self.store_capacity()),
+            _ => Err(status_error(status)),
+        }
     }
 }
 
-impl Drop for PlasmaClient {
-    fn drop(&mut self) {
-        plasma::disconnect(self.client_ptr.as_ref().unwrap());
+impl PlasmaClient {
+    /// Returns a reference to the underlying C++ client.
+    fn client(&self) -> &plasma::PlasmaClient {
+        self.inner.client_ptr.as_ref().unwrap()
+    }
+
+    /// Releases and then aborts an unsealed object abandoned by a buffer of this client; the
+    /// underlying client aborts an object only if it holds exactly one reference to it, so the
+    /// reference held on behalf of the dropped buffer is released first.
+    fn abort_abandoned(&self, oid: &ObjectId) -> Result<(), PlasmaError> {
+        let status = plasma::release(self.client(), oid.inner());
+        if status.code != plasma::StatusCode::OK {
+            return Err(PlasmaError::UnknownError(
+                status.code,
+                format!("release failed: {}", status.msg),
+            ));
+        }
+        let status = plasma::abort(self.client(), oid.inner());
+        match status.code {
+            plasma::StatusCode::OK => Ok(()),
+            _ => Err(status_error(status)),
+        }
     }
 }
 
 impl Debug for PlasmaClient {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "PlasmaClient {{ socket: {} }}", self.socket_name)
+        write!(f, "PlasmaClient {{ socket: {} }}", self.inner.socket_name)
+    }
+}
+
+impl Drop for PlasmaClientInner {
+    fn drop(&mut self) {
+        plasma::disconnect(self.client_ptr.as_ref().unwrap());
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Copies the specified object IDs into a C++ vector so that they can be passed to batch
+/// functions of the plasma client.
+fn build_oid_vector(object_ids: &[ObjectId]) -> UniquePtr<CxxVector<plasma::ObjectID>> {
+    let mut oids = plasma::new_oid_vector();
+    for oid in object_ids {
+        plasma::push_oid(oids.pin_mut(), oid.inner());
+    }
+    oids
+}
+
+/// Converts a timeout into the number of milliseconds expected by the plasma client; `None` is
+/// converted to -1 (no timeout), and durations which do not fit into i64 milliseconds are
+/// saturated to i64::MAX.
+fn timeout_to_ms(timeout: Option<Duration>) -> i64 {
+    match timeout {
+        Some(timeout) => i64::try_from(timeout.as_millis()).unwrap_or(i64::MAX),
+        None => -1,
+    }
+}
+
+/// Returns the error reported by `abort_many()` for an object which was not abandoned by a
+/// buffer of the client, given the state of the object in the store (if the object is there).
+fn not_abandoned_error(oid: &ObjectId, state: Option<&ObjectState>) -> PlasmaError {
+    match state {
+        Some(ObjectState::Sealed) => PlasmaError::AlreadySealed,
+        Some(ObjectState::Created) => PlasmaError::UnknownError(
+            StatusCode::Invalid,
+            format!(
+                "object {} was not abandoned by this client; it is either in use or was created \
+                by another client",
+                oid.to_hex()
+            ),
+        ),
+        Some(ObjectState::Evicted) | None => PlasmaError::UnknownError(
+            StatusCode::KeyError,
+            format!("object {} is not in the store", oid.to_hex()),
+        ),
+    }
+}
+
+/// Logs an error if the status returned by the plasma client when releasing an object buffer
+/// on drop is not OK.
+fn log_release_status(oid: &ObjectId, status: plasma::ArrowStatus) {
+    if let plasma::StatusCode::OK = status.code {
+        return;
+    }
+    error!(
+        "failed to release object buffer {}: {}",
+        oid.to_hex(),
+        status_error(status)
+    );
+}
+
+/// Converts a non-OK status returned by the plasma client into an error. The client reports
+/// IO errors only when communication over the store socket fails, which means that the store
+/// process has gone away; such errors are surfaced as `PlasmaError::StoreDisconnected`.
+fn status_error(status: plasma::ArrowStatus) -> PlasmaError {
+    match status.code {
+        plasma::StatusCode::IOError => PlasmaError::StoreDisconnected(status.msg),
+        _ => PlasmaError::UnknownError(status.code, status.msg),
+    }
+}
+
+/// Extracts memory usage from the debug string returned by the store. The string consists of
+/// `name: value` lines; the number of allocated bytes is reported as `allocated bytes`, and the
+/// number of objects as `num objects` (prefixed with the name of the eviction cache).
+fn parse_memory_usage(debug: &str, capacity_bytes: usize) -> Result<MemoryUsage, PlasmaError> {
+    let mut used_bytes = None;
+    let mut num_objects = None;
+    for line in debug.lines() {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => continue,
+        };
+        if used_bytes.is_none() && name == "allocated bytes" {
+            used_bytes = value.parse::<usize>().ok();
+        } else if num_objects.is_none() && name.ends_with("num objects") {
+            num_objects = value.parse::<usize>().ok();
+        }
+    }
+
+    match (used_bytes, num_objects) {
+        (Some(used_bytes), Some(num_objects)) => Ok(MemoryUsage {
+            capacity_bytes,
+            used_bytes,
+            num_objects,
+        }),
+        _ => Err(PlasmaError::UnknownError(
+            StatusCode::UnknownError,
+            format!(
+                "failed to parse memory usage from store debug string: {}",
+                debug
+            ),
+        )),
+    }
+}
+
+/// Converts a non-OK status returned by the plasma client when creating an object into an
+/// error. In addition to errors described for `status_error()`, this distinguishes objects
+/// which already exist from objects which could not be created because the store is full.
+fn create_error(status: plasma::ArrowStatus) -> PlasmaError {
+    match status.code {
+        plasma::StatusCode::AlreadyExists => PlasmaError::AlreadyExists,
+        plasma::StatusCode::OutOfMemory => PlasmaError::OutOfMemory(status.msg),
+        _ => status_error(status),
     }
 }
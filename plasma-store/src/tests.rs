@@ -4,11 +4,16 @@
 // LICENSE file in the root directory of this source tree.
 
 use super::*;
+use std::{
+    process::{Child, Command},
+    thread,
+};
 
 /// CONSTANTS
 /// ===============================================================================================
 
 const PLASMA_SOCKET: &str = "/tmp/plasma";
+const PLASMA_RESTART_SOCKET: &str = "/tmp/plasma-restart";
 
 /// OBJECT ID TESTS
 /// ===============================================================================================
@@ -23,6 +28,71 @@ fn plasma_object_id_new() {
     assert_eq!("0102030405060708090a0b0c0d0e0f1011121314", oid.to_hex());
 }
 
+#[test]
+fn plasma_object_id_from_bytes() {
+    let bytes = [
+        1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20,
+    ];
+    assert_eq!(ObjectId::new(bytes), ObjectId::from(bytes));
+    assert_eq!(
+        ObjectId::new(bytes),
+        ObjectId::try_from(&bytes[..]).unwrap()
+    );
+
+    // slices of incorrect length should result in an error
+    assert!(ObjectId::try_from(&bytes[..19]).is_err());
+    let mut long_bytes = bytes.to_vec();
+    long_bytes.push(21);
+    assert!(ObjectId::try_from(&long_bytes[..]).is_err());
+}
+
+#[test]
+fn plasma_object_id_rand_with() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // identically-seeded generators should produce identical sequences of IDs
+    let mut rng1 = StdRng::seed_from_u64(42);
+    let mut rng2 = StdRng::seed_from_u64(42);
+    let oid = ObjectId::rand_with(&mut rng1);
+    assert_eq!(oid, ObjectId::rand_with(&mut rng2));
+    assert_ne!(oid, ObjectId::rand_with(&mut rng1));
+    assert_eq!(
+        ObjectId::rand_with(&mut rng1),
+        ObjectId::rand_with(&mut rng2)
+    );
+}
+
+#[test]
+fn plasma_object_id_from_hex() {
+    let oid = ObjectId::rand();
+    assert_eq!(oid, ObjectId::from_hex(&oid.to_hex()).unwrap());
+
+    let oid = ObjectId::from_hex("0102030405060708090a0b0c0d0e0f1011121314").unwrap();
+    assert_eq!("0102030405060708090a0b0c0d0e0f1011121314", oid.to_hex());
+}
+
+#[test]
+fn plasma_object_id_from_hex_error() {
+    // too short
+    assert!(ObjectId::from_hex("0102030405060708090a0b0c0d0e0f10111213").is_err());
+    // too long
+    assert!(ObjectId::from_hex("0102030405060708090a0b0c0d0e0f101112131415").is_err());
+    // odd number of characters
+    assert!(ObjectId::from_hex("0102030405060708090a0b0c0d0e0f101112131").is_err());
+    // invalid hex digits
+    assert!(ObjectId::from_hex("0102030405060708090a0b0c0d0e0f10111213zz").is_err());
+}
+
+#[test]
+fn plasma_object_id_from_content() {
+    // the ID consists of the first 20 bytes of SHA-256 digest of the data
+    let oid = ObjectId::from_content(b"abc");
+    assert_eq!("ba7816bf8f01cfea414140de5dae2223b00361a3", oid.to_hex());
+
+    assert_eq!(oid, ObjectId::from_content(b"abc"));
+    assert_ne!(oid, ObjectId::from_content(b"abd"));
+}
+
 #[test]
 fn plasma_object_id_rand() {
     let oid1 = ObjectId::rand();
@@ -37,6 +107,80 @@ fn plasma_object_id_clone() {
     assert_eq!(oid1, oid2);
 }
 
+#[test]
+fn plasma_object_id_hash() {
+    let oid1 = ObjectId::rand();
+    let oid2 = ObjectId::rand();
+
+    let mut set = std::collections::HashSet::new();
+    assert!(set.insert(oid1.clone()));
+    assert!(set.insert(oid2.clone()));
+
+    // inserting clones of the same IDs should not change the set
+    assert!(!set.insert(oid1.clone()));
+    assert!(!set.insert(oid2.clone()));
+    assert_eq!(2, set.len());
+    assert!(set.contains(&oid1));
+    assert!(set.contains(&oid2));
+}
+
+#[test]
+fn plasma_object_id_ord() {
+    let mut oids: Vec<ObjectId> = (0..10).map(|_| ObjectId::rand()).collect();
+    oids.sort();
+    for pair in oids.windows(2) {
+        assert!(pair[0].to_bytes() <= pair[1].to_bytes());
+    }
+
+    // ordering should be consistent with equality
+    let oid1 = ObjectId::new([1u8; 20]);
+    let oid2 = ObjectId::new([2u8; 20]);
+    assert_eq!(std::cmp::Ordering::Less, oid1.cmp(&oid2));
+    assert_eq!(std::cmp::Ordering::Equal, oid1.cmp(&oid1.clone()));
+    assert_eq!(std::cmp::Ordering::Greater, oid2.cmp(&oid1));
+}
+
+#[test]
+fn plasma_create_error_mapping() {
+    let status = |code| plasma::ArrowStatus {
+        code,
+        msg: String::from("error"),
+    };
+
+    assert!(matches!(
+        create_error(status(plasma::StatusCode::AlreadyExists)),
+        PlasmaError::AlreadyExists
+    ));
+    assert!(matches!(
+        create_error(status(plasma::StatusCode::OutOfMemory)),
+        PlasmaError::OutOfMemory(_)
+    ));
+    assert!(matches!(
+        create_error(status(plasma::StatusCode::IOError)),
+        PlasmaError::StoreDisconnected(_)
+    ));
+    assert!(matches!(
+        create_error(status(plasma::StatusCode::Invalid)),
+        PlasmaError::UnknownError(StatusCode::Invalid, _)
+    ));
+}
+
+#[test]
+fn plasma_error_unknown_error_code() {
+    let err = status_error(plasma::ArrowStatus {
+        code: StatusCode::CapacityError,
+        msg: String::from("store is full"),
+    });
+    match &err {
+        PlasmaError::UnknownError(code, msg) => {
+            assert_eq!(StatusCode::CapacityError, *code);
+            assert_eq!("store is full", msg);
+        }
+        err => panic!("expected unknown error, but got {:?}", err),
+    }
+    assert_eq!("unknown error: store is full", err.to_string());
+}
+
 /// CLIENT TESTS
 /// ===============================================================================================
 // tests below require plasma store server to be running on the local machine; building plasma
@@ -64,6 +208,104 @@ fn plasma_client_create_and_seal() {
     assert!(pc.create_and_seal(oid.clone(), &data, &meta).is_err());
 }
 
+#[test]
+#[ignore]
+fn plasma_client_put() {
+    let pc = build_client();
+    let data: Vec<u8> = (0..32).map(|_| rand::random()).collect();
+    let meta = [1u8, 2, 3, 4];
+
+    // the object ID is derived from the data
+    let oid = pc.put(&data, &meta).unwrap();
+    assert_eq!(ObjectId::from_content(&data), oid);
+    let ob = pc.get(oid.clone(), None).unwrap().unwrap();
+    assert_eq!(data, ob.data());
+    assert_eq!(meta, ob.meta());
+
+    // putting the same data again should succeed and return the same ID
+    assert_eq!(oid, pc.put(&data, &meta).unwrap());
+}
+
+#[test]
+#[ignore]
+fn plasma_object_buffer_copy() {
+    let pc = build_client();
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+    let ob = pc.get(oid, None).unwrap().unwrap();
+
+    // copied bytes are appended to the existing contents
+    let mut data_buf = vec![0u8; 2];
+    let mut meta_buf = vec![0u8; 2];
+    ob.copy_data_into(&mut data_buf);
+    ob.copy_meta_into(&mut meta_buf);
+    assert_eq!(ob.data(), &data_buf[2..]);
+    assert_eq!(ob.meta(), &meta_buf[2..]);
+
+    // owned copies remain valid after the buffer is released
+    let (owned_data, owned_meta) = ob.to_owned();
+    ob.release().unwrap();
+    assert_eq!(data.to_vec(), owned_data);
+    assert_eq!(meta.to_vec(), owned_meta);
+}
+
+#[test]
+#[ignore]
+fn plasma_object_buffer_parts() {
+    let pc = build_client();
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+    let ob = pc.get(oid, None).unwrap().unwrap();
+
+    let (ob_meta, ob_data) = ob.parts();
+    assert_eq!(ob.meta(), ob_meta);
+    assert_eq!(ob.data(), ob_data);
+    assert_eq!(&meta, ob_meta);
+    assert_eq!(&data, ob_data);
+}
+
+#[test]
+#[ignore]
+fn plasma_client_create_and_seal_many() {
+    let pc = build_client();
+
+    // create 1,000 small objects with a single call
+    let oids: Vec<ObjectId> = (0..1000).map(|_| ObjectId::rand()).collect();
+    let data: Vec<[u8; 4]> = (0..1000u32).map(|i| i.to_le_bytes()).collect();
+    let meta = [1u8, 2];
+    let objects: Vec<(ObjectId, &[u8], &[u8])> = oids
+        .iter()
+        .zip(data.iter())
+        .map(|(oid, data)| (oid.clone(), &data[..], &meta[..]))
+        .collect();
+    let results = pc.create_and_seal_many(&objects).unwrap();
+    assert_eq!(1000, results.len());
+    assert!(results.iter().all(|result| result.is_ok()));
+
+    let ob = pc.get(oids[500].clone(), None).unwrap().unwrap();
+    assert_eq!(&500u32.to_le_bytes(), ob.data());
+    assert_eq!(&meta, ob.meta());
+    drop(ob);
+
+    // objects which already exist should be reported per entry, while the rest are created
+    let new_oid = ObjectId::rand();
+    let objects = vec![
+        (oids[0].clone(), &data[0][..], &meta[..]),
+        (new_oid.clone(), &data[1][..], &meta[..]),
+    ];
+    let results = pc.create_and_seal_many(&objects).unwrap();
+    assert!(matches!(results[0], Err(PlasmaError::AlreadyExists)));
+    assert!(results[1].is_ok());
+    assert!(pc.contains(&new_oid).unwrap());
+
+    pc.delete_many(&oids).unwrap();
+    pc.delete(&new_oid).unwrap();
+}
+
 #[test]
 #[ignore]
 fn plasma_client_get() {
@@ -76,16 +318,94 @@ fn plasma_client_get() {
     pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
 
     // get object out of the store and make sure data and metadata are the same
-    let ob = pc.get(oid, 5).unwrap().unwrap();
+    let ob = pc
+        .get(oid, Some(Duration::from_millis(5)))
+        .unwrap()
+        .unwrap();
     assert_eq!(data, ob.data(), "object data should match");
     assert_eq!(meta, ob.meta(), "object metadata should match");
     assert_eq!(false, ob.is_mutable(), "object should not be mutable");
 
     // if we try to retrieve a non-existent object, we should get None back
-    let ob = pc.get(ObjectId::rand(), 5).unwrap();
+    let ob = pc
+        .get(ObjectId::rand(), Some(Duration::from_millis(5)))
+        .unwrap();
     assert!(ob.is_none());
 }
 
+#[test]
+#[ignore]
+fn plasma_client_get_not_mutable() {
+    let pc = build_client();
+
+    // put object into the store
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+
+    // trying to get a mutable data buffer of a retrieved object should result in an error
+    let mut ob = pc
+        .get(oid, Some(Duration::from_millis(5)))
+        .unwrap()
+        .unwrap();
+    assert!(matches!(ob.data_mut(), Err(PlasmaError::NotMutable)));
+}
+
+#[test]
+#[ignore]
+fn plasma_client_get_metadata() {
+    let pc = build_client();
+
+    // put object into the store
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+
+    // get only the metadata and make sure it is the same
+    let timeout = Some(Duration::from_millis(5));
+    let result = pc.get_metadata(oid, timeout).unwrap();
+    assert_eq!(Some(meta.to_vec()), result, "object metadata should match");
+
+    // if we try to retrieve metadata of a non-existent object, we should get None back
+    let result = pc.get_metadata(ObjectId::rand(), timeout).unwrap();
+    assert!(result.is_none());
+}
+
+#[test]
+#[ignore]
+fn plasma_client_get_range() {
+    let pc = build_client();
+
+    // put object into the store
+    let oid = ObjectId::rand();
+    let data: Vec<u8> = (0..1024u32).map(|i| (i % 251) as u8).collect();
+    pc.create_and_seal(oid.clone(), &data, &[1, 2]).unwrap();
+
+    // range reads should match the corresponding slices of the full data
+    let timeout = Some(Duration::from_millis(5));
+    let ob = pc.get(oid.clone(), timeout).unwrap().unwrap();
+    assert_eq!(&data[100..164], ob.data_range(100, 64));
+    assert_eq!(&data[1000..], ob.data_range(1000, 24));
+    assert!(ob.data_range(1024, 0).is_empty());
+    drop(ob);
+    let result = pc.get_range(oid.clone(), 1016..1024, timeout).unwrap();
+    assert_eq!(Some(data[1016..].to_vec()), result);
+
+    // ranges which do not fit into the object data should result in an error
+    let result = pc.get_range(oid.clone(), 1000..1025, timeout);
+    assert!(matches!(
+        result,
+        Err(PlasmaError::UnknownError(StatusCode::Invalid, _))
+    ));
+
+    // if we try to read a range of a non-existent object, we should get None back
+    let result = pc.get_range(ObjectId::rand(), 0..8, timeout).unwrap();
+    assert!(result.is_none());
+
+    pc.delete(&oid).unwrap();
+}
+
 #[test]
 #[ignore]
 fn plasma_client_get_many() {
@@ -104,7 +424,7 @@ fn plasma_client_get_many() {
 
     // get objects out of the store and make sure they are returned correctly
     let oids = [oid1, oid2, ObjectId::rand()];
-    let mut result = pc.get_many(&oids, 5).unwrap();
+    let mut result = pc.get_many(&oids, Some(Duration::from_millis(5))).unwrap();
     assert_eq!(
         oids.len(),
         result.len(),
@@ -176,6 +496,50 @@ fn plasma_client_contains_many() {
     assert_eq!(oid2, result[1], "oid2 data should match");
 }
 
+#[test]
+#[ignore]
+fn plasma_object_buffer_release() {
+    let pc = build_client();
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+
+    // get the object twice, so that it is in use until both buffers are released
+    let ob1 = pc.get(oid.clone(), None).unwrap().unwrap();
+    let ob2 = pc.get(oid.clone(), None).unwrap().unwrap();
+
+    // releasing the first buffer explicitly should not release it again when it is dropped;
+    // otherwise, the object would no longer be in use and would be deleted below
+    ob1.release().unwrap();
+    pc.delete(&oid).unwrap();
+    assert!(pc.contains(&oid).unwrap(), "object should still be in use");
+
+    // once the second buffer is released, the object can be deleted
+    ob2.release().unwrap();
+    pc.delete(&oid).unwrap();
+    assert!(!pc.contains(&oid).unwrap(), "object should be deleted");
+}
+
+#[test]
+#[ignore]
+fn plasma_client_contains_many_mixed() {
+    let pc = build_client();
+
+    // put every other object into the store
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let oids: Vec<ObjectId> = (0..10).map(|_| ObjectId::rand()).collect();
+    for oid in oids.iter().step_by(2) {
+        pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+    }
+
+    // only the objects which were put into the store should be returned, in request order
+    let result = pc.contains_many(&oids).unwrap();
+    let expected: Vec<ObjectId> = oids.iter().step_by(2).cloned().collect();
+    assert_eq!(expected, result);
+
+    pc.delete_many(&oids).unwrap();
+}
+
 #[test]
 #[ignore]
 fn plasma_client_create_then_seal() {
@@ -190,6 +554,11 @@ fn plasma_client_create_then_seal() {
 
     assert_eq!(true, ob.is_mutable(), "object should be mutable");
     assert_eq!(meta, ob.meta(), "object metadata should match");
+    assert_eq!(
+        0,
+        ob.device_num(),
+        "object should be allocated in CPU memory"
+    );
     assert_eq!(
         data_size,
         ob.data().len(),
@@ -203,7 +572,7 @@ fn plasma_client_create_then_seal() {
 
     // update data buffer and seal the object
     let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
-    let data_buf = ob.data_mut();
+    let data_buf = ob.data_mut().unwrap();
     for i in 0..data_buf.len() {
         data_buf[i] = data[i];
     }
@@ -221,7 +590,10 @@ fn plasma_client_create_then_seal() {
     assert!(ob.seal().is_err());
 
     // make sure the object can be retrieved correctly from another client
-    let ob = pc2.get(oid, 5).unwrap().unwrap();
+    let ob = pc2
+        .get(oid, Some(Duration::from_millis(5)))
+        .unwrap()
+        .unwrap();
     assert_eq!(data, ob.data(), "client2: object data should match");
     assert_eq!(meta, ob.meta(), "client2: object metadata should match");
     assert_eq!(
@@ -242,7 +614,10 @@ fn plasma_client_create_then_seal_error() {
     pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
 
     // get the object from the store
-    let mut ob = pc.get(oid, 5).unwrap().unwrap();
+    let mut ob = pc
+        .get(oid, Some(Duration::from_millis(5)))
+        .unwrap()
+        .unwrap();
 
     // trying to seal this object should result in an error
     assert!(ob.seal().is_err());
@@ -261,7 +636,7 @@ fn plasma_client_create_then_abort() {
 
     // write data into the object's data buffer
     let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
-    let data_buf = ob.data_mut();
+    let data_buf = ob.data_mut().unwrap();
     for i in 0..data_buf.len() {
         data_buf[i] = data[i];
     }
@@ -275,6 +650,96 @@ fn plasma_client_create_then_abort() {
     );
 }
 
+#[test]
+#[ignore]
+fn plasma_client_abort_in_place() {
+    let pc = build_client();
+
+    // create an object and abort it without consuming the buffer
+    let oid = ObjectId::rand();
+    let mut ob = pc.create(oid.clone(), 16, &[1, 2]).unwrap();
+    ob.abort_in_place().unwrap();
+    assert!(
+        !pc.contains(&oid).unwrap(),
+        "object should not be in the store"
+    );
+
+    // the buffer is left in an aborted state and cannot be aborted again
+    assert!(ob.is_released);
+    assert!(!ob.is_mutable());
+    assert!(matches!(ob.abort_in_place(), Err(PlasmaError::NotMutable)));
+
+    // dropping the buffer should not release the object again; so, an object with the same ID
+    // can be created and sealed, and its reference is held until it is released explicitly
+    drop(ob);
+    let mut ob = pc.create(oid.clone(), 16, &[1, 2]).unwrap();
+    ob.seal().unwrap();
+    ob.release().unwrap();
+    assert!(pc.contains(&oid).unwrap(), "object should be in the store");
+}
+
+#[test]
+#[ignore]
+fn plasma_client_abort_many() {
+    let pc = build_client();
+
+    // create several objects, but leave them unsealed as if the producer failed part way
+    let oids: Vec<ObjectId> = (0..5).map(|_| ObjectId::rand()).collect();
+    for oid in oids.iter() {
+        let ob = pc.create(oid.clone(), 16, &[1, 2]).unwrap();
+        drop(ob);
+    }
+
+    // a sealed object should be skipped, and a missing object should be reported
+    let sealed_oid = ObjectId::rand();
+    pc.create_and_seal(sealed_oid.clone(), &[1, 2, 3, 4], &[])
+        .unwrap();
+    let missing_oid = ObjectId::rand();
+
+    // unsealed objects which were not abandoned by this client must be left alone: aborting
+    // them would crash the underlying client
+    let pc2 = build_client();
+    let foreign_oid = ObjectId::rand();
+    let foreign_ob = pc2.create(foreign_oid.clone(), 16, &[]).unwrap();
+    let in_use_oid = ObjectId::rand();
+    let mut in_use_ob = pc.create(in_use_oid.clone(), 16, &[]).unwrap();
+
+    let mut object_ids = oids.clone();
+    object_ids.push(sealed_oid.clone());
+    object_ids.push(missing_oid);
+    object_ids.push(foreign_oid.clone());
+    object_ids.push(in_use_oid.clone());
+    let results = pc.abort_many(&object_ids);
+    assert_eq!(object_ids.len(), results.len());
+    assert!(results[..oids.len()].iter().all(|result| result.is_ok()));
+    assert!(matches!(results[5], Err(PlasmaError::AlreadySealed)));
+    assert!(matches!(
+        results[6],
+        Err(PlasmaError::UnknownError(StatusCode::KeyError, _))
+    ));
+    assert!(matches!(
+        results[7],
+        Err(PlasmaError::UnknownError(StatusCode::Invalid, _))
+    ));
+    assert!(matches!(
+        results[8],
+        Err(PlasmaError::UnknownError(StatusCode::Invalid, _))
+    ));
+
+    // none of the aborted objects should remain in the store, while the sealed one should
+    let listed = pc.list().unwrap();
+    assert!(listed.iter().all(|info| !oids.contains(&info.id)));
+    assert!(pc.contains(&sealed_oid).unwrap());
+    pc.delete(&sealed_oid).unwrap();
+
+    // objects which were skipped can still be completed by their owners
+    in_use_ob.seal().unwrap();
+    drop(in_use_ob);
+    assert!(pc.contains(&in_use_oid).unwrap());
+    pc.delete(&in_use_oid).unwrap();
+    foreign_ob.abort().unwrap();
+}
+
 #[test]
 #[ignore]
 fn plasma_client_create_error() {
@@ -343,9 +808,483 @@ fn plasma_client_delete_many() {
     assert_eq!(0, result.len(), "all objects should be deleted");
 }
 
+#[test]
+#[ignore]
+fn plasma_client_delete_many_batch() {
+    let pc = build_client();
+
+    // put 100 objects into the store
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let oids: Vec<ObjectId> = (0..100).map(|_| ObjectId::rand()).collect();
+    for oid in oids.iter() {
+        pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+    }
+    assert_eq!(100, pc.contains_many(&oids).unwrap().len());
+
+    // delete all of them with a single call
+    pc.delete_many(&oids).unwrap();
+
+    let result = pc.contains_many(&oids).unwrap();
+    assert_eq!(0, result.len(), "all objects should be deleted");
+}
+
+#[test]
+#[ignore]
+fn plasma_client_refresh() {
+    let pc = build_client();
+
+    // put object into the store
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+
+    // refresh the object and make sure it is still in the store
+    pc.refresh(std::slice::from_ref(&oid)).unwrap();
+    assert_eq!(
+        true,
+        pc.contains(&oid).unwrap(),
+        "object should be in the store"
+    );
+}
+
+#[test]
+#[ignore]
+fn plasma_client_list() {
+    let pc = build_client();
+
+    // put a few objects into the store
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    let object_ids = [ObjectId::rand(), ObjectId::rand(), ObjectId::rand()];
+    for oid in object_ids.iter() {
+        pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+    }
+
+    // make sure all objects are listed with correct sizes and state
+    let objects = pc.list().unwrap();
+    for oid in object_ids.iter() {
+        let info = objects
+            .iter()
+            .find(|info| info.id == *oid)
+            .expect("object should be listed");
+        assert_eq!(data.len(), info.data_size);
+        assert_eq!(meta.len(), info.metadata_size);
+        assert_eq!(ObjectState::Sealed, info.state);
+    }
+}
+
+#[test]
+#[ignore]
+fn plasma_client_object_size() {
+    let pc = build_client();
+
+    // sizes of a sealed object should be returned without getting the object
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+    assert_eq!(
+        Some((data.len(), meta.len())),
+        pc.object_size(&oid).unwrap()
+    );
+
+    // objects which have not been sealed yet, or are not in the store, have no size
+    let unsealed_oid = ObjectId::rand();
+    let ob = pc.create(unsealed_oid.clone(), 8, &meta).unwrap();
+    assert_eq!(None, pc.object_size(&unsealed_oid).unwrap());
+    drop(ob);
+    assert_eq!(None, pc.object_size(&ObjectId::rand()).unwrap());
+
+    pc.delete(&oid).unwrap();
+}
+
+#[test]
+#[ignore]
+fn plasma_object_buffer_seal_with_hash() {
+    let pc = build_client();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+
+    // the digest of a sealed object should be reported by both hash() and list()
+    let source_oid = ObjectId::rand();
+    pc.create_and_seal(source_oid.clone(), &data, &meta)
+        .unwrap();
+    let digest = pc.hash(&source_oid).unwrap();
+    let listed = pc.list().unwrap();
+    let info = listed.iter().find(|info| info.id == source_oid).unwrap();
+    assert_eq!(digest, info.digest);
+
+    // a copy of the object should be sealed with the same digest
+    let copy_oid = ObjectId::rand();
+    let mut ob = pc.create(copy_oid.clone(), data.len(), &meta).unwrap();
+    ob.data_mut().unwrap().copy_from_slice(&data);
+    ob.seal_with_hash(&digest).unwrap();
+    drop(ob);
+
+    // an object with different data should be sealed, but reported as mismatching
+    let corrupted_oid = ObjectId::rand();
+    let mut ob = pc.create(corrupted_oid.clone(), data.len(), &meta).unwrap();
+    ob.data_mut().unwrap().copy_from_slice(&[0u8; 16]);
+    assert!(matches!(
+        ob.seal_with_hash(&digest),
+        Err(PlasmaError::HashMismatch(_))
+    ));
+    drop(ob);
+
+    pc.delete_many(&[source_oid, copy_oid, corrupted_oid])
+        .unwrap();
+}
+
+#[test]
+#[ignore]
+fn plasma_client_subscribe() {
+    let pc = build_client();
+    let subscription = pc.subscribe().unwrap();
+
+    // seal an object using a different client
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    build_client()
+        .create_and_seal(oid.clone(), &data, &meta)
+        .unwrap();
+
+    // skip notifications for objects created by other tests
+    let notification = loop {
+        let notification = subscription.next().unwrap();
+        if notification.object_id == oid {
+            break notification;
+        }
+    };
+    assert_eq!(false, notification.is_deletion());
+    assert_eq!(data.len() as i64, notification.data_size);
+    assert_eq!(meta.len() as i64, notification.metadata_size);
+}
+
+#[test]
+#[ignore]
+fn plasma_client_wait_for() {
+    let pc = build_client();
+
+    // an object which never appears results in a timeout
+    let start = Instant::now();
+    assert!(!pc
+        .wait_for(&ObjectId::rand(), Duration::from_millis(100))
+        .unwrap());
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // seal an object using a different client while waiting for it
+    let oid = ObjectId::rand();
+    let sealer = {
+        let oid = oid.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            build_client()
+                .create_and_seal(oid, &[1, 2, 3, 4], &[])
+                .unwrap();
+        })
+    };
+    assert!(pc.wait_for(&oid, Duration::from_secs(5)).unwrap());
+    sealer.join().unwrap();
+
+    // an object which is already in the store is reported right away
+    assert!(pc.wait_for(&oid, Duration::from_millis(0)).unwrap());
+}
+
+#[test]
+#[ignore]
+fn plasma_client_evict() {
+    let pc = build_client();
+
+    // fill the store with objects which are not in use by any client
+    let data = vec![1u8; 1024 * 1024];
+    let num_objects = pc.store_capacity() / data.len();
+    for _ in 0..num_objects {
+        if pc.create_and_seal(ObjectId::rand(), &data, &[]).is_err() {
+            break;
+        }
+    }
+
+    // evict some of the objects and make sure space was reclaimed
+    let num_bytes_evicted = pc.evict(data.len() * 4).unwrap();
+    assert!(num_bytes_evicted > 0, "some bytes should be evicted");
+}
+
+#[test]
+#[ignore]
+fn plasma_client_clone() {
+    let pc = build_client();
+    let pc2 = pc.clone();
+
+    // put object into the store using the original client, and then drop it
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+    drop(pc);
+
+    // the clone should still be connected to the store
+    assert_eq!(
+        true,
+        pc2.contains(&oid).unwrap(),
+        "object should be in the store"
+    );
+    let ob = pc2
+        .get(oid, Some(Duration::from_millis(5)))
+        .unwrap()
+        .unwrap();
+    assert_eq!(data, ob.data(), "object data should match");
+}
+
+#[test]
+#[ignore]
+fn plasma_client_memory_usage() {
+    let pc = build_client();
+    let before = pc.memory_usage().unwrap();
+    assert_eq!(pc.store_capacity(), before.capacity_bytes);
+
+    let data = vec![1u8; 1024 * 1024];
+    let object_ids: Vec<ObjectId> = (0..4).map(|_| ObjectId::rand()).collect();
+    for oid in object_ids.iter() {
+        pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+    }
+
+    let after = pc.memory_usage().unwrap();
+    assert!(after.used_bytes >= before.used_bytes + object_ids.len() * data.len());
+    assert_eq!(before.num_objects + object_ids.len(), after.num_objects);
+
+    pc.delete_many(&object_ids).unwrap();
+}
+
+#[test]
+#[ignore]
+fn plasma_client_reconnect() {
+    // this test starts and stops its own plasma store server; this requires
+    // plasma-store-server executable to be on the PATH
+    let mut plasma_store = start_plasma_store(PLASMA_RESTART_SOCKET);
+    let mut pc = PlasmaClient::new(PLASMA_RESTART_SOCKET, 0).unwrap();
+    let data = [1u8, 2, 3, 4];
+    assert!(pc.create_and_seal(ObjectId::rand(), &data, &[]).is_ok());
+
+    // once the store is restarted, the old connection should not be usable
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+    let mut plasma_store = start_plasma_store(PLASMA_RESTART_SOCKET);
+    assert!(pc.create_and_seal(ObjectId::rand(), &data, &[]).is_err());
+
+    // after reconnecting, the client should be able to resume operations
+    pc.reconnect().unwrap();
+    let oid = ObjectId::rand();
+    assert!(pc.create_and_seal(oid.clone(), &data, &[]).is_ok());
+    assert!(pc.contains(&oid).unwrap());
+
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+}
+
+#[test]
+#[ignore]
+fn plasma_client_is_connected() {
+    // this test starts and stops its own plasma store server; this requires
+    // plasma-store-server executable to be on the PATH
+    let mut plasma_store = start_plasma_store(PLASMA_RESTART_SOCKET);
+    let mut pc = PlasmaClient::new(PLASMA_RESTART_SOCKET, 0).unwrap();
+    assert_eq!(PLASMA_RESTART_SOCKET, pc.socket_name());
+    assert!(pc.is_connected());
+
+    // once the store goes away, the client should report that it is disconnected, and keep
+    // doing so after the store is restarted
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+    assert!(!pc.is_connected());
+    let mut plasma_store = start_plasma_store(PLASMA_RESTART_SOCKET);
+    assert!(!pc.is_connected());
+
+    // after reconnecting, the client should be connected again
+    pc.reconnect().unwrap();
+    assert!(pc.is_connected());
+    assert_eq!(PLASMA_RESTART_SOCKET, pc.socket_name());
+
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+}
+
+#[test]
+#[ignore]
+fn plasma_client_drop_buffer_after_disconnect() {
+    // this test starts and stops its own plasma store server; this requires
+    // plasma-store-server executable to be on the PATH
+    let mut plasma_store = start_plasma_store(PLASMA_RESTART_SOCKET);
+    let pc = PlasmaClient::new(PLASMA_RESTART_SOCKET, 0).unwrap();
+    let oid = ObjectId::rand();
+    let data = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+    let ob = pc.get(oid, None).unwrap().unwrap();
+
+    // once the store goes away, releasing the buffer on drop fails; the failure should be
+    // logged rather than cause a panic
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(ob)));
+    assert!(result.is_ok());
+    assert!(!pc.is_connected());
+}
+
+#[cfg(feature = "cuda")]
+#[test]
+#[ignore]
+fn plasma_client_create_on_device() {
+    let pc = build_client();
+
+    // create an object in GPU memory; its data should not be accessible from CPU
+    let oid = ObjectId::rand();
+    let meta = [1u8, 2, 3, 4];
+    let mut ob = pc.create_on_device(oid.clone(), 16, &meta, 1).unwrap();
+    assert_eq!(1, ob.device_num());
+    assert_eq!(meta.len() + 16, ob.size());
+    assert!(ob.data().is_empty());
+    assert!(matches!(ob.data_mut(), Err(PlasmaError::NotOnCpu(1))));
+    ob.seal().unwrap();
+    drop(ob);
+
+    // the retrieved object should be on the same device and metadata should be on CPU
+    let ob = pc.get(oid, None).unwrap().unwrap();
+    assert_eq!(1, ob.device_num());
+    assert_eq!(&meta, ob.meta());
+}
+
+/// ASYNC CLIENT TESTS
+/// ===============================================================================================
+
+#[cfg(feature = "async")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn plasma_async_client_roundtrip() {
+    let pc = AsyncPlasmaClient::new(PLASMA_SOCKET, 0).await.unwrap();
+    let oid = ObjectId::rand();
+    let data = vec![1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = vec![9u8, 10];
+
+    pc.create_and_seal(oid.clone(), data.clone(), meta.clone())
+        .await
+        .unwrap();
+    assert!(pc.contains(&oid).await.unwrap());
+
+    let (ob_meta, ob_data) = pc
+        .get(oid.clone(), Some(Duration::from_millis(5)))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(meta, ob_meta);
+    assert_eq!(data, ob_data);
+
+    // objects can be processed in place without copying them out of the store
+    let data_size = pc
+        .get_with(oid.clone(), None, |ob| ob.map(|ob| ob.data().len()))
+        .await
+        .unwrap();
+    assert_eq!(Some(data.len()), data_size);
+
+    pc.delete(&oid).await.unwrap();
+    assert!(!pc.contains(&oid).await.unwrap());
+}
+
+/// ARROW TESTS
+/// ===============================================================================================
+
+#[cfg(feature = "arrow")]
+#[test]
+#[ignore]
+fn plasma_object_buffer_into_arrow() {
+    use arrow::{array::Int32Array, buffer::ScalarBuffer};
+
+    let pc = build_client();
+    let oid = ObjectId::rand();
+    let values = [1i32, 2, 3, 4];
+    let data: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+    pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+
+    // the array should be backed by the memory of the object in the store
+    let ob = pc.get(oid.clone(), None).unwrap().unwrap();
+    let data_ptr = ob.data().as_ptr();
+    let buffer = ob.into_arrow_buffer().unwrap();
+    assert_eq!(data_ptr, buffer.as_ptr());
+    let array = Int32Array::new(ScalarBuffer::new(buffer, 0, values.len()), None);
+    assert_eq!(&values[..], array.values().as_ref());
+
+    // the object should be released once the array is dropped
+    drop(array);
+    pc.delete(&oid).unwrap();
+    assert!(!pc.contains(&oid).unwrap());
+
+    // unsealed objects cannot be converted
+    let oid = ObjectId::rand();
+    let ob = pc.create(oid.clone(), 8, &[]).unwrap();
+    assert!(matches!(
+        ob.into_arrow_buffer(),
+        Err(PlasmaError::NotSealed)
+    ));
+}
+
+/// MEMORY USAGE TESTS
+/// ===============================================================================================
+
+#[test]
+fn plasma_parse_memory_usage() {
+    let debug = "num clients with quota: 0\nquota map size: 0\npinned quota map size: 0\n\
+        allocated bytes: 2097152\nallocation limit: 1000000000\npinned bytes: 0\n\
+        (global lru) capacity: 1000000000\n(global lru) used: 0.2097%\n\
+        (global lru) num objects: 2\n(global lru) num evictions: 0\n";
+    let usage = parse_memory_usage(debug, 1_000_000_000).unwrap();
+    assert_eq!(
+        MemoryUsage {
+            capacity_bytes: 1_000_000_000,
+            used_bytes: 2_097_152,
+            num_objects: 2,
+        },
+        usage
+    );
+
+    // strings which do not report memory usage should result in an error
+    assert!(parse_memory_usage("error sending request", 1024).is_err());
+    assert!(parse_memory_usage("allocated bytes: 1024", 1024).is_err());
+}
+
+/// TIMEOUT TESTS
+/// ===============================================================================================
+
+#[test]
+fn plasma_timeout_to_ms() {
+    assert_eq!(-1, timeout_to_ms(None));
+    assert_eq!(0, timeout_to_ms(Some(Duration::from_millis(0))));
+    assert_eq!(5, timeout_to_ms(Some(Duration::from_millis(5))));
+    assert_eq!(1500, timeout_to_ms(Some(Duration::from_secs_f64(1.5))));
+
+    // sub-millisecond durations are truncated
+    assert_eq!(1, timeout_to_ms(Some(Duration::from_micros(1999))));
+
+    // durations which do not fit into i64 milliseconds are saturated
+    assert_eq!(i64::MAX, timeout_to_ms(Some(Duration::from_secs(u64::MAX))));
+    let max_ms = Duration::from_millis(i64::MAX as u64);
+    assert_eq!(i64::MAX, timeout_to_ms(Some(max_ms)));
+    assert_eq!(
+        i64::MAX,
+        timeout_to_ms(Some(max_ms + Duration::from_millis(1)))
+    );
+}
+
 /// HELPER FUNCTIONS
 /// ===============================================================================================
 
 fn build_client() -> PlasmaClient {
     PlasmaClient::new(PLASMA_SOCKET, 0).unwrap()
 }
+
+fn start_plasma_store(socket: &str) -> Child {
+    let child = Command::new("plasma-store-server")
+        .args(["-m", "10000000", "-s", socket])
+        .spawn()
+        .unwrap();
+    // give the server some time to start listening on the socket
+    thread::sleep(Duration::from_millis(500));
+    child
+}
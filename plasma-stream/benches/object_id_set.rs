@@ -0,0 +1,67 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use plasma_stream::{ObjectId, OBJECT_ID_BYTES};
+use rustc_hash::FxHashSet;
+use std::collections::HashSet;
+
+const NUM_OBJECTS: usize = 100_000;
+
+// BENCHMARKS
+// ================================================================================================
+
+/// Compares inserting object IDs into a set using the default (SipHash) hasher against the
+/// non-cryptographic hasher used by the server for its receiving/deleting sets.
+fn insert(c: &mut Criterion) {
+    let object_ids = build_object_ids(NUM_OBJECTS);
+    let mut group = c.benchmark_group("object_id_set_insert");
+
+    group.bench_function(BenchmarkId::new("sip_hash", NUM_OBJECTS), |b| {
+        b.iter(|| {
+            let mut set = HashSet::new();
+            for oid in object_ids.iter() {
+                set.insert(*oid);
+            }
+            black_box(set)
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("fx_hash", NUM_OBJECTS), |b| {
+        b.iter(|| {
+            let mut set = FxHashSet::default();
+            for oid in object_ids.iter() {
+                set.insert(*oid);
+            }
+            black_box(set)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(object_id_set_group, insert);
+criterion_main!(object_id_set_group);
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Generates a list of pseudo-random object IDs using a xorshift generator; object IDs are
+/// expected to be random, so this mimics the distribution of IDs seen by the server.
+fn build_object_ids(n: usize) -> Vec<ObjectId> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..n)
+        .map(|_| {
+            let mut oid = [0u8; OBJECT_ID_BYTES];
+            for chunk in oid.chunks_mut(8) {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                chunk.copy_from_slice(&state.to_le_bytes()[..chunk.len()]);
+            }
+            oid
+        })
+        .collect()
+}
@@ -3,8 +3,10 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
-use plasma_stream::{Client, ObjectId, PeerRequest};
-use std::{convert::TryInto, io::prelude::*, net::SocketAddr, time::Instant};
+use plasma_stream::{
+    handshake::AuthToken, Client, ConnectOptions, ObjectId, PeerAddr, PeerRequest,
+};
+use std::{convert::TryInto, io::prelude::*, time::Instant};
 use structopt::StructOpt;
 
 // COMMAND LINE ARGUMENTS
@@ -16,6 +18,11 @@ pub struct ClientOptions {
     /// Address of the Plasma Stream server
     #[structopt(short, long)]
     address: String,
+
+    /// Token (64 hexadecimal characters) to authenticate with; required only if the server is
+    /// configured with a token
+    #[structopt(long, env = "PLASMA_STREAM_AUTH_TOKEN", hide_env_values = true)]
+    auth_token: Option<AuthToken>,
 }
 
 // PROGRAM ENTRY POINT
@@ -28,7 +35,11 @@ pub async fn main() -> plasma_stream::Result<()> {
     let address = options.address;
 
     // connect to the server
-    let mut client = Client::connect(address.clone()).await?;
+    let connect_options = ConnectOptions {
+        auth_token: options.auth_token,
+        ..ConnectOptions::default()
+    };
+    let mut client = Client::connect_with_options(address.clone(), connect_options).await?;
     println!("connected to {}", address);
 
     // read line from command line, convert it to a SYNC request, and execute it
@@ -56,13 +67,12 @@ fn parse_request(line: String) -> Result<Vec<PeerRequest>, String> {
 
     if tokens.len() < 3 {
         return Err(String::from(
-            "invalid request; must be [COPY|TAKE] [server address] [object ID list]",
+            "invalid request; must be [COPY|TAKE|PUSH] [server address] [object ID list]",
         ));
     }
 
     let req_type = tokens[0].to_string();
-    let address: SocketAddr = tokens[1]
-        .to_string()
+    let address: PeerAddr = tokens[1]
         .parse()
         .map_err(|err| format!("server address {} is invalid: {}", tokens[1], err))?;
 
@@ -84,7 +94,11 @@ fn parse_request(line: String) -> Result<Vec<PeerRequest>, String> {
             from: address,
             objects: object_ids,
         },
-        _ => return Err(String::from("requests must start with either COPY or TAKE")),
+        "push" | "PUSH" => PeerRequest::Push {
+            to: address,
+            objects: object_ids,
+        },
+        _ => return Err(String::from("requests must start with COPY, TAKE, or PUSH")),
     };
 
     Ok(vec![peer_req])
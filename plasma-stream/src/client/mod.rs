@@ -5,45 +5,593 @@
 
 use crate::{
     errors::{ClientError, PeerResult},
-    ObjectId, PeerRequest, Request,
+    handshake::{self, AuthToken, Capabilities},
+    status_codes,
+    utils::{
+        from_bitmap, object_checksum, read_data_chunked, read_data_compressed, read_object_header,
+        read_object_ids,
+    },
+    Codec, ManifestEntry, ObjectId, PeerAddr, PeerRequest, Request, TransferEstimate,
+    TransferManifest, MAX_DATA_SIZE,
+};
+use futures_util::stream::{self, Stream};
+use std::{
+    io::{Error, ErrorKind},
+    net::SocketAddr,
+    path::Path,
+    time::Duration,
 };
 use tokio::{
-    io::AsyncReadExt,
-    net::{TcpStream, ToSocketAddrs},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite},
+    net::{TcpStream, ToSocketAddrs, UnixStream},
+    time,
 };
 
+#[cfg(test)]
+mod tests;
+
+// CONSTANTS
+// ================================================================================================
+
+/// Maximum time allowed for connecting to a server and completing the protocol handshake,
+/// unless a different timeout is specified; this keeps clients from blocking until the OS
+/// gives up on connections to unreachable hosts.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of objects requested from the server by a single TAKE request sent while
+/// draining the server via `Client::drain()`.
+pub const DRAIN_BATCH_SIZE: usize = 64;
+
 // CLIENT
 // ================================================================================================
 
-pub struct Client {
-    socket: TcpStream,
+/// Client for a Plasma Stream server. By default, the client talks to the server over a TCP
+/// connection; servers running on the same host can be reached over a Unix socket via
+/// `Client::connect_unix()`, and any other stream implementing `AsyncRead` and `AsyncWrite`
+/// can be used via `Client::from_stream()`.
+pub struct Client<S = TcpStream> {
+    socket: S,
+    capabilities: Capabilities,
+
+    /// Set when a request timed out or was abandoned part way through; the rest of the response
+    /// may still arrive at any time, so the connection cannot be used for further requests.
+    poisoned: bool,
+
+    /// Maximum number of bytes of received object data and metadata held by the client before
+    /// they are handed over to the caller; `None` means no limit.
+    max_in_flight_bytes: Option<usize>,
+
+    /// Maximum time to wait for a response to a SYNC request sent via `sync()`; `None` means
+    /// waiting for as long as it takes.
+    sync_timeout: Option<Duration>,
+
+    /// Address of the server together with the builder used to connect to it; this is used to
+    /// re-establish the connection when retrying requests. `None` for clients created via
+    /// `from_stream()` and `connect_unix()`, which cannot reconnect.
+    origin: Option<(SocketAddr, ClientBuilder)>,
+}
+
+/// Options which control optional protocol features requested by the client; a feature is
+/// enabled only if the server supports it as well. The options also limit how much memory the
+/// client uses for received objects.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// Request object data to be compressed by the server; this reduces the amount of data
+    /// sent over the network for compressible objects at the expense of CPU time. Data of
+    /// objects whose metadata carries a codec hint (see `CODEC_HINT_PREFIX`) is encoded as
+    /// hinted; e.g. objects holding already compressed data can be sent as is.
+    pub compression: bool,
+
+    /// Token to authenticate with; required only if the server is configured with a token.
+    pub auth_token: Option<AuthToken>,
+
+    /// Maximum number of bytes of received object data and metadata the client holds before
+    /// handing them over to the caller. `copy()` and `take()` hold all requested objects, while
+    /// `copy_stream()` and `drain()` hold a single object at a time and stop reading from the
+    /// connection until the caller asks for the next object. Once receiving the next object
+    /// would exceed the cap, the request fails before the object is read, and the client
+    /// cannot be used for further requests. `None` means no limit.
+    pub max_in_flight_bytes: Option<usize>,
+
+    /// Request the server to send a manifest of the objects (object IDs, per-object SHA-256
+    /// hashes, and the total size) before the objects of every COPY and TAKE response. Once
+    /// all objects of a response have been received, they are verified against the manifest,
+    /// and any discrepancies are reported via `ClientError::ManifestMismatch`; streams returned
+    /// by `copy_stream()` and `drain()` yield the error after the last object of the response.
+    pub verify_manifest: bool,
+}
+
+// RETRY POLICY
+// ================================================================================================
+
+/// Policy for retrying SYNC requests sent via `Client::sync_with_retry()` which fail because of
+/// connection errors. The delay before the first retry is `base_delay`, and it is multiplied by
+/// `multiplier` before every subsequent retry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Time to wait before the first retry.
+    pub base_delay: Duration,
+    /// Factor by which the delay grows after every retry.
+    pub multiplier: f64,
+    /// Maximum number of attempts, including the first one; values below 2 disable retries.
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    /// Returns a policy which makes up to 3 attempts, waiting 100 ms before the first retry and
+    /// 200 ms before the second one.
+    fn default() -> Self {
+        RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_attempts: 3,
+        }
+    }
+}
+
+// REPLICATION COUNT
+// ================================================================================================
+
+/// Outcome of checking which of a set of Plasma Stream servers hold an object via
+/// `Client::replication_count()`.
+#[derive(Debug)]
+pub struct ReplicationCount<T> {
+    /// Number of servers which hold the object.
+    pub holding: usize,
+    /// Servers which could not be asked whether they hold the object, together with the reason;
+    /// these are not counted as either holding or not holding the object.
+    pub unreachable: Vec<(T, ClientError)>,
+}
+
+// CLIENT BUILDER
+// ================================================================================================
+
+/// Builder for a client connected to a Plasma Stream server over TCP; in addition to the
+/// protocol features controlled by `ConnectOptions`, the builder configures the underlying
+/// TCP connection and timeouts applied by the client.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    options: ConnectOptions,
+    nodelay: bool,
+    connect_timeout: Option<Duration>,
+    sync_timeout: Option<Duration>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        ClientBuilder {
+            options: ConnectOptions::default(),
+            nodelay: true,
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            sync_timeout: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// Returns a new builder with default options: TCP_NODELAY is set, connecting times out
+    /// after `DEFAULT_CONNECT_TIMEOUT`, and SYNC requests do not time out.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of the TCP_NODELAY option on the connection; when set (the default),
+    /// small requests are sent immediately rather than being buffered.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Sets the maximum time allowed for establishing the connection and completing the
+    /// protocol handshake.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum time to wait for a response to SYNC requests sent via `sync()`; see
+    /// `Client::sync_with_timeout()` for how timed out requests affect the client.
+    pub fn sync_timeout(mut self, timeout: Duration) -> Self {
+        self.sync_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the token to authenticate with; required only if the server is configured with a
+    /// token.
+    pub fn auth_token(mut self, token: AuthToken) -> Self {
+        self.options.auth_token = Some(token);
+        self
+    }
+
+    /// Sets whether object data should be compressed by the server.
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.options.compression = compression;
+        self
+    }
+
+    /// Sets whether received objects should be verified against a manifest sent by the server;
+    /// see `ConnectOptions::verify_manifest` for details.
+    pub fn verify_manifest(mut self, verify_manifest: bool) -> Self {
+        self.options.verify_manifest = verify_manifest;
+        self
+    }
+
+    /// Sets the maximum number of bytes of received objects the client holds in memory; see
+    /// `ConnectOptions::max_in_flight_bytes` for details.
+    pub fn max_in_flight_bytes(mut self, max_bytes: usize) -> Self {
+        self.options.max_in_flight_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Connects to the Plasma Stream server at the specified address using options of this
+    /// builder. Returns an error if the connection is not established within the connect
+    /// timeout, or for any of the reasons listed in `Client::connect_with_options()`.
+    pub async fn connect<T: ToSocketAddrs>(self, address: T) -> Result<Client, ClientError> {
+        let connect_timeout = self.connect_timeout;
+        let connect = async move {
+            let socket = TcpStream::connect(address).await.map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to connect"), err)
+            })?;
+            socket.set_nodelay(self.nodelay).map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to set TCP_NODELAY"), err)
+            })?;
+            let server_address = socket.peer_addr().map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to get server address"), err)
+            })?;
+            let mut client = Client::from_stream(socket, self.options.clone()).await?;
+            client.sync_timeout = self.sync_timeout;
+            client.origin = Some((server_address, self));
+            Ok(client)
+        };
+
+        match connect_timeout {
+            Some(timeout) => time::timeout(timeout, connect)
+                .await
+                .unwrap_or_else(|_| Err(connect_timed_out(timeout))),
+            None => connect.await,
+        }
+    }
+
+    /// Counts how many of the specified Plasma Stream servers hold the object with the
+    /// specified ID; see `Client::replication_count()` for details. Connections to the servers
+    /// are established using options of this builder.
+    pub async fn replication_count<T: ToSocketAddrs + Clone>(
+        &self,
+        oid: ObjectId,
+        peers: &[T],
+    ) -> ReplicationCount<T> {
+        let mut result = ReplicationCount {
+            holding: 0,
+            unreachable: Vec::new(),
+        };
+        for peer in peers {
+            let contains = match self.clone().connect(peer.clone()).await {
+                Ok(mut client) => client.contains(&[oid]).await,
+                Err(err) => Err(err),
+            };
+            match contains {
+                Ok(flags) if flags[0] => result.holding += 1,
+                Ok(_) => (),
+                Err(err) => result.unreachable.push((peer.clone(), err)),
+            }
+        }
+        result
+    }
 }
 
 impl Client {
-    /// Connects to the Plasma Stream server at the specified address.
-    pub async fn connect<T: ToSocketAddrs>(address: T) -> Result<Self, std::io::Error> {
-        let socket = TcpStream::connect(address).await?;
-        let client = Client { socket };
+    /// Returns a builder for configuring the connection to a Plasma Stream server.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Connects to the Plasma Stream server at the specified address using default options.
+    /// Returns an error if the server speaks a different version of the protocol, or if the
+    /// connection is not established within `DEFAULT_CONNECT_TIMEOUT`.
+    pub async fn connect<T: ToSocketAddrs>(address: T) -> Result<Self, ClientError> {
+        Self::connect_with_options(address, ConnectOptions::default()).await
+    }
+
+    /// Connects to the Plasma Stream server at the specified address; returns an error of kind
+    /// `ErrorKind::TimedOut` if the connection is not established and the protocol handshake
+    /// is not completed within the specified timeout. `connect()` does the same with
+    /// `DEFAULT_CONNECT_TIMEOUT`.
+    pub async fn connect_timeout<T: ToSocketAddrs>(
+        address: T,
+        timeout: Duration,
+    ) -> Result<Self, ClientError> {
+        ClientBuilder::default()
+            .connect_timeout(timeout)
+            .connect(address)
+            .await
+    }
+
+    /// Connects to the Plasma Stream server at the specified address and requests optional
+    /// protocol features as specified by `options`. Object checksums are always requested,
+    /// and TCP_NODELAY is set on the connection; use `Client::builder()` to change this.
+    /// Returns an error if the server speaks a different version of the protocol, or if the
+    /// server rejects the authentication token.
+    pub async fn connect_with_options<T: ToSocketAddrs>(
+        address: T,
+        options: ConnectOptions,
+    ) -> Result<Self, ClientError> {
+        ClientBuilder {
+            options,
+            ..ClientBuilder::default()
+        }
+        .connect(address)
+        .await
+    }
+
+    /// Counts how many of the specified Plasma Stream servers hold the object with the
+    /// specified ID; this can be used to monitor whether an object is replicated widely enough.
+    /// Every server is asked via a CONTAINS request over a new connection established using
+    /// default options; servers are asked one at a time, and each of them is given up on after
+    /// `DEFAULT_CONNECT_TIMEOUT`. Servers which cannot be reached, or which fail to answer the
+    /// request, are reported separately rather than being counted as not holding the object.
+    pub async fn replication_count<T: ToSocketAddrs + Clone>(
+        oid: ObjectId,
+        peers: &[T],
+    ) -> ReplicationCount<T> {
+        ClientBuilder::default().replication_count(oid, peers).await
+    }
+
+    /// Same as `sync()`, but if the request fails because of a connection error (e.g. the
+    /// server was briefly unavailable), the connection is re-established and the whole request
+    /// is retried according to the specified policy. Errors which reflect a definitive outcome
+    /// of the request, such as a malformed request or failed peer requests, are returned
+    /// without retrying.
+    ///
+    /// Peer requests which completed before the connection was lost may fail when retried
+    /// (e.g. because the objects are already in the store of the server); such failures are
+    /// reported as usual. Clients created via `from_stream()` cannot reconnect, and thus, the
+    /// request is attempted only once for them.
+    pub async fn sync_with_retry(
+        &mut self,
+        requests: Vec<PeerRequest>,
+        policy: RetryPolicy,
+    ) -> Result<(), ClientError> {
+        let mut delay = policy.base_delay;
+        let mut attempt = 1;
+        let mut result = self.sync(requests.clone()).await;
+        while attempt < policy.max_attempts && self.origin.is_some() {
+            if !matches!(result, Err(ClientError::ConnectionError(..))) {
+                break;
+            }
+            time::sleep(delay).await;
+            delay = delay.mul_f64(policy.multiplier);
+            attempt += 1;
+
+            // failing to reconnect counts as a failed attempt
+            result = match self.reconnect().await {
+                Ok(()) => self.sync(requests.clone()).await,
+                Err(err) => Err(err),
+            };
+        }
+        result
+    }
+
+    /// Replaces the connection to the server with a new one, established in the same way as
+    /// the original connection; this also clears the effects of interrupted requests.
+    async fn reconnect(&mut self) -> Result<(), ClientError> {
+        if let Some((address, builder)) = self.origin.clone() {
+            *self = builder.connect(address).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Client<UnixStream> {
+    /// Connects to the Plasma Stream server listening on the Unix socket at the specified path
+    /// using default options; this is intended for servers running on the same host. Returns an
+    /// error if the server speaks a different version of the protocol, or if the connection is
+    /// not established within `DEFAULT_CONNECT_TIMEOUT`.
+    pub async fn connect_unix<P: AsRef<Path>>(path: P) -> Result<Self, ClientError> {
+        let connect = async move {
+            let socket = UnixStream::connect(path).await.map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to connect"), err)
+            })?;
+            Client::from_stream(socket, ConnectOptions::default()).await
+        };
+        time::timeout(DEFAULT_CONNECT_TIMEOUT, connect)
+            .await
+            .unwrap_or_else(|_| Err(connect_timed_out(DEFAULT_CONNECT_TIMEOUT)))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Client<S> {
+    /// Performs the protocol handshake over an already established stream to the Plasma Stream
+    /// server, and requests optional protocol features as specified by `options`. Returns an
+    /// error if the server speaks a different version of the protocol, or if the server
+    /// rejects the authentication token.
+    pub async fn from_stream(mut socket: S, options: ConnectOptions) -> Result<Self, ClientError> {
+        let mut requested = Capabilities::CHECKSUMS;
+        if options.compression {
+            requested |= Capabilities::COMPRESSION | Capabilities::CODECS;
+        }
+        if options.verify_manifest {
+            requested |= Capabilities::MANIFEST;
+        }
+        let requested = Capabilities::from_bits(requested);
+        let capabilities = handshake::initiate(&mut socket, requested, options.auth_token.as_ref())
+            .await
+            .map_err(ClientError::HandshakeError)?;
+        let client = Client {
+            socket,
+            capabilities,
+            poisoned: false,
+            max_in_flight_bytes: options.max_in_flight_bytes,
+            sync_timeout: None,
+            origin: None,
+        };
         Ok(client)
     }
 
-    /// Retrieves objects with the specified IDs from the remote plasma store.
-    pub fn copy(&self, _object_ids: &[ObjectId]) {
-        // TODO: implement
-        unimplemented!("not yet implemented");
+    /// Retrieves objects with the specified IDs from the Plasma Stream server. Metadata and
+    /// data of the retrieved objects are returned in the same order as the objects were
+    /// requested.
+    pub async fn copy(
+        &mut self,
+        object_ids: &[ObjectId],
+    ) -> Result<Vec<(ObjectId, Vec<u8>, Vec<u8>)>, ClientError> {
+        self.ensure_not_poisoned()?;
+        let request = Request::Copy(object_ids.to_vec());
+        self.receive_objects(request, object_ids).await
     }
 
-    /// Retrieves objects with the specified IDs from Plasma Stream server. The retrieved
-    /// objects are deleted from the remote plasma store.
-    pub fn take(&self, _object_ids: &[ObjectId]) {
-        // TODO: implement
-        unimplemented!("not yet implemented");
+    /// Same as `copy()`, but the retrieved objects are yielded one by one as they arrive, in
+    /// the same order as they were requested. The next object is read from the connection only
+    /// once the caller asks for it, so only a single object is held in memory at a time, and the
+    /// server is slowed down to the pace at which the objects are consumed.
+    ///
+    /// The stream ends after the first error. If the stream is dropped before it ends, the
+    /// client cannot be used for further requests.
+    pub fn copy_stream(
+        &mut self,
+        object_ids: &[ObjectId],
+    ) -> impl Stream<Item = Result<(ObjectId, Vec<u8>, Vec<u8>), ClientError>> + '_ {
+        let state = ObjectStream {
+            client: self,
+            object_ids: Some(object_ids.to_vec()),
+            take: false,
+            next: 0,
+            batch_end: 0,
+            done: false,
+            manifest: None,
+        };
+        stream::unfold(state, |mut state| async move {
+            let object = state.next_object().await?;
+            Some((object, state))
+        })
     }
 
-    /// Instructs the Plasma Stream server to execute the specified requests.
-    pub async fn sync(&mut self, requests: Vec<PeerRequest>) -> Result<(), ClientError> {
-        let num_requests = requests.len();
-        let request = Request::Sync(requests);
+    /// Retrieves objects with the specified IDs from the Plasma Stream server. The retrieved
+    /// objects are deleted from the remote plasma store. Metadata and data of the retrieved
+    /// objects are returned in the same order as the objects were requested.
+    pub async fn take(
+        &mut self,
+        object_ids: &[ObjectId],
+    ) -> Result<Vec<(ObjectId, Vec<u8>, Vec<u8>)>, ClientError> {
+        self.ensure_not_poisoned()?;
+        let request = Request::Take(object_ids.to_vec());
+        self.receive_objects(request, object_ids).await
+    }
+
+    /// Retrieves all objects held in the plasma store of the Plasma Stream server and deletes
+    /// them from the remote plasma store. Objects are yielded one by one as they arrive, together
+    /// with their metadata and data.
+    ///
+    /// IDs of the objects are obtained via a LIST request, and the objects are then retrieved via
+    /// TAKE requests for at most `DRAIN_BATCH_SIZE` objects at a time; so, only a single object
+    /// is held in memory at a time regardless of the number of objects in the store. Objects
+    /// sealed on the server after the LIST request was processed are not retrieved.
+    ///
+    /// Before every TAKE request, the server is asked via a CONTAINS request which objects of
+    /// the batch it still holds; objects deleted from the server since the LIST request (e.g.
+    /// because they expired) are skipped. An object deleted between the CONTAINS request and
+    /// the TAKE request still fails the batch; the stream then ends with an `ObjectsNotFound`
+    /// error, as the server closes the connection after a failed request.
+    ///
+    /// The stream ends after the first error. If the stream is dropped before it ends, objects
+    /// remaining in the current batch are still deleted from the remote store, and the client
+    /// cannot be used for further requests.
+    pub fn drain(
+        &mut self,
+    ) -> impl Stream<Item = Result<(ObjectId, Vec<u8>, Vec<u8>), ClientError>> + '_ {
+        let state = ObjectStream {
+            client: self,
+            object_ids: None,
+            take: true,
+            next: 0,
+            batch_end: 0,
+            done: false,
+            manifest: None,
+        };
+        stream::unfold(state, |mut state| async move {
+            let object = state.next_object().await?;
+            Some((object, state))
+        })
+    }
+
+    /// Returns an estimate of what retrieving objects with the specified IDs via `copy()` or
+    /// `take()` would transfer, without transferring any object data. Objects which are not
+    /// held by the server, or which exceed its object size limits, are listed in the estimate
+    /// rather than reported as errors.
+    pub async fn estimate(
+        &mut self,
+        object_ids: &[ObjectId],
+    ) -> Result<TransferEstimate, ClientError> {
+        self.ensure_not_poisoned()?;
+        self.begin_transfer(Request::Estimate(object_ids.to_vec()))
+            .await?;
+        TransferEstimate::read_from(&mut self.socket)
+            .await
+            .map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to read transfer estimate"), err)
+            })
+    }
+
+    /// Returns IDs of all sealed objects held in the plasma store of the Plasma Stream server.
+    pub async fn list(&mut self) -> Result<Vec<ObjectId>, ClientError> {
+        self.ensure_not_poisoned()?;
+
+        // send the request
+        Request::List
+            .write_into(&mut self.socket)
+            .await
+            .map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to send a request"), err)
+            })?;
+
+        // read the first byte of the response; BEGIN indicates the the server is about to
+        // send the list of object IDs
+        let status = self.socket.read_u8().await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to get a response"), err)
+        })?;
+        if status != status_codes::BEGIN {
+            return Err(ClientError::TransferError(PeerResult::from(status)));
+        }
+
+        read_object_ids(&mut self.socket).await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to read object IDs"), err)
+        })
+    }
+
+    /// Checks which of the specified objects are held in the plasma store of the Plasma Stream
+    /// server without transferring any object data. The returned flags are in the same order
+    /// as the objects were requested.
+    pub async fn contains(&mut self, object_ids: &[ObjectId]) -> Result<Vec<bool>, ClientError> {
+        self.ensure_not_poisoned()?;
+        let request = Request::Contains(object_ids.to_vec());
+        request.validate().map_err(ClientError::MalformedRequest)?;
+
+        // send the request
+        request.write_into(&mut self.socket).await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to send a request"), err)
+        })?;
+
+        // read the first byte of the response; BEGIN indicates the the server is about to
+        // send the bitmap with one bit per requested object
+        let status = self.socket.read_u8().await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to get a response"), err)
+        })?;
+        if status != status_codes::BEGIN {
+            return Err(ClientError::TransferError(PeerResult::from(status)));
+        }
+
+        let mut bitmap = vec![0u8; object_ids.len().div_ceil(8)];
+        self.socket.read_exact(&mut bitmap).await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to get a response"), err)
+        })?;
+        Ok(from_bitmap(&bitmap, object_ids.len()))
+    }
+
+    /// Instructs the Plasma Stream server to delete objects with the specified IDs from its
+    /// plasma store without transferring them. Deletion is best-effort: objects which are in use
+    /// by other clients of the remote plasma store may not be deleted.
+    pub async fn delete(&mut self, object_ids: &[ObjectId]) -> Result<(), ClientError> {
+        self.ensure_not_poisoned()?;
+        let request = Request::Delete(object_ids.to_vec());
         request.validate().map_err(ClientError::MalformedRequest)?;
 
         // send the request
@@ -51,30 +599,442 @@ impl Client {
             ClientError::ConnectionError(String::from("failed to send a request"), err)
         })?;
 
-        // read the response; there should be exactly one byte returned for every
-        // peer request sent
-        let mut response = vec![0u8; num_requests];
-        self.socket.read_exact(&mut response).await.map_err(|err| {
+        // read the response; a single status byte is returned for the entire request
+        let status = self.socket.read_u8().await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to get a response"), err)
+        })?;
+        match PeerResult::from(status) {
+            PeerResult::Ok => Ok(()),
+            result => Err(ClientError::DeleteError(result)),
+        }
+    }
+
+    /// Checks whether the Plasma Stream server is alive and its connection to the plasma store
+    /// is healthy. Returns an error if the server reports that the plasma store is unavailable.
+    pub async fn ping(&mut self) -> Result<(), ClientError> {
+        self.ensure_not_poisoned()?;
+
+        // send the request
+        Request::Ping
+            .write_into(&mut self.socket)
+            .await
+            .map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to send a request"), err)
+            })?;
+
+        // read the response; a single status byte is returned
+        let status = self.socket.read_u8().await.map_err(|err| {
             ClientError::ConnectionError(String::from("failed to get a response"), err)
         })?;
+        match PeerResult::from(status) {
+            PeerResult::Ok => Ok(()),
+            result => Err(ClientError::PingError(result)),
+        }
+    }
+
+    /// Instructs the Plasma Stream server to execute the specified requests. Unless the client
+    /// was built with a SYNC timeout, this waits for the server to respond for as long as it
+    /// takes; use `sync_with_timeout()` to limit the wait time for a single request.
+    pub async fn sync(&mut self, requests: Vec<PeerRequest>) -> Result<(), ClientError> {
+        let peers = get_peers(&requests);
+        let timeout = self.sync_timeout;
+        self.execute_sync(Request::Sync(requests), peers, timeout)
+            .await
+    }
+
+    /// Same as `sync()`, but the same object may be listed under several peer requests. Such an
+    /// object is received only once: from the first peer listing it, or, if that fails, from
+    /// the next peer listing it, and so on. A peer request is reported as successful if all of
+    /// its objects were received, even if some of them were received from other peers.
+    pub async fn sync_first_wins(&mut self, requests: Vec<PeerRequest>) -> Result<(), ClientError> {
+        let peers = get_peers(&requests);
+        let timeout = self.sync_timeout;
+        self.execute_sync(Request::SyncFirstWins(requests), peers, timeout)
+            .await
+    }
+
+    /// Instructs the Plasma Stream server to execute the specified requests; returns an error
+    /// if sending the request and receiving the response takes longer than `timeout`.
+    ///
+    /// If the request times out, the response may still arrive later; so, the connection is
+    /// considered unusable and all subsequent requests made via this client will fail.
+    pub async fn sync_with_timeout(
+        &mut self,
+        requests: Vec<PeerRequest>,
+        timeout: Duration,
+    ) -> Result<(), ClientError> {
+        let peers = get_peers(&requests);
+        self.execute_sync(Request::Sync(requests), peers, Some(timeout))
+            .await
+    }
+
+    /// Instructs the Plasma Stream server to open connections to the specified peers ahead of
+    /// latency-sensitive SYNC requests; peer requests of subsequent SYNC requests reuse these
+    /// connections instead of connecting and performing the handshake. Connections are kept
+    /// only if the server keeps a pool of idle connections to peers (see `--peer-pool-size`),
+    /// and only for as long as the server keeps idle connections. If any of the peers cannot
+    /// be connected to, the returned `ClientError::SyncError` pairs the result for every peer
+    /// with its address.
+    pub async fn prewarm(&mut self, peers: Vec<PeerAddr>) -> Result<(), ClientError> {
+        let timeout = self.sync_timeout;
+        self.execute_sync(Request::Prewarm(peers.clone()), peers, timeout)
+            .await
+    }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+
+    /// Returns an error if a previous request on this connection timed out or was abandoned.
+    fn ensure_not_poisoned(&self) -> Result<(), ClientError> {
+        if self.poisoned {
+            return Err(ClientError::ConnectionError(
+                String::from("connection is unusable after an interrupted request"),
+                Error::from(ErrorKind::NotConnected),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sends the SYNC (or PREWARM) request to the server and reads the response; `peers` must
+    /// contain the peer address of every peer request in the SYNC request, in the same order.
+    /// If `timeout` is specified and the response is not received in time, the connection is
+    /// marked as poisoned.
+    async fn execute_sync(
+        &mut self,
+        request: Request,
+        peers: Vec<PeerAddr>,
+        timeout: Option<Duration>,
+    ) -> Result<(), ClientError> {
+        self.ensure_not_poisoned()?;
+
+        request.validate().map_err(ClientError::MalformedRequest)?;
+
+        let num_requests = peers.len();
+        let socket = &mut self.socket;
+        let exchange = async move {
+            // send the request
+            request.write_into(socket).await.map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to send a request"), err)
+            })?;
+
+            // read the response; there should be exactly one byte returned for every
+            // peer request sent
+            let mut response = vec![0u8; num_requests];
+            socket.read_exact(&mut response).await.map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to get a response"), err)
+            })?;
+            Ok(response)
+        };
+
+        let response = match timeout {
+            Some(timeout) => match time::timeout(timeout, exchange).await {
+                Ok(result) => result?,
+                Err(_) => {
+                    // a partially read response is discarded together with the connection
+                    self.poisoned = true;
+                    return Err(ClientError::ConnectionError(
+                        format!("no response received within {:?}", timeout),
+                        Error::from(ErrorKind::TimedOut),
+                    ));
+                }
+            },
+            None => exchange.await?,
+        };
 
         // check if the response contains any errors
-        parse_sync_response(&response)
+        parse_sync_response(peers, &response)
+    }
+
+    /// Sends the specified COPY or TAKE request to the server and reads the objects streamed
+    /// back by the server. `object_ids` must be the IDs of objects in the request.
+    async fn receive_objects(
+        &mut self,
+        request: Request,
+        object_ids: &[ObjectId],
+    ) -> Result<Vec<(ObjectId, Vec<u8>, Vec<u8>)>, ClientError> {
+        self.begin_transfer(request).await?;
+        let manifest = self.read_manifest().await?;
+
+        // receive objects one-by-one; objects are sent in the same order as they were requested
+        // and all of them are held in memory, so they all count towards the in-flight cap
+        let mut objects = Vec::with_capacity(object_ids.len());
+        let mut in_flight = 0;
+        for oid in object_ids {
+            let budget = self.max_in_flight_bytes.map(|max| max - in_flight);
+            let (meta, data) = self.receive_object(budget).await?;
+            in_flight += meta.len() + data.len();
+            objects.push((*oid, meta, data));
+        }
+
+        if let Some(manifest) = manifest {
+            let received: Vec<ManifestEntry> = objects
+                .iter()
+                .map(|(oid, meta, data)| ManifestEntry::new(*oid, meta, data))
+                .collect();
+            verify_manifest(&manifest, &received)?;
+        }
+
+        Ok(objects)
+    }
+
+    /// Sends the specified COPY, TAKE, or ESTIMATE request to the server and reads the first
+    /// byte of the response; once this returns successfully, the requested objects (or the
+    /// estimate) can be read from the socket.
+    async fn begin_transfer(&mut self, request: Request) -> Result<(), ClientError> {
+        request.validate().map_err(ClientError::MalformedRequest)?;
+
+        // send the request
+        request.write_into(&mut self.socket).await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to send a request"), err)
+        })?;
+
+        // read the first byte of the response; BEGIN indicates the the server is about to start
+        // sending objects; otherwise, there was some kind of error on the server side and
+        // nothing will be sent
+        let status = self.socket.read_u8().await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to get a response"), err)
+        })?;
+        if status != status_codes::BEGIN {
+            return Err(ClientError::TransferError(PeerResult::from(status)));
+        }
+
+        Ok(())
+    }
+
+    /// Reads the manifest of a COPY or TAKE response from the socket if manifests were
+    /// negotiated for the connection; this must be called right after `begin_transfer()`.
+    async fn read_manifest(&mut self) -> Result<Option<TransferManifest>, ClientError> {
+        if !self.capabilities.manifest() {
+            return Ok(None);
+        }
+        let manifest = TransferManifest::read_from(&mut self.socket)
+            .await
+            .map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to read the manifest"), err)
+            })?;
+        Ok(Some(manifest))
+    }
+
+    /// Reads a single object from the socket and returns its metadata and data. If checksums
+    /// were negotiated for the connection, the object is verified against the checksum sent
+    /// in its header; if the object data was compressed, it is decompressed.
+    ///
+    /// If `budget` is set and the object is larger than the budget, the object is not read,
+    /// and the connection is marked as poisoned.
+    async fn receive_object(
+        &mut self,
+        budget: Option<usize>,
+    ) -> Result<(Vec<u8>, Vec<u8>), ClientError> {
+        let (meta_size, data_size) = read_object_header(&mut self.socket).await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to read object header"), err)
+        })?;
+        let codec = Codec::read_from(&mut self.socket, self.capabilities)
+            .await
+            .map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to read object header"), err)
+            })?;
+        let checksum = if self.capabilities.checksums() {
+            let checksum = self.socket.read_u32_le().await.map_err(|err| {
+                ClientError::ConnectionError(String::from("failed to read object header"), err)
+            })?;
+            Some(checksum)
+        } else {
+            None
+        };
+
+        // make sure we don't allocate more memory than the protocol allows
+        if data_size as u64 > MAX_DATA_SIZE {
+            return Err(ClientError::TransferError(PeerResult::ObjectDataTooLarge));
+        }
+        if budget.is_some_and(|budget| meta_size + data_size > budget) {
+            // the rest of the object is left unread, so the connection cannot be used anymore
+            self.poisoned = true;
+            return Err(ClientError::InFlightLimitExceeded(
+                self.max_in_flight_bytes.unwrap_or_default(),
+            ));
+        }
+
+        let mut meta = vec![0u8; meta_size];
+        let mut data = vec![0u8; data_size];
+        self.socket.read_exact(&mut meta).await.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to read object metadata"), err)
+        })?;
+        let read_data = if codec == Codec::Lz4 {
+            read_data_compressed(&mut self.socket, &mut data).await
+        } else {
+            read_data_chunked(&mut self.socket, &mut data).await
+        };
+        read_data.map_err(|err| {
+            ClientError::ConnectionError(String::from("failed to read object data"), err)
+        })?;
+
+        if let Some(checksum) = checksum {
+            if object_checksum(&meta, &data) != checksum {
+                return Err(ClientError::TransferError(PeerResult::ChecksumMismatch));
+            }
+        }
+
+        Ok((meta, data))
+    }
+}
+
+// OBJECT STREAM STATE
+// ================================================================================================
+
+/// State of a stream returned by `Client::copy_stream()` or `Client::drain()`. Objects are
+/// requested in batches, and are read from the socket one at a time.
+struct ObjectStream<'a, S> {
+    client: &'a mut Client<S>,
+    /// IDs of objects to retrieve; when draining the server, this is `None` until the LIST
+    /// request has been executed.
+    object_ids: Option<Vec<ObjectId>>,
+    /// If set, objects are retrieved via TAKE requests for at most `DRAIN_BATCH_SIZE` objects
+    /// each; otherwise, all objects are retrieved via a single COPY request.
+    take: bool,
+    /// Index of the next object to be read from the socket.
+    next: usize,
+    /// Index one past the last object requested by the current request.
+    batch_end: usize,
+    /// Set once all objects have been retrieved or an error has been returned.
+    done: bool,
+    /// Manifest of the current request, if manifests were negotiated for the connection,
+    /// together with entries describing the objects of the request read so far; objects are
+    /// verified against the manifest once all of them have been read.
+    manifest: Option<(TransferManifest, Vec<ManifestEntry>)>,
+}
+
+impl<'a, S> ObjectStream<'a, S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns the next retrieved object, or `None` if the stream has ended.
+    async fn next_object(&mut self) -> Option<Result<(ObjectId, Vec<u8>, Vec<u8>), ClientError>> {
+        if self.done {
+            return None;
+        }
+        let result = self.try_next_object().await;
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+
+    async fn try_next_object(
+        &mut self,
+    ) -> Result<Option<(ObjectId, Vec<u8>, Vec<u8>)>, ClientError> {
+        let object_ids = match self.object_ids.take() {
+            Some(object_ids) => object_ids,
+            None => self.client.list().await?,
+        };
+        let object_ids = self.object_ids.insert(object_ids);
+
+        // once all objects of the previous batch have been read, request the next batch; while
+        // the batch is being read, the connection is marked as poisoned so that the client
+        // cannot be used for other requests if the stream is dropped part way through the batch
+        while self.next == self.batch_end {
+            if let Some((manifest, received)) = self.manifest.take() {
+                verify_manifest(&manifest, &received)?;
+            }
+            if self.next == object_ids.len() {
+                return Ok(None);
+            }
+            self.client.ensure_not_poisoned()?;
+            let (batch_end, request) = if self.take {
+                // objects may have been deleted from the server (e.g. because they expired)
+                // since they were listed; the server would fail the entire TAKE request for
+                // them, so they are dropped from the batch, and batches left empty are skipped
+                let batch_end = object_ids.len().min(self.next + DRAIN_BATCH_SIZE);
+                let held = self
+                    .client
+                    .contains(&object_ids[self.next..batch_end])
+                    .await?;
+                let batch: Vec<ObjectId> = object_ids[self.next..batch_end]
+                    .iter()
+                    .zip(held)
+                    .filter_map(|(oid, held)| held.then_some(*oid))
+                    .collect();
+                object_ids.splice(self.next..batch_end, batch.iter().copied());
+                if batch.is_empty() {
+                    continue;
+                }
+                (self.next + batch.len(), Request::Take(batch))
+            } else {
+                let request = Request::Copy(object_ids[self.next..].to_vec());
+                (object_ids.len(), request)
+            };
+            self.client.poisoned = true;
+            if let Err(err) = self.client.begin_transfer(request).await {
+                // if the server rejected the request, no objects will follow, and the connection
+                // can still be used
+                if matches!(err, ClientError::TransferError(_)) {
+                    self.client.poisoned = false;
+                }
+                return Err(err);
+            }
+            self.batch_end = batch_end;
+            self.manifest = self
+                .client
+                .read_manifest()
+                .await?
+                .map(|manifest| (manifest, Vec::new()));
+        }
+
+        let oid = object_ids[self.next];
+        let budget = self.client.max_in_flight_bytes;
+        let (meta, data) = self.client.receive_object(budget).await?;
+        if let Some((_, received)) = self.manifest.as_mut() {
+            received.push(ManifestEntry::new(oid, &meta, &data));
+        }
+        self.next += 1;
+        if self.next == self.batch_end {
+            self.client.poisoned = false;
+        }
+        Ok(Some((oid, meta, data)))
     }
 }
 
 // HELPER FUNCTIONS
 // ================================================================================================
 
-fn parse_sync_response(response: &[u8]) -> Result<(), ClientError> {
+/// Returns the error reported when connecting to a server does not complete within the
+/// specified timeout.
+fn connect_timed_out(timeout: Duration) -> ClientError {
+    ClientError::ConnectionError(
+        format!("failed to connect within {:?}", timeout),
+        Error::from(ErrorKind::TimedOut),
+    )
+}
+
+/// Verifies received objects described by `received` against the manifest sent by the server.
+fn verify_manifest(
+    manifest: &TransferManifest,
+    received: &[ManifestEntry],
+) -> Result<(), ClientError> {
+    let discrepancies = manifest.verify(received);
+    if discrepancies.is_empty() {
+        Ok(())
+    } else {
+        Err(ClientError::ManifestMismatch(discrepancies))
+    }
+}
+
+/// Returns addresses of peers of the specified peer requests, in the same order as the requests.
+fn get_peers(requests: &[PeerRequest]) -> Vec<PeerAddr> {
+    requests
+        .iter()
+        .map(|request| request.peer().clone())
+        .collect()
+}
+
+fn parse_sync_response(peers: Vec<PeerAddr>, response: &[u8]) -> Result<(), ClientError> {
     let mut results = Vec::with_capacity(response.len());
     let mut err_count = 0;
-    for peer_response in response {
+    for (peer, peer_response) in peers.into_iter().zip(response) {
         let result = PeerResult::from(*peer_response);
         if !result.is_ok() {
             err_count += 1;
         }
-        results.push(result);
+        results.push((peer, result));
     }
 
     if err_count > 0 {
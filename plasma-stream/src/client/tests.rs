@@ -0,0 +1,1117 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use super::*;
+use crate::{
+    handshake::Capabilities,
+    utils::{object_checksum, write_object},
+    ManifestDiscrepancy,
+};
+use futures_util::StreamExt;
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, UnixListener},
+    task::JoinHandle,
+};
+
+// COPY TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_copy() {
+    let objects = vec![
+        (
+            [1u8; 20],
+            vec![1u8, 2, 3, 4],
+            vec![1u8, 2, 3, 4, 5, 6, 7, 8],
+        ),
+        ([2u8; 20], vec![], vec![9u8, 10, 11, 12]),
+    ];
+
+    // start a mock server which streams back the objects
+    let (address, server) = start_mock_server(objects.clone(), status_codes::BEGIN).await;
+
+    // request the objects and make sure they were received correctly and in order
+    let mut client = Client::connect(address).await.unwrap();
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let received = client.copy(&object_ids).await.unwrap();
+    assert_eq!(objects, received);
+
+    // make sure the server received a COPY request for the right objects
+    match server.await.unwrap() {
+        Request::Copy(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected a COPY request, but received {}", request),
+    }
+}
+
+#[tokio::test]
+async fn client_copy_over_duplex() {
+    let objects = vec![
+        ([5u8; 20], vec![1u8, 2], vec![3u8; 1000]),
+        ([6u8; 20], vec![], vec![4u8, 5, 6]),
+    ];
+
+    // run the server side of the connection over an in-memory pipe instead of a socket
+    let (client_stream, mut server_stream) = tokio::io::duplex(64);
+    let server_objects = objects.clone();
+    let server = tokio::spawn(async move {
+        serve_objects(&mut server_stream, server_objects, status_codes::BEGIN).await
+    });
+
+    let options = ConnectOptions {
+        compression: true,
+        ..ConnectOptions::default()
+    };
+    let mut client = Client::from_stream(client_stream, options).await.unwrap();
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let received = client.copy(&object_ids).await.unwrap();
+    assert_eq!(objects, received);
+
+    match server.await.unwrap() {
+        Request::Copy(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected a COPY request, but received {}", request),
+    }
+}
+
+#[tokio::test]
+async fn client_copy_over_unix_socket() {
+    let objects = vec![
+        ([7u8; 20], vec![1u8, 2, 3], vec![4u8; 100]),
+        ([8u8; 20], vec![], vec![5u8, 6, 7]),
+    ];
+
+    // run the server side of the connection over a Unix socket
+    let path = std::env::temp_dir().join(format!("plasma-stream-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let server_objects = objects.clone();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        serve_objects(&mut socket, server_objects, status_codes::BEGIN).await
+    });
+
+    let mut client = Client::connect_unix(&path).await.unwrap();
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let received = client.copy(&object_ids).await.unwrap();
+    assert_eq!(objects, received);
+
+    match server.await.unwrap() {
+        Request::Copy(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected a COPY request, but received {}", request),
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn client_copy_large_object() {
+    // the object spans many data chunks, and the last chunk is not full
+    let data: Vec<u8> = (0..100_000_000u32).map(|i| (i % 251) as u8).collect();
+    let objects = vec![([12u8; 20], vec![1u8, 2, 3], data)];
+
+    let (address, _server) = start_mock_server(objects.clone(), status_codes::BEGIN).await;
+    let mut client = Client::connect(address).await.unwrap();
+    let received = client.copy(&[objects[0].0]).await.unwrap();
+    assert!(objects == received);
+}
+
+#[tokio::test]
+async fn client_copy_checksum_mismatch() {
+    let meta = vec![1u8, 2, 3];
+    let data = vec![4u8, 5, 6, 7, 8];
+
+    // start a mock server which corrupts a byte of object data after computing the checksum
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (server_meta, mut server_data) = (meta.clone(), data.clone());
+    let _server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        Request::read_from(&mut socket).await.unwrap().unwrap();
+        let checksum = object_checksum(&server_meta, &server_data);
+        server_data[2] ^= 0xFF;
+
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        let header = server_meta.len() as u64 | ((server_data.len() as u64) << 16);
+        socket.write_u64_le(header).await.unwrap();
+        socket.write_u32_le(checksum).await.unwrap();
+        socket.write_all(&server_meta).await.unwrap();
+        socket.write_all(&server_data).await.unwrap();
+    });
+
+    // the corrupted object should be rejected
+    let mut client = Client::connect(address).await.unwrap();
+    let result = client.copy(&[[3u8; 20]]).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::TransferError(PeerResult::ChecksumMismatch))
+    ));
+}
+
+#[tokio::test]
+async fn client_copy_compressed() {
+    // a highly compressible object
+    let data = vec![7u8; 1_000_000];
+    let objects = vec![([13u8; 20], vec![1u8, 2, 3], data.clone())];
+
+    // the object should take up much less space on the wire than its data
+    let mut wire = Vec::new();
+    let capabilities = Capabilities::all();
+    write_object(&objects[0].1, &data, capabilities, &mut wire)
+        .await
+        .unwrap();
+    assert!(wire.len() < data.len() / 10);
+
+    // start a mock server which streams back the compressed object
+    let (address, _server) = start_mock_server(objects.clone(), status_codes::BEGIN).await;
+    let options = ConnectOptions {
+        compression: true,
+        ..ConnectOptions::default()
+    };
+    let mut client = Client::connect_with_options(address, options)
+        .await
+        .unwrap();
+    assert!(client.capabilities.compression());
+    let received = client.copy(&[objects[0].0]).await.unwrap();
+    assert_eq!(objects, received);
+}
+
+#[tokio::test]
+async fn client_copy_codec_hints() {
+    // the second object is hinted not to be compressed
+    let objects = vec![
+        ([19u8; 20], vec![1u8, 2, 3], vec![7u8; 1_000_000]),
+        ([20u8; 20], b"codec=none;".to_vec(), vec![8u8; 1_000_000]),
+    ];
+
+    // start a mock server which streams back the objects, each with its own codec
+    let (address, _server) = start_mock_server(objects.clone(), status_codes::BEGIN).await;
+    let mut client = Client::builder()
+        .compression(true)
+        .connect(address)
+        .await
+        .unwrap();
+    assert!(client.capabilities.codecs());
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let received = client.copy(&object_ids).await.unwrap();
+    assert_eq!(objects, received);
+}
+
+#[tokio::test]
+async fn client_copy_verifies_manifest() {
+    let objects = vec![
+        ([14u8; 20], vec![1u8, 2], vec![3u8; 100]),
+        ([15u8; 20], vec![], vec![4u8, 5, 6]),
+    ];
+
+    // the mock server sends a manifest which matches the objects
+    let (address, _server) = start_mock_server(objects.clone(), status_codes::BEGIN).await;
+    let mut client = Client::builder()
+        .verify_manifest(true)
+        .connect(address)
+        .await
+        .unwrap();
+    assert!(client.capabilities.manifest());
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let received = client.copy(&object_ids).await.unwrap();
+    assert_eq!(objects, received);
+}
+
+#[tokio::test]
+async fn client_copy_manifest_mismatch() {
+    let objects = vec![
+        ([16u8; 20], vec![1u8, 2], vec![3u8; 100]),
+        ([17u8; 20], vec![], vec![4u8, 5, 6]),
+    ];
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+
+    // the manifest lists different data for the second object, and an object which is not
+    // sent at all
+    let mut listed = objects.clone();
+    listed[1].2[1] ^= 0xFF;
+    listed.push(([18u8; 20], vec![], vec![7u8; 10]));
+    let manifest = build_manifest(&listed);
+    let expected = vec![
+        ManifestDiscrepancy::HashMismatch(object_ids[1]),
+        ManifestDiscrepancy::NotReceived([18u8; 20]),
+        ManifestDiscrepancy::TotalSizeMismatch(115, 105),
+    ];
+
+    // the client should flag the discrepancies once all objects have been received
+    for streamed in [false, true] {
+        let (client_stream, mut server_stream) = tokio::io::duplex(1024);
+        let server_objects = objects.clone();
+        let server_manifest = manifest.clone();
+        let server = tokio::spawn(async move {
+            accept_handshake(&mut server_stream).await;
+            Request::read_from(&mut server_stream)
+                .await
+                .unwrap()
+                .unwrap();
+            server_stream.write_u8(status_codes::BEGIN).await.unwrap();
+            server_manifest
+                .write_into(&mut server_stream)
+                .await
+                .unwrap();
+            for (_, meta, data) in server_objects.iter() {
+                write_object(meta, data, Capabilities::all(), &mut server_stream)
+                    .await
+                    .unwrap();
+            }
+            server_stream
+        });
+
+        let options = ConnectOptions {
+            compression: true,
+            verify_manifest: true,
+            ..ConnectOptions::default()
+        };
+        let mut client = Client::from_stream(client_stream, options).await.unwrap();
+        let result = if streamed {
+            // the objects are still yielded, and the discrepancies are reported after them
+            let results: Vec<_> = client.copy_stream(&object_ids).collect().await;
+            assert_eq!(3, results.len());
+            for (result, object) in results.iter().zip(objects.iter()) {
+                assert_eq!(object, result.as_ref().unwrap());
+            }
+            results.into_iter().last().unwrap().map(|_| ())
+        } else {
+            client.copy(&object_ids).await.map(|_| ())
+        };
+        match result {
+            Err(ClientError::ManifestMismatch(discrepancies)) => {
+                assert_eq!(expected, discrepancies)
+            }
+            result => panic!("expected a manifest mismatch, but got {:?}", result),
+        }
+        server.await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn client_copy_error() {
+    // start a mock server which responds with an error
+    let (address, _server) = start_mock_server(vec![], status_codes::OB_NOT_FOUND_ERR).await;
+
+    // the error should be surfaced to the caller
+    let mut client = Client::connect(address).await.unwrap();
+    let result = client.copy(&[[1u8; 20]]).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::TransferError(PeerResult::ObjectsNotFound))
+    ));
+}
+
+#[tokio::test]
+async fn client_copy_stream_bounds_memory() {
+    let objects: Vec<(ObjectId, Vec<u8>, Vec<u8>)> = (0..16u8)
+        .map(|i| ([i; 20], vec![i, 1], vec![i; 1000]))
+        .collect();
+
+    // the pipe buffers far less than a single object, so the server can only write as fast as
+    // the client reads
+    let (client_stream, mut server_stream) = tokio::io::duplex(64);
+    let server_objects = objects.clone();
+    let server = tokio::spawn(async move {
+        serve_objects(&mut server_stream, server_objects, status_codes::BEGIN).await
+    });
+
+    let options = ConnectOptions {
+        max_in_flight_bytes: Some(1024),
+        ..ConnectOptions::default()
+    };
+    let mut client = Client::from_stream(client_stream, options).await.unwrap();
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let mut stream = Box::pin(client.copy_stream(&object_ids));
+
+    // while the first object is being processed, the client stops reading, and so the server
+    // cannot finish sending the rest of the objects
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!(objects[0], first);
+    time::sleep(Duration::from_millis(50)).await;
+    assert!(!server.is_finished());
+
+    // the transfer completes once the rest of the objects are consumed
+    let mut received = vec![first];
+    while let Some(object) = stream.next().await {
+        received.push(object.unwrap());
+    }
+    assert_eq!(objects, received);
+    drop(stream);
+    match server.await.unwrap() {
+        Request::Copy(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected a COPY request, but received {}", request),
+    }
+
+    // the connection can be used for further requests
+    assert!(!client.poisoned);
+}
+
+#[tokio::test]
+async fn client_copy_in_flight_limit() {
+    let objects: Vec<(ObjectId, Vec<u8>, Vec<u8>)> = (0..4u8)
+        .map(|i| ([i; 20], vec![i, 1], vec![i; 1000]))
+        .collect();
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+
+    // copy() holds all objects in memory, so it fails once the objects exceed the cap
+    let (address, _server) = start_mock_server(objects.clone(), status_codes::BEGIN).await;
+    let mut client = Client::builder()
+        .max_in_flight_bytes(2500)
+        .connect(address)
+        .await
+        .unwrap();
+    let result = client.copy(&object_ids).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::InFlightLimitExceeded(2500))
+    ));
+
+    // the rest of the response was left unread, so the client cannot be used anymore
+    assert!(matches!(
+        client.copy(&object_ids).await,
+        Err(ClientError::ConnectionError(_, _))
+    ));
+
+    // copy_stream() holds one object at a time, so it fails only for objects over the cap
+    let (address, _server) = start_mock_server(objects.clone(), status_codes::BEGIN).await;
+    let mut client = Client::builder()
+        .max_in_flight_bytes(500)
+        .connect(address)
+        .await
+        .unwrap();
+    let mut stream = Box::pin(client.copy_stream(&object_ids));
+    assert!(matches!(
+        stream.next().await,
+        Some(Err(ClientError::InFlightLimitExceeded(500)))
+    ));
+    assert!(stream.next().await.is_none());
+}
+
+// TAKE TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_take() {
+    let objects = vec![([3u8; 20], vec![1u8, 2], vec![3u8, 4, 5, 6])];
+
+    // start a mock server which streams back the objects
+    let (address, server) = start_mock_server(objects.clone(), status_codes::BEGIN).await;
+
+    // request the objects and make sure they were received correctly
+    let mut client = Client::connect(address).await.unwrap();
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let received = client.take(&object_ids).await.unwrap();
+    assert_eq!(objects, received);
+
+    // make sure the server received a TAKE request for the right objects
+    match server.await.unwrap() {
+        Request::Take(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected a TAKE request, but received {}", request),
+    }
+}
+
+// ESTIMATE TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_estimate() {
+    let object_ids = vec![[10u8; 20], [11u8; 20]];
+    let estimate = TransferEstimate {
+        num_objects: 1,
+        total_size: 1024,
+        missing: vec![[11u8; 20]],
+        oversized: vec![],
+    };
+
+    // start a mock server which responds to an ESTIMATE request with the estimate
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server_estimate = estimate.clone();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let request = Request::read_from(&mut socket).await.unwrap().unwrap();
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        server_estimate.write_into(&mut socket).await.unwrap();
+        request
+    });
+
+    let mut client = Client::connect(address).await.unwrap();
+    assert_eq!(estimate, client.estimate(&object_ids).await.unwrap());
+    match server.await.unwrap() {
+        Request::Estimate(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected an ESTIMATE request, but received {}", request),
+    }
+}
+
+// LIST TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_list() {
+    let object_ids = vec![[4u8; 20], [5u8; 20], [6u8; 20]];
+
+    // start a mock server which responds to a LIST request with the object IDs
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server_object_ids = object_ids.clone();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let request = Request::read_from(&mut socket).await.unwrap().unwrap();
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        crate::utils::write_object_ids(&server_object_ids, &mut socket)
+            .await
+            .unwrap();
+        request
+    });
+
+    let mut client = Client::connect(address).await.unwrap();
+    assert_eq!(object_ids, client.list().await.unwrap());
+    assert!(matches!(server.await.unwrap(), Request::List));
+}
+
+// DRAIN TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_drain() {
+    // the server holds more objects than fit into a single batch
+    let num_objects = DRAIN_BATCH_SIZE * 2 + 5;
+    let objects: Vec<(ObjectId, Vec<u8>, Vec<u8>)> = (0..num_objects)
+        .map(|i| {
+            let mut oid = [0u8; 20];
+            oid[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            (oid, vec![i as u8], vec![i as u8; i + 1])
+        })
+        .collect();
+
+    // start a mock server which holds all the objects it lists
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let (address, server) = start_drain_server(object_ids, objects.clone()).await;
+
+    // drain the server and make sure all objects were received in order
+    let mut client = Client::connect(address).await.unwrap();
+    let mut received = Vec::new();
+    {
+        let drain = client.drain();
+        tokio::pin!(drain);
+        while let Some(object) = drain.next().await {
+            received.push(object.unwrap());
+        }
+    }
+    assert_eq!(objects, received);
+    assert!(!client.poisoned);
+
+    // every object should have been sent exactly once
+    drop(client);
+    let sent = server.await.unwrap();
+    let expected: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    assert_eq!(expected, sent);
+}
+
+#[tokio::test]
+async fn client_drain_skips_deleted_objects() {
+    let objects: Vec<(ObjectId, Vec<u8>, Vec<u8>)> =
+        (0..4u8).map(|i| ([i; 20], vec![i], vec![i; 8])).collect();
+
+    // the second and the third objects are deleted from the server after they are listed
+    let object_ids: Vec<ObjectId> = objects.iter().map(|(oid, _, _)| *oid).collect();
+    let held = vec![objects[0].clone(), objects[3].clone()];
+    let (address, server) = start_drain_server(object_ids, held.clone()).await;
+
+    // the deleted objects should be skipped rather than fail the entire batch
+    let mut client = Client::connect(address).await.unwrap();
+    let mut received = Vec::new();
+    {
+        let drain = client.drain();
+        tokio::pin!(drain);
+        while let Some(object) = drain.next().await {
+            received.push(object.unwrap());
+        }
+    }
+    assert_eq!(held, received);
+    assert!(!client.poisoned);
+
+    drop(client);
+    let sent = server.await.unwrap();
+    assert_eq!(vec![objects[0].0, objects[3].0], sent);
+}
+
+// CONTAINS TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_contains() {
+    let object_ids = vec![[7u8; 20], [8u8; 20], [9u8; 20]];
+
+    // start a mock server which reports that only the second object is present
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let request = Request::read_from(&mut socket).await.unwrap().unwrap();
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        socket.write_u8(0b0000_0010).await.unwrap();
+        request
+    });
+
+    let mut client = Client::connect(address).await.unwrap();
+    let result = client.contains(&object_ids).await.unwrap();
+    assert_eq!(vec![false, true, false], result);
+    match server.await.unwrap() {
+        Request::Contains(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected a CONTAINS request, but received {}", request),
+    }
+}
+
+#[tokio::test]
+async fn client_replication_count() {
+    let oid = [12u8; 20];
+
+    // two servers hold the object, and one does not
+    let mut peers = Vec::new();
+    let mut servers = Vec::new();
+    for holds in [true, false, true] {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        peers.push(listener.local_addr().unwrap());
+        servers.push(tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            accept_handshake(&mut socket).await;
+            let request = Request::read_from(&mut socket).await.unwrap().unwrap();
+            socket.write_u8(status_codes::BEGIN).await.unwrap();
+            socket.write_u8(holds as u8).await.unwrap();
+            request
+        }));
+    }
+
+    // one server refuses connections, and another cannot reach its plasma store
+    let unreachable = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    peers.push(unreachable);
+    let (failing, _server) = start_mock_server(vec![], status_codes::PLASMA_STORE_ERR).await;
+    peers.push(failing.parse().unwrap());
+
+    let result = Client::replication_count(oid, &peers).await;
+    assert_eq!(2, result.holding);
+    assert_eq!(2, result.unreachable.len());
+    assert_eq!(unreachable, result.unreachable[0].0);
+    assert!(matches!(
+        result.unreachable[0].1,
+        ClientError::ConnectionError(_, _)
+    ));
+    assert_eq!(peers[4], result.unreachable[1].0);
+    assert!(matches!(
+        result.unreachable[1].1,
+        ClientError::TransferError(PeerResult::PlasmaStoreError)
+    ));
+
+    // every server should have been asked about the object only
+    for server in servers {
+        match server.await.unwrap() {
+            Request::Contains(requested) => assert_eq!(vec![oid], requested),
+            request => panic!("expected a CONTAINS request, but received {}", request),
+        }
+    }
+}
+
+// DELETE TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_delete() {
+    let object_ids = vec![[10u8; 20], [11u8; 20]];
+
+    // a successful delete
+    let (address, server) = start_mock_server(vec![], status_codes::SUCCESS).await;
+    let mut client = Client::connect(address).await.unwrap();
+    client.delete(&object_ids).await.unwrap();
+    match server.await.unwrap() {
+        Request::Delete(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected a DELETE request, but received {}", request),
+    }
+
+    // the server refuses to delete objects which are already scheduled for deletion
+    let (address, _server) =
+        start_mock_server(vec![], status_codes::OB_DELETION_SCHEDULED_ERR).await;
+    let mut client = Client::connect(address).await.unwrap();
+    let result = client.delete(&object_ids).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::DeleteError(
+            PeerResult::ObjectDeletionScheduled
+        ))
+    ));
+}
+
+// PING TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_ping() {
+    // a healthy server
+    let (address, server) = start_mock_server(vec![], status_codes::SUCCESS).await;
+    let mut client = Client::connect(address).await.unwrap();
+    client.ping().await.unwrap();
+    assert!(matches!(server.await.unwrap(), Request::Ping));
+
+    // a server which cannot reach its plasma store
+    let (address, _server) = start_mock_server(vec![], status_codes::PLASMA_STORE_ERR).await;
+    let mut client = Client::connect(address).await.unwrap();
+    let result = client.ping().await;
+    assert!(matches!(
+        result,
+        Err(ClientError::PingError(PeerResult::PlasmaStoreError))
+    ));
+}
+
+// SYNC TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_sync_timeout() {
+    // start a mock server which accepts a SYNC request but never replies
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let request = Request::read_from(&mut socket).await.unwrap().unwrap();
+        // keep the connection open until the client goes away
+        let _ = socket.read_u8().await;
+        request
+    });
+
+    let mut client = Client::connect(address).await.unwrap();
+    let requests = vec![PeerRequest::Copy {
+        from: "127.0.0.1:2022".parse().unwrap(),
+        objects: vec![[3u8; 20]],
+    }];
+    let result = client
+        .sync_with_timeout(requests, Duration::from_millis(100))
+        .await;
+    assert!(matches!(
+        result,
+        Err(ClientError::ConnectionError(_, ref err)) if err.kind() == ErrorKind::TimedOut
+    ));
+
+    // the connection should not be usable after the timeout
+    let result = client.list().await;
+    assert!(matches!(
+        result,
+        Err(ClientError::ConnectionError(_, ref err)) if err.kind() == ErrorKind::NotConnected
+    ));
+
+    drop(client);
+    assert!(matches!(server.await.unwrap(), Request::Sync(_)));
+}
+
+#[tokio::test]
+async fn client_sync_identifies_failed_peer() {
+    // start a mock server which reports that the second peer request failed
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let _server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let _ = Request::read_from(&mut socket).await.unwrap().unwrap();
+        let response = [status_codes::SUCCESS, status_codes::PEER_CONNECTION_ERR];
+        socket.write_all(&response).await.unwrap();
+    });
+
+    let mut client = Client::connect(address).await.unwrap();
+    let ok_peer: PeerAddr = "127.0.0.1:2022".parse().unwrap();
+    let failed_peer: PeerAddr = "127.0.0.1:2023".parse().unwrap();
+    let requests = vec![
+        PeerRequest::Copy {
+            from: ok_peer.clone(),
+            objects: vec![[3u8; 20]],
+        },
+        PeerRequest::Copy {
+            from: failed_peer.clone(),
+            objects: vec![[4u8; 20]],
+        },
+    ];
+    match client.sync(requests).await {
+        Err(ClientError::SyncError(results)) => {
+            assert_eq!(2, results.len());
+            assert_eq!(ok_peer, results[0].0);
+            assert!(results[0].1.is_ok());
+            assert_eq!(failed_peer, results[1].0);
+            assert!(matches!(results[1].1, PeerResult::PeerConnectionError));
+
+            // the error message names the peer which failed
+            let err = ClientError::SyncError(results).to_string();
+            assert!(
+                err.contains("127.0.0.1:2023: connection to peer(s) failed"),
+                "{}",
+                err
+            );
+        }
+        result => panic!("expected a SYNC error, but got {:?}", result.err()),
+    }
+}
+
+#[tokio::test]
+async fn client_sync_first_wins() {
+    // start a mock server which reports success for both peer requests
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let request = Request::read_from(&mut socket).await.unwrap().unwrap();
+        socket.write_all(&[status_codes::SUCCESS; 2]).await.unwrap();
+        request
+    });
+
+    // the same object is listed under both peers
+    let mut client = Client::connect(address).await.unwrap();
+    let requests = vec![
+        PeerRequest::Copy {
+            from: "127.0.0.1:2022".parse().unwrap(),
+            objects: vec![[3u8; 20], [4u8; 20]],
+        },
+        PeerRequest::Copy {
+            from: "127.0.0.1:2023".parse().unwrap(),
+            objects: vec![[3u8; 20]],
+        },
+    ];
+    client.sync_first_wins(requests).await.unwrap();
+
+    match server.await.unwrap() {
+        Request::SyncFirstWins(requests) => assert_eq!(2, requests.len()),
+        request => panic!(
+            "expected a SYNC FIRST WINS request, but received {}",
+            request
+        ),
+    }
+}
+
+#[tokio::test]
+async fn client_sync_with_retry_reconnects() {
+    // start a mock server which drops the first connection without responding to the request,
+    // and responds to the request received over the second connection
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let _ = Request::read_from(&mut socket).await.unwrap().unwrap();
+        drop(socket);
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        serve_objects(&mut socket, vec![], status_codes::SUCCESS).await
+    });
+
+    let mut client = Client::connect(address).await.unwrap();
+    let requests = vec![PeerRequest::Copy {
+        from: "127.0.0.1:2022".parse().unwrap(),
+        objects: vec![[3u8; 20]],
+    }];
+    let policy = RetryPolicy {
+        base_delay: Duration::from_millis(10),
+        ..RetryPolicy::default()
+    };
+    client.sync_with_retry(requests, policy).await.unwrap();
+    assert!(matches!(server.await.unwrap(), Request::Sync(_)));
+}
+
+#[tokio::test]
+async fn client_sync_with_retry_does_not_retry_peer_errors() {
+    // start a mock server which accepts a single connection and reports a failed peer request
+    let (address, server) = start_mock_server(vec![], status_codes::PEER_CONNECTION_ERR).await;
+
+    let mut client = Client::connect(address).await.unwrap();
+    let policy = RetryPolicy {
+        base_delay: Duration::from_millis(10),
+        ..RetryPolicy::default()
+    };
+
+    // malformed requests are rejected before anything is sent to the server
+    let result = client.sync_with_retry(vec![], policy).await;
+    assert!(matches!(result, Err(ClientError::MalformedRequest(_))));
+
+    // the peer request failure is definitive, so the request is not retried; retrying would
+    // fail with a connection error since the server does not accept another connection
+    let requests = vec![PeerRequest::Copy {
+        from: "127.0.0.1:2022".parse().unwrap(),
+        objects: vec![[3u8; 20]],
+    }];
+    let result = client.sync_with_retry(requests, policy).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::SyncError(ref results))
+            if matches!(results[0].1, PeerResult::PeerConnectionError)
+    ));
+    assert!(matches!(server.await.unwrap(), Request::Sync(_)));
+}
+
+// PREWARM TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_prewarm() {
+    let peers: Vec<PeerAddr> = vec![
+        "127.0.0.1:2022".parse().unwrap(),
+        "127.0.0.1:2023".parse().unwrap(),
+    ];
+
+    // start a mock server which fails to connect to the second peer
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let request = Request::read_from(&mut socket).await.unwrap().unwrap();
+        let response = [status_codes::SUCCESS, status_codes::PEER_CONNECTION_ERR];
+        socket.write_all(&response).await.unwrap();
+        request
+    });
+
+    let mut client = Client::connect(address).await.unwrap();
+    match client.prewarm(peers.clone()).await {
+        Err(ClientError::SyncError(results)) => {
+            assert_eq!(peers[0], results[0].0);
+            assert!(results[0].1.is_ok());
+            assert_eq!(peers[1], results[1].0);
+            assert!(matches!(results[1].1, PeerResult::PeerConnectionError));
+        }
+        result => panic!("expected a SYNC error, but got {:?}", result),
+    }
+    match server.await.unwrap() {
+        Request::Prewarm(requested) => assert_eq!(peers, requested),
+        request => panic!("expected a PREWARM request, but received {}", request),
+    }
+}
+
+// BUILDER TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn client_builder_sets_nodelay() {
+    let (address, _server) = start_mock_server(vec![], status_codes::SUCCESS).await;
+    let client = Client::builder()
+        .nodelay(false)
+        .connect(address)
+        .await
+        .unwrap();
+    assert!(!client.socket.nodelay().unwrap());
+
+    // TCP_NODELAY is set by default
+    let (address, _server) = start_mock_server(vec![], status_codes::SUCCESS).await;
+    let client = Client::builder().connect(address).await.unwrap();
+    assert!(client.socket.nodelay().unwrap());
+}
+
+#[tokio::test]
+async fn client_connect_sets_nodelay() {
+    let (address, _server) = start_mock_server(vec![], status_codes::SUCCESS).await;
+    let client = Client::connect(address).await.unwrap();
+    assert!(client.socket.nodelay().unwrap());
+}
+
+#[tokio::test]
+async fn client_builder_connect_timeout() {
+    // connecting to a non-routable address never completes; depending on the network
+    // configuration, it may also fail right away
+    let timeout = Duration::from_millis(100);
+    let started = std::time::Instant::now();
+    let result = Client::builder()
+        .connect_timeout(timeout)
+        .connect("10.255.255.1:2021")
+        .await;
+    assert!(result.is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // the timeout also covers the handshake, so a server which accepts the connection but
+    // never responds should not block the client either
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let _server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let _ = socket.read_to_end(&mut request).await;
+    });
+    let result = Client::builder()
+        .connect_timeout(timeout)
+        .connect(address)
+        .await;
+    assert!(matches!(
+        result,
+        Err(ClientError::ConnectionError(_, ref err)) if err.kind() == ErrorKind::TimedOut
+    ));
+}
+
+#[tokio::test]
+async fn client_connect_timeout() {
+    // connecting to a non-routable address never completes, unless the network rejects the
+    // connection right away; either way, the client must give up within the timeout
+    let timeout = Duration::from_millis(200);
+    let started = std::time::Instant::now();
+    let result = Client::connect_timeout("10.255.255.1:1", timeout).await;
+    assert!(started.elapsed() < timeout + Duration::from_millis(500));
+    match result {
+        Err(ClientError::ConnectionError(msg, err)) if err.kind() == ErrorKind::TimedOut => {
+            assert!(msg.contains("failed to connect within"), "{}", msg)
+        }
+        Err(_) => {}
+        Ok(_) => panic!("connecting to a non-routable address should fail"),
+    }
+}
+
+#[tokio::test]
+async fn client_builder_sync_timeout() {
+    // start a mock server which accepts a SYNC request but never replies
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let _server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        accept_handshake(&mut socket).await;
+        let _ = Request::read_from(&mut socket).await;
+        let _ = socket.read_u8().await;
+    });
+
+    let mut client = Client::builder()
+        .sync_timeout(Duration::from_millis(100))
+        .connect(address)
+        .await
+        .unwrap();
+    let requests = vec![PeerRequest::Copy {
+        from: "127.0.0.1:2022".parse().unwrap(),
+        objects: vec![[3u8; 20]],
+    }];
+    let result = client.sync(requests).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::ConnectionError(_, ref err)) if err.kind() == ErrorKind::TimedOut
+    ));
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Starts a server which accepts a single connection, reads a single request, and responds
+/// with the specified status code; if the status code is BEGIN, the provided objects are
+/// then streamed back. The returned handle resolves to the request read by the server.
+async fn start_mock_server(
+    objects: Vec<(ObjectId, Vec<u8>, Vec<u8>)>,
+    status: u8,
+) -> (String, JoinHandle<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        serve_objects(&mut socket, objects, status).await
+    });
+
+    (address, handle)
+}
+
+/// Acts as a server on the specified stream: accepts the handshake, reads a single request,
+/// and responds with the specified status followed by the objects (if the status is BEGIN).
+/// Returns the request read from the stream.
+async fn serve_objects<S: AsyncRead + AsyncWrite + Unpin>(
+    socket: &mut S,
+    objects: Vec<(ObjectId, Vec<u8>, Vec<u8>)>,
+    status: u8,
+) -> Request {
+    let capabilities = accept_handshake(socket).await;
+    let request = Request::read_from(socket).await.unwrap().unwrap();
+
+    socket.write_u8(status).await.unwrap();
+    if status == status_codes::BEGIN {
+        if capabilities.manifest() {
+            build_manifest(&objects).write_into(socket).await.unwrap();
+        }
+        for (_, meta, data) in objects.iter() {
+            write_object(meta, data, capabilities, socket)
+                .await
+                .unwrap();
+        }
+    }
+    request
+}
+
+/// Returns a manifest listing the specified objects.
+fn build_manifest(objects: &[(ObjectId, Vec<u8>, Vec<u8>)]) -> TransferManifest {
+    let entries = objects
+        .iter()
+        .map(|(oid, meta, data)| ManifestEntry::new(*oid, meta, data))
+        .collect();
+    TransferManifest::new(entries)
+}
+
+/// Starts a server which accepts a single connection, responds to a LIST request with the
+/// specified object IDs, and then serves CONTAINS and TAKE requests for the objects it holds
+/// until the connection is closed; TAKE requests for objects it does not hold fail the test.
+/// The returned handle resolves to the IDs of all objects sent by the server.
+async fn start_drain_server(
+    listed: Vec<ObjectId>,
+    held: Vec<(ObjectId, Vec<u8>, Vec<u8>)>,
+) -> (SocketAddr, JoinHandle<Vec<ObjectId>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let capabilities = accept_handshake(&mut socket).await;
+        let request = Request::read_from(&mut socket).await.unwrap().unwrap();
+        assert!(matches!(request, Request::List));
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        crate::utils::write_object_ids(&listed, &mut socket)
+            .await
+            .unwrap();
+
+        let find = |oid: &ObjectId| held.iter().find(|(id, _, _)| id == oid);
+        let mut sent = Vec::new();
+        while let Some(request) = Request::read_from(&mut socket).await.unwrap() {
+            match request {
+                Request::Contains(requested) => {
+                    let flags: Vec<bool> =
+                        requested.iter().map(|oid| find(oid).is_some()).collect();
+                    socket.write_u8(status_codes::BEGIN).await.unwrap();
+                    let bitmap = crate::utils::to_bitmap(&flags);
+                    socket.write_all(&bitmap).await.unwrap();
+                }
+                Request::Take(requested) => {
+                    assert!(requested.len() <= DRAIN_BATCH_SIZE);
+                    socket.write_u8(status_codes::BEGIN).await.unwrap();
+                    for oid in requested {
+                        let (_, meta, data) = find(&oid).unwrap();
+                        write_object(meta, data, capabilities, &mut socket)
+                            .await
+                            .unwrap();
+                        sent.push(oid);
+                    }
+                }
+                request => panic!(
+                    "expected a CONTAINS or TAKE request, but received {}",
+                    request
+                ),
+            }
+        }
+        sent
+    });
+
+    (address, handle)
+}
+
+/// Performs the server side of the handshake and returns the negotiated capabilities; all
+/// capabilities are supported by mock servers, and checksums are expected to be requested by
+/// the client.
+async fn accept_handshake<S: AsyncRead + AsyncWrite + Unpin>(socket: &mut S) -> Capabilities {
+    let capabilities = handshake::accept(socket, Capabilities::all(), None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(capabilities.checksums());
+    capabilities
+}
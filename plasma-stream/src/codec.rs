@@ -0,0 +1,87 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::handshake::Capabilities;
+use std::io::{Error, ErrorKind};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+// CONSTANTS
+// ================================================================================================
+
+/// Object metadata which starts with this prefix followed by the name of a codec and a `;`
+/// (e.g. `codec=none;`) carries a codec hint; the sender encodes data of such objects with the
+/// named codec. Metadata is otherwise opaque to Plasma Stream, and is sent as is.
+pub const CODEC_HINT_PREFIX: &[u8] = b"codec=";
+
+// CODEC
+// ================================================================================================
+
+/// Encoding of object data on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// Object data is sent as is; the codec is named `none` in codec hints.
+    None = 0,
+    /// Object data is compressed with LZ4 chunk by chunk (see `utils::write_data_compressed()`);
+    /// the codec is named `lz4` in codec hints.
+    Lz4 = 1,
+}
+
+impl Codec {
+    /// Returns the codec identified by the specified byte of an object header.
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Codec::None),
+            1 => Some(Codec::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Returns the codec named by the codec hint in the specified object metadata, if any;
+    /// hints naming unknown codecs are ignored.
+    pub fn from_hint(meta: &[u8]) -> Option<Self> {
+        let hint = meta.strip_prefix(CODEC_HINT_PREFIX)?;
+        let end = hint.iter().position(|&byte| byte == b';')?;
+        match &hint[..end] {
+            b"none" => Some(Codec::None),
+            b"lz4" => Some(Codec::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Returns the codec for data of an object with the specified metadata sent over a
+    /// connection with the specified capabilities. Data is compressed only if compression was
+    /// negotiated for the connection; in this case, a codec hint in the metadata is honored,
+    /// and data of objects without a hint is compressed with LZ4.
+    pub fn select(meta: &[u8], capabilities: Capabilities) -> Self {
+        if !capabilities.compression() {
+            return Codec::None;
+        }
+        Self::from_hint(meta).unwrap_or(Codec::Lz4)
+    }
+
+    /// Reads the codec of an object written via `utils::write_object()` for the specified
+    /// capabilities from the socket; this must be called right after the object header has
+    /// been read. If codecs were not negotiated for the connection, nothing is read: data of
+    /// all objects is framed as LZ4-compressed data if compression was negotiated (with chunks
+    /// which were not compressed sent as is), and is sent as is otherwise.
+    pub async fn read_from<R: AsyncRead + Unpin>(
+        socket: &mut R,
+        capabilities: Capabilities,
+    ) -> std::io::Result<Self> {
+        if !capabilities.codecs() {
+            return Ok(match capabilities.compression() {
+                true => Codec::Lz4,
+                false => Codec::None,
+            });
+        }
+        let byte = socket.read_u8().await?;
+        Self::from_byte(byte).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown codec of object data: {}", byte),
+            )
+        })
+    }
+}
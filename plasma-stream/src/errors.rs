@@ -3,11 +3,11 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::{status_codes, ObjectId, MAX_DATA_SIZE, MAX_META_SIZE};
+use crate::{status_codes, ManifestDiscrepancy, ObjectId, PeerAddr};
 use plasma_store::PlasmaError;
 use std::{
     fmt::{self, Display, Formatter},
-    net::SocketAddr,
+    path::PathBuf,
 };
 use thiserror::{private::AsDynError, Error};
 use tokio::task::JoinError;
@@ -19,12 +19,21 @@ use tokio::task::JoinError;
 /// Stream server to another.
 #[derive(Debug)]
 pub enum ObjectSendError {
-    ObjectDeletionScheduled(SocketAddr, Vec<ObjectId>),
-    ObjectMetaTooLarge(SocketAddr, ObjectId, usize),
-    ObjectDataTooLarge(SocketAddr, ObjectId, usize),
-    StoreError(SocketAddr, PlasmaError),
-    ObjectsNotFound(SocketAddr, Vec<ObjectId>),
-    ConnectionError(Option<SocketAddr>, std::io::Error),
+    ObjectDeletionScheduled(PeerAddr, Vec<ObjectId>),
+    ObjectMetaTooLarge(PeerAddr, ObjectId, usize),
+    ObjectDataTooLarge(PeerAddr, ObjectId, usize),
+    ZeroLengthObjectData(PeerAddr, ObjectId),
+    StoreError(PeerAddr, PlasmaError),
+    ObjectsNotFound(PeerAddr, Vec<ObjectId>),
+    ConnectionError(Option<PeerAddr>, std::io::Error),
+    ObjectsPartiallySent {
+        peer: PeerAddr,
+        sent: Vec<ObjectId>,
+        failed_at: ObjectId,
+        source: std::io::Error,
+    },
+    /// The transfer was cancelled; includes IDs of objects sent before the cancellation.
+    TransferCancelled(PeerAddr, Vec<ObjectId>),
 }
 
 impl ObjectSendError {
@@ -33,9 +42,12 @@ impl ObjectSendError {
             Self::ObjectDeletionScheduled(_, _) => Some(status_codes::OB_DELETION_SCHEDULED_ERR),
             Self::ObjectMetaTooLarge(_, _, _) => Some(status_codes::OB_META_TOO_LARGE_ERR),
             Self::ObjectDataTooLarge(_, _, _) => Some(status_codes::OB_DATA_TOO_LARGE_ERR),
+            Self::ZeroLengthObjectData(_, _) => Some(status_codes::OB_DATA_ZERO_LENGTH_ERR),
             Self::ObjectsNotFound(_, _) => Some(status_codes::OB_NOT_FOUND_ERR),
             Self::StoreError(_, _) => Some(status_codes::PLASMA_STORE_ERR),
             Self::ConnectionError(_, _) => None,
+            Self::ObjectsPartiallySent { .. } => None,
+            Self::TransferCancelled(_, _) => None,
         }
     }
 }
@@ -69,6 +81,14 @@ impl Display for ObjectSendError {
                     hex::encode(oid),
                 )?;
             }
+            Self::ZeroLengthObjectData(peer, oid) => {
+                write!(
+                    f,
+                    "failed to send objects to {}; zero-length data for 0x{}",
+                    peer,
+                    hex::encode(oid),
+                )?;
+            }
             Self::ObjectsNotFound(peer, objects) => {
                 write!(f, "failed to send objects to {}; objects not found:", peer)?;
                 for oid in objects {
@@ -86,6 +106,29 @@ impl Display for ObjectSendError {
                 Some(peer) => write!(f, "failed to send objects to {}: {}", peer, err)?,
                 None => write!(f, "failed to send objects: {}", err)?,
             },
+            Self::ObjectsPartiallySent {
+                peer,
+                sent,
+                failed_at,
+                source,
+            } => {
+                write!(
+                    f,
+                    "failed to send objects to {}; sent {} objects before failing at 0x{}: {}",
+                    peer,
+                    sent.len(),
+                    hex::encode(failed_at),
+                    source,
+                )?;
+            }
+            Self::TransferCancelled(peer, sent) => {
+                write!(
+                    f,
+                    "sending objects to {} was cancelled after {} objects were sent",
+                    peer,
+                    sent.len(),
+                )?;
+            }
         };
 
         Ok(())
@@ -96,6 +139,7 @@ impl std::error::Error for ObjectSendError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::ConnectionError(_, err) => Some(err.as_dyn_error()),
+            Self::ObjectsPartiallySent { source, .. } => Some(source.as_dyn_error()),
             Self::StoreError(_, err) => Some(err.as_dyn_error()),
             _ => None,
         }
@@ -109,14 +153,16 @@ impl std::error::Error for ObjectSendError {
 /// Plasma stream server to another.
 #[derive(Debug)]
 pub enum ObjectReceiveError {
-    AlreadyReceiving(SocketAddr, Vec<ObjectId>),
-    AlreadyInStore(SocketAddr, Vec<ObjectId>),
-    ObjectMetaTooLarge(SocketAddr, ObjectId, usize),
-    ObjectDataTooLarge(SocketAddr, ObjectId, usize),
-    ZeroLengthObjectData(SocketAddr, ObjectId),
-    PeerError(SocketAddr, u8),
-    StoreError(SocketAddr, PlasmaError),
-    ConnectionError(Option<SocketAddr>, std::io::Error),
+    AlreadyReceiving(PeerAddr, Vec<ObjectId>),
+    AlreadyInStore(PeerAddr, Vec<ObjectId>),
+    ObjectMetaTooLarge(PeerAddr, ObjectId, usize),
+    ObjectDataTooLarge(PeerAddr, ObjectId, usize),
+    ZeroLengthObjectData(PeerAddr, ObjectId),
+    ChecksumMismatch(PeerAddr, ObjectId),
+    PeerError(PeerAddr, u8),
+    StoreError(PeerAddr, PlasmaError),
+    ConnectionError(Option<PeerAddr>, std::io::Error),
+    TransferCancelled(PeerAddr),
 }
 
 impl ObjectReceiveError {
@@ -127,12 +173,14 @@ impl ObjectReceiveError {
             Self::ObjectMetaTooLarge(_, _, _) => status_codes::OB_META_TOO_LARGE_ERR,
             Self::ObjectDataTooLarge(_, _, _) => status_codes::OB_DATA_TOO_LARGE_ERR,
             Self::ZeroLengthObjectData(_, _) => status_codes::OB_DATA_ZERO_LENGTH_ERR,
+            Self::ChecksumMismatch(_, _) => status_codes::OB_CHECKSUM_MISMATCH_ERR,
             Self::PeerError(_, status_code) => match *status_code {
                 status_codes::PLASMA_STORE_ERR => status_codes::PEER_PLASMA_STORE_ERR,
                 _ => *status_code,
             },
             Self::StoreError(_, _) => status_codes::PLASMA_STORE_ERR,
             Self::ConnectionError(_, _) => status_codes::PEER_CONNECTION_ERR,
+            Self::TransferCancelled(_) => status_codes::TRANSFER_CANCELLED,
         }
     }
 }
@@ -184,6 +232,14 @@ impl Display for ObjectReceiveError {
                     hex::encode(oid),
                 )?;
             }
+            Self::ChecksumMismatch(peer, oid) => {
+                write!(
+                    f,
+                    "failed to receive objects from {}; checksum mismatch for 0x{}",
+                    peer,
+                    hex::encode(oid),
+                )?;
+            }
             Self::PeerError(peer, response_code) => {
                 write!(f, "failed to receive objects from {}; ", peer)?;
                 match *response_code {
@@ -206,6 +262,9 @@ impl Display for ObjectReceiveError {
                 Some(peer) => write!(f, "failed to receive objects from {}: {}", peer, err)?,
                 None => write!(f, "failed to receive objects: {}", err)?,
             },
+            Self::TransferCancelled(peer) => {
+                write!(f, "receiving objects from {} was cancelled", peer)?
+            }
         };
 
         Ok(())
@@ -222,6 +281,61 @@ impl std::error::Error for ObjectReceiveError {
     }
 }
 
+// OBJECT DELETE ERROR
+// ================================================================================================
+
+/// Describes possible errors which can be encountered while deleting objects from the local
+/// plasma store on behalf of a peer.
+#[derive(Debug)]
+pub enum ObjectDeleteError {
+    ObjectDeletionScheduled(PeerAddr, Vec<ObjectId>),
+    StoreError(PeerAddr, PlasmaError),
+}
+
+impl ObjectDeleteError {
+    pub fn response_code(&self) -> u8 {
+        match self {
+            Self::ObjectDeletionScheduled(_, _) => status_codes::OB_DELETION_SCHEDULED_ERR,
+            Self::StoreError(_, _) => status_codes::PLASMA_STORE_ERR,
+        }
+    }
+}
+
+impl Display for ObjectDeleteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ObjectDeletionScheduled(peer, objects) => {
+                write!(
+                    f,
+                    "failed to delete objects for {}; object scheduled for deletion:",
+                    peer
+                )?;
+                for oid in objects {
+                    write!(f, "\n0x{}", hex::encode(oid))?
+                }
+            }
+            Self::StoreError(peer, err) => {
+                write!(
+                    f,
+                    "failed to delete objects for {}; plasma store error: {}",
+                    peer, err,
+                )?;
+            }
+        };
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ObjectDeleteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::StoreError(_, err) => Some(err.as_dyn_error()),
+            _ => None,
+        }
+    }
+}
+
 // REQUEST ERROR
 // ================================================================================================
 
@@ -237,6 +351,12 @@ pub enum RequestError {
     #[error("invalid peer address type {0}")]
     InvalidPeerAddressType(u8),
 
+    #[error("invalid peer hostname '{0}'")]
+    InvalidPeerHostname(String),
+
+    #[error("invalid peer socket path '{}'", .0.display())]
+    InvalidPeerSocketPath(PathBuf),
+
     #[error("object ID list is empty")]
     ObjectIdListTooShort,
 
@@ -251,6 +371,48 @@ pub enum RequestError {
 
     #[error("peer request list is too long {0}")]
     PeerRequestListTooLong(usize),
+
+    #[error("PUSH peer requests are not allowed in SYNC FIRST WINS requests")]
+    UnexpectedPushRequest,
+
+    #[error("peer list is empty")]
+    PeerListTooShort,
+
+    #[error("peer list is too long {0}")]
+    PeerListTooLong(usize),
+}
+
+// HANDSHAKE ERROR
+// ================================================================================================
+
+/// Describes errors which can be encountered during the protocol handshake.
+#[derive(Error, Debug)]
+pub enum HandshakeError {
+    #[error("protocol version mismatch: local version is {local}, remote version is {remote}")]
+    VersionMismatch { local: u8, remote: u8 },
+
+    #[error("authentication failed: tokens do not match")]
+    AuthFailed,
+
+    #[error("server is busy: connection limit reached")]
+    ServerBusy,
+
+    #[error("unexpected handshake response {0}")]
+    UnexpectedResponse(u8),
+
+    #[error("handshake failed: {0}")]
+    ConnectionError(#[from] std::io::Error),
+}
+
+impl HandshakeError {
+    pub fn response_code(&self) -> u8 {
+        match self {
+            Self::VersionMismatch { .. } => status_codes::PROTOCOL_VERSION_MISMATCH,
+            Self::AuthFailed => status_codes::AUTH_FAILED,
+            Self::ServerBusy => status_codes::SERVER_BUSY,
+            _ => status_codes::PEER_CONNECTION_ERR,
+        }
+    }
 }
 
 // SYNC ERROR
@@ -259,9 +421,15 @@ pub enum RequestError {
 /// Describes errors which can be encountered while fulfilling SYNC requests.
 #[derive(Debug)]
 pub enum SyncError {
-    PeerConnectionFailed(SocketAddr, std::io::Error),
-    PeerRequestNotSent(SocketAddr, std::io::Error),
+    PeerResolutionFailed(PeerAddr, std::io::Error),
+    /// Connecting to a peer failed; includes the number of objects requested from the peer.
+    PeerConnectionFailed(PeerAddr, usize, std::io::Error),
+    PeerRequestNotSent(PeerAddr, std::io::Error),
+    PeerHandshakeFailed(PeerAddr, HandshakeError),
     ReceiverError(ObjectReceiveError),
+    SenderError(ObjectSendError),
+    /// The peer did not accept objects pushed to it; includes the status code sent by the peer.
+    PushRejected(PeerAddr, u8),
     PeerRequestPanicked(JoinError),
     ClientConnectionError(std::io::Error),
     PeerAddressIsSelf,
@@ -270,9 +438,18 @@ pub enum SyncError {
 impl SyncError {
     pub fn response_code(&self) -> u8 {
         match self {
-            Self::PeerConnectionFailed(_, _) => status_codes::PEER_CONNECTION_ERR,
+            Self::PeerResolutionFailed(_, _) => status_codes::PEER_CONNECTION_ERR,
+            Self::PeerConnectionFailed(_, _, _) => status_codes::PEER_CONNECTION_ERR,
             Self::PeerRequestNotSent(_, _) => status_codes::PEER_CONNECTION_ERR,
+            Self::PeerHandshakeFailed(_, err) => err.response_code(),
             Self::ReceiverError(err) => err.response_code(),
+            Self::SenderError(err) => err
+                .response_code()
+                .unwrap_or(status_codes::PEER_CONNECTION_ERR),
+            Self::PushRejected(_, status_code) => match *status_code {
+                status_codes::PLASMA_STORE_ERR => status_codes::PEER_PLASMA_STORE_ERR,
+                _ => *status_code,
+            },
             Self::PeerRequestPanicked(_) => status_codes::PEER_REQUEST_PANICKED,
             Self::ClientConnectionError(_) => status_codes::CLIENT_CONNECTION_ERR,
             Self::PeerAddressIsSelf => status_codes::PEER_CONNECTION_ERR,
@@ -283,13 +460,28 @@ impl SyncError {
 impl Display for SyncError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Self::PeerConnectionFailed(peer, err) => {
-                write!(f, "peer connection to {} failed: {}", peer, err)?
+            Self::PeerResolutionFailed(peer, err) => {
+                write!(f, "failed to resolve peer address {}: {}", peer, err)?
             }
+            Self::PeerConnectionFailed(peer, num_objects, err) => write!(
+                f,
+                "peer connection to {} failed while requesting {} object(s): {}",
+                peer, num_objects, err
+            )?,
             Self::PeerRequestNotSent(peer, err) => {
                 write!(f, "failed to send request to {}: {}", peer, err)?
             }
+            Self::PeerHandshakeFailed(peer, err) => {
+                write!(f, "handshake with {} failed: {}", peer, err)?
+            }
             Self::ReceiverError(err) => write!(f, "f{}", err)?,
+            Self::SenderError(err) => write!(f, "{}", err)?,
+            Self::PushRejected(peer, status_code) => write!(
+                f,
+                "{} did not accept pushed objects: {}",
+                peer,
+                PeerResult::from(*status_code)
+            )?,
             Self::PeerRequestPanicked(err) => write!(f, "peer request panicked: {}", err)?,
             Self::ClientConnectionError(err) => write!(f, "client connection failed: {}", err)?,
             Self::PeerAddressIsSelf => write!(f, "cannot make a peer request to self")?,
@@ -301,8 +493,10 @@ impl Display for SyncError {
 impl std::error::Error for SyncError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::PeerConnectionFailed(_, err) => Some(err.as_dyn_error()),
+            Self::PeerResolutionFailed(_, err) => Some(err.as_dyn_error()),
+            Self::PeerConnectionFailed(_, _, err) => Some(err.as_dyn_error()),
             Self::PeerRequestNotSent(_, err) => Some(err.as_dyn_error()),
+            Self::PeerHandshakeFailed(_, err) => Some(err.as_dyn_error()),
             _ => None,
         }
     }
@@ -318,14 +512,20 @@ pub enum PeerResult {
     ObjectMetaTooLarge,
     ObjectDataTooLarge,
     ZeroLengthObjectData,
+    ChecksumMismatch,
     PlasmaStoreError,
     PeerPlasmaStoreError,
     PeerRequestPanicked,
+    TransferCancelled,
     ObjectDeletionScheduled,
     ObjectsNotFound,
     AlreadyReceiving,
     AlreadyInStore,
     PeerConnectionError,
+    ConnectionLimitExceeded,
+    ProtocolVersionMismatch,
+    AuthFailed,
+    ServerBusy,
     UnknownError,
 }
 
@@ -336,14 +536,20 @@ impl PeerResult {
             status_codes::OB_META_TOO_LARGE_ERR => Self::ObjectMetaTooLarge,
             status_codes::OB_DATA_TOO_LARGE_ERR => Self::ObjectDataTooLarge,
             status_codes::OB_DATA_ZERO_LENGTH_ERR => Self::ZeroLengthObjectData,
+            status_codes::OB_CHECKSUM_MISMATCH_ERR => Self::ChecksumMismatch,
             status_codes::PLASMA_STORE_ERR => Self::PlasmaStoreError,
             status_codes::PEER_PLASMA_STORE_ERR => Self::PeerPlasmaStoreError,
             status_codes::PEER_REQUEST_PANICKED => Self::PeerRequestPanicked,
+            status_codes::TRANSFER_CANCELLED => Self::TransferCancelled,
             status_codes::OB_DELETION_SCHEDULED_ERR => Self::ObjectDeletionScheduled,
             status_codes::OB_NOT_FOUND_ERR => Self::ObjectsNotFound,
             status_codes::OB_ALREADY_RECEIVING_ERR => Self::AlreadyReceiving,
             status_codes::OB_ALREADY_IN_STORE_ERR => Self::AlreadyInStore,
             status_codes::PEER_CONNECTION_ERR => Self::PeerConnectionError,
+            status_codes::CONNECTION_LIMIT_EXCEEDED => Self::ConnectionLimitExceeded,
+            status_codes::PROTOCOL_VERSION_MISMATCH => Self::ProtocolVersionMismatch,
+            status_codes::AUTH_FAILED => Self::AuthFailed,
+            status_codes::SERVER_BUSY => Self::ServerBusy,
             _ => Self::UnknownError,
         }
     }
@@ -357,14 +563,14 @@ impl Display for PeerResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::Ok => write!(f, "Ok")?,
-            Self::ObjectMetaTooLarge => {
-                write!(f, "object metadata exceeds {} bytes", MAX_META_SIZE)?
-            }
-            Self::ObjectDataTooLarge => write!(f, "object data exceeds {} bytes", MAX_DATA_SIZE)?,
+            Self::ObjectMetaTooLarge => write!(f, "object metadata exceeds the size limit")?,
+            Self::ObjectDataTooLarge => write!(f, "object data exceeds the size limit")?,
             Self::ZeroLengthObjectData => write!(f, "zero-length object data")?,
+            Self::ChecksumMismatch => write!(f, "object checksum mismatch")?,
             Self::PlasmaStoreError => write!(f, "local plasma store error")?,
             Self::PeerPlasmaStoreError => write!(f, "peer plasma store error")?,
             Self::PeerRequestPanicked => write!(f, "peer request panicked")?,
+            Self::TransferCancelled => write!(f, "transfer cancelled")?,
             Self::ObjectDeletionScheduled => {
                 write!(f, "requested object(s) scheduled for deletion")?
             }
@@ -372,6 +578,14 @@ impl Display for PeerResult {
             Self::AlreadyReceiving => write!(f, "duplicate request for object(s)")?,
             Self::AlreadyInStore => write!(f, "requested object(s) already in local store")?,
             Self::PeerConnectionError => write!(f, "connection to peer(s) failed")?,
+            Self::ConnectionLimitExceeded => {
+                write!(f, "connection exceeded the server's per-connection limits")?
+            }
+            Self::ProtocolVersionMismatch => {
+                write!(f, "peer(s) speak a different protocol version")?
+            }
+            Self::AuthFailed => write!(f, "peer(s) rejected the authentication token")?,
+            Self::ServerBusy => write!(f, "peer(s) at their connection limit")?,
             Self::UnknownError => write!(f, "Unknown error")?,
         };
         Ok(())
@@ -385,7 +599,19 @@ impl Display for PeerResult {
 pub enum ClientError {
     MalformedRequest(RequestError),
     ConnectionError(String, std::io::Error),
-    SyncError(Vec<PeerResult>),
+    HandshakeError(HandshakeError),
+    /// Results of all peer requests in a SYNC request, each paired with the address of the
+    /// peer the request was sent to; returned if at least one of the peer requests failed.
+    SyncError(Vec<(PeerAddr, PeerResult)>),
+    TransferError(PeerResult),
+    DeleteError(PeerResult),
+    PingError(PeerResult),
+    /// Receiving the next object would take the amount of received object data and metadata
+    /// held by the client over its in-flight cap (see `ConnectOptions::max_in_flight_bytes`).
+    InFlightLimitExceeded(usize),
+    /// Received objects do not match the manifest sent by the server (see
+    /// `ConnectOptions::verify_manifest`); lists all discrepancies found.
+    ManifestMismatch(Vec<ManifestDiscrepancy>),
 }
 
 impl Display for ClientError {
@@ -393,10 +619,25 @@ impl Display for ClientError {
         match self {
             Self::MalformedRequest(err) => write!(f, "malformed request: {}", err)?,
             Self::ConnectionError(msg, err) => write!(f, "{}: {}", msg, err)?,
+            Self::HandshakeError(err) => write!(f, "{}", err)?,
             Self::SyncError(results) => {
                 write!(f, "peer requests resolved as follows:")?;
-                for result in results {
-                    write!(f, "\n{}", result)?;
+                for (peer, result) in results {
+                    write!(f, "\n{}: {}", peer, result)?;
+                }
+            }
+            Self::TransferError(result) => write!(f, "failed to receive objects: {}", result)?,
+            Self::DeleteError(result) => write!(f, "failed to delete objects: {}", result)?,
+            Self::PingError(result) => write!(f, "server is unhealthy: {}", result)?,
+            Self::InFlightLimitExceeded(limit) => write!(
+                f,
+                "failed to receive objects: more than {} bytes would be held in memory",
+                limit
+            )?,
+            Self::ManifestMismatch(discrepancies) => {
+                write!(f, "received objects do not match the manifest:")?;
+                for discrepancy in discrepancies {
+                    write!(f, "\n{}", discrepancy)?;
                 }
             }
         };
@@ -410,6 +651,7 @@ impl std::error::Error for ClientError {
         match self {
             Self::ConnectionError(_, err) => Some(err.as_dyn_error()),
             Self::MalformedRequest(err) => Some(err.as_dyn_error()),
+            Self::HandshakeError(err) => Some(err.as_dyn_error()),
             _ => None,
         }
     }
@@ -0,0 +1,60 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::{
+    utils::{read_object_ids, write_object_ids},
+    ObjectId,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// TRANSFER ESTIMATE
+// ================================================================================================
+
+/// Describes what a COPY or TAKE request for a set of objects would transfer; this is returned
+/// by the server in response to an ESTIMATE request, and no object data is sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferEstimate {
+    /// Number of requested objects found in the store of the server.
+    pub num_objects: usize,
+    /// Total number of bytes of data and metadata of the objects found in the store.
+    pub total_size: u64,
+    /// IDs of requested objects which were not found in the store.
+    pub missing: Vec<ObjectId>,
+    /// IDs of requested objects which exceed object size limits of the server or have no data;
+    /// such objects are counted in `num_objects` and `total_size`, but cannot be transferred.
+    pub oversized: Vec<ObjectId>,
+}
+
+impl TransferEstimate {
+    /// Returns true if all of the requested objects can be transferred.
+    pub fn is_transferable(&self) -> bool {
+        self.missing.is_empty() && self.oversized.is_empty()
+    }
+
+    /// Reads an estimate from the specified socket; the estimate must have been written via
+    /// `write_into()`.
+    pub async fn read_from<R: AsyncRead + Unpin>(socket: &mut R) -> std::io::Result<Self> {
+        let num_objects = socket.read_u32_le().await? as usize;
+        let total_size = socket.read_u64_le().await?;
+        let missing = read_object_ids(socket).await?;
+        let oversized = read_object_ids(socket).await?;
+        Ok(TransferEstimate {
+            num_objects,
+            total_size,
+            missing,
+            oversized,
+        })
+    }
+
+    /// Writes this estimate into the specified socket. The number of objects (as u32) and the
+    /// total size (as u64) are written first, followed by the lists of missing and oversized
+    /// object IDs written via `utils::write_object_ids()`.
+    pub async fn write_into<W: AsyncWrite + Unpin>(&self, socket: &mut W) -> std::io::Result<()> {
+        socket.write_u32_le(self.num_objects as u32).await?;
+        socket.write_u64_le(self.total_size).await?;
+        write_object_ids(&self.missing, socket).await?;
+        write_object_ids(&self.oversized, socket).await
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::{errors::HandshakeError, status_codes, AUTH_TOKEN_BYTES, PROTOCOL_VERSION};
+use std::{
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// PROTOCOL HANDSHAKE
+// ================================================================================================
+// Every connection starts with a handshake: the client sends the version of the protocol it
+// speaks, followed by an authentication token (all zeros if the client has no token), and a
+// bitmask of optional capabilities it would like to use. If the server speaks the same version,
+// and either does not require authentication or the token matches its own, it responds with
+// SUCCESS followed by the capabilities which will be used for the connection; these are the
+// requested capabilities the server also supports. If versions differ, the server responds with
+// PROTOCOL_VERSION_MISMATCH followed by its own version; if the token does not match, the
+// server responds with AUTH_FAILED. In both cases, the server then closes the connection.
+// A server which rejects connections over its connection limit responds with SERVER_BUSY
+// instead, without reading the handshake.
+
+/// Performs the client side of the handshake over the specified socket, requesting the
+/// specified capabilities and authenticating with the specified token, if any. Returns the
+/// capabilities negotiated for the connection.
+pub async fn initiate<S: AsyncRead + AsyncWrite + Unpin>(
+    socket: &mut S,
+    requested: Capabilities,
+    token: Option<&AuthToken>,
+) -> Result<Capabilities, HandshakeError> {
+    let mut message = Vec::with_capacity(AUTH_TOKEN_BYTES + 2);
+    message.push(PROTOCOL_VERSION);
+    message.extend_from_slice(&token.map_or([0; AUTH_TOKEN_BYTES], |token| token.0));
+    message.push(requested.bits());
+    socket.write_all(&message).await?;
+    match socket.read_u8().await? {
+        status_codes::SUCCESS => {
+            // the server must not enable capabilities which were not requested
+            let negotiated = Capabilities::from_bits(socket.read_u8().await?);
+            Ok(negotiated.intersect(requested))
+        }
+        status_codes::PROTOCOL_VERSION_MISMATCH => {
+            let remote = socket.read_u8().await?;
+            Err(HandshakeError::VersionMismatch {
+                local: PROTOCOL_VERSION,
+                remote,
+            })
+        }
+        status_codes::AUTH_FAILED => Err(HandshakeError::AuthFailed),
+        status_codes::SERVER_BUSY => Err(HandshakeError::ServerBusy),
+        status => Err(HandshakeError::UnexpectedResponse(status)),
+    }
+}
+
+/// Performs the server side of the handshake over the specified socket. If `expected` token is
+/// provided, clients which do not present the same token are rejected; otherwise, tokens sent
+/// by clients are ignored. Returns capabilities negotiated for the connection, or `None` if the
+/// connection was closed before the client sent its protocol version.
+pub async fn accept<S: AsyncRead + AsyncWrite + Unpin>(
+    socket: &mut S,
+    supported: Capabilities,
+    expected: Option<&AuthToken>,
+) -> Result<Option<Capabilities>, HandshakeError> {
+    let remote = match socket.read_u8().await {
+        Ok(version) => version,
+        Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    // a client speaking a different version of the protocol may not send capabilities, so
+    // we respond right away without reading anything else
+    if remote != PROTOCOL_VERSION {
+        socket
+            .write_all(&[status_codes::PROTOCOL_VERSION_MISMATCH, PROTOCOL_VERSION])
+            .await?;
+        return Err(HandshakeError::VersionMismatch {
+            local: PROTOCOL_VERSION,
+            remote,
+        });
+    }
+
+    let mut token = [0u8; AUTH_TOKEN_BYTES];
+    socket.read_exact(&mut token).await?;
+    let requested = Capabilities::from_bits(socket.read_u8().await?);
+
+    if let Some(expected) = expected {
+        if !expected.matches(&token) {
+            socket.write_all(&[status_codes::AUTH_FAILED]).await?;
+            return Err(HandshakeError::AuthFailed);
+        }
+    }
+
+    let negotiated = requested.intersect(supported);
+    socket
+        .write_all(&[status_codes::SUCCESS, negotiated.bits()])
+        .await?;
+    Ok(Some(negotiated))
+}
+
+// AUTHENTICATION TOKEN
+// ================================================================================================
+
+/// A shared secret which clients present to the server during the handshake. Tokens are parsed
+/// from 64-character hexadecimal strings.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthToken([u8; AUTH_TOKEN_BYTES]);
+
+impl AuthToken {
+    /// Creates a new token from the specified bytes.
+    pub fn new(bytes: [u8; AUTH_TOKEN_BYTES]) -> Self {
+        AuthToken(bytes)
+    }
+
+    /// Returns true if the specified bytes are the same as the bytes of this token. The time
+    /// it takes to compare the tokens does not depend on their contents so that the token
+    /// cannot be guessed byte-by-byte by timing failed handshakes.
+    pub fn matches(&self, other: &[u8; AUTH_TOKEN_BYTES]) -> bool {
+        let diff = self
+            .0
+            .iter()
+            .zip(other.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b));
+        diff == 0
+    }
+}
+
+impl FromStr for AuthToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0u8; AUTH_TOKEN_BYTES];
+        hex::decode_to_slice(s.trim(), &mut bytes).map_err(|_| {
+            format!(
+                "authentication token must be a {}-character hexadecimal string",
+                AUTH_TOKEN_BYTES * 2
+            )
+        })?;
+        Ok(AuthToken(bytes))
+    }
+}
+
+impl Debug for AuthToken {
+    // the token is a secret, so it should never end up in logs
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "AuthToken(..)")
+    }
+}
+
+// CAPABILITIES
+// ================================================================================================
+
+/// A set of optional protocol features which can be enabled for a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    /// Object headers carry a CRC32 checksum of object metadata and data.
+    pub const CHECKSUMS: u8 = 0x01;
+
+    /// Object data is compressed with LZ4 chunk by chunk; every chunk is preceded by its size
+    /// on the wire.
+    pub const COMPRESSION: u8 = 0x02;
+
+    /// COPY and TAKE responses carry a `TransferManifest` of the transferred objects between
+    /// BEGIN and the first object.
+    pub const MANIFEST: u8 = 0x04;
+
+    /// Object headers carry a byte identifying the codec of object data (see `Codec`), so that
+    /// the codec can be chosen per object; this matters only if compression is enabled as well.
+    pub const CODECS: u8 = 0x08;
+
+    /// Returns an empty set of capabilities.
+    pub fn none() -> Self {
+        Capabilities(0)
+    }
+
+    /// Returns the set of all capabilities supported by this implementation.
+    pub fn all() -> Self {
+        Capabilities(Self::CHECKSUMS | Self::COMPRESSION | Self::MANIFEST | Self::CODECS)
+    }
+
+    /// Builds a set of capabilities from a bitmask; unknown bits are ignored.
+    pub fn from_bits(bits: u8) -> Self {
+        Capabilities(bits & Self::all().0)
+    }
+
+    /// Returns the bitmask of this set of capabilities.
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns capabilities present in both sets.
+    pub fn intersect(&self, other: Capabilities) -> Self {
+        Capabilities(self.0 & other.0)
+    }
+
+    /// Returns true if object checksums are enabled.
+    pub fn checksums(&self) -> bool {
+        self.0 & Self::CHECKSUMS != 0
+    }
+
+    /// Returns true if compression of object data is enabled.
+    pub fn compression(&self) -> bool {
+        self.0 & Self::COMPRESSION != 0
+    }
+
+    /// Returns true if COPY and TAKE responses carry a manifest of the transferred objects.
+    pub fn manifest(&self) -> bool {
+        self.0 & Self::MANIFEST != 0
+    }
+
+    /// Returns true if object headers carry the codec of object data.
+    pub fn codecs(&self) -> bool {
+        self.0 & Self::CODECS != 0
+    }
+}
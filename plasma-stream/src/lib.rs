@@ -4,23 +4,52 @@
 // LICENSE file in the root directory of this source tree.
 
 mod request;
-pub use request::{PeerRequest, Request};
+pub use request::{PeerAddr, PeerRequest, Request, VerboseRequest};
+
+mod estimate;
+pub use estimate::TransferEstimate;
+
+mod codec;
+pub use codec::{Codec, CODEC_HINT_PREFIX};
+
+mod manifest;
+pub use manifest::{
+    object_hash, ManifestDiscrepancy, ManifestEntry, ObjectHash, TransferManifest,
+    OBJECT_HASH_BYTES,
+};
 
 mod client;
-pub use client::Client;
+pub use client::{
+    Client, ClientBuilder, ConnectOptions, ReplicationCount, RetryPolicy, DEFAULT_CONNECT_TIMEOUT,
+    DRAIN_BATCH_SIZE,
+};
 
 pub mod errors;
+pub mod handshake;
 pub mod utils;
 
+#[cfg(test)]
+mod tests;
+
 // CONSTANTS
 // ================================================================================================
 
+/// Version of the wire protocol; clients send this as the first byte on every connection.
+pub const PROTOCOL_VERSION: u8 = 3;
+
 pub const OBJECT_ID_BYTES: usize = 20;
 
+/// Size of the authentication token clients send during the protocol handshake.
+pub const AUTH_TOKEN_BYTES: usize = 32;
+
 pub const MAX_META_SIZE: u64 = 65_536; // 2^16 or 64 KB
 pub const MAX_DATA_SIZE: u64 = 17_592_186_044_416; // 2^44 or 16 TB
 
-const MAX_OBJECT_ID_LIST_LEN: usize = 65_536; // 2^16
+/// Object data is written into / read from sockets in chunks of at most this many bytes.
+pub const DATA_CHUNK_SIZE: usize = 4_194_304; // 2^22 or 4 MB
+
+/// Maximum number of object IDs in a single request or peer request.
+pub const MAX_OBJECT_ID_LIST_LEN: usize = 1_048_576; // 2^20
 const MAX_NUM_SYNC_PEERS: usize = 1024;
 
 pub mod status_codes {
@@ -29,15 +58,21 @@ pub mod status_codes {
     pub const OB_META_TOO_LARGE_ERR: u8 = 0x50;
     pub const OB_DATA_TOO_LARGE_ERR: u8 = 0x51;
     pub const OB_DATA_ZERO_LENGTH_ERR: u8 = 0x52;
+    pub const OB_CHECKSUM_MISMATCH_ERR: u8 = 0x53;
     pub const PLASMA_STORE_ERR: u8 = 0x60;
     pub const PEER_PLASMA_STORE_ERR: u8 = 0x61;
     pub const PEER_REQUEST_PANICKED: u8 = 0x62;
+    pub const TRANSFER_CANCELLED: u8 = 0x63;
     pub const OB_DELETION_SCHEDULED_ERR: u8 = 0x70;
     pub const OB_NOT_FOUND_ERR: u8 = 0x71;
     pub const OB_ALREADY_RECEIVING_ERR: u8 = 0x80;
     pub const OB_ALREADY_IN_STORE_ERR: u8 = 0x81;
     pub const PEER_CONNECTION_ERR: u8 = 0x90;
     pub const CLIENT_CONNECTION_ERR: u8 = 0x91;
+    pub const CONNECTION_LIMIT_EXCEEDED: u8 = 0x92;
+    pub const PROTOCOL_VERSION_MISMATCH: u8 = 0xA0;
+    pub const AUTH_FAILED: u8 = 0xA1;
+    pub const SERVER_BUSY: u8 = 0xA2;
 }
 
 // CONVENIENCE TYPES
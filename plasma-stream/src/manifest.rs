@@ -0,0 +1,193 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::{ObjectId, OBJECT_ID_BYTES};
+use rustc_hash::FxHashMap;
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Display, Formatter};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// CONSTANTS
+// ================================================================================================
+
+/// Size of an object hash (in bytes) listed in a transfer manifest.
+pub const OBJECT_HASH_BYTES: usize = 32;
+
+/// SHA-256 hash of object metadata followed by object data.
+pub type ObjectHash = [u8; OBJECT_HASH_BYTES];
+
+// TRANSFER MANIFEST
+// ================================================================================================
+
+/// Describes the objects a COPY or TAKE response is about to transfer; if manifests are
+/// negotiated for a connection, the server writes the manifest between BEGIN and the first
+/// object of the response, and the client verifies the received objects against it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferManifest {
+    /// Objects to be transferred, in the order in which they are sent.
+    pub entries: Vec<ManifestEntry>,
+    /// Total number of bytes of data and metadata of all objects to be transferred.
+    pub total_size: u64,
+}
+
+/// Describes a single object listed in a `TransferManifest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub id: ObjectId,
+    /// Number of bytes of data and metadata of the object.
+    pub size: u64,
+    pub hash: ObjectHash,
+}
+
+impl ManifestEntry {
+    /// Returns an entry describing an object with the specified ID, metadata, and data.
+    pub fn new(id: ObjectId, meta: &[u8], data: &[u8]) -> Self {
+        ManifestEntry {
+            id,
+            size: (meta.len() + data.len()) as u64,
+            hash: object_hash(meta, data),
+        }
+    }
+}
+
+impl TransferManifest {
+    /// Returns a manifest listing the specified entries; the total size is the sum of sizes
+    /// of all entries.
+    pub fn new(entries: Vec<ManifestEntry>) -> Self {
+        let total_size = entries.iter().map(|entry| entry.size).sum();
+        TransferManifest {
+            entries,
+            total_size,
+        }
+    }
+
+    /// Compares the received objects described by `received` against this manifest, and
+    /// returns all discrepancies found; an empty list means that exactly the objects listed in
+    /// the manifest were received.
+    pub fn verify(&self, received: &[ManifestEntry]) -> Vec<ManifestDiscrepancy> {
+        let mut listed: FxHashMap<&ObjectId, &ManifestEntry> = self
+            .entries
+            .iter()
+            .map(|entry| (&entry.id, entry))
+            .collect();
+
+        let mut discrepancies = Vec::new();
+        for entry in received {
+            match listed.remove(&entry.id) {
+                None => discrepancies.push(ManifestDiscrepancy::Unlisted(entry.id)),
+                Some(expected) if expected.size != entry.size => discrepancies.push(
+                    ManifestDiscrepancy::SizeMismatch(entry.id, expected.size, entry.size),
+                ),
+                Some(expected) if expected.hash != entry.hash => {
+                    discrepancies.push(ManifestDiscrepancy::HashMismatch(entry.id))
+                }
+                Some(_) => (),
+            }
+        }
+
+        // report objects which were listed but not received in the order they were listed
+        for entry in self.entries.iter() {
+            if listed.contains_key(&entry.id) {
+                discrepancies.push(ManifestDiscrepancy::NotReceived(entry.id));
+            }
+        }
+
+        let received_size = received.iter().map(|entry| entry.size).sum();
+        if self.total_size != received_size {
+            discrepancies.push(ManifestDiscrepancy::TotalSizeMismatch(
+                self.total_size,
+                received_size,
+            ));
+        }
+
+        discrepancies
+    }
+
+    /// Reads a manifest from the specified socket; the manifest must have been written via
+    /// `write_into()`.
+    pub async fn read_from<R: AsyncRead + Unpin>(socket: &mut R) -> std::io::Result<Self> {
+        let num_entries = socket.read_u32_le().await? as usize;
+        let mut entries = Vec::new();
+        for _ in 0..num_entries {
+            let mut id = [0u8; OBJECT_ID_BYTES];
+            socket.read_exact(&mut id).await?;
+            let size = socket.read_u64_le().await?;
+            let mut hash = [0u8; OBJECT_HASH_BYTES];
+            socket.read_exact(&mut hash).await?;
+            entries.push(ManifestEntry { id, size, hash });
+        }
+        let total_size = socket.read_u64_le().await?;
+        Ok(TransferManifest {
+            entries,
+            total_size,
+        })
+    }
+
+    /// Writes this manifest into the specified socket. The number of entries (as u32) is
+    /// written first, followed by the entries, each as an object ID, object size (as u64), and
+    /// object hash; the total size (as u64) is written last.
+    pub async fn write_into<W: AsyncWrite + Unpin>(&self, socket: &mut W) -> std::io::Result<()> {
+        socket.write_u32_le(self.entries.len() as u32).await?;
+        for entry in self.entries.iter() {
+            socket.write_all(&entry.id).await?;
+            socket.write_u64_le(entry.size).await?;
+            socket.write_all(&entry.hash).await?;
+        }
+        socket.write_u64_le(self.total_size).await
+    }
+}
+
+// MANIFEST DISCREPANCY
+// ================================================================================================
+
+/// Describes how received objects differ from the manifest sent by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestDiscrepancy {
+    /// An object was received, but was not listed in the manifest.
+    Unlisted(ObjectId),
+    /// An object was listed in the manifest, but was not received.
+    NotReceived(ObjectId),
+    /// Size of a received object differs from the size listed in the manifest; sizes are
+    /// listed as (listed, received).
+    SizeMismatch(ObjectId, u64, u64),
+    /// Hash of a received object differs from the hash listed in the manifest.
+    HashMismatch(ObjectId),
+    /// Total size of received objects differs from the total size listed in the manifest;
+    /// sizes are listed as (listed, received).
+    TotalSizeMismatch(u64, u64),
+}
+
+impl Display for ManifestDiscrepancy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unlisted(oid) => write!(f, "0x{} was not listed", hex::encode(oid)),
+            Self::NotReceived(oid) => write!(f, "0x{} was not received", hex::encode(oid)),
+            Self::SizeMismatch(oid, listed, received) => write!(
+                f,
+                "0x{} has {} bytes, but {} bytes were listed",
+                hex::encode(oid),
+                received,
+                listed
+            ),
+            Self::HashMismatch(oid) => write!(f, "0x{} does not match its hash", hex::encode(oid)),
+            Self::TotalSizeMismatch(listed, received) => write!(
+                f,
+                "received {} bytes in total, but {} bytes were listed",
+                received, listed
+            ),
+        }
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Computes a SHA-256 hash of object metadata followed by object data.
+pub fn object_hash(meta: &[u8], data: &[u8]) -> ObjectHash {
+    let mut hasher = Sha256::new();
+    hasher.update(meta);
+    hasher.update(data);
+    hasher.finalize().into()
+}
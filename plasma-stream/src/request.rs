@@ -6,14 +6,19 @@
 use crate::{
     errors::RequestError, ObjectId, MAX_NUM_SYNC_PEERS, MAX_OBJECT_ID_LIST_LEN, OBJECT_ID_BYTES,
 };
+use rustc_hash::FxHashSet;
 use std::{
-    collections::HashSet,
+    convert::TryInto,
+    ffi::OsStr,
     fmt::{Display, Formatter},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+    str::FromStr,
 };
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::lookup_host,
 };
 
 // CONSTANTS
@@ -21,18 +26,59 @@ use tokio::{
 const SYNC_TYPE_ID: u8 = 1;
 const COPY_TYPE_ID: u8 = 2;
 const TAKE_TYPE_ID: u8 = 3;
+const LIST_TYPE_ID: u8 = 4;
+const CONTAINS_TYPE_ID: u8 = 5;
+const DELETE_TYPE_ID: u8 = 6;
+const PING_TYPE_ID: u8 = 7;
+const SYNC_FIRST_WINS_TYPE_ID: u8 = 8;
+const ESTIMATE_TYPE_ID: u8 = 9;
+const PUSH_TYPE_ID: u8 = 10;
+const PREWARM_TYPE_ID: u8 = 11;
 
+const HOSTNAME_TYPE_ID: u8 = 1;
+const UNIX_TYPE_ID: u8 = 2;
 const IPV4_TYPE_ID: u8 = 4;
 const IPV6_TYPE_ID: u8 = 6;
 
+/// Maximum length of a peer hostname in bytes; this is the maximum length of a DNS name.
+const MAX_HOSTNAME_LEN: usize = 255;
+
+/// Maximum length of a Unix socket path in bytes; this is the size of `sun_path` on Linux less
+/// the terminating null byte.
+const MAX_UNIX_PATH_LEN: usize = 107;
+
+/// Prefix which distinguishes Unix socket paths from network addresses in peer address strings.
+const UNIX_ADDR_PREFIX: &str = "unix:";
+
+/// Maximum number of object IDs displayed for every object ID list of a non-verbose request.
+const MAX_DISPLAYED_OBJECT_IDS: usize = 5;
+
 // REQUEST
 // ================================================================================================
 
 #[derive(Debug)]
 pub enum Request {
     Sync(Vec<PeerRequest>),
+    /// Same as SYNC, but the same object may be listed under several peer requests; such an
+    /// object is requested from the first peer listing it, and the following peers are used
+    /// as fallbacks only if receiving the object from the preceding peers fails.
+    SyncFirstWins(Vec<PeerRequest>),
     Copy(Vec<ObjectId>),
     Take(Vec<ObjectId>),
+    List,
+    Contains(Vec<ObjectId>),
+    Delete(Vec<ObjectId>),
+    Ping,
+    /// Same as COPY, but no objects are sent; instead, the server responds with a description
+    /// of what a COPY request for the same objects would transfer.
+    Estimate(Vec<ObjectId>),
+    /// Reverse of COPY: the objects are sent by the initiator of the request, and the server
+    /// saves them into its store.
+    Push(Vec<ObjectId>),
+    /// Opens connections to the listed peers ahead of a SYNC request; the server keeps the
+    /// connections in its pool of idle connections, and peer requests of subsequent SYNC
+    /// requests reuse them. A peer listed several times gets several connections.
+    Prewarm(Vec<PeerAddr>),
 }
 
 impl Request {
@@ -41,7 +87,7 @@ impl Request {
     /// * The socket has been closed; in this case `None` will be returned.
     /// * The data read from the socket does not represent a valid request; in this case
     ///   an error will be returned.
-    pub async fn read_from(socket: &mut TcpStream) -> crate::Result<Option<Self>> {
+    pub async fn read_from<R: AsyncRead + Unpin>(socket: &mut R) -> crate::Result<Option<Self>> {
         // determine request type; also return `None` if the connection has been closed
         let request_type = match socket.read_u8().await {
             Ok(request_type) => request_type,
@@ -52,14 +98,13 @@ impl Request {
         // based on the type, read the rest of the request
         match request_type {
             SYNC_TYPE_ID => {
-                let num_peer_requests = socket.read_u16_le().await?;
-                let mut peer_requests = Vec::with_capacity(num_peer_requests as usize);
-                for _ in 0..num_peer_requests {
-                    let peer_request = PeerRequest::read_from(socket).await?;
-                    peer_requests.push(peer_request);
-                }
+                let peer_requests = read_peer_request_list(socket).await?;
                 Ok(Some(Self::Sync(peer_requests)))
             }
+            SYNC_FIRST_WINS_TYPE_ID => {
+                let peer_requests = read_peer_request_list(socket).await?;
+                Ok(Some(Self::SyncFirstWins(peer_requests)))
+            }
             COPY_TYPE_ID => {
                 let object_ids = read_object_id_list(socket).await?;
                 Ok(Some(Self::Copy(object_ids)))
@@ -68,19 +113,45 @@ impl Request {
                 let object_ids = read_object_id_list(socket).await?;
                 Ok(Some(Self::Take(object_ids)))
             }
+            LIST_TYPE_ID => Ok(Some(Self::List)),
+            CONTAINS_TYPE_ID => {
+                let object_ids = read_object_id_list(socket).await?;
+                Ok(Some(Self::Contains(object_ids)))
+            }
+            DELETE_TYPE_ID => {
+                let object_ids = read_object_id_list(socket).await?;
+                Ok(Some(Self::Delete(object_ids)))
+            }
+            PING_TYPE_ID => Ok(Some(Self::Ping)),
+            ESTIMATE_TYPE_ID => {
+                let object_ids = read_object_id_list(socket).await?;
+                Ok(Some(Self::Estimate(object_ids)))
+            }
+            PUSH_TYPE_ID => {
+                let object_ids = read_object_id_list(socket).await?;
+                Ok(Some(Self::Push(object_ids)))
+            }
+            PREWARM_TYPE_ID => {
+                let peers = read_peer_addr_list(socket).await?;
+                Ok(Some(Self::Prewarm(peers)))
+            }
             _ => Err(RequestError::InvalidRequestType(request_type).into()),
         }
     }
 
     /// Writes this request into the socket.
-    pub async fn write_into(&self, socket: &mut TcpStream) -> Result<(), std::io::Error> {
+    pub async fn write_into<W: AsyncWrite + Unpin>(
+        &self,
+        socket: &mut W,
+    ) -> Result<(), std::io::Error> {
         match self {
             Request::Sync(peer_requests) => {
                 socket.write_u8(SYNC_TYPE_ID).await?;
-                socket.write_u16_le(peer_requests.len() as u16).await?;
-                for peer_request in peer_requests.iter() {
-                    peer_request.write_into(socket).await?;
-                }
+                write_peer_request_list(peer_requests, socket).await?;
+            }
+            Request::SyncFirstWins(peer_requests) => {
+                socket.write_u8(SYNC_FIRST_WINS_TYPE_ID).await?;
+                write_peer_request_list(peer_requests, socket).await?;
             }
             Request::Copy(object_ids) => {
                 socket.write_u8(COPY_TYPE_ID).await?;
@@ -90,27 +161,47 @@ impl Request {
                 socket.write_u8(TAKE_TYPE_ID).await?;
                 write_object_id_list(object_ids, socket).await?;
             }
+            Request::List => {
+                socket.write_u8(LIST_TYPE_ID).await?;
+            }
+            Request::Contains(object_ids) => {
+                socket.write_u8(CONTAINS_TYPE_ID).await?;
+                write_object_id_list(object_ids, socket).await?;
+            }
+            Request::Delete(object_ids) => {
+                socket.write_u8(DELETE_TYPE_ID).await?;
+                write_object_id_list(object_ids, socket).await?;
+            }
+            Request::Ping => {
+                socket.write_u8(PING_TYPE_ID).await?;
+            }
+            Request::Estimate(object_ids) => {
+                socket.write_u8(ESTIMATE_TYPE_ID).await?;
+                write_object_id_list(object_ids, socket).await?;
+            }
+            Request::Push(object_ids) => {
+                socket.write_u8(PUSH_TYPE_ID).await?;
+                write_object_id_list(object_ids, socket).await?;
+            }
+            Request::Prewarm(peers) => {
+                socket.write_u8(PREWARM_TYPE_ID).await?;
+                write_peer_addr_list(peers, socket).await?;
+            }
         }
         Ok(())
     }
 
     /// Checks if this request is valid. Specifically, makes sure:
-    /// * There are no duplicated object IDs present in the request.
+    /// * There are no duplicated object IDs present in the request; for SYNC FIRST WINS
+    ///   requests, the same ID may appear in different peer requests, but not in the same one.
     /// * Number of objects in a single request does not exceed the allowed limit.
+    /// * Number of peers in a PREWARM request does not exceed the limit on peer requests.
     pub fn validate(&self) -> Result<(), RequestError> {
         match self {
             Request::Sync(peer_requests) => {
-                // make sure peer request lists is neither too long nor too short
-                if peer_requests.is_empty() {
-                    return Err(RequestError::PeerRequestListTooShort);
-                }
-                if peer_requests.len() > MAX_NUM_SYNC_PEERS {
-                    return Err(RequestError::PeerRequestListTooLong(peer_requests.len()));
-                }
-                // TODO: use non-cryptographic hashing
-                let mut unique_objects = HashSet::new();
+                validate_peer_request_list(peer_requests)?;
+                let mut unique_objects = FxHashSet::default();
                 for peer_request in peer_requests.iter() {
-                    peer_request.validate()?;
                     let incoming_objects = peer_request.incoming_objects();
                     // if a duplicate ID is found, return an error
                     for oid in incoming_objects {
@@ -120,7 +211,26 @@ impl Request {
                     }
                 }
             }
-            Request::Take(object_ids) | Request::Copy(object_ids) => {
+            Request::SyncFirstWins(peer_requests) => {
+                validate_peer_request_list(peer_requests)?;
+                for peer_request in peer_requests.iter() {
+                    if let PeerRequest::Push { .. } = peer_request {
+                        return Err(RequestError::UnexpectedPushRequest);
+                    }
+                    let mut unique_objects = FxHashSet::default();
+                    for oid in peer_request.incoming_objects() {
+                        if !unique_objects.insert(oid) {
+                            return Err(RequestError::DuplicateObjectIds);
+                        }
+                    }
+                }
+            }
+            Request::Take(object_ids)
+            | Request::Copy(object_ids)
+            | Request::Contains(object_ids)
+            | Request::Delete(object_ids)
+            | Request::Estimate(object_ids)
+            | Request::Push(object_ids) => {
                 // make sure object ID list is neither too long nor too short
                 if object_ids.is_empty() {
                     return Err(RequestError::ObjectIdListTooShort);
@@ -129,83 +239,157 @@ impl Request {
                     return Err(RequestError::ObjectIdListTooLong(object_ids.len()));
                 }
                 // if a duplicate ID is found, return an error
-                // TODO: use non-cryptographic hashing
-                let mut unique_objects = HashSet::new();
+                let mut unique_objects = FxHashSet::default();
                 for oid in object_ids {
                     if !unique_objects.insert(oid) {
                         return Err(RequestError::DuplicateObjectIds);
                     }
                 }
             }
+            Request::Prewarm(peers) => {
+                if peers.is_empty() {
+                    return Err(RequestError::PeerListTooShort);
+                }
+                if peers.len() > MAX_NUM_SYNC_PEERS {
+                    return Err(RequestError::PeerListTooLong(peers.len()));
+                }
+                for peer in peers.iter() {
+                    peer.validate()?;
+                }
+            }
+            Request::List | Request::Ping => {}
         }
         Ok(())
     }
-}
 
-impl Display for Request {
-    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+    /// Returns a wrapper which displays this request with all object IDs listed; by default,
+    /// only the first few object IDs of every list are displayed.
+    pub fn verbose(&self) -> VerboseRequest<'_> {
+        VerboseRequest(self)
+    }
+
+    fn fmt_with(&self, f: &mut Formatter, verbose: bool) -> core::fmt::Result {
         match self {
             Request::Sync(requests) => {
                 write!(f, "SYNC")?;
                 for request in requests.iter() {
-                    write!(f, "\n{}", request)?;
+                    writeln!(f)?;
+                    request.fmt_with(f, verbose)?;
                 }
-                write!(f, "")
+                Ok(())
+            }
+            Request::SyncFirstWins(requests) => {
+                write!(f, "SYNC FIRST WINS")?;
+                for request in requests.iter() {
+                    writeln!(f)?;
+                    request.fmt_with(f, verbose)?;
+                }
+                Ok(())
             }
             Request::Copy(object_ids) => {
-                write!(
-                    f,
-                    "COPY {:?}",
-                    object_ids.iter().map(hex::encode).collect::<Vec<_>>()
-                )
+                write!(f, "COPY ")?;
+                fmt_object_id_list(f, object_ids, verbose)
             }
             Request::Take(object_ids) => {
-                write!(
-                    f,
-                    "TAKE {:?}",
-                    object_ids.iter().map(hex::encode).collect::<Vec<_>>()
-                )
+                write!(f, "TAKE ")?;
+                fmt_object_id_list(f, object_ids, verbose)
+            }
+            Request::List => write!(f, "LIST"),
+            Request::Contains(object_ids) => {
+                write!(f, "CONTAINS ")?;
+                fmt_object_id_list(f, object_ids, verbose)
+            }
+            Request::Delete(object_ids) => {
+                write!(f, "DELETE ")?;
+                fmt_object_id_list(f, object_ids, verbose)
+            }
+            Request::Ping => write!(f, "PING"),
+            Request::Estimate(object_ids) => {
+                write!(f, "ESTIMATE ")?;
+                fmt_object_id_list(f, object_ids, verbose)
+            }
+            Request::Push(object_ids) => {
+                write!(f, "PUSH ")?;
+                fmt_object_id_list(f, object_ids, verbose)
+            }
+            Request::Prewarm(peers) => {
+                write!(f, "PREWARM")?;
+                for peer in peers.iter() {
+                    write!(f, " {}", peer)?;
+                }
+                Ok(())
             }
         }
     }
 }
 
+/// Displays at most `MAX_DISPLAYED_OBJECT_IDS` object IDs of every object ID list in the request
+/// followed by the total number of IDs in the list; use `Request::verbose()` to display all IDs.
+impl Display for Request {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        self.fmt_with(f, false)
+    }
+}
+
+/// Wrapper which displays a request with all object IDs listed.
+pub struct VerboseRequest<'a>(&'a Request);
+
+impl<'a> Display for VerboseRequest<'a> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        self.0.fmt_with(f, true)
+    }
+}
+
 // PEER REQUESTS
 // ================================================================================================
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum PeerRequest {
     Copy {
-        from: SocketAddr,
+        from: PeerAddr,
         objects: Vec<ObjectId>,
     },
     Take {
-        from: SocketAddr,
+        from: PeerAddr,
+        objects: Vec<ObjectId>,
+    },
+    /// Sends objects from the store of the server executing the SYNC request to the peer; the
+    /// objects are not deleted from the local store.
+    Push {
+        to: PeerAddr,
         objects: Vec<ObjectId>,
     },
 }
 
 impl PeerRequest {
     /// Reads a SYNC peer request from the specified socket.
-    pub async fn read_from(socket: &mut TcpStream) -> crate::Result<Self> {
+    pub async fn read_from<R: AsyncRead + Unpin>(socket: &mut R) -> crate::Result<Self> {
         let request_type = socket.read_u8().await?;
         match request_type {
             COPY_TYPE_ID => {
-                let from = read_socket_addr(socket).await?;
+                let from = read_peer_addr(socket).await?;
                 let objects = read_object_id_list(socket).await?;
                 Ok(PeerRequest::Copy { from, objects })
             }
             TAKE_TYPE_ID => {
-                let from = read_socket_addr(socket).await?;
+                let from = read_peer_addr(socket).await?;
                 let objects = read_object_id_list(socket).await?;
                 Ok(PeerRequest::Take { from, objects })
             }
+            PUSH_TYPE_ID => {
+                let to = read_peer_addr(socket).await?;
+                let objects = read_object_id_list(socket).await?;
+                Ok(PeerRequest::Push { to, objects })
+            }
             _ => Err(RequestError::InvalidPeerRequestType(request_type).into()),
         }
     }
 
     // Writes a SYNC peer request into the specified socket.
-    pub async fn write_into(&self, socket: &mut TcpStream) -> Result<(), std::io::Error> {
+    pub async fn write_into<W: AsyncWrite + Unpin>(
+        &self,
+        socket: &mut W,
+    ) -> Result<(), std::io::Error> {
         match self {
             Self::Copy { from, objects } => {
                 socket.write_u8(COPY_TYPE_ID).await?;
@@ -217,22 +401,36 @@ impl PeerRequest {
                 write_peer_addr(from, socket).await?;
                 write_object_id_list(objects, socket).await?;
             }
+            Self::Push { to, objects } => {
+                socket.write_u8(PUSH_TYPE_ID).await?;
+                write_peer_addr(to, socket).await?;
+                write_object_id_list(objects, socket).await?;
+            }
         }
         Ok(())
     }
 
-    // Checks whether this peer request is valid.
+    // Checks whether this peer request is valid. Objects received from peers are checked for
+    // duplicates across the entire SYNC request, while objects pushed to a peer must be unique
+    // within the peer request.
     pub fn validate(&self) -> Result<(), RequestError> {
-        match self {
-            Self::Copy { objects, .. } | Self::Take { objects, .. } => {
-                // make sure object ID list is neither too long nor too short
-                if objects.is_empty() {
-                    return Err(RequestError::ObjectIdListTooShort);
-                }
-                if objects.len() > MAX_OBJECT_ID_LIST_LEN {
-                    return Err(RequestError::ObjectIdListTooLong(objects.len()));
+        let (peer, objects) = match self {
+            Self::Copy { from, objects } | Self::Take { from, objects } => (from, objects),
+            Self::Push { to, objects } => {
+                let mut unique_objects = FxHashSet::default();
+                if !objects.iter().all(|oid| unique_objects.insert(oid)) {
+                    return Err(RequestError::DuplicateObjectIds);
                 }
+                (to, objects)
             }
+        };
+        peer.validate()?;
+        // make sure object ID list is neither too long nor too short
+        if objects.is_empty() {
+            return Err(RequestError::ObjectIdListTooShort);
+        }
+        if objects.len() > MAX_OBJECT_ID_LIST_LEN {
+            return Err(RequestError::ObjectIdListTooLong(objects.len()));
         }
         Ok(())
     }
@@ -242,116 +440,407 @@ impl PeerRequest {
         match self {
             PeerRequest::Copy { objects, .. } => &objects,
             PeerRequest::Take { objects, .. } => &objects,
+            PeerRequest::Push { .. } => &[],
+        }
+    }
+
+    /// Returns IDs of all objects listed in this peer request, regardless of whether they are
+    /// received from or sent to the peer.
+    pub fn objects(&self) -> &[ObjectId] {
+        match self {
+            PeerRequest::Copy { objects, .. }
+            | PeerRequest::Take { objects, .. }
+            | PeerRequest::Push { objects, .. } => objects,
+        }
+    }
+
+    /// Returns address of the peer from which objects will be received, or to which objects
+    /// will be sent.
+    pub fn peer(&self) -> &PeerAddr {
+        match self {
+            PeerRequest::Copy { from, .. } => from,
+            PeerRequest::Take { from, .. } => from,
+            PeerRequest::Push { to, .. } => to,
         }
     }
 
-    /// Returns true if this peer requests contains the specified peer address.
+    /// Returns true if this peer requests contains the specified peer address. Hostnames are
+    /// not resolved, and thus, never match a socket address.
     pub fn contains_peer(&self, address: &SocketAddr) -> bool {
+        matches!(self.peer(), PeerAddr::Socket(from) if from == address)
+    }
+
+    fn fmt_with(&self, f: &mut Formatter, verbose: bool) -> core::fmt::Result {
         match self {
-            PeerRequest::Copy { from, .. } => from == address,
-            PeerRequest::Take { from, .. } => from == address,
+            PeerRequest::Copy { from, objects } => {
+                write!(f, "COPY {} ", from)?;
+                fmt_object_id_list(f, objects, verbose)
+            }
+            PeerRequest::Take { from, objects } => {
+                write!(f, "TAKE {} ", from)?;
+                fmt_object_id_list(f, objects, verbose)
+            }
+            PeerRequest::Push { to, objects } => {
+                write!(f, "PUSH {} ", to)?;
+                fmt_object_id_list(f, objects, verbose)
+            }
         }
     }
 }
 
 impl Display for PeerRequest {
     fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        self.fmt_with(f, false)
+    }
+}
+
+// PEER ADDRESS
+// ================================================================================================
+
+/// Address of a peer in a SYNC request; a peer can be specified by its socket address, by a
+/// hostname and a port, or by the path of a Unix domain socket when the peer runs on the same
+/// host as the server. Hostnames are resolved by the server executing the request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PeerAddr {
+    Socket(SocketAddr),
+    Host(String, u16),
+    Unix(PathBuf),
+}
+
+impl PeerAddr {
+    /// Resolves this address into a socket address; if a hostname resolves to multiple
+    /// addresses, the first one is returned. Unix socket paths cannot be resolved into socket
+    /// addresses, and an error is returned for them.
+    pub async fn resolve(&self) -> Result<SocketAddr, std::io::Error> {
         match self {
-            PeerRequest::Copy { from, objects } => {
-                write!(
-                    f,
-                    "COPY {} {:?}",
-                    from,
-                    objects.iter().map(hex::encode).collect::<Vec<_>>()
-                )
+            Self::Socket(addr) => Ok(*addr),
+            Self::Host(host, port) => lookup_host((host.as_str(), *port))
+                .await?
+                .next()
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("no addresses found for {}", host),
+                    )
+                }),
+            Self::Unix(path) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a network address", path.display()),
+            )),
+        }
+    }
+
+    /// Checks whether this address can be written into a request.
+    pub fn validate(&self) -> Result<(), RequestError> {
+        match self {
+            Self::Socket(_) => Ok(()),
+            Self::Host(host, _) => {
+                if host.is_empty() || host.len() > MAX_HOSTNAME_LEN {
+                    return Err(RequestError::InvalidPeerHostname(host.clone()));
+                }
+                Ok(())
             }
-            PeerRequest::Take { from, objects } => {
-                write!(
-                    f,
-                    "TAKE {} {:x?}",
-                    from,
-                    objects.iter().map(hex::encode).collect::<Vec<_>>()
-                )
+            Self::Unix(path) => {
+                let len = path.as_os_str().len();
+                if len == 0 || len > MAX_UNIX_PATH_LEN {
+                    return Err(RequestError::InvalidPeerSocketPath(path.clone()));
+                }
+                Ok(())
             }
         }
     }
 }
 
+impl From<SocketAddr> for PeerAddr {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Socket(addr)
+    }
+}
+
+impl FromStr for PeerAddr {
+    type Err = String;
+
+    /// Parses a peer address from a socket address (e.g. `127.0.0.1:2021`), a `host:port`
+    /// string (e.g. `localhost:2021`), or a Unix socket path prefixed with `unix:` (e.g.
+    /// `unix:/tmp/plasma-stream.sock`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix(UNIX_ADDR_PREFIX) {
+            let addr = Self::Unix(PathBuf::from(path));
+            addr.validate().map_err(|err| err.to_string())?;
+            return Ok(addr);
+        }
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(Self::Socket(addr));
+        }
+        let (host, port) = s
+            .rsplit_once(':')
+            .ok_or_else(|| String::from("address must be in host:port format"))?;
+        let port = port
+            .parse::<u16>()
+            .map_err(|err| format!("port {} is invalid: {}", port, err))?;
+        let addr = Self::Host(String::from(host), port);
+        addr.validate().map_err(|err| err.to_string())?;
+        Ok(addr)
+    }
+}
+
+impl Display for PeerAddr {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            Self::Socket(addr) => write!(f, "{}", addr),
+            Self::Host(host, port) => write!(f, "{}:{}", host, port),
+            Self::Unix(path) => write!(f, "{}{}", UNIX_ADDR_PREFIX, path.display()),
+        }
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Makes sure the list of peer requests within a SYNC request is neither too long nor too short,
+/// and that every peer request in the list is valid.
+fn validate_peer_request_list(peer_requests: &[PeerRequest]) -> Result<(), RequestError> {
+    if peer_requests.is_empty() {
+        return Err(RequestError::PeerRequestListTooShort);
+    }
+    if peer_requests.len() > MAX_NUM_SYNC_PEERS {
+        return Err(RequestError::PeerRequestListTooLong(peer_requests.len()));
+    }
+    for peer_request in peer_requests.iter() {
+        peer_request.validate()?;
+    }
+    Ok(())
+}
+
+/// Writes object IDs in hex as a list; unless `verbose` is set, only the first
+/// `MAX_DISPLAYED_OBJECT_IDS` IDs are written followed by the total number of IDs.
+fn fmt_object_id_list(
+    f: &mut Formatter,
+    object_ids: &[ObjectId],
+    verbose: bool,
+) -> core::fmt::Result {
+    let num_displayed = if verbose {
+        object_ids.len()
+    } else {
+        object_ids.len().min(MAX_DISPLAYED_OBJECT_IDS)
+    };
+    write!(f, "[")?;
+    for (i, oid) in object_ids[..num_displayed].iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "\"{}\"", hex::encode(oid))?;
+    }
+    if num_displayed < object_ids.len() {
+        write!(f, ", ... ({} total)", object_ids.len())?;
+    }
+    write!(f, "]")
+}
+
 // HELPER READERS
 // ================================================================================================
 
 /// Reads peer address from the specified socket.
-async fn read_socket_addr(socket: &mut TcpStream) -> crate::Result<SocketAddr> {
+async fn read_peer_addr<R: AsyncRead + Unpin>(socket: &mut R) -> crate::Result<PeerAddr> {
     let addr_type = socket.read_u8().await?;
     let port = socket.read_u16_le().await?;
 
     match addr_type {
         IPV4_TYPE_ID => {
             let addr = read_ipv4_address(socket).await?;
-            Ok(SocketAddr::new(IpAddr::V4(addr), port))
+            Ok(SocketAddr::new(IpAddr::V4(addr), port).into())
         }
         IPV6_TYPE_ID => {
             let addr = read_ipv6_address(socket).await?;
-            Ok(SocketAddr::new(IpAddr::V6(addr), port))
+            Ok(SocketAddr::new(IpAddr::V6(addr), port).into())
+        }
+        HOSTNAME_TYPE_ID => {
+            let host = read_hostname(socket).await?;
+            Ok(PeerAddr::Host(host, port))
+        }
+        UNIX_TYPE_ID => {
+            let path = read_socket_path(socket).await?;
+            Ok(PeerAddr::Unix(path))
         }
         _ => Err(RequestError::InvalidPeerAddressType(addr_type).into()),
     }
 }
 
-/// Reads an IPv4 address from the specified socket.
-async fn read_ipv4_address(socket: &mut TcpStream) -> Result<Ipv4Addr, std::io::Error> {
-    let a = socket.read_u32_le().await?;
-    Ok(Ipv4Addr::new(
-        a as u8,
-        (a >> 8) as u8,
-        (a >> 16) as u8,
-        (a >> 24) as u8,
-    ))
+/// Reads a hostname from the specified socket; the hostname is expected to be written as a
+/// non-empty UTF-8 string prefixed with its length in bytes (as u8).
+async fn read_hostname<R: AsyncRead + Unpin>(socket: &mut R) -> crate::Result<String> {
+    let len = socket.read_u8().await? as usize;
+    let mut bytes = vec![0u8; len];
+    socket.read_exact(&mut bytes).await?;
+    match String::from_utf8(bytes) {
+        Ok(host) if !host.is_empty() => Ok(host),
+        Ok(host) => Err(RequestError::InvalidPeerHostname(host).into()),
+        Err(err) => Err(RequestError::InvalidPeerHostname(
+            String::from_utf8_lossy(err.as_bytes()).into_owned(),
+        )
+        .into()),
+    }
+}
+
+/// Reads a Unix socket path from the specified socket; the path is expected to be written as a
+/// non-empty sequence of bytes prefixed with its length (as u8).
+async fn read_socket_path<R: AsyncRead + Unpin>(socket: &mut R) -> crate::Result<PathBuf> {
+    let len = socket.read_u8().await? as usize;
+    let mut bytes = vec![0u8; len];
+    socket.read_exact(&mut bytes).await?;
+    let path = PathBuf::from(OsStr::from_bytes(&bytes));
+    if len == 0 || len > MAX_UNIX_PATH_LEN {
+        return Err(RequestError::InvalidPeerSocketPath(path).into());
+    }
+    Ok(path)
 }
 
-/// Reads an IPv6 address from the specified socket.
-async fn read_ipv6_address(_socket: &mut TcpStream) -> Result<Ipv6Addr, std::io::Error> {
-    // TODO: add support for IPv6 addresses
-    unimplemented!()
+/// Reads an IPv4 address from the specified socket; the address is expected to be written as
+/// 4 octets in network byte order.
+async fn read_ipv4_address<R: AsyncRead + Unpin>(
+    socket: &mut R,
+) -> Result<Ipv4Addr, std::io::Error> {
+    let a = socket.read_u8().await?;
+    let b = socket.read_u8().await?;
+    let c = socket.read_u8().await?;
+    let d = socket.read_u8().await?;
+    Ok(Ipv4Addr::new(a, b, c, d))
 }
 
-/// Reads a list of object IDs from the specified socket.
-async fn read_object_id_list(socket: &mut TcpStream) -> Result<Vec<ObjectId>, std::io::Error> {
-    // determine number of object IDs
-    let num_ids = socket.read_u16_le().await? as usize;
+/// Reads an IPv6 address from the specified socket; the address is expected to be written as
+/// 16 octets in network byte order.
+async fn read_ipv6_address<R: AsyncRead + Unpin>(
+    socket: &mut R,
+) -> Result<Ipv6Addr, std::io::Error> {
+    let mut octets = [0u8; 16];
+    socket.read_exact(&mut octets).await?;
+    Ok(Ipv6Addr::from(octets))
+}
+
+/// Reads a list of peer requests from the specified socket; number of peer requests is expected
+/// to be written first (as u16).
+async fn read_peer_request_list<R: AsyncRead + Unpin>(
+    socket: &mut R,
+) -> crate::Result<Vec<PeerRequest>> {
+    let num_peer_requests = socket.read_u16_le().await?;
+    let mut peer_requests = Vec::with_capacity(num_peer_requests as usize);
+    for _ in 0..num_peer_requests {
+        let peer_request = PeerRequest::read_from(socket).await?;
+        peer_requests.push(peer_request);
+    }
+    Ok(peer_requests)
+}
+
+/// Reads a list of peer addresses from the specified socket; number of addresses is expected
+/// to be written first (as u16).
+async fn read_peer_addr_list<R: AsyncRead + Unpin>(socket: &mut R) -> crate::Result<Vec<PeerAddr>> {
+    let num_peers = socket.read_u16_le().await?;
+    let mut peers = Vec::with_capacity(num_peers as usize);
+    for _ in 0..num_peers {
+        peers.push(read_peer_addr(socket).await?);
+    }
+    Ok(peers)
+}
+
+/// Reads a list of object IDs from the specified socket; returns an error if the list is
+/// longer than the allowed limit.
+async fn read_object_id_list<R: AsyncRead + Unpin>(socket: &mut R) -> crate::Result<Vec<ObjectId>> {
+    // determine number of object IDs; we check the number against the limit here (rather than
+    // in validate()) to avoid allocating memory for a list which will be rejected anyway
+    let num_ids = socket.read_u32_le().await? as usize;
+    if num_ids > MAX_OBJECT_ID_LIST_LEN {
+        return Err(RequestError::ObjectIdListTooLong(num_ids).into());
+    }
 
     // read all object ID bytes
     let mut result = vec![0u8; OBJECT_ID_BYTES * num_ids];
     socket.read_exact(&mut result).await?;
 
-    // convert the vector of bytes into a vector of 20-byte arrays
-    let mut v = std::mem::ManuallyDrop::new(result);
-    let p = v.as_mut_ptr();
-    let len = v.len() / OBJECT_ID_BYTES;
-    let cap = v.capacity() / OBJECT_ID_BYTES;
-    unsafe { Ok(Vec::from_raw_parts(p as *mut ObjectId, len, cap)) }
+    Ok(to_object_ids(&result))
+}
+
+/// Splits a buffer of concatenated object IDs into a vector of object IDs; trailing bytes
+/// which do not form a complete object ID are ignored.
+pub(crate) fn to_object_ids(bytes: &[u8]) -> Vec<ObjectId> {
+    // `unwrap()` is OK here because every chunk is exactly OBJECT_ID_BYTES long
+    bytes
+        .chunks_exact(OBJECT_ID_BYTES)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect()
 }
 
 // HELPER WRITERS
 // ================================================================================================
 
+/// Writes a list of peer requests into the socket. Number of peer requests is written into the
+/// socket first (as u16), followed by the actual peer requests.
+async fn write_peer_request_list<W: AsyncWrite + Unpin>(
+    peer_requests: &[PeerRequest],
+    socket: &mut W,
+) -> Result<(), std::io::Error> {
+    socket.write_u16_le(peer_requests.len() as u16).await?;
+    for peer_request in peer_requests.iter() {
+        peer_request.write_into(socket).await?;
+    }
+    Ok(())
+}
+
+/// Writes a list of peer addresses into the socket. Number of addresses is written into the
+/// socket first (as u16), followed by the actual addresses.
+async fn write_peer_addr_list<W: AsyncWrite + Unpin>(
+    peers: &[PeerAddr],
+    socket: &mut W,
+) -> Result<(), std::io::Error> {
+    socket.write_u16_le(peers.len() as u16).await?;
+    for peer in peers.iter() {
+        write_peer_addr(peer, socket).await?;
+    }
+    Ok(())
+}
+
 /// Writes a list of object IDs into the socket. Number of object IDs is written into the
-/// socket first (as u16), followed by the actual object IDs.
-async fn write_object_id_list(
+/// socket first (as u32), followed by the actual object IDs.
+async fn write_object_id_list<W: AsyncWrite + Unpin>(
     object_ids: &[ObjectId],
-    socket: &mut TcpStream,
+    socket: &mut W,
 ) -> Result<(), std::io::Error> {
-    socket.write_u16_le(object_ids.len() as u16).await?;
+    socket.write_u32_le(object_ids.len() as u32).await?;
     for id in object_ids.iter() {
         socket.write_all(id).await?;
     }
     Ok(())
 }
 
-/// Writes socket address of the peer into the socket.
-async fn write_peer_addr(
+/// Writes address of the peer into the socket; hostnames longer than 255 bytes and Unix socket
+/// paths longer than 107 bytes are rejected by `validate()`, and must not be written. Unix
+/// socket paths are written with port 0 so that all address types share the same layout.
+async fn write_peer_addr<W: AsyncWrite + Unpin>(
+    peer_addr: &PeerAddr,
+    socket: &mut W,
+) -> Result<(), std::io::Error> {
+    match peer_addr {
+        PeerAddr::Socket(addr) => write_socket_addr(addr, socket).await?,
+        PeerAddr::Host(host, port) => {
+            socket.write_u8(HOSTNAME_TYPE_ID).await?;
+            socket.write_u16_le(*port).await?;
+            socket.write_u8(host.len() as u8).await?;
+            socket.write_all(host.as_bytes()).await?;
+        }
+        PeerAddr::Unix(path) => {
+            let bytes = path.as_os_str().as_bytes();
+            socket.write_u8(UNIX_TYPE_ID).await?;
+            socket.write_u16_le(0).await?;
+            socket.write_u8(bytes.len() as u8).await?;
+            socket.write_all(bytes).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a numeric socket address of the peer into the socket.
+async fn write_socket_addr<W: AsyncWrite + Unpin>(
     peer_addr: &SocketAddr,
-    socket: &mut TcpStream,
+    socket: &mut W,
 ) -> Result<(), std::io::Error> {
     match peer_addr {
         SocketAddr::V4(peer_addr) => {
@@ -0,0 +1,88 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+// BYTE BUDGET
+// ================================================================================================
+
+/// Limits the total number of bytes of objects which are being received at the same time across
+/// all connections. Receivers acquire a part of the budget proportional to the size of an object
+/// before creating it in the store, and release it once the object is sealed (or receiving of
+/// the object fails).
+///
+/// Waiting receivers are not served in any particular order, so a receiver waiting for a large
+/// part of the budget may be overtaken by receivers of smaller objects.
+#[derive(Debug)]
+pub struct ByteBudget {
+    /// Total number of bytes in the budget.
+    capacity: u64,
+    /// Number of bytes which have not been acquired yet.
+    available: Mutex<u64>,
+    /// Notifies waiting receivers when a part of the budget is released.
+    released: Notify,
+}
+
+impl ByteBudget {
+    /// Returns a new budget of the specified number of bytes.
+    pub fn new(capacity: u64) -> Self {
+        ByteBudget {
+            capacity,
+            available: Mutex::new(capacity),
+            released: Notify::new(),
+        }
+    }
+
+    /// Returns the number of bytes which can be acquired without waiting.
+    pub fn available(&self) -> u64 {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        *self.available.lock().unwrap()
+    }
+
+    /// Acquires the specified number of bytes from the budget, waiting until enough bytes are
+    /// released if needed; the bytes are released when the returned permit is dropped. Requests
+    /// for more bytes than the capacity of the budget acquire the entire budget, so objects
+    /// larger than the budget can still be received, one at a time.
+    pub async fn acquire(self: &Arc<Self>, num_bytes: u64) -> BudgetPermit {
+        let num_bytes = num_bytes.min(self.capacity);
+        loop {
+            // register for notifications before checking the budget, so that a release which
+            // happens after the check is not missed
+            let released = self.released.notified();
+            {
+                // `unwrap()` is OK here because no thread will panic wile holding the lock
+                let mut available = self.available.lock().unwrap();
+                if *available >= num_bytes {
+                    *available -= num_bytes;
+                    return BudgetPermit {
+                        budget: self.clone(),
+                        num_bytes,
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+}
+
+// BUDGET PERMIT
+// ================================================================================================
+
+/// A part of a byte budget acquired by a receiver; the bytes are returned to the budget when the
+/// permit is dropped.
+#[derive(Debug)]
+pub struct BudgetPermit {
+    budget: Arc<ByteBudget>,
+    num_bytes: u64,
+}
+
+impl Drop for BudgetPermit {
+    fn drop(&mut self) {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        *self.budget.available.lock().unwrap() += self.num_bytes;
+        self.budget.released.notify_waiters();
+    }
+}
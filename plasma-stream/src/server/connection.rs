@@ -0,0 +1,161 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use super::PeerAddr;
+use std::{
+    io::{ErrorKind, IoSlice},
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{unix, TcpStream, UnixStream},
+};
+
+// CONNECTION
+// ================================================================================================
+
+/// A connection to a client or a peer; connections are made over TCP, or over Unix domain
+/// sockets when the client or the peer runs on the same host as the server. Requests are
+/// processed in the same way regardless of the transport.
+#[derive(Debug)]
+pub enum Connection {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Connection {
+    /// Returns address of the remote end of this connection. Clients connecting over Unix
+    /// sockets are usually not bound to a path; for such connections, the path of the socket
+    /// on which the server accepted the connection is returned instead.
+    pub fn peer_addr(&self) -> std::io::Result<PeerAddr> {
+        match self {
+            Self::Tcp(socket) => socket.peer_addr().map(PeerAddr::from),
+            Self::Unix(socket) => match to_path(socket.peer_addr()?) {
+                Some(path) => Ok(PeerAddr::Unix(path)),
+                None => self.local_addr(),
+            },
+        }
+    }
+
+    /// Returns address of the local end of this connection; for Unix socket connections which
+    /// are not bound to a path, an empty path is returned.
+    pub fn local_addr(&self) -> std::io::Result<PeerAddr> {
+        match self {
+            Self::Tcp(socket) => socket.local_addr().map(PeerAddr::from),
+            Self::Unix(socket) => {
+                let path = to_path(socket.local_addr()?).unwrap_or_default();
+                Ok(PeerAddr::Unix(path))
+            }
+        }
+    }
+
+    /// Waits for the connection to become readable.
+    pub async fn readable(&self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(socket) => socket.readable().await,
+            Self::Unix(socket) => socket.readable().await,
+        }
+    }
+
+    /// Returns true if the connection is open and no data is waiting to be read from it; this
+    /// never blocks. Any data which is waiting is consumed, so a connection for which this
+    /// returns false should be closed.
+    pub fn is_idle(&self) -> bool {
+        let mut buf = [0u8; 1];
+        let result = match self {
+            Self::Tcp(socket) => socket.try_read(&mut buf),
+            Self::Unix(socket) => socket.try_read(&mut buf),
+        };
+        matches!(result, Err(err) if err.kind() == ErrorKind::WouldBlock)
+    }
+
+    /// Sets TCP_NODELAY on TCP connections; this is a no-op for Unix socket connections.
+    pub fn set_nodelay(&self, nodelay: bool) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(socket) => socket.set_nodelay(nodelay),
+            Self::Unix(_) => Ok(()),
+        }
+    }
+}
+
+impl From<TcpStream> for Connection {
+    fn from(socket: TcpStream) -> Self {
+        Self::Tcp(socket)
+    }
+}
+
+impl From<UnixStream> for Connection {
+    fn from(socket: UnixStream) -> Self {
+        Self::Unix(socket)
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(socket) => Pin::new(socket).poll_read(cx, buf),
+            Self::Unix(socket) => Pin::new(socket).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tcp(socket) => Pin::new(socket).poll_write(cx, buf),
+            Self::Unix(socket) => Pin::new(socket).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tcp(socket) => Pin::new(socket).poll_write_vectored(cx, bufs),
+            Self::Unix(socket) => Pin::new(socket).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            Self::Tcp(socket) => socket.is_write_vectored(),
+            Self::Unix(socket) => socket.is_write_vectored(),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(socket) => Pin::new(socket).poll_flush(cx),
+            Self::Unix(socket) => Pin::new(socket).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(socket) => Pin::new(socket).poll_shutdown(cx),
+            Self::Unix(socket) => Pin::new(socket).poll_shutdown(cx),
+        }
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns the path to which a Unix socket is bound, or `None` if the socket is unnamed.
+fn to_path(addr: unix::SocketAddr) -> Option<PathBuf> {
+    addr.as_pathname().map(|path| path.to_path_buf())
+}
@@ -0,0 +1,79 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use super::{handshake::AuthToken, ServerOptions};
+use std::{
+    io::{Error, ErrorKind},
+    path::Path,
+};
+use tracing::info;
+
+// CONSTANTS
+// ================================================================================================
+
+/// Name of the credential which contains path to the Plasma Store socket.
+pub const PLASMA_SOCKET_CREDENTIAL: &str = "plasma-socket";
+
+/// Name of the credential which contains the token clients must authenticate with.
+pub const AUTH_TOKEN_CREDENTIAL: &str = "auth-token";
+
+// CREDENTIALS
+// ================================================================================================
+
+/// Overrides server options with values read from the credentials directory (e.g. the
+/// directory systemd exposes via `$CREDENTIALS_DIRECTORY` for `LoadCredential=` entries).
+/// Credentials which are not present in the directory are skipped.
+///
+/// Returns an error if the credentials directory does not exist, or if any of the present
+/// credentials cannot be read.
+pub fn load(options: &mut ServerOptions) -> std::io::Result<()> {
+    let credentials_dir = match &options.credentials_dir {
+        Some(credentials_dir) => credentials_dir.clone(),
+        None => return Ok(()),
+    };
+
+    if !credentials_dir.is_dir() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "credentials directory {} does not exist",
+                credentials_dir.display()
+            ),
+        ));
+    }
+
+    if let Some(plasma_socket) = read_credential(&credentials_dir, PLASMA_SOCKET_CREDENTIAL)? {
+        info!("read plasma socket path from credentials");
+        options.plasma_socket = plasma_socket;
+    }
+
+    if let Some(auth_token) = read_credential(&credentials_dir, AUTH_TOKEN_CREDENTIAL)? {
+        info!("read authentication token from credentials");
+        let auth_token = auth_token
+            .parse::<AuthToken>()
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        options.auth_token = Some(auth_token);
+    }
+
+    Ok(())
+}
+
+/// Reads the credential with the specified name from the credentials directory. Leading and
+/// trailing whitespace is removed from the credential value. Returns `None` if the credential
+/// does not exist.
+pub fn read_credential(credentials_dir: &Path, name: &str) -> std::io::Result<Option<String>> {
+    let path = credentials_dir.join(name);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let value = std::fs::read_to_string(&path).map_err(|err| {
+        Error::new(
+            err.kind(),
+            format!("failed to read credential {}: {}", path.display(), err),
+        )
+    })?;
+    Ok(Some(value.trim().to_string()))
+}
@@ -3,130 +3,722 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::{errors::SyncError, status_codes, PeerRequest, Request, Store};
-use std::sync::Arc;
-use tokio::{io::AsyncWriteExt, net::TcpStream};
-use tracing::error;
+use crate::{
+    errors::{ObjectSendError, SyncError},
+    handshake::{self, AuthToken, Capabilities},
+    status_codes, Connection, ObjectId, ObjectStore, PeerAddr, PeerRequest, Request, Store,
+    MAX_OBJECT_ID_LIST_LEN,
+};
+use plasma_store::PlasmaClient;
+use rustc_hash::{FxHashMap, FxHashSet};
+use socket2::{SockRef, TcpKeepalive};
+use std::{collections::VecDeque, mem, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UnixStream},
+    time,
+};
+use tracing::{debug, error, instrument, Instrument};
 
-pub struct Dispatcher {
+pub struct Dispatcher<S: ObjectStore = PlasmaClient> {
     /// Shared handle to the Plasma Store.
-    pub store: Arc<Store>,
+    pub store: Arc<Store<S>>,
+
+    /// Optional protocol features requested from peers.
+    pub capabilities: Capabilities,
+
+    /// Determines how connections to peers are retried when they cannot be established.
+    pub connect_retry: ConnectRetry,
+
+    /// Token presented to peers during the handshake, if any.
+    pub auth_token: Option<Arc<AuthToken>>,
+
+    /// Whether TCP_NODELAY is set on connections opened to peers.
+    pub nodelay: bool,
+
+    /// If set, TCP keepalive is enabled on connections opened to peers with the specified idle
+    /// time and probe interval.
+    pub keepalive: Option<Duration>,
+}
+
+// PEER CONNECTION RETRY POLICY
+// ================================================================================================
+
+/// Policy for retrying connections to peers which refuse or otherwise fail to accept them
+/// (e.g. because the peer is briefly restarting). Backoff starts at `initial_backoff` and is
+/// doubled after every failed attempt. The default policy does not retry failed connections.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConnectRetry {
+    /// Number of times a connection is retried after the first attempt fails.
+    pub max_retries: u32,
+    /// Time to wait before the first retry.
+    pub initial_backoff: Duration,
 }
 
 // SYNC REQUEST DISPATCHER
 // ================================================================================================
 
-impl Dispatcher {
+impl<S: ObjectStore> Dispatcher<S> {
     /// Dispatches requests to the peer Plasma Stream nodes, collects the replies, and writes
     /// the result of peer requests into `client_socket`. Each peer requests may move one or more
-    /// objects between plasma stores on local or and peer machines. Currently, the only two
-    /// possible peer request: COPY and TAKE. Both of them transfer objects from a peer to the
-    /// local plasma store.
+    /// objects between plasma stores on local or and peer machines. COPY and TAKE peer requests
+    /// transfer objects from a peer to the local plasma store, while PUSH peer requests transfer
+    /// objects from the local plasma store to a peer.
+    ///
+    /// Returns the number of bytes of object data and metadata transferred between the local
+    /// plasma store and the peers by successful peer requests.
     pub async fn run(
         &self,
         requests: Vec<PeerRequest>,
-        client_socket: &mut TcpStream,
+        client_socket: &mut Connection,
+    ) -> Result<u64, SyncError> {
+        let local_address = get_local_address(&requests, client_socket)?;
+        let (response, bytes_transferred) = self.dispatch(requests, &local_address).await;
+
+        // write the response into client socket, and if there is an error propagate it forward
+        client_socket
+            .write_all(&response)
+            .await
+            .map_err(SyncError::ClientConnectionError)?;
+        Ok(bytes_transferred)
+    }
+
+    /// Same as `run()`, but an object may be listed in more than one peer request. Each object
+    /// is requested only from the first peer request listing it; if that peer request fails,
+    /// the object is requested from the next peer request listing it, and so on. Peer requests
+    /// which are tried at the same time are executed in parallel.
+    ///
+    /// The response contains SUCCESS for a peer request if all of its objects were received,
+    /// regardless of which peers they were received from; otherwise, it contains the error
+    /// code of the last failed attempt to execute this peer request.
+    pub async fn run_first_wins(
+        &self,
+        requests: Vec<PeerRequest>,
+        client_socket: &mut Connection,
+    ) -> Result<u64, SyncError> {
+        let local_address = get_local_address(&requests, client_socket)?;
+
+        // for every object, build a queue of peer requests listing it, in the order in which
+        // the peer requests appear in the SYNC request
+        let mut pending: FxHashMap<ObjectId, VecDeque<usize>> = FxHashMap::default();
+        for (i, request) in requests.iter().enumerate() {
+            for oid in request.incoming_objects() {
+                pending.entry(*oid).or_default().push_back(i);
+            }
+        }
+
+        let mut received = FxHashSet::default();
+        let mut response = vec![status_codes::SUCCESS; requests.len()];
+        let mut bytes_transferred = 0;
+        while !pending.is_empty() {
+            // assign every pending object to the peer request at the head of its queue; objects
+            // are kept in the same order as in the original peer requests
+            let mut batches = Vec::new();
+            for (i, request) in requests.iter().enumerate() {
+                let objects: Vec<ObjectId> = request
+                    .incoming_objects()
+                    .iter()
+                    .filter(|&oid| pending.get(oid).and_then(|queue| queue.front()) == Some(&i))
+                    .copied()
+                    .collect();
+                if !objects.is_empty() {
+                    batches.push((i, objects));
+                }
+            }
+
+            // execute all batches in parallel and update state of the objects based on results
+            let batch_requests = batches
+                .iter()
+                .map(|(i, objects)| with_objects(&requests[*i], objects.clone()))
+                .collect();
+            let (results, batch_bytes) = self.dispatch(batch_requests, &local_address).await;
+            bytes_transferred += batch_bytes;
+            for ((i, objects), result) in batches.into_iter().zip(results) {
+                for oid in objects.iter() {
+                    if result == status_codes::SUCCESS {
+                        pending.remove(oid);
+                        received.insert(*oid);
+                    } else if let Some(queue) = pending.get_mut(oid) {
+                        queue.pop_front();
+                        if queue.is_empty() {
+                            pending.remove(oid);
+                        }
+                    }
+                }
+                if result != status_codes::SUCCESS {
+                    response[i] = result;
+                }
+            }
+        }
+
+        // peer requests all objects of which were received from some peer are successful
+        for (i, request) in requests.iter().enumerate() {
+            let objects = request.incoming_objects();
+            if objects.iter().all(|oid| received.contains(oid)) {
+                response[i] = status_codes::SUCCESS;
+            }
+        }
+
+        // write the response into client socket, and if there is an error propagate it forward
+        client_socket
+            .write_all(&response)
+            .await
+            .map_err(SyncError::ClientConnectionError)?;
+        Ok(bytes_transferred)
+    }
+
+    /// Opens a connection to every one of the specified peers and puts it into the pool of idle
+    /// connections of the store, so that peer requests of subsequent SYNC requests reuse these
+    /// connections instead of connecting and performing the handshake; writes a response code
+    /// for every peer into `client_socket`. Connections are opened in parallel, and a peer
+    /// listed several times gets several connections. If the store does not keep a pool of
+    /// idle connections, the connections are closed right away, and so this only checks that
+    /// the peers are reachable.
+    pub async fn prewarm(
+        &self,
+        peers: Vec<PeerAddr>,
+        client_socket: &mut Connection,
     ) -> Result<(), SyncError> {
-        // make sure none of the peer requests is for the local address
         let local_address = client_socket
             .local_addr()
             .map_err(SyncError::ClientConnectionError)?;
-        for request in requests.iter() {
-            if request.contains_peer(&local_address) {
-                return Err(SyncError::PeerAddressIsSelf);
+        if peers.contains(&local_address) {
+            return Err(SyncError::PeerAddressIsSelf);
+        }
+
+        let mut handles = Vec::new();
+        for peer in peers.into_iter() {
+            let store = self.store.clone();
+            let capabilities = self.capabilities;
+            let connect_retry = self.connect_retry;
+            let auth_token = self.auth_token.clone();
+            let nodelay = self.nodelay;
+            let keepalive = self.keepalive;
+            let local_address = local_address.clone();
+            let handle = tokio::spawn(
+                async move {
+                    prewarm_connection(
+                        store,
+                        peer,
+                        local_address,
+                        capabilities,
+                        connect_retry,
+                        auth_token,
+                        nodelay,
+                        keepalive,
+                    )
+                    .await
+                }
+                .in_current_span(),
+            );
+            handles.push(handle);
+        }
+
+        // wait for all connections to be opened; errors have already been logged within the
+        // span of the peer, and are reported to the client via response codes
+        let mut response = vec![status_codes::SUCCESS; handles.len()];
+        for (i, handle) in handles.into_iter().enumerate() {
+            match handle.await {
+                Ok(Ok(())) => (),
+                Ok(Err(err)) => {
+                    self.store.metrics().peer_error();
+                    response[i] = err.response_code();
+                }
+                Err(err) => {
+                    error!("prewarming connection {} panicked: {}", i, err);
+                    self.store.metrics().peer_error();
+                    response[i] = status_codes::PEER_REQUEST_PANICKED;
+                }
             }
         }
 
+        // write the response into client socket, and if there is an error propagate it forward
+        client_socket
+            .write_all(&response)
+            .await
+            .map_err(SyncError::ClientConnectionError)
+    }
+
+    /// Executes the specified peer requests in parallel and returns a response code for each
+    /// of them, together with the total number of bytes transferred by successful peer
+    /// requests; errors are logged, but are not propagated forward.
+    ///
+    /// Peer requests of the same type for the same peer are merged into a single peer request,
+    /// so that only one connection is opened to every peer; each of the merged peer requests
+    /// gets the response code of the merged request.
+    async fn dispatch(
+        &self,
+        requests: Vec<PeerRequest>,
+        local_address: &PeerAddr,
+    ) -> (Vec<u8>, u64) {
+        let num_requests = requests.len();
+        let (requests, merged_into) = coalesce(requests);
+        if requests.len() < num_requests {
+            debug!(
+                "merged {} peer requests into {}",
+                num_requests,
+                requests.len()
+            );
+        }
+
         // use separate task to fullfil each peer request; this is done to enable parallel
         // streaming of objects from multiple peers
         let mut handles = Vec::new();
         for request in requests.into_iter() {
             let store = self.store.clone();
-            let handle = tokio::spawn(async move { process_peer_request(store, request).await });
+            let capabilities = self.capabilities;
+            let connect_retry = self.connect_retry;
+            let auth_token = self.auth_token.clone();
+            let nodelay = self.nodelay;
+            let keepalive = self.keepalive;
+            let local_address = local_address.clone();
+            // the spawned task is attached to the current span, so that the span of the peer
+            // request is nested within the span of the SYNC request
+            let handle = tokio::spawn(
+                async move {
+                    process_peer_request(
+                        store,
+                        request,
+                        local_address,
+                        capabilities,
+                        connect_retry,
+                        auth_token,
+                        nodelay,
+                        keepalive,
+                    )
+                    .await
+                }
+                .in_current_span(),
+            );
             handles.push(handle);
         }
 
         // wait for all requests to finish and collect the results into a response; if there
         // were errors, log them, but don't propagate them forward.
         let mut response = vec![status_codes::SUCCESS; handles.len()];
+        let mut bytes_transferred = 0;
         for (i, handle) in handles.into_iter().enumerate() {
             match handle.await {
-                Ok(result) => {
-                    if let Err(err) = result {
-                        error!("{}", err);
-                        response[i] = err.response_code();
-                    }
+                Ok(Ok(num_bytes)) => bytes_transferred += num_bytes as u64,
+                Ok(Err(err)) => {
+                    // the error has already been logged within the span of the peer request
+                    self.store.metrics().peer_error();
+                    response[i] = err.response_code();
                 }
                 Err(err) => {
                     error!("peer request {} panicked: {}", i, err);
+                    self.store.metrics().peer_error();
                     response[i] = status_codes::PEER_REQUEST_PANICKED;
                 }
             }
         }
 
-        // write the response into client socket, and if there is an error propagate it forward
-        client_socket
-            .write_all(&response)
-            .await
-            .map_err(SyncError::ClientConnectionError)
+        // map the results of merged peer requests back to the original peer requests
+        let response = merged_into.into_iter().map(|i| response[i]).collect();
+        (response, bytes_transferred)
     }
 }
 
 // HELPER FUNCTIONS
 // ================================================================================================
 
-async fn process_peer_request(store: Arc<Store>, request: PeerRequest) -> Result<(), SyncError> {
+/// Returns local address of the client connection; also makes sure none of the peer requests
+/// is for the local address. Peers specified by hostname are checked once their addresses are
+/// resolved. For clients connected over a Unix socket, the local address is the path of the
+/// socket on which the server listens.
+fn get_local_address(
+    requests: &[PeerRequest],
+    client_socket: &Connection,
+) -> Result<PeerAddr, SyncError> {
+    let local_address = client_socket
+        .local_addr()
+        .map_err(SyncError::ClientConnectionError)?;
+    for request in requests.iter() {
+        if request.peer() == &local_address {
+            return Err(SyncError::PeerAddressIsSelf);
+        }
+    }
+    Ok(local_address)
+}
+
+/// Merges peer requests of the same type for the same peer into a single peer request which
+/// lists objects of all merged requests in their original order. Returns the merged peer
+/// requests, and for every original peer request, the index of the merged request it was
+/// merged into.
+///
+/// Peers are compared as specified, without resolving hostnames. Peer requests are not merged
+/// if the merged object list would exceed the protocol limit. PUSH peer requests are never
+/// merged, because objects pushed by one of them may be listed by another.
+pub fn coalesce(requests: Vec<PeerRequest>) -> (Vec<PeerRequest>, Vec<usize>) {
+    let mut merged: Vec<PeerRequest> = Vec::with_capacity(requests.len());
+    let mut merged_into = Vec::with_capacity(requests.len());
+    let mut targets: FxHashMap<_, usize> = FxHashMap::default();
+    for request in requests {
+        let key = (mem::discriminant(&request), request.peer().clone());
+        match targets.get(&key) {
+            Some(&i)
+                if merged[i].objects().len() + request.objects().len()
+                    <= MAX_OBJECT_ID_LIST_LEN =>
+            {
+                match &mut merged[i] {
+                    PeerRequest::Copy { objects, .. }
+                    | PeerRequest::Take { objects, .. }
+                    | PeerRequest::Push { objects, .. } => {
+                        objects.extend_from_slice(request.objects())
+                    }
+                }
+                merged_into.push(i);
+            }
+            _ => {
+                if !matches!(request, PeerRequest::Push { .. }) {
+                    targets.insert(key, merged.len());
+                }
+                merged_into.push(merged.len());
+                merged.push(request);
+            }
+        }
+    }
+    (merged, merged_into)
+}
+
+/// Returns a peer request of the same type and for the same peer as the specified request, but
+/// for the specified list of objects.
+fn with_objects(request: &PeerRequest, objects: Vec<ObjectId>) -> PeerRequest {
     match request {
-        PeerRequest::Copy { from, objects } => {
+        PeerRequest::Copy { from, .. } => PeerRequest::Copy {
+            from: from.clone(),
+            objects,
+        },
+        PeerRequest::Take { from, .. } => PeerRequest::Take {
+            from: from.clone(),
+            objects,
+        },
+        PeerRequest::Push { to, .. } => PeerRequest::Push {
+            to: to.clone(),
+            objects,
+        },
+    }
+}
+
+#[instrument(
+    name = "peer",
+    skip_all,
+    fields(peer = %request.peer(), objects = request.objects().len()),
+    err(Display)
+)]
+#[allow(clippy::too_many_arguments)]
+async fn process_peer_request<S: ObjectStore>(
+    store: Arc<Store<S>>,
+    request: PeerRequest,
+    local_address: PeerAddr,
+    capabilities: Capabilities,
+    connect_retry: ConnectRetry,
+    auth_token: Option<Arc<AuthToken>>,
+    nodelay: bool,
+    keepalive: Option<Duration>,
+) -> Result<usize, SyncError> {
+    let from = resolve_peer(request.peer(), &local_address).await?;
+
+    let num_bytes = match request {
+        PeerRequest::Copy { objects, .. } => {
             // build the receiver and prepare it to receive objects
-            let receiver = store.build_receiver(from, objects.clone());
+            // SYNC responses carry a single status code per peer request, so partially
+            // received objects cannot be reported and are cleaned up instead
+            let receiver = store.build_receiver(from.clone(), objects.clone(), true);
             receiver.prepare().map_err(SyncError::ReceiverError)?;
 
-            // open the socket and send COPY request
-            let mut socket = TcpStream::connect(from)
-                .await
-                .map_err(|err| SyncError::PeerConnectionFailed(from, err))?;
+            // get a connection to the peer and send COPY request
+            let (mut socket, capabilities) = checkout_connection(
+                &store,
+                &from,
+                objects.len(),
+                capabilities,
+                connect_retry,
+                auth_token.as_deref(),
+                nodelay,
+                keepalive,
+            )
+            .await?;
             let request = Request::Copy(objects);
             request
                 .write_into(&mut socket)
                 .await
-                .map_err(|err| SyncError::PeerRequestNotSent(from, err))?;
+                .map_err(|err| SyncError::PeerRequestNotSent(from.clone(), err))?;
 
-            // read the response and close connection when done
-            receiver
-                .run(&mut socket)
+            // read the response and release the connection when done
+            let report = receiver
+                .run(&mut socket, capabilities)
                 .await
                 .map_err(SyncError::ReceiverError)?;
-            socket.shutdown().await.or_else(|err| {
-                error!("connection to {} did not shut down cleanly: {}", from, err);
-                Ok(())
-            })?;
+            let num_bytes = report.bytes_received;
+            report.into_result().map_err(SyncError::ReceiverError)?;
+            release_connection(&store, from, socket, capabilities).await;
+            num_bytes
         }
-        PeerRequest::Take { from, objects } => {
+        PeerRequest::Take { objects, .. } => {
             // build the receiver and prepare it to receive objects
-            let receiver = store.build_receiver(from, objects.clone());
+            // SYNC responses carry a single status code per peer request, so partially
+            // received objects cannot be reported and are cleaned up instead
+            let receiver = store.build_receiver(from.clone(), objects.clone(), true);
             receiver.prepare().map_err(SyncError::ReceiverError)?;
 
-            // open the socket and send TAKE request
-            let mut socket = TcpStream::connect(from)
-                .await
-                .map_err(|err| SyncError::PeerConnectionFailed(from, err))?;
+            // get a connection to the peer and send TAKE request
+            let (mut socket, capabilities) = checkout_connection(
+                &store,
+                &from,
+                objects.len(),
+                capabilities,
+                connect_retry,
+                auth_token.as_deref(),
+                nodelay,
+                keepalive,
+            )
+            .await?;
             let request = Request::Take(objects);
             request
                 .write_into(&mut socket)
                 .await
-                .map_err(|err| SyncError::PeerRequestNotSent(from, err))?;
+                .map_err(|err| SyncError::PeerRequestNotSent(from.clone(), err))?;
 
-            // read the response and close connection when done
-            receiver
-                .run(&mut socket)
+            // read the response and release the connection when done
+            let report = receiver
+                .run(&mut socket, capabilities)
                 .await
                 .map_err(SyncError::ReceiverError)?;
-            socket.shutdown().await.or_else(|err| {
-                error!("connection to {} did not shut down cleanly: {}", from, err);
-                Ok(())
-            })?;
+            let num_bytes = report.bytes_received;
+            report.into_result().map_err(SyncError::ReceiverError)?;
+            release_connection(&store, from, socket, capabilities).await;
+            num_bytes
         }
-    }
+        PeerRequest::Push { objects, .. } => {
+            // get a connection to the peer and send PUSH request
+            let (mut socket, capabilities) = checkout_connection(
+                &store,
+                &from,
+                objects.len(),
+                capabilities,
+                connect_retry,
+                auth_token.as_deref(),
+                nodelay,
+                keepalive,
+            )
+            .await?;
+            let request = Request::Push(objects.clone());
+            request
+                .write_into(&mut socket)
+                .await
+                .map_err(|err| SyncError::PeerRequestNotSent(from.clone(), err))?;
+
+            // the peer responds with SUCCESS once it is ready to receive the objects; then
+            // the objects are sent, and the peer responds with SUCCESS once all of them have
+            // been saved into its store
+            read_push_status(&mut socket, &from).await?;
+            let num_bytes = store
+                .build_sender(from.clone(), objects, false, None)
+                .run(&mut socket, capabilities)
+                .await
+                .map_err(SyncError::SenderError)?;
+            read_push_status(&mut socket, &from).await?;
+            release_connection(&store, from, socket, capabilities).await;
+            num_bytes
+        }
+    };
+    Ok(num_bytes)
+}
+
+/// Opens a new connection to the specified peer and releases it via `release_connection()`, so
+/// that the connection is kept in the pool of idle connections of the store for subsequent
+/// peer requests.
+#[instrument(name = "peer", skip_all, fields(peer = %peer), err(Display))]
+#[allow(clippy::too_many_arguments)]
+async fn prewarm_connection<S: ObjectStore>(
+    store: Arc<Store<S>>,
+    peer: PeerAddr,
+    local_address: PeerAddr,
+    capabilities: Capabilities,
+    connect_retry: ConnectRetry,
+    auth_token: Option<Arc<AuthToken>>,
+    nodelay: bool,
+    keepalive: Option<Duration>,
+) -> Result<(), SyncError> {
+    let peer = resolve_peer(&peer, &local_address).await?;
+    let (socket, capabilities) = connect_to_peer(
+        &peer,
+        0,
+        capabilities,
+        connect_retry,
+        auth_token.as_deref(),
+        nodelay,
+        keepalive,
+    )
+    .await?;
+    release_connection(&store, peer, socket, capabilities).await;
     Ok(())
 }
+
+/// Resolves the specified peer address; for peers specified by socket address or by Unix socket
+/// path this is a no-op. Returns an error if the peer resolves to the local address.
+async fn resolve_peer(peer: &PeerAddr, local_address: &PeerAddr) -> Result<PeerAddr, SyncError> {
+    let resolved = match peer {
+        PeerAddr::Host(..) => peer
+            .resolve()
+            .await
+            .map(PeerAddr::from)
+            .map_err(|err| SyncError::PeerResolutionFailed(peer.clone(), err))?,
+        peer => peer.clone(),
+    };
+    if &resolved == local_address {
+        return Err(SyncError::PeerAddressIsSelf);
+    }
+    Ok(resolved)
+}
+
+/// Reads a status byte sent by the peer in response to a PUSH request; returns an error if the
+/// status is anything other than SUCCESS.
+async fn read_push_status(socket: &mut Connection, peer: &PeerAddr) -> Result<(), SyncError> {
+    let status = socket.read_u8().await.map_err(|err| {
+        SyncError::SenderError(ObjectSendError::ConnectionError(Some(peer.clone()), err))
+    })?;
+    match status {
+        status_codes::SUCCESS => Ok(()),
+        _ => Err(SyncError::PushRejected(peer.clone(), status)),
+    }
+}
+
+/// Returns a connection to the specified peer together with capabilities negotiated for it. If
+/// the store keeps a pool of idle connections and the pool holds a live connection to the peer,
+/// that connection is reused; otherwise, a new connection is opened via `connect_to_peer()`.
+#[allow(clippy::too_many_arguments)]
+async fn checkout_connection<S: ObjectStore>(
+    store: &Store<S>,
+    peer: &PeerAddr,
+    num_objects: usize,
+    requested: Capabilities,
+    retry: ConnectRetry,
+    token: Option<&AuthToken>,
+    nodelay: bool,
+    keepalive: Option<Duration>,
+) -> Result<(Connection, Capabilities), SyncError> {
+    if let Some(connection) = store.connection_pool().and_then(|pool| pool.take(peer)) {
+        debug!("reusing idle connection to {}", peer);
+        return Ok(connection);
+    }
+    connect_to_peer(
+        peer,
+        num_objects,
+        requested,
+        retry,
+        token,
+        nodelay,
+        keepalive,
+    )
+    .await
+}
+
+/// Releases a connection over which a peer request has been completed: the connection is put
+/// into the pool of idle connections of the store, or shut down if connections are not pooled.
+/// Connections over which a peer request failed must not be released this way, as they may be
+/// left in the middle of a response; such connections are closed by dropping them instead.
+async fn release_connection<S: ObjectStore>(
+    store: &Store<S>,
+    peer: PeerAddr,
+    mut socket: Connection,
+    capabilities: Capabilities,
+) {
+    match store.connection_pool() {
+        Some(pool) => {
+            pool.put(peer.clone(), socket, capabilities);
+            debug!(
+                "keeping connection to {} for reuse; {} idle connections to the peer",
+                peer,
+                pool.num_idle(&peer)
+            );
+        }
+        None => {
+            if let Err(err) = socket.shutdown().await {
+                error!("connection to {} did not shut down cleanly: {}", peer, err);
+            }
+        }
+    }
+}
+
+/// Opens a connection to the specified peer and performs the protocol handshake over it,
+/// authenticating with the specified token, if any. Returns the socket together with
+/// capabilities negotiated for the connection; `num_objects` is the number of objects which
+/// will be requested from the peer and is used only to describe connection errors.
+async fn connect_to_peer(
+    peer: &PeerAddr,
+    num_objects: usize,
+    requested: Capabilities,
+    retry: ConnectRetry,
+    token: Option<&AuthToken>,
+    nodelay: bool,
+    keepalive: Option<Duration>,
+) -> Result<(Connection, Capabilities), SyncError> {
+    let mut socket = connect_with_retry(peer, num_objects, retry).await?;
+    socket
+        .set_nodelay(nodelay)
+        .map_err(|err| SyncError::PeerConnectionFailed(peer.clone(), num_objects, err))?;
+    if let (Some(interval), Connection::Tcp(tcp_socket)) = (keepalive, &socket) {
+        set_keepalive(tcp_socket, interval)
+            .map_err(|err| SyncError::PeerConnectionFailed(peer.clone(), num_objects, err))?;
+    }
+    let capabilities = handshake::initiate(&mut socket, requested, token)
+        .await
+        .map_err(|err| SyncError::PeerHandshakeFailed(peer.clone(), err))?;
+    Ok((socket, capabilities))
+}
+
+/// Opens a connection to the specified peer, retrying failed attempts according to the
+/// specified policy. Peers specified by Unix socket path are connected to over the Unix socket,
+/// and all other peers over TCP. Only establishing the connection is retried; errors which
+/// happen after the connection is established are not.
+pub(crate) async fn connect_with_retry(
+    peer: &PeerAddr,
+    num_objects: usize,
+    retry: ConnectRetry,
+) -> Result<Connection, SyncError> {
+    let mut backoff = retry.initial_backoff;
+    let mut attempt = 0;
+
+    loop {
+        let result = match peer {
+            PeerAddr::Socket(addr) => TcpStream::connect(addr).await.map(Connection::from),
+            PeerAddr::Host(host, port) => TcpStream::connect((host.as_str(), *port))
+                .await
+                .map(Connection::from),
+            PeerAddr::Unix(path) => UnixStream::connect(path).await.map(Connection::from),
+        };
+        match result {
+            Ok(socket) => return Ok(socket),
+            Err(err) => {
+                if attempt >= retry.max_retries {
+                    let err = SyncError::PeerConnectionFailed(peer.clone(), num_objects, err);
+                    return Err(err);
+                }
+                debug!(
+                    "failed to connect to peer {}: {}; retrying in {} ms",
+                    peer,
+                    err,
+                    backoff.as_millis()
+                );
+            }
+        }
+
+        // wait for the backoff period to elapse and double it for the next attempt
+        time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+/// Enables TCP keepalive on the specified socket: once the connection has been idle for the
+/// specified interval, keepalive probes are sent at the same interval. This way, a peer which
+/// went away without closing the connection cannot stall a transfer for longer than a few
+/// intervals.
+pub(crate) fn set_keepalive(socket: &TcpStream, interval: Duration) -> std::io::Result<()> {
+    let keepalive = TcpKeepalive::new()
+        .with_time(interval)
+        .with_interval(interval);
+    SockRef::from(socket).set_tcp_keepalive(&keepalive)
+}
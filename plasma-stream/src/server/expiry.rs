@@ -0,0 +1,102 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use super::{ObjectId, Store};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::time;
+use tracing::{debug, warn};
+
+// CONSTANTS
+// ================================================================================================
+
+/// Maximum time between two consecutive checks for expired objects.
+const MAX_CHECK_INTERVAL: Duration = Duration::from_millis(1000);
+
+// EXPIRY TRACKER
+// ================================================================================================
+
+/// Keeps track of deadlines after which objects received from peers expire. Deadlines are
+/// kept in a min-heap, so expired objects can be found without scanning all tracked objects.
+#[derive(Debug)]
+pub struct ExpiryTracker {
+    /// Time for which objects are kept in the store after they have been received.
+    ttl: Duration,
+    /// Deadlines of all tracked objects; the same object may be tracked more than once (e.g.
+    /// if it was deleted and then received again), in which case it expires at the earliest
+    /// of its deadlines.
+    deadlines: Mutex<BinaryHeap<Reverse<(Instant, ObjectId)>>>,
+}
+
+impl ExpiryTracker {
+    /// Returns a new tracker for objects which expire after the specified time-to-live.
+    pub fn new(ttl: Duration) -> Self {
+        ExpiryTracker {
+            ttl,
+            deadlines: Mutex::new(BinaryHeap::new()),
+        }
+    }
+
+    /// Returns the interval at which expired objects should be checked for; this is also the
+    /// delay after which deleting of objects which could not be deleted is retried.
+    pub fn check_interval(&self) -> Duration {
+        self.ttl.min(MAX_CHECK_INTERVAL)
+    }
+
+    /// Starts tracking the specified objects; the objects expire once the time-to-live elapses.
+    pub fn track(&self, object_ids: &[ObjectId]) {
+        self.track_until(object_ids, Instant::now() + self.ttl);
+    }
+
+    /// Starts tracking the specified objects; the objects expire at the specified deadline.
+    pub fn track_until(&self, object_ids: &[ObjectId], deadline: Instant) {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        let mut deadlines = self.deadlines.lock().unwrap();
+        deadlines.extend(object_ids.iter().map(|oid| Reverse((deadline, *oid))));
+    }
+
+    /// Stops tracking objects which expired at or before `now`, and returns their IDs.
+    pub fn take_expired(&self, now: Instant) -> Vec<ObjectId> {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        let mut deadlines = self.deadlines.lock().unwrap();
+        let mut expired = Vec::new();
+        while let Some(Reverse((deadline, oid))) = deadlines.peek() {
+            if *deadline > now {
+                break;
+            }
+            expired.push(*oid);
+            deadlines.pop();
+        }
+        expired
+    }
+}
+
+// EXPIRY TASK
+// ================================================================================================
+
+/// Spawns a task which periodically deletes expired objects from the store; errors are logged,
+/// and deleting of objects which could not be deleted is retried. This is a no-op if objects
+/// in the store do not expire.
+pub fn spawn_task(store: Arc<Store>) {
+    let interval = match store.expiry() {
+        Some(expiry) => expiry.check_interval(),
+        None => return,
+    };
+    tokio::spawn(async move {
+        let mut ticker = time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match store.expire_objects() {
+                Ok(0) => (),
+                Ok(num_deleted) => debug!("deleted {} expired objects", num_deleted),
+                Err(err) => warn!("failed to delete expired objects: {}", err),
+            }
+        }
+    });
+}
@@ -3,10 +3,25 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
-use super::{Dispatcher, Request, Store};
-use std::sync::Arc;
-use tokio::{net::TcpStream, sync::Semaphore};
-use tracing::debug;
+use super::{
+    handshake::{self, AuthToken, Capabilities},
+    status_codes, utils, ConnectRetry, Connection, Dispatcher, Request, Shutdown, Store,
+    MAX_REQUEST_CAPTURE_SIZE,
+};
+use std::{
+    future::Future,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::TcpStream,
+    sync::{mpsc, Semaphore},
+    time,
+};
+use tracing::{debug, error, field, info, info_span, instrument, Instrument};
 
 // CONNECTION HANDLER
 // ================================================================================================
@@ -14,65 +29,383 @@ use tracing::debug;
 /// Per-connection handler
 #[derive(Debug)]
 pub struct Handler {
-    /// TCP connection for this handler.
-    socket: TcpStream,
+    /// Connection to the client for this handler.
+    socket: Connection,
     /// Shared handle to the Plasma Store.
     store: Arc<Store>,
     /// Limit the max number of connections to the server.
     limit_connections: Arc<Semaphore>,
+    /// Directory into which raw bytes of failed requests are written.
+    capture_dir: Option<Arc<PathBuf>>,
+    /// Listens for the server shutdown signal.
+    shutdown: Shutdown,
+    /// Maximum time to wait for the client to send the next request; `None` means waiting
+    /// indefinitely.
+    idle_timeout: Option<Duration>,
+    /// Optional protocol features supported by this server.
+    supported: Capabilities,
+    /// Optional protocol features negotiated with the client during the handshake.
+    capabilities: Capabilities,
+    /// Determines how connections to peers are retried while processing SYNC requests.
+    connect_retry: ConnectRetry,
+    /// Token which the client must present during the handshake, and which is presented to
+    /// peers; `None` means that connections are not authenticated.
+    auth_token: Option<Arc<AuthToken>>,
+    /// Whether TCP_NODELAY is set on connections opened to peers.
+    nodelay: bool,
+    /// If set, TCP keepalive is enabled on connections opened to peers with this interval.
+    keepalive: Option<Duration>,
+    /// Limits on requests processed and bytes sent over this connection.
+    limits: ConnectionLimits,
+    /// Number of requests processed over this connection so far.
+    num_requests: u64,
+    /// Number of bytes of object data and metadata transferred for this connection so far, in
+    /// either direction; this includes objects moved between this server and peers by SYNC
+    /// requests.
+    bytes_transferred: u64,
+    /// Not used directly; when the handler is dropped, the sender is dropped as well, and once
+    /// all handlers are dropped, the listener knows that all connections have been drained.
+    _shutdown_complete: mpsc::Sender<()>,
 }
 
 impl Handler {
-    pub fn new(socket: TcpStream, store: Arc<Store>, limit_connections: Arc<Semaphore>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        socket: Connection,
+        store: Arc<Store>,
+        limit_connections: Arc<Semaphore>,
+        capture_dir: Option<Arc<PathBuf>>,
+        idle_timeout: Option<Duration>,
+        supported: Capabilities,
+        connect_retry: ConnectRetry,
+        auth_token: Option<Arc<AuthToken>>,
+        nodelay: bool,
+        keepalive: Option<Duration>,
+        limits: ConnectionLimits,
+        shutdown: Shutdown,
+        shutdown_complete: mpsc::Sender<()>,
+    ) -> Self {
+        store.metrics().connection_opened();
         Handler {
             socket,
             store,
             limit_connections,
+            capture_dir,
+            shutdown,
+            idle_timeout,
+            supported,
+            capabilities: Capabilities::none(),
+            connect_retry,
+            auth_token,
+            nodelay,
+            keepalive,
+            limits,
+            num_requests: 0,
+            bytes_transferred: 0,
+            _shutdown_complete: shutdown_complete,
         }
     }
 
     /// Process a single connection.
     ///
     /// Requests are read from the socket and processed until there are no requests left.
+    /// If request capture is enabled and processing of a request fails, the raw bytes of
+    /// the request are written into the capture directory.
+    ///
+    /// When the shutdown signal is received, the handler stops reading new requests; a request
+    /// which is already being processed is allowed to complete.
+    ///
+    /// If the client does not send the next request within the idle timeout, the connection
+    /// is closed. The connection is also closed when a request arrives after the connection
+    /// has reached its request or byte limit; such a request is rejected with a status code.
+    ///
+    /// All events logged while processing the connection are attached to a `connection` span
+    /// carrying the address of the client; each request gets a nested `request` span.
     pub async fn run(&mut self) -> crate::Result<()> {
-        // read requests until no more requests are available
-        loop {
-            // If no request was read then the peer closed the socket. There is no further work
-            // to do and the task can be terminated.
-            let request = match Request::read_from(&mut self.socket).await? {
-                Some(request) => request,
+        let span = info_span!("connection", peer = field::Empty);
+        if let Ok(peer_addr) = self.socket.peer_addr() {
+            span.record("peer", field::display(peer_addr));
+        }
+        self.serve().instrument(span).await
+    }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+
+    /// Processes requests received over the connection; see `run()` for details.
+    async fn serve(&mut self) -> crate::Result<()> {
+        // every connection starts with a protocol handshake; if the client speaks a different
+        // version of the protocol or fails to authenticate, it is notified and the connection
+        // is closed. If the connection is closed or the server shuts down before that, there
+        // is nothing to do
+        let handshake =
+            handshake::accept(&mut self.socket, self.supported, self.auth_token.as_deref());
+        let accepted = tokio::select! {
+            res = with_timeout(self.idle_timeout, handshake) => res,
+            _ = self.shutdown.recv() => return Ok(()),
+        };
+        self.capabilities = match accepted {
+            Some(res) => match res? {
+                Some(capabilities) => capabilities,
                 None => return Ok(()),
+            },
+            None => {
+                self.log_idle_timeout();
+                return Ok(());
+            }
+        };
+
+        // read requests until no more requests are available or the server is shutting down
+        while !self.shutdown.is_shutdown() {
+            // wait for the next request to arrive; if the shutdown signal is received while
+            // waiting, or the request does not arrive within the idle timeout, stop processing
+            // the connection
+            let readable = tokio::select! {
+                res = with_timeout(self.idle_timeout, self.socket.readable()) => res,
+                _ = self.shutdown.recv() => return Ok(()),
             };
-            let peer_addr = self.socket.peer_addr()?;
-            debug!("Received request from {}\n{}", peer_addr, request);
-
-            // make sure the received request is valid
-            request.validate()?;
-
-            // process the request
-            match request {
-                Request::Copy(object_ids) => {
-                    // for COPY request, just send the objects to the requesting peer
-                    self.store
-                        .build_sender(peer_addr, object_ids, false)
-                        .run(&mut self.socket)
-                        .await?;
+            match readable {
+                Some(res) => res?,
+                None => {
+                    self.log_idle_timeout();
+                    return Ok(());
                 }
-                Request::Take(object_ids) => {
-                    // for TAKE request, send the objects, but also delete them afterwards
-                    self.store
-                        .build_sender(peer_addr, object_ids, true)
-                        .run(&mut self.socket)
-                        .await?;
+            }
+
+            // if request capture is enabled, take a snapshot of the incoming request; this
+            // does not consume any bytes from the socket. Unix sockets cannot be peeked into,
+            // so requests received over them are not captured
+            let snapshot = match (&self.capture_dir, &self.socket) {
+                (Some(_), Connection::Tcp(socket)) => {
+                    Some((socket.peer_addr()?, peek_request(socket).await?))
                 }
-                Request::Sync(requests) => {
-                    // for SYNC request, use use a dispatcher to process peer requests
-                    let dispatcher = Dispatcher {
-                        store: self.store.clone(),
-                    };
-                    dispatcher.run(requests, &mut self.socket).await?;
+                _ => None,
+            };
+
+            match self.process_request().await {
+                Ok(true) => continue,
+                Ok(false) => return Ok(()),
+                Err(err) => {
+                    self.store.metrics().failed_request();
+                    if let (Some(capture_dir), Some((peer_addr, snapshot))) =
+                        (&self.capture_dir, snapshot)
+                    {
+                        match write_capture(capture_dir, peer_addr, &snapshot) {
+                            Ok(path) => info!("captured failed request in {}", path.display()),
+                            Err(err) => error!("failed to capture request: {}", err),
+                        }
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Logs that the connection is about to be closed because the client did not send a request
+    /// within the idle timeout; this is not treated as an error.
+    fn log_idle_timeout(&self) {
+        let timeout = self.idle_timeout.unwrap_or_default().as_millis();
+        match self.socket.peer_addr() {
+            Ok(peer_addr) => info!(
+                "closing connection to {} idle for {} ms",
+                peer_addr, timeout
+            ),
+            Err(_) => info!("closing connection idle for {} ms", timeout),
+        }
+    }
+
+    /// Reads a single request from the socket and processes it. Returns false if the peer
+    /// closed the socket and there are no more requests to process.
+    async fn process_request(&mut self) -> crate::Result<bool> {
+        // If no request was read then the peer closed the socket. There is no further work
+        // to do and the task can be terminated. The same applies if the peer started sending
+        // a request but did not finish it within the idle timeout.
+        let request =
+            match with_timeout(self.idle_timeout, Request::read_from(&mut self.socket)).await {
+                Some(request) => match request? {
+                    Some(request) => request,
+                    None => return Ok(false),
+                },
+                None => {
+                    self.log_idle_timeout();
+                    return Ok(false);
                 }
             };
+        self.handle_request(request).await
+    }
+
+    /// Processes a single request read from the socket. Returns false if the connection should
+    /// be closed after the request.
+    #[instrument(
+        name = "request",
+        skip_all,
+        fields(id = self.num_requests, kind = request_kind(&request))
+    )]
+    async fn handle_request(&mut self, request: Request) -> crate::Result<bool> {
+        let peer_addr = self.socket.peer_addr()?;
+        debug!("Received request from {}\n{}", peer_addr, request);
+
+        // if the connection has reached its limits, reject the request and close the connection
+        if self
+            .limits
+            .is_exceeded(self.num_requests, self.bytes_transferred)
+        {
+            info!(
+                "closing connection to {} after {} requests and {} bytes transferred",
+                peer_addr, self.num_requests, self.bytes_transferred
+            );
+            self.socket
+                .write_u8(status_codes::CONNECTION_LIMIT_EXCEEDED)
+                .await?;
+            return Ok(false);
+        }
+        self.num_requests += 1;
+
+        // if connection to the plasma store was lost, try to re-establish it; if this fails,
+        // the request will fail fast with a plasma store error
+        let _ = self.store.reconnect_if_unhealthy();
+
+        // make sure the received request is valid
+        request.validate()?;
+
+        // process the request
+        match request {
+            Request::Copy(object_ids) => {
+                // for COPY request, just send the objects to the requesting peer
+                let bytes_sent = self
+                    .store
+                    .build_sender(peer_addr, object_ids, false, None)
+                    .run(&mut self.socket, self.capabilities)
+                    .await?;
+                self.bytes_transferred += bytes_sent as u64;
+            }
+            Request::Take(object_ids) => {
+                // for TAKE request, send the objects, but also delete them afterwards
+                let bytes_sent = self
+                    .store
+                    .build_sender(peer_addr, object_ids, true, None)
+                    .run(&mut self.socket, self.capabilities)
+                    .await?;
+                self.bytes_transferred += bytes_sent as u64;
+            }
+            Request::Estimate(object_ids) => {
+                // for ESTIMATE request, run the sender in dry-run mode; it responds with an
+                // estimate of the transfer instead of sending the objects
+                let mut sender = self.store.build_sender(peer_addr, object_ids, false, None);
+                sender.dry_run = true;
+                sender.run(&mut self.socket, self.capabilities).await?;
+            }
+            Request::Push(object_ids) => {
+                // for PUSH request, tell the client whether it can start sending the objects;
+                // once the objects are received, respond with the final status byte
+                let receiver = self.store.build_receiver(peer_addr, object_ids, true);
+                if let Err(err) = receiver.prepare() {
+                    self.socket.write_u8(err.response_code()).await?;
+                    return Err(err.into());
+                }
+                self.socket.write_u8(status_codes::SUCCESS).await?;
+                let result = receiver
+                    .run(&mut self.socket, self.capabilities)
+                    .await
+                    .and_then(|report| {
+                        self.bytes_transferred += report.bytes_received as u64;
+                        report.into_result()
+                    });
+                match result {
+                    Ok(()) => self.socket.write_u8(status_codes::SUCCESS).await?,
+                    Err(err) => {
+                        self.socket.write_u8(err.response_code()).await?;
+                        return Err(err.into());
+                    }
+                }
+            }
+            Request::Sync(requests) => {
+                // for SYNC request, use use a dispatcher to process peer requests
+                self.store.metrics().sync_request();
+                let dispatcher = self.dispatcher();
+                self.bytes_transferred += dispatcher.run(requests, &mut self.socket).await?;
+            }
+            Request::SyncFirstWins(requests) => {
+                // same as SYNC, but objects listed by several peer requests are received once
+                self.store.metrics().sync_request();
+                let dispatcher = self.dispatcher();
+                self.bytes_transferred += dispatcher
+                    .run_first_wins(requests, &mut self.socket)
+                    .await?;
+            }
+            Request::Prewarm(peers) => {
+                // for PREWARM request, use a dispatcher to open connections to the peers ahead
+                // of subsequent SYNC requests
+                let dispatcher = self.dispatcher();
+                dispatcher.prewarm(peers, &mut self.socket).await?;
+            }
+            Request::List => {
+                // for LIST request, send back IDs of all sealed objects in the store
+                match self.store.list_objects() {
+                    Ok(object_ids) => {
+                        self.socket.write_u8(status_codes::BEGIN).await?;
+                        utils::write_object_ids(&object_ids, &mut self.socket).await?;
+                    }
+                    Err(err) => {
+                        self.socket.write_u8(status_codes::PLASMA_STORE_ERR).await?;
+                        return Err(err.into());
+                    }
+                }
+            }
+            Request::Contains(object_ids) => {
+                // for CONTAINS request, send back a bitmap indicating which of the requested
+                // objects are in the store; no object data is sent
+                match self.store.contains_objects(&object_ids) {
+                    Ok(flags) => {
+                        self.socket.write_u8(status_codes::BEGIN).await?;
+                        self.socket.write_all(&utils::to_bitmap(&flags)).await?;
+                    }
+                    Err(err) => {
+                        self.socket.write_u8(status_codes::PLASMA_STORE_ERR).await?;
+                        return Err(err.into());
+                    }
+                }
+            }
+            Request::Delete(object_ids) => {
+                // for DELETE request, delete the objects without sending them anywhere and
+                // respond with a single status byte
+                match self.store.delete_objects(&peer_addr, &object_ids) {
+                    Ok(()) => self.socket.write_u8(status_codes::SUCCESS).await?,
+                    Err(err) => {
+                        self.socket.write_u8(err.response_code()).await?;
+                        return Err(err.into());
+                    }
+                }
+            }
+            Request::Ping => {
+                // for PING request, respond with a single status byte indicating whether the
+                // plasma store is reachable
+                match self.store.check_health() {
+                    Ok(()) => self.socket.write_u8(status_codes::SUCCESS).await?,
+                    Err(err) => {
+                        self.socket.write_u8(status_codes::PLASMA_STORE_ERR).await?;
+                        return Err(err.into());
+                    }
+                }
+            }
+        };
+
+        Ok(true)
+    }
+
+    /// Returns a dispatcher which processes peer requests on behalf of this connection. Objects
+    /// received from peers are verified against their checksums as they arrive, so manifests
+    /// are not requested from peers.
+    fn dispatcher(&self) -> Dispatcher {
+        Dispatcher {
+            store: self.store.clone(),
+            capabilities: Capabilities::from_bits(self.supported.bits() & !Capabilities::MANIFEST),
+            connect_retry: self.connect_retry,
+            auth_token: self.auth_token.clone(),
+            nodelay: self.nodelay,
+            keepalive: self.keepalive,
         }
     }
 }
@@ -82,6 +415,94 @@ impl Drop for Handler {
         // Add a permit back to the semaphore. Doing so unblocks the listener if the max
         // number of connections has been reached.
         self.limit_connections.add_permits(1);
+        self.store.metrics().connection_closed();
         debug!("closed connection to {}", self.socket.peer_addr().unwrap());
     }
 }
+
+// CONNECTION LIMITS
+// ================================================================================================
+
+/// Limits on a single client connection; once any of the limits is reached, the next request
+/// received over the connection is rejected and the connection is closed. The limits are
+/// checked before every request, so a single request may exceed them. By default, the
+/// connection is not limited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionLimits {
+    /// Maximum number of requests processed over the connection.
+    pub max_requests: Option<u64>,
+    /// Maximum number of bytes of object data and metadata transferred for the connection, in
+    /// either direction.
+    pub max_bytes: Option<u64>,
+}
+
+impl ConnectionLimits {
+    /// Returns true if a connection which has processed the specified number of requests and
+    /// transferred the specified number of bytes has reached any of the limits.
+    pub fn is_exceeded(&self, num_requests: u64, bytes_transferred: u64) -> bool {
+        self.max_requests.is_some_and(|max| num_requests >= max)
+            || self.max_bytes.is_some_and(|max| bytes_transferred >= max)
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns the name of the command of the specified request.
+fn request_kind(request: &Request) -> &'static str {
+    match request {
+        Request::Copy(_) => "COPY",
+        Request::Take(_) => "TAKE",
+        Request::Sync(_) => "SYNC",
+        Request::SyncFirstWins(_) => "SYNC FIRST WINS",
+        Request::List => "LIST",
+        Request::Contains(_) => "CONTAINS",
+        Request::Delete(_) => "DELETE",
+        Request::Ping => "PING",
+        Request::Estimate(_) => "ESTIMATE",
+        Request::Push(_) => "PUSH",
+        Request::Prewarm(_) => "PREWARM",
+    }
+}
+
+/// Drives the future to completion; returns `None` if the future did not complete within the
+/// specified timeout. If `timeout` is `None`, waits for the future indefinitely.
+async fn with_timeout<F: Future>(timeout: Option<Duration>, future: F) -> Option<F::Output> {
+    match timeout {
+        Some(timeout) => time::timeout(timeout, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// Returns up to MAX_REQUEST_CAPTURE_SIZE bytes which are currently available in the socket
+/// without removing them from the socket's receive queue. This waits until at least one byte
+/// is available or the socket is closed; requests which have not been fully received yet
+/// will be captured partially.
+pub async fn peek_request(socket: &TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; MAX_REQUEST_CAPTURE_SIZE];
+    let n = socket.peek(&mut buf).await?;
+    buf.truncate(n);
+    Ok(buf)
+}
+
+/// Writes captured request bytes into a new file in the specified directory and returns
+/// the path to the file.
+pub fn write_capture(
+    capture_dir: &Path,
+    peer_addr: SocketAddr,
+    bytes: &[u8],
+) -> std::io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_micros())
+        .unwrap_or_default();
+    let file_name = format!(
+        "request-{}-{}-{}.bin",
+        peer_addr.ip(),
+        peer_addr.port(),
+        timestamp
+    );
+    let path = capture_dir.join(file_name);
+    std::fs::write(&path, bytes)?;
+    Ok(path)
+}
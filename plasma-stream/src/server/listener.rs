@@ -3,16 +3,25 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
-use plasma_store::PlasmaClient;
-use std::sync::Arc;
+use std::{
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::{
-    net::{TcpListener, TcpStream},
-    sync::Semaphore,
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, UnixListener},
+    sync::{broadcast, mpsc, Semaphore},
     time::{self, Duration},
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-use super::{Handler, Result, ServerOptions, Store, PLASMA_CONNECT_RETRIES};
+use super::{
+    expiry,
+    handshake::{AuthToken, Capabilities},
+    metrics, status_codes, ConnectRetry, Connection, ConnectionLimits, Handler, Result,
+    ServerOptions, Shutdown, SizeLimits, Store, CANCEL_GRACE_PERIOD,
+};
 
 #[derive(Debug)]
 pub struct Listener {
@@ -20,6 +29,10 @@ pub struct Listener {
     /// will listen for connections at this address.
     listener: TcpListener,
 
+    /// Unix socket listener bound to the path provided during server startup, if any; the
+    /// server accepts connections on both listeners, and handles them in the same way.
+    unix_listener: Option<(UnixListener, PathBuf)>,
+
     /// Shared handle to the Plasma Store. Contains a reference to Plasma Store client
     /// as well as other info needed to ensure data is read from / written to the store
     /// in a consistent manner.
@@ -30,6 +43,49 @@ pub struct Listener {
     /// the listener waits for one. When handlers complete processing a connection, the
     /// permit is returned to the semaphore.
     limit_connections: Arc<Semaphore>,
+
+    /// If set, connections are accepted even when no permits are available, and wait for a
+    /// permit for up to this long; connections which do not get a permit in time are rejected
+    /// with SERVER_BUSY instead of being left in the accept queue.
+    busy_grace_period: Option<Duration>,
+
+    /// Directory into which raw bytes of failed requests are written. If set to `None`,
+    /// requests are not captured.
+    capture_dir: Option<Arc<PathBuf>>,
+
+    /// Maximum time to wait for a client to send the next request before closing the connection;
+    /// `None` means waiting indefinitely.
+    idle_timeout: Option<Duration>,
+
+    /// Optional protocol features which this server supports; these are negotiated with every
+    /// client and peer during the handshake.
+    capabilities: Capabilities,
+
+    /// Determines how connections to peers are retried while processing SYNC requests.
+    connect_retry: ConnectRetry,
+
+    /// Token which clients must present during the handshake; `None` means that connections
+    /// are not authenticated.
+    auth_token: Option<Arc<AuthToken>>,
+
+    /// Whether TCP_NODELAY is set on accepted connections and on connections opened to peers.
+    nodelay: bool,
+
+    /// If set, TCP keepalive is enabled on connections opened to peers with this interval.
+    peer_keepalive: Option<Duration>,
+
+    /// Limits on requests processed and bytes sent over every accepted connection.
+    connection_limits: ConnectionLimits,
+
+    /// Broadcasts a shutdown signal to all active connections. When the listener is shut down,
+    /// the sender is dropped, which notifies all handlers that the server is shutting down.
+    notify_shutdown: broadcast::Sender<()>,
+
+    /// Used as part of the graceful shutdown process to wait for client connections to complete
+    /// processing. Each handler holds a clone of the sender; once all handlers have been dropped,
+    /// the receiver returns `None`.
+    shutdown_complete_tx: mpsc::Sender<()>,
+    shutdown_complete_rx: mpsc::Receiver<()>,
 }
 
 impl Listener {
@@ -38,23 +94,121 @@ impl Listener {
         let address = format!("127.0.0.1:{}", options.port);
         info!("starting server on {}", address);
         let listener = TcpListener::bind(&address).await?;
+        let unix_listener = match options.unix_socket {
+            Some(path) => {
+                info!("starting server on {}", path.display());
+                Some((bind_unix_socket(&path)?, path))
+            }
+            None => None,
+        };
 
         // create a semaphore to enforce connection limit
         let limit_connections = Arc::new(Semaphore::new(options.max_connections as usize));
 
-        // connect to the plasma store
-        let plasma_socket = options.plasma_socket.as_str();
-        let plasma_client = PlasmaClient::new(plasma_socket, PLASMA_CONNECT_RETRIES)?;
+        // connect to the plasma store and create an object store; if no separate timeout was
+        // specified for TAKE requests, the same timeout is used for both COPY and TAKE requests
+        let copy_timeout = to_timeout(options.plasma_timeout);
+        let take_timeout = options.take_timeout.map_or(copy_timeout, to_timeout);
+        let size_limits = to_size_limits(options.max_data_size, options.max_meta_size)?;
+        let object_ttl = to_object_ttl(options.object_ttl)?;
+        let store = Arc::new(
+            Store::new(
+                &options.plasma_socket,
+                options.plasma_connect_retries,
+                copy_timeout,
+                take_timeout,
+            )?
+            .with_size_limits(size_limits)
+            .with_receive_timeout(to_timeout(options.receive_timeout))
+            .with_object_ttl(object_ttl)
+            .with_receive_budget(options.max_receive_bytes)
+            .with_verify_received(options.verify_received_objects)
+            .with_connection_pool(
+                options.peer_pool_size,
+                Duration::from_millis(options.peer_pool_idle_timeout),
+            ),
+        );
         info!("connected to plasma store at {}", options.plasma_socket);
 
-        // create an object store
-        let plasma_timeout_ms = options.plasma_timeout;
-        let store = Arc::new(Store::new(plasma_client, plasma_timeout_ms));
+        // delete objects received from peers once their time-to-live elapses
+        if let Some(object_ttl) = object_ttl {
+            info!(
+                "objects received from peers expire after {} ms",
+                object_ttl.as_millis()
+            );
+            expiry::spawn_task(store.clone());
+        }
+
+        // expose metrics over HTTP if requested; the endpoint is bound to the same interface
+        // as the server itself
+        if let Some(metrics_port) = options.metrics_port {
+            let metrics_address = format!("127.0.0.1:{}", metrics_port);
+            let metrics_listener = TcpListener::bind(&metrics_address).await?;
+            info!("serving metrics on {}", metrics_address);
+            metrics::spawn_endpoint(metrics_listener, store.metrics().clone());
+        }
+
+        // make sure request capture directory exists
+        let capture_dir = match options.capture_dir {
+            Some(capture_dir) => {
+                std::fs::create_dir_all(&capture_dir)?;
+                info!("capturing failed requests into {}", capture_dir.display());
+                Some(Arc::new(capture_dir))
+            }
+            None => None,
+        };
+
+        // object checksums, transfer manifests, and codecs are always supported, while
+        // compression is opt-in
+        let mut capabilities =
+            Capabilities::CHECKSUMS | Capabilities::MANIFEST | Capabilities::CODECS;
+        if options.compression {
+            info!("object compression enabled");
+            capabilities |= Capabilities::COMPRESSION;
+        }
+        let capabilities = Capabilities::from_bits(capabilities);
+
+        let auth_token = match options.auth_token {
+            Some(auth_token) => {
+                info!("client authentication enabled");
+                Some(Arc::new(auth_token))
+            }
+            None => {
+                warn!("client authentication disabled; any client can access the store");
+                None
+            }
+        };
+
+        let connect_retry = ConnectRetry {
+            max_retries: options.peer_connect_retries,
+            initial_backoff: Duration::from_millis(options.peer_connect_backoff),
+        };
+        let peer_keepalive = to_keepalive(options.peer_keepalive)?;
+
+        // set up channels used for graceful shutdown
+        let (notify_shutdown, _) = broadcast::channel(1);
+        let (shutdown_complete_tx, shutdown_complete_rx) = mpsc::channel(1);
 
         Ok(Listener {
             listener,
+            unix_listener,
             store,
             limit_connections,
+            busy_grace_period: options.busy_grace_period.map(Duration::from_millis),
+            capture_dir,
+            idle_timeout: to_timeout(options.idle_timeout),
+            capabilities,
+            connect_retry,
+            auth_token,
+            nodelay: !options.disable_tcp_nodelay,
+            peer_keepalive,
+            connection_limits: ConnectionLimits {
+                max_requests: options.max_requests_per_connection,
+                max_bytes: options.max_bytes_per_connection,
+            },
+            notify_shutdown,
+            shutdown_complete_tx,
+            shutdown_complete_rx,
         })
     }
 
@@ -66,30 +220,135 @@ impl Listener {
         info!("accepting inbound connections");
 
         loop {
-            // Wait for a permit to become available
+            // Wait for a permit to become available, unless connections over the limit are
+            // rejected; in that case, the permit is acquired after the connection is accepted
             //
             // `acquire()` returns `Err` when the semaphore has been closed. We don't ever
             // close the semaphore, so `unwrap()` is safe.
-            self.limit_connections.acquire().await.unwrap().forget();
+            if self.busy_grace_period.is_none() {
+                self.limit_connections.acquire().await.unwrap().forget();
+            }
 
             // Accept a new socket. This will attempt to perform error handling. The `accept`
             // method internally attempts to recover errors, so an error here is non-recoverable.
             let socket = self.accept().await?;
-            debug!("accepted connection from {}", socket.peer_addr().unwrap());
+            if let Ok(peer_addr) = socket.peer_addr() {
+                debug!("accepted connection from {}", peer_addr);
+            }
 
             // Create the necessary per-connection handler state. The handler needs a handle to
             // the max connections semaphore. When the handler is done processing the connection,
             // a permit is added back to the semaphore.
-            let mut handler =
-                Handler::new(socket, self.store.clone(), self.limit_connections.clone());
+            let new_handler = self.new_handler();
 
-            // Spawn a new task to process the connections
-            tokio::spawn(async move {
-                // Process the connection. If an error is encountered, log it.
-                if let Err(err) = handler.run().await {
-                    error!("{}", err);
+            // If connections over the limit are rejected and no permit is available right away,
+            // the connection waits for a permit in its own task, so that accepting (and
+            // rejecting) other connections is not delayed by the wait
+            if let Some(grace_period) = self.busy_grace_period {
+                match self.limit_connections.try_acquire() {
+                    Ok(permit) => permit.forget(),
+                    Err(_) => {
+                        let limit_connections = self.limit_connections.clone();
+                        tokio::spawn(async move {
+                            let admitted = admit(socket, &limit_connections, grace_period).await;
+                            if let Some(socket) = admitted {
+                                run_handler(new_handler(socket)).await;
+                            }
+                        });
+                        continue;
+                    }
                 }
-            });
+            }
+
+            // Spawn a new task to process the connections
+            tokio::spawn(run_handler(new_handler(socket)));
+        }
+    }
+
+    /// Shuts down the listener gracefully: all active connections are notified that the server
+    /// is shutting down, and are given up to `drain_timeout` to finish processing the requests
+    /// they are currently handling. Transfers which are still in progress after that are
+    /// cancelled.
+    ///
+    /// This should be called only after `start()` has returned (or its future has been dropped)
+    /// so that no new connections are accepted.
+    pub async fn shutdown(self, drain_timeout: Duration) {
+        let Listener {
+            unix_listener,
+            store,
+            notify_shutdown,
+            shutdown_complete_tx,
+            mut shutdown_complete_rx,
+            ..
+        } = self;
+
+        // dropping the broadcast sender notifies all subscribed handlers; dropping our own
+        // copy of the completion sender ensures that only handlers hold the remaining copies
+        drop(notify_shutdown);
+        drop(shutdown_complete_tx);
+
+        // no new connections are accepted at this point, so the Unix socket can be removed;
+        // established connections are not affected by this
+        if let Some((unix_listener, path)) = unix_listener {
+            drop(unix_listener);
+            if let Err(err) = std::fs::remove_file(&path) {
+                warn!("failed to remove Unix socket {}: {}", path.display(), err);
+            }
+        }
+
+        // wait for all active connections to finish processing; if this takes too long, the
+        // transfers which are still in progress are cancelled, and the connections are given a
+        // short grace period to clean up partially received objects and report cancellation.
+        // Connection tasks which are still running after that are dropped with the runtime
+        if time::timeout(drain_timeout, shutdown_complete_rx.recv())
+            .await
+            .is_err()
+        {
+            warn!(
+                "active connections did not complete within {} ms; cancelling their transfers",
+                drain_timeout.as_millis()
+            );
+            store.cancel_transfers();
+            if time::timeout(CANCEL_GRACE_PERIOD, shutdown_complete_rx.recv())
+                .await
+                .is_err()
+            {
+                warn!("active connections did not stop after cancellation; aborting them");
+            }
+        }
+    }
+
+    /// Returns a function which creates a handler for an accepted connection; the function
+    /// holds its own copies of the handler state, so it can be moved into a separate task.
+    fn new_handler(&self) -> impl FnOnce(Connection) -> Handler + Send + 'static {
+        let store = self.store.clone();
+        let limit_connections = self.limit_connections.clone();
+        let capture_dir = self.capture_dir.clone();
+        let idle_timeout = self.idle_timeout;
+        let capabilities = self.capabilities;
+        let connect_retry = self.connect_retry;
+        let auth_token = self.auth_token.clone();
+        let nodelay = self.nodelay;
+        let peer_keepalive = self.peer_keepalive;
+        let connection_limits = self.connection_limits;
+        let shutdown = Shutdown::new(self.notify_shutdown.subscribe());
+        let shutdown_complete_tx = self.shutdown_complete_tx.clone();
+        move |socket| {
+            Handler::new(
+                socket,
+                store,
+                limit_connections,
+                capture_dir,
+                idle_timeout,
+                capabilities,
+                connect_retry,
+                auth_token,
+                nodelay,
+                peer_keepalive,
+                connection_limits,
+                shutdown,
+                shutdown_complete_tx,
+            )
         }
     }
 
@@ -99,14 +358,35 @@ impl Listener {
     /// After the first failure, the task waits for 1 second. After the second failure, the task
     /// waits for 2 seconds. Each subsequent failure increases the wait time by 1 second. If
     /// accepting fails on the 5th try after waiting for 4 seconds, an error is returned.
-    async fn accept(&mut self) -> crate::Result<TcpStream> {
+    ///
+    /// If the server listens on a Unix socket as well, a connection is accepted from whichever
+    /// listener receives one first.
+    async fn accept(&mut self) -> crate::Result<Connection> {
         let mut backoff = 1;
 
         loop {
             // Perform the accept operation. If a socket is successfully accepted, return it.
             // Otherwise, save the error.
-            match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
+            let accepted = match &self.unix_listener {
+                Some((unix_listener, _)) => tokio::select! {
+                    res = self.listener.accept() => res.map(|(socket, _)| Connection::from(socket)),
+                    res = unix_listener.accept() => res.map(|(socket, _)| Connection::from(socket)),
+                },
+                None => self
+                    .listener
+                    .accept()
+                    .await
+                    .map(|(socket, _)| Connection::from(socket)),
+            };
+            match accepted {
+                Ok(socket) => {
+                    // failing to set TCP_NODELAY affects only latency, so the connection is
+                    // still accepted
+                    if let Err(err) = socket.set_nodelay(self.nodelay) {
+                        warn!("failed to set TCP_NODELAY on accepted connection: {}", err);
+                    }
+                    return Ok(socket);
+                }
                 Err(err) => {
                     // If accept has failed too many times. Return the error.
                     debug!("failed to accept connection: {}", err);
@@ -124,3 +404,134 @@ impl Listener {
         }
     }
 }
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Processes the connection served by the specified handler; if an error is encountered, it
+/// is logged.
+async fn run_handler(mut handler: Handler) {
+    if let Err(err) = handler.run().await {
+        error!("{}", err);
+    }
+}
+
+/// Waits up to `grace_period` for a connection permit for an accepted connection, and returns
+/// the connection once the permit is acquired. If no permit becomes available in time, the
+/// client is sent SERVER_BUSY, the connection is closed, and `None` is returned. Since this
+/// waits for up to `grace_period`, it should not be called from the accept loop.
+///
+/// The permit is forgotten; it is returned to the semaphore by the connection handler.
+pub async fn admit(
+    socket: Connection,
+    limit_connections: &Semaphore,
+    grace_period: Duration,
+) -> Option<Connection> {
+    // `acquire()` returns `Err` only when the semaphore has been closed, which never happens
+    if let Ok(permit) = time::timeout(grace_period, limit_connections.acquire()).await {
+        permit.unwrap().forget();
+        return Some(socket);
+    }
+
+    // the client may have gone away while waiting for the permit, in which case its address
+    // is no longer available; rejecting the connection is done in a separate task so that
+    // the caller is not delayed by a slow client
+    match socket.peer_addr() {
+        Ok(peer_addr) => info!("rejecting connection from {}: server is busy", peer_addr),
+        Err(_) => info!("rejecting connection: server is busy"),
+    }
+    tokio::spawn(async move {
+        if let Err(err) = reject_busy(socket, grace_period).await {
+            debug!("failed to reject connection: {}", err);
+        }
+    });
+    None
+}
+
+/// Responds to the client with SERVER_BUSY and closes the connection. The handshake sent by the
+/// client is drained (for up to `drain_timeout`) before the connection is closed; closing a TCP
+/// connection with unread data resets it, which could discard the response before the client
+/// reads it.
+async fn reject_busy(mut socket: Connection, drain_timeout: Duration) -> std::io::Result<()> {
+    socket.write_u8(status_codes::SERVER_BUSY).await?;
+    socket.shutdown().await?;
+    let mut buf = [0u8; 64];
+    let drain = async {
+        while socket.read(&mut buf).await? > 0 {}
+        Ok(())
+    };
+    time::timeout(drain_timeout, drain).await.unwrap_or(Ok(()))
+}
+
+/// Binds a Unix socket listener to the specified path. If a socket already exists at the path
+/// (e.g. left behind by a server which did not shut down cleanly), it is removed first; any
+/// other file at the path is left intact, and binding fails.
+fn bind_unix_socket(path: &Path) -> Result<UnixListener> {
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            warn!("removing stale Unix socket {}", path.display());
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(UnixListener::bind(path)?)
+}
+
+/// Builds object size limits from the specified values, using protocol limits for values which
+/// were not specified. Returns an error if any of the values exceeds the protocol limit.
+fn to_size_limits(max_data_size: Option<u64>, max_meta_size: Option<u64>) -> Result<SizeLimits> {
+    let defaults = SizeLimits::default();
+    let size_limits = SizeLimits {
+        max_data_size: max_data_size.unwrap_or(defaults.max_data_size),
+        max_meta_size: max_meta_size.unwrap_or(defaults.max_meta_size),
+    };
+    if size_limits.max_data_size > defaults.max_data_size {
+        return Err(format!(
+            "max data size cannot exceed {} bytes, but was {}",
+            defaults.max_data_size, size_limits.max_data_size
+        )
+        .into());
+    }
+    if size_limits.max_meta_size > defaults.max_meta_size {
+        return Err(format!(
+            "max metadata size cannot exceed {} bytes, but was {}",
+            defaults.max_meta_size, size_limits.max_meta_size
+        )
+        .into());
+    }
+    Ok(size_limits)
+}
+
+/// Converts the keepalive interval specified in milliseconds on the command line into a
+/// duration. Keepalive intervals are specified in whole seconds on most platforms, so
+/// intervals shorter than a second are rejected.
+fn to_keepalive(interval_ms: Option<u64>) -> Result<Option<Duration>> {
+    match interval_ms {
+        Some(interval_ms) if interval_ms < 1000 => Err(format!(
+            "peer keepalive interval must be at least 1000 ms, but was {} ms",
+            interval_ms
+        )
+        .into()),
+        Some(interval_ms) => Ok(Some(Duration::from_millis(interval_ms))),
+        None => Ok(None),
+    }
+}
+
+/// Converts time-to-live of objects specified in milliseconds on the command line into a
+/// duration; the time-to-live must be positive.
+fn to_object_ttl(ttl_ms: Option<u64>) -> Result<Option<Duration>> {
+    match ttl_ms {
+        Some(0) => Err("object time-to-live must be at least 1 ms".into()),
+        Some(ttl_ms) => Ok(Some(Duration::from_millis(ttl_ms))),
+        None => Ok(None),
+    }
+}
+
+/// Converts a timeout specified in milliseconds on the command line into a duration; negative
+/// values mean that there is no timeout.
+fn to_timeout(timeout_ms: i64) -> Option<Duration> {
+    if timeout_ms < 0 {
+        None
+    } else {
+        Some(Duration::from_millis(timeout_ms as u64))
+    }
+}
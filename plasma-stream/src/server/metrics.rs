@@ -0,0 +1,217 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, error};
+
+// CONSTANTS
+// ================================================================================================
+
+/// Path at which metrics are exposed by the metrics endpoint.
+const METRICS_PATH: &str = "/metrics";
+
+/// Maximum size of an HTTP request head accepted by the metrics endpoint.
+const MAX_HTTP_REQUEST_SIZE: usize = 8_192;
+
+// METRICS REGISTRY
+// ================================================================================================
+
+/// Counters describing activity of the server. All counters are plain atomics updated with
+/// relaxed ordering, so updating them on the hot path does not introduce contention; values
+/// read while the server is running are not guaranteed to be consistent with each other.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Number of objects sent to clients and peers.
+    objects_sent: AtomicU64,
+    /// Number of bytes of object data and metadata sent to clients and peers.
+    bytes_sent: AtomicU64,
+    /// Number of objects received from peers and saved into the local store.
+    objects_received: AtomicU64,
+    /// Number of bytes of object data and metadata received from peers.
+    bytes_received: AtomicU64,
+    /// Number of SYNC requests processed.
+    sync_requests: AtomicU64,
+    /// Number of peer requests within SYNC requests which failed.
+    peer_errors: AtomicU64,
+    /// Number of client requests which failed.
+    failed_requests: AtomicU64,
+    /// Number of currently open client connections.
+    active_connections: AtomicI64,
+}
+
+impl Metrics {
+    /// Records that an object of the specified size (in bytes) has been sent.
+    pub fn object_sent(&self, size: usize) {
+        self.objects_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    /// Records that an object of the specified size (in bytes) has been received.
+    pub fn object_received(&self, size: usize) {
+        self.objects_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    /// Records that a SYNC request has been received.
+    pub fn sync_request(&self) {
+        self.sync_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a peer request within a SYNC request failed.
+    pub fn peer_error(&self) {
+        self.peer_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a client request failed.
+    pub fn failed_request(&self) {
+        self.failed_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a client connection has been opened.
+    pub fn connection_opened(&self) {
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a client connection has been closed.
+    pub fn connection_closed(&self) {
+        self.active_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Renders all metrics in Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let counters = [
+            (
+                "objects_sent_total",
+                "Number of objects sent to clients and peers.",
+                &self.objects_sent,
+            ),
+            (
+                "bytes_sent_total",
+                "Number of object bytes sent to clients and peers.",
+                &self.bytes_sent,
+            ),
+            (
+                "objects_received_total",
+                "Number of objects received from peers.",
+                &self.objects_received,
+            ),
+            (
+                "bytes_received_total",
+                "Number of object bytes received from peers.",
+                &self.bytes_received,
+            ),
+            (
+                "sync_requests_total",
+                "Number of SYNC requests processed.",
+                &self.sync_requests,
+            ),
+            (
+                "peer_errors_total",
+                "Number of failed peer requests.",
+                &self.peer_errors,
+            ),
+            (
+                "failed_requests_total",
+                "Number of failed client requests.",
+                &self.failed_requests,
+            ),
+        ];
+
+        // writing into a string cannot fail, so unwrapping results below is OK
+        let mut result = String::new();
+        for (name, help, value) in counters.iter() {
+            writeln!(result, "# HELP plasma_stream_{} {}", name, help).unwrap();
+            writeln!(result, "# TYPE plasma_stream_{} counter", name).unwrap();
+            let value = value.load(Ordering::Relaxed);
+            writeln!(result, "plasma_stream_{} {}", name, value).unwrap();
+        }
+
+        let name = "plasma_stream_active_connections";
+        writeln!(result, "# HELP {} Number of open client connections.", name).unwrap();
+        writeln!(result, "# TYPE {} gauge", name).unwrap();
+        let value = self.active_connections.load(Ordering::Relaxed);
+        writeln!(result, "{} {}", name, value).unwrap();
+
+        result
+    }
+}
+
+// METRICS ENDPOINT
+// ================================================================================================
+
+/// Serves metrics over HTTP at `/metrics` on connections accepted from the specified listener;
+/// requests for any other path are answered with 404. Runs until accepting a connection fails.
+pub async fn serve(listener: TcpListener, metrics: Arc<Metrics>) -> std::io::Result<()> {
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(err) = respond(socket, &metrics).await {
+                debug!("failed to serve metrics: {}", err);
+            }
+        });
+    }
+}
+
+/// Spawns a task which serves metrics on the specified listener; errors are logged.
+pub fn spawn_endpoint(listener: TcpListener, metrics: Arc<Metrics>) {
+    tokio::spawn(async move {
+        if let Err(err) = serve(listener, metrics).await {
+            error!("metrics endpoint stopped: {}", err);
+        }
+    });
+}
+
+/// Reads a single HTTP request from the socket and writes the response; the connection is
+/// closed afterwards.
+async fn respond(mut socket: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    // read the request head; the body, if any, is ignored
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        if request.len() > MAX_HTTP_REQUEST_SIZE {
+            return write_response(&mut socket, "431 Request Header Fields Too Large", "").await;
+        }
+        let n = socket.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    // only GET requests for the metrics path are served
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(METRICS_PATH)) => {
+            write_response(&mut socket, "200 OK", &metrics.render()).await
+        }
+        (Some("GET"), _) => write_response(&mut socket, "404 Not Found", "").await,
+        _ => write_response(&mut socket, "405 Method Not Allowed", "").await,
+    }
+}
+
+async fn write_response(socket: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+        Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await
+}
@@ -4,22 +4,26 @@
 // LICENSE file in the root directory of this source tree.
 
 use plasma_stream::{
-    errors, status_codes, utils, ObjectId, PeerRequest, Request, Result, MAX_DATA_SIZE,
-    MAX_META_SIZE,
+    errors, handshake, status_codes, utils, ObjectId, PeerAddr, PeerRequest, Request, Result,
+    TransferEstimate, DATA_CHUNK_SIZE, MAX_DATA_SIZE, MAX_META_SIZE, MAX_OBJECT_ID_LIST_LEN,
 };
+use std::{path::PathBuf, time::Duration};
 use structopt::StructOpt;
 use tokio::signal;
-use tracing::{error, info, Level};
+use tracing::{error, info, Level, Subscriber};
 use tracing_subscriber::FmtSubscriber;
 
 mod listener;
 use listener::Listener;
 
+mod connection;
+use connection::Connection;
+
 mod handler;
-use handler::Handler;
+use handler::{ConnectionLimits, Handler};
 
 mod store;
-use store::Store;
+use store::{SizeLimits, Store};
 
 mod sender;
 use sender::ObjectSender;
@@ -28,17 +32,52 @@ mod receiver;
 use receiver::ObjectReceiver;
 
 mod dispatcher;
-use dispatcher::Dispatcher;
+use dispatcher::{ConnectRetry, Dispatcher};
+
+mod shutdown;
+use shutdown::Shutdown;
+
+mod credentials;
+
+mod metrics;
+use metrics::Metrics;
+
+mod expiry;
+use expiry::ExpiryTracker;
+
+mod budget;
+use budget::ByteBudget;
+
+mod pool;
+use pool::ConnectionPool;
+
+mod object_store;
+use object_store::{ObjectStore, StoredObject};
+
+#[cfg(test)]
+mod tests;
 
 // CONSTANTS
 // ================================================================================================
 
 const DEFAULT_PORT: &str = "2021";
 const DEFAULT_PLASMA_SOCKET: &str = "/tmp/plasma";
-const DEFAULT_PLASMA_TIMEOUT: &str = "10";
+const DEFAULT_PLASMA_TIMEOUT: &str = "1000";
+const DEFAULT_PLASMA_CONNECT_RETRIES: &str = "4";
 const DEFAULT_MAX_CONNECTIONS: &str = "128";
+const DEFAULT_SHUTDOWN_TIMEOUT: &str = "10000";
+const DEFAULT_IDLE_TIMEOUT: &str = "60000";
+const DEFAULT_RECEIVE_TIMEOUT: &str = "30000";
+const DEFAULT_PEER_CONNECT_RETRIES: &str = "3";
+const DEFAULT_PEER_CONNECT_BACKOFF: &str = "100";
+const DEFAULT_PEER_POOL_SIZE: &str = "0";
+const DEFAULT_PEER_POOL_IDLE_TIMEOUT: &str = "30000";
+const DEFAULT_LOG_LEVEL: &str = "info";
 
-const PLASMA_CONNECT_RETRIES: u32 = 4;
+const MAX_REQUEST_CAPTURE_SIZE: usize = 65_536; // 64 KB
+
+/// Time given to connections to stop after their transfers were cancelled during shutdown.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_millis(1000);
 
 // COMMAND LINE ARGUMENTS
 // ================================================================================================
@@ -50,17 +89,166 @@ pub struct ServerOptions {
     #[structopt(short, long, default_value=DEFAULT_PORT)]
     port: String,
 
+    /// Path of a Unix domain socket on which the server accepts connections in addition to the
+    /// TCP port; this is intended for clients and peers running on the same host. A stale
+    /// socket left at this path by a previous run is removed on startup
+    #[structopt(long, parse(from_os_str))]
+    unix_socket: Option<PathBuf>,
+
     /// Maximum number of TCP connections accepted by this server
     #[structopt(short="c", long, default_value=DEFAULT_MAX_CONNECTIONS)]
     max_connections: u32,
 
+    /// The amount of time in milliseconds a connection accepted while max-connections
+    /// connections are open waits for one of them to close; if none closes in time, the
+    /// connection is rejected with SERVER_BUSY. If not set, such connections are not accepted
+    /// until a connection closes
+    #[structopt(long)]
+    busy_grace_period: Option<u64>,
+
+    /// Maximum number of requests processed on a single connection; once the limit is reached,
+    /// the next request is rejected and the connection is closed. If not set, the number of
+    /// requests is not limited
+    #[structopt(long)]
+    max_requests_per_connection: Option<u64>,
+
+    /// Maximum number of bytes of object data and metadata transferred for a single connection,
+    /// in either direction and including objects moved between this server and peers by SYNC
+    /// requests. The limit is checked before every request, so the request which reaches it is
+    /// completed; the next request is then rejected and the connection is closed. If not set,
+    /// the number of bytes is not limited
+    #[structopt(long)]
+    max_bytes_per_connection: Option<u64>,
+
     /// Unix socket bound to the local Plasma Store
     #[structopt(short="s", long, default_value=DEFAULT_PLASMA_SOCKET)]
     plasma_socket: String,
 
-    /// The amount of time in milliseconds to wait before requests to Plasma Store time out.
+    /// Number of attempts to connect to the Plasma Store socket on startup
+    #[structopt(short="r", long, default_value=DEFAULT_PLASMA_CONNECT_RETRIES)]
+    plasma_connect_retries: u32,
+
+    /// The amount of time in milliseconds to wait for requested objects to be sealed in the
+    /// Plasma Store before requests time out; a negative value disables the timeout
     #[structopt(short="t", long, default_value=DEFAULT_PLASMA_TIMEOUT)]
     plasma_timeout: i64,
+
+    /// The amount of time in milliseconds to wait for objects requested via TAKE to be sealed
+    /// in the Plasma Store; defaults to the value of plasma-timeout
+    #[structopt(long)]
+    take_timeout: Option<i64>,
+
+    /// The amount of time in milliseconds to wait for a client to send the next request before
+    /// closing the connection; a negative value disables the timeout
+    #[structopt(long, default_value=DEFAULT_IDLE_TIMEOUT)]
+    idle_timeout: i64,
+
+    /// The amount of time in milliseconds to wait for a peer to send the next part of an object
+    /// (its header, metadata, or a chunk of up to 4 MB of data) while receiving objects from
+    /// it; if the peer stalls for longer, the transfer fails and partially received objects are
+    /// deleted. A negative value disables the timeout
+    #[structopt(long, default_value=DEFAULT_RECEIVE_TIMEOUT)]
+    receive_timeout: i64,
+
+    /// Maximum total size in bytes of objects which are received from peers at the same time
+    /// across all connections; objects are received only once enough of this budget is
+    /// available for them, and objects larger than the budget are received one at a time. If not
+    /// set, the size is not limited
+    #[structopt(long)]
+    max_receive_bytes: Option<u64>,
+
+    /// The amount of time in milliseconds for which objects received from peers are kept in the
+    /// Plasma Store; once it elapses, the objects are deleted. Objects which are in use by
+    /// other clients of the store are deleted once released. If not set, objects are kept until
+    /// they are deleted explicitly
+    #[structopt(long)]
+    object_ttl: Option<u64>,
+
+    /// Number of times a connection to a peer is retried when it cannot be established while
+    /// processing SYNC requests
+    #[structopt(long, default_value=DEFAULT_PEER_CONNECT_RETRIES)]
+    peer_connect_retries: u32,
+
+    /// The amount of time in milliseconds to wait before retrying a connection to a peer; the
+    /// wait time doubles with every subsequent retry
+    #[structopt(long, default_value=DEFAULT_PEER_CONNECT_BACKOFF)]
+    peer_connect_backoff: u64,
+
+    /// The amount of time in milliseconds a connection to a peer may stay idle before TCP
+    /// keepalive probes are sent, and the interval between the probes; this bounds how long a
+    /// peer which went away without closing the connection can stall a SYNC request. Must be
+    /// at least 1000; if not set, keepalive is not enabled
+    #[structopt(long)]
+    peer_keepalive: Option<u64>,
+
+    /// Maximum number of idle connections to every peer kept open after SYNC requests complete,
+    /// so that subsequent SYNC requests to the same peer can reuse them; 0 disables reuse of
+    /// connections. Peers should not limit the number of requests per connection when this is
+    /// set, as a request sent over a connection closed by the peer fails
+    #[structopt(long, default_value=DEFAULT_PEER_POOL_SIZE)]
+    peer_pool_size: usize,
+
+    /// The amount of time in milliseconds an idle connection to a peer is kept open for reuse;
+    /// this should be shorter than the idle-timeout of the peer
+    #[structopt(long, default_value=DEFAULT_PEER_POOL_IDLE_TIMEOUT)]
+    peer_pool_idle_timeout: u64,
+
+    /// Maximum size in bytes of data of objects sent and received by this server; defaults to
+    /// (and cannot exceed) the protocol limit of 16 TB
+    #[structopt(long)]
+    max_data_size: Option<u64>,
+
+    /// Maximum size in bytes of metadata of objects sent and received by this server; defaults
+    /// to (and cannot exceed) the protocol limit of 64 KB
+    #[structopt(long)]
+    max_meta_size: Option<u64>,
+
+    /// The amount of time in milliseconds to wait for active connections to finish processing
+    /// their current requests when the server is shutting down
+    #[structopt(long, default_value=DEFAULT_SHUTDOWN_TIMEOUT)]
+    shutdown_timeout: u64,
+
+    /// Do not set TCP_NODELAY on connections accepted from clients and peers, and on
+    /// connections opened to peers; by default, TCP_NODELAY is set to avoid delaying small
+    /// writes, such as object headers
+    #[structopt(long)]
+    disable_tcp_nodelay: bool,
+
+    /// Compress object data sent to clients and peers which request compression; objects
+    /// received from peers are always requested compressed when this is set
+    #[structopt(long)]
+    compression: bool,
+
+    /// Read every object received from a peer back from the Plasma Store after it is sealed,
+    /// and fail the transfer if it does not match the received object; this is intended for
+    /// debugging, as it costs an extra read of every received object
+    #[structopt(long)]
+    verify_received_objects: bool,
+
+    /// TCP port on which metrics are exposed over HTTP at /metrics; if not set, metrics are
+    /// not exposed
+    #[structopt(long)]
+    metrics_port: Option<u16>,
+
+    /// Directory into which raw bytes of requests which could not be processed are written;
+    /// this is intended for debugging protocol errors
+    #[structopt(long, parse(from_os_str))]
+    capture_dir: Option<PathBuf>,
+
+    /// Token (64 hexadecimal characters) which clients and peers must present to connect; if
+    /// not set, connections are not authenticated. The same token is presented to peers when
+    /// processing SYNC requests
+    #[structopt(long)]
+    auth_token: Option<handshake::AuthToken>,
+
+    /// Directory from which credentials (e.g. plasma-socket) are read; values read from this
+    /// directory take precedence over the values passed on the command line
+    #[structopt(long, env = "CREDENTIALS_DIRECTORY", parse(from_os_str))]
+    credentials_dir: Option<PathBuf>,
+
+    /// Maximum level of log messages: error, warn, info, debug, or trace
+    #[structopt(long, default_value=DEFAULT_LOG_LEVEL)]
+    log_level: Level,
 }
 
 // PROGRAM ENTRY POINT
@@ -68,26 +256,22 @@ pub struct ServerOptions {
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
-    // turn tracing on
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::TRACE)
-        .with_target(false)
-        .with_thread_ids(true)
-        .finish();
+    // read command-line args and turn tracing on at the requested level
+    let mut options = ServerOptions::from_args();
+    let subscriber = build_subscriber(options.log_level);
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
     // listen to shutdown signal
     let shutdown = signal::ctrl_c();
 
-    // read command-line args
-    let options = ServerOptions::from_args();
+    // override command-line args with credentials, if any were provided
+    credentials::load(&mut options)?;
 
     // create the listener
+    let shutdown_timeout = Duration::from_millis(options.shutdown_timeout);
     let mut server = Listener::new(options).await?;
 
-    // run the server until the shutdown signal is received. Currently, this is a
-    // placeholder for graceful shutdown capability.
-    // TODO: implement graceful shutdown
+    // run the server until the shutdown signal is received
     tokio::select! {
         res = server.start() => {
             // If an error is received here, accepting connections from the TCP listener failed
@@ -105,5 +289,22 @@ pub async fn main() -> Result<()> {
         }
     }
 
+    // no new connections are accepted at this point; let active connections finish their
+    // current requests before exiting
+    server.shutdown(shutdown_timeout).await;
+    info!("server stopped");
+
     Ok(())
 }
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Builds the subscriber which writes log messages up to the specified level to stdout.
+fn build_subscriber(level: Level) -> impl Subscriber + Send + Sync {
+    FmtSubscriber::builder()
+        .with_max_level(level)
+        .with_target(false)
+        .with_thread_ids(true)
+        .finish()
+}
@@ -0,0 +1,207 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use plasma_store::{MemoryUsage, ObjectBuffer, ObjectInfo, PlasmaClient, PlasmaError};
+use std::{
+    fmt::{Debug, Display},
+    time::Duration,
+};
+
+// OBJECT STORE
+// ================================================================================================
+
+/// Operations on the local object store used by the server. This is implemented for
+/// `PlasmaClient`, which is what the server runs against; other implementations (e.g. an
+/// in-memory store) make it possible to exercise the server without a running Plasma Store.
+///
+/// Cloning a store must be cheap, and all clones must operate on the same objects.
+pub trait ObjectStore: Clone + Debug + Send + Sync + 'static {
+    /// An object retrieved from or created in the store; the object is released when dropped.
+    type Object<'a>: StoredObject
+    where
+        Self: 'a;
+
+    /// Connects to the store at the specified socket, making up to `num_retries` attempts.
+    fn connect(socket: &str, num_retries: u32) -> Result<Self, PlasmaError>;
+
+    /// Retrieves an object from the store, waiting for it to be sealed until the timeout
+    /// expires; `None` is returned if the object was not sealed in time.
+    fn get(
+        &self,
+        oid: plasma_store::ObjectId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Self::Object<'_>>, PlasmaError>;
+
+    /// Retrieves the specified objects from the store, waiting for them to be sealed until the
+    /// timeout expires; objects which were not sealed in time are returned as `None`.
+    fn get_many(
+        &self,
+        object_ids: &[plasma_store::ObjectId],
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Option<Self::Object<'_>>>, PlasmaError>;
+
+    /// Creates an object with the specified data size and metadata in the store; the returned
+    /// object must be sealed to make it available to other clients of the store.
+    fn create(
+        &self,
+        oid: plasma_store::ObjectId,
+        data_size: usize,
+        meta: &[u8],
+    ) -> Result<Self::Object<'_>, PlasmaError>;
+
+    /// Asks the store to evict at least the specified number of bytes of objects which are not
+    /// in use; returns the number of bytes evicted.
+    fn evict(&self, num_bytes: usize) -> Result<usize, PlasmaError>;
+
+    /// Returns IDs of the specified objects which are held in the store.
+    fn contains_many(
+        &self,
+        object_ids: &[plasma_store::ObjectId],
+    ) -> Result<Vec<plasma_store::ObjectId>, PlasmaError>;
+
+    /// Deletes the specified objects from the store; objects which are in use by other clients
+    /// of the store are skipped.
+    fn delete_many(&self, object_ids: &[plasma_store::ObjectId]) -> Result<(), PlasmaError>;
+
+    /// Returns descriptions of all objects held in the store.
+    fn list(&self) -> Result<Vec<ObjectInfo>, PlasmaError>;
+
+    /// Returns memory usage of the store; this requires a round trip to the store.
+    fn memory_usage(&self) -> Result<MemoryUsage, PlasmaError>;
+}
+
+/// An object retrieved from or created in an `ObjectStore`.
+pub trait StoredObject: Display + Send + Sync {
+    /// Returns ID of this object.
+    fn id(&self) -> &plasma_store::ObjectId;
+
+    /// Returns data of this object.
+    fn data(&self) -> &[u8];
+
+    /// Returns metadata of this object.
+    fn meta(&self) -> &[u8];
+
+    /// Returns mutable data of this object; this fails for objects which have been sealed.
+    fn data_mut(&mut self) -> Result<&mut [u8], PlasmaError>;
+
+    /// Seals this object, making it available to other clients of the store.
+    fn seal(&mut self) -> Result<(), PlasmaError>;
+
+    /// Releases this object; unlike dropping the object, this reports errors to the caller.
+    fn release(self) -> Result<(), PlasmaError>
+    where
+        Self: Sized;
+
+    /// Aborts this unsealed object, removing it from the store as if it was never created; this
+    /// fails for objects which have been sealed. The object must not be used once aborted.
+    fn abort_in_place(&mut self) -> Result<(), PlasmaError>;
+
+    /// Returns metadata and data of this object as `(meta, data)`.
+    fn parts(&self) -> (&[u8], &[u8]) {
+        (self.meta(), self.data())
+    }
+
+    /// Returns the size of this object in bytes, including both data and metadata.
+    fn size(&self) -> usize {
+        self.meta().len() + self.data().len()
+    }
+}
+
+// PLASMA CLIENT
+// ================================================================================================
+
+impl ObjectStore for PlasmaClient {
+    type Object<'a> = ObjectBuffer<'a>;
+
+    fn connect(socket: &str, num_retries: u32) -> Result<Self, PlasmaError> {
+        PlasmaClient::new(socket, num_retries)
+    }
+
+    fn get(
+        &self,
+        oid: plasma_store::ObjectId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<ObjectBuffer<'_>>, PlasmaError> {
+        PlasmaClient::get(self, oid, timeout)
+    }
+
+    fn get_many(
+        &self,
+        object_ids: &[plasma_store::ObjectId],
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Option<ObjectBuffer<'_>>>, PlasmaError> {
+        PlasmaClient::get_many(self, object_ids, timeout)
+    }
+
+    fn create(
+        &self,
+        oid: plasma_store::ObjectId,
+        data_size: usize,
+        meta: &[u8],
+    ) -> Result<ObjectBuffer<'_>, PlasmaError> {
+        PlasmaClient::create(self, oid, data_size, meta)
+    }
+
+    fn evict(&self, num_bytes: usize) -> Result<usize, PlasmaError> {
+        PlasmaClient::evict(self, num_bytes)
+    }
+
+    fn contains_many(
+        &self,
+        object_ids: &[plasma_store::ObjectId],
+    ) -> Result<Vec<plasma_store::ObjectId>, PlasmaError> {
+        PlasmaClient::contains_many(self, object_ids)
+    }
+
+    fn delete_many(&self, object_ids: &[plasma_store::ObjectId]) -> Result<(), PlasmaError> {
+        PlasmaClient::delete_many(self, object_ids)
+    }
+
+    fn list(&self) -> Result<Vec<ObjectInfo>, PlasmaError> {
+        PlasmaClient::list(self)
+    }
+
+    fn memory_usage(&self) -> Result<MemoryUsage, PlasmaError> {
+        PlasmaClient::memory_usage(self)
+    }
+}
+
+impl<'a> StoredObject for ObjectBuffer<'a> {
+    fn id(&self) -> &plasma_store::ObjectId {
+        ObjectBuffer::id(self)
+    }
+
+    fn data(&self) -> &[u8] {
+        ObjectBuffer::data(self)
+    }
+
+    fn meta(&self) -> &[u8] {
+        ObjectBuffer::meta(self)
+    }
+
+    fn data_mut(&mut self) -> Result<&mut [u8], PlasmaError> {
+        ObjectBuffer::data_mut(self)
+    }
+
+    fn seal(&mut self) -> Result<(), PlasmaError> {
+        ObjectBuffer::seal(self)
+    }
+
+    fn release(self) -> Result<(), PlasmaError> {
+        ObjectBuffer::release(self)
+    }
+
+    fn abort_in_place(&mut self) -> Result<(), PlasmaError> {
+        ObjectBuffer::abort_in_place(self)
+    }
+
+    fn parts(&self) -> (&[u8], &[u8]) {
+        ObjectBuffer::parts(self)
+    }
+
+    fn size(&self) -> usize {
+        ObjectBuffer::size(self)
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use super::{handshake::Capabilities, Connection, PeerAddr};
+use rustc_hash::FxHashMap;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// CONNECTION POOL
+// ================================================================================================
+
+/// Keeps idle connections to peers so that subsequent peer requests to the same peer can reuse
+/// them instead of opening a new connection and repeating the handshake. Connections are put
+/// into the pool only once a peer request has been completed over them, so a connection taken
+/// from the pool is always at a request boundary.
+#[derive(Debug)]
+pub struct ConnectionPool {
+    /// Maximum number of idle connections kept for a single peer.
+    max_per_peer: usize,
+    /// Maximum time a connection can stay in the pool; connections which have been idle for
+    /// longer are closed instead of being reused.
+    max_idle: Duration,
+    /// Idle connections for every peer, ordered from the least to the most recently used.
+    idle: Mutex<FxHashMap<PeerAddr, Vec<IdleConnection>>>,
+}
+
+#[derive(Debug)]
+struct IdleConnection {
+    socket: Connection,
+    capabilities: Capabilities,
+    since: Instant,
+}
+
+impl ConnectionPool {
+    /// Returns a new pool which keeps up to `max_per_peer` connections for every peer, each for
+    /// up to `max_idle`.
+    pub fn new(max_per_peer: usize, max_idle: Duration) -> Self {
+        ConnectionPool {
+            max_per_peer,
+            max_idle,
+            idle: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    /// Takes the most recently used idle connection to the specified peer out of the pool,
+    /// together with capabilities negotiated for it. Connections which have been idle for too
+    /// long, have been closed by the peer, or have unexpected data waiting to be read from
+    /// them are closed; `None` is returned if no connection can be reused.
+    pub fn take(&self, peer: &PeerAddr) -> Option<(Connection, Capabilities)> {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        let mut idle = self.idle.lock().unwrap();
+        let connections = idle.get_mut(peer)?;
+        let mut result = None;
+        while let Some(connection) = connections.pop() {
+            if connection.since.elapsed() <= self.max_idle && connection.socket.is_idle() {
+                result = Some((connection.socket, connection.capabilities));
+                break;
+            }
+        }
+        if connections.is_empty() {
+            idle.remove(peer);
+        }
+        result
+    }
+
+    /// Puts a connection to the specified peer into the pool; the connection must not be in
+    /// the middle of a request. If the pool already holds the maximum number of connections for
+    /// the peer, the least recently used connection is closed.
+    pub fn put(&self, peer: PeerAddr, socket: Connection, capabilities: Capabilities) {
+        if self.max_per_peer == 0 {
+            return;
+        }
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        let mut idle = self.idle.lock().unwrap();
+        let connections = idle.entry(peer).or_default();
+        connections.retain(|connection| connection.since.elapsed() <= self.max_idle);
+        if connections.len() >= self.max_per_peer {
+            connections.remove(0);
+        }
+        connections.push(IdleConnection {
+            socket,
+            capabilities,
+            since: Instant::now(),
+        });
+    }
+
+    /// Returns the number of idle connections to the specified peer currently in the pool.
+    pub fn num_idle(&self, peer: &PeerAddr) -> usize {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        let idle = self.idle.lock().unwrap();
+        idle.get(peer).map_or(0, Vec::len)
+    }
+}
@@ -4,43 +4,93 @@
 // LICENSE file in the root directory of this source tree.
 
 use super::{
-    errors::ObjectReceiveError, status_codes, utils::map_object_ids, ObjectId, MAX_DATA_SIZE,
-    MAX_META_SIZE,
+    errors::ObjectReceiveError,
+    handshake::Capabilities,
+    status_codes,
+    store::{check_store_error, ensure_healthy, SizeLimits},
+    utils::{map_object_ids, object_checksum, read_compressed_chunk, read_object_header},
+    ByteBudget, ExpiryTracker, Metrics, ObjectId, ObjectStore, PeerAddr, StoredObject,
+    DATA_CHUNK_SIZE,
 };
-use plasma_store::{ObjectBuffer, PlasmaClient};
+use plasma_store::{PlasmaClient, PlasmaError};
+use plasma_stream::Codec;
+use rustc_hash::FxHashSet;
 use std::{
-    collections::HashSet,
     convert::TryInto,
-    net::SocketAddr,
-    sync::{Arc, Mutex},
+    future::Future,
+    io::ErrorKind,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
 };
-use tokio::{io::AsyncReadExt, net::TcpStream};
-use tracing::{debug, info};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    time,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
 
 // OBJECT RECEIVER
 // ================================================================================================
 
-pub struct ObjectReceiver {
+pub struct ObjectReceiver<S: ObjectStore = PlasmaClient> {
     /// Address of the peer from which the objects will be received.
-    pub peer_addr: SocketAddr,
+    pub peer_addr: PeerAddr,
 
     /// IDs for object to be received by this receiver.
     pub object_ids: Vec<ObjectId>,
 
     /// Reference to the plasma store client.
-    pub plasma_client: Arc<PlasmaClient>,
+    pub plasma_client: S,
+
+    /// Health flag of the plasma store connection shared across all senders and receivers.
+    pub healthy: Arc<AtomicBool>,
 
     /// Reference to a set of objects currently being received across all receivers.
-    pub receiving: Arc<Mutex<HashSet<ObjectId>>>,
+    pub receiving: Arc<Mutex<FxHashSet<ObjectId>>>,
+
+    /// If set, objects received before a failure are deleted from the local plasma store;
+    /// otherwise, they are retained and reported as received.
+    pub cleanup_on_error: bool,
+
+    /// Maximum sizes of data and metadata of objects which can be received.
+    pub size_limits: SizeLimits,
+
+    /// Maximum time to wait for a single read from the peer (an object header, metadata, or a
+    /// chunk of data) to complete; `None` means waiting indefinitely.
+    pub read_timeout: Option<Duration>,
+
+    /// Tracks deadlines of received objects if objects expire after a time-to-live; objects are
+    /// tracked only if they remain in the store once the receiver is done.
+    pub expiry: Option<Arc<ExpiryTracker>>,
+
+    /// Limits the total size of objects being received at the same time across all receivers;
+    /// every object is received only once enough of the budget is available for it.
+    pub budget: Option<Arc<ByteBudget>>,
+
+    /// If set, every object is read back from the store after it is sealed and compared against
+    /// the received metadata and data; this is a debugging aid, as it costs an extra read.
+    pub verify_after_seal: bool,
+
+    /// Cancels the transfer when cancelled; cancelling the parent token held by the store
+    /// cancels this token as well.
+    pub cancel: CancellationToken,
+
+    /// Counters describing activity of the server.
+    pub metrics: Arc<Metrics>,
 }
 
-impl ObjectReceiver {
+impl<S: ObjectStore> ObjectReceiver<S> {
     /// Prepares this receiver for receiving objects from the specified peer.
     ///
     /// Will return an error if:
     /// * Some of the objects are currently being received as a part of a different request.
     /// * Some of the objects are already present in the local plasma store.
+    /// * Connection to the local plasma store has been lost.
     pub fn prepare(&self) -> Result<(), ObjectReceiveError> {
+        // fail fast if connection to the plasma store has been lost
+        ensure_healthy(&self.healthy)
+            .map_err(|err| ObjectReceiveError::StoreError(self.peer_addr.clone(), err))?;
+
         // mark the objects as being received; if any of the object IDs is already marked
         // as being received, this will return an error; this is to make sure we don't try
         // to receive the same object twice (e.g. from two different peers)
@@ -51,13 +101,19 @@ impl ObjectReceiver {
         let in_store = self
             .plasma_client
             .contains_many(&plasma_object_ids)
-            .map_err(|err| ObjectReceiveError::StoreError(self.peer_addr, err))?;
+            .map_err(|err| {
+                check_store_error(&self.healthy, &err);
+                ObjectReceiveError::StoreError(self.peer_addr.clone(), err)
+            })?;
         if !in_store.is_empty() {
             let in_store = in_store
                 .into_iter()
                 .map(|oid| oid.to_bytes().try_into().unwrap())
                 .collect();
-            return Err(ObjectReceiveError::AlreadyInStore(self.peer_addr, in_store));
+            return Err(ObjectReceiveError::AlreadyInStore(
+                self.peer_addr.clone(),
+                in_store,
+            ));
         }
         Ok(())
     }
@@ -67,13 +123,32 @@ impl ObjectReceiver {
     ///
     /// Will return an error if:
     /// * The peer sends an error code as the first byte of the response.
-    /// * Creating and sealing an object in the local plasma store fails for any reason.
-    /// * Peer closes connection for any reason.
-    pub async fn run(&self, socket: &mut TcpStream) -> Result<(), ObjectReceiveError> {
+    /// * Peer closes connection before sending the first byte of the response.
+    ///
+    /// Once the peer starts sending objects, failures are recorded in the returned report,
+    /// which also lists the objects received before the failure. If `cleanup_on_error` is set,
+    /// objects received so far are deleted from the local plasma store when receiving fails or
+    /// the returned future is dropped before completion; otherwise, they are retained.
+    ///
+    /// If checksums were negotiated for the connection, every object is verified against the
+    /// checksum sent in its header before it is sealed; objects which fail verification are
+    /// deleted from the local plasma store. If compression was negotiated, object data is
+    /// decompressed before it is written into the local plasma store.
+    ///
+    /// If the `cancel` token is cancelled while objects are being received, the object being
+    /// received is abandoned and the cancellation is recorded in the returned report; objects
+    /// are cleaned up in the same way as when receiving fails.
+    ///
+    /// If `read_timeout` is set and a read of an object from the peer does not complete within
+    /// it, receiving fails with a connection error; this keeps a stalled peer from holding up
+    /// the receiver indefinitely.
+    pub async fn run<R: AsyncRead + Unpin>(
+        &self,
+        socket: &mut R,
+        capabilities: Capabilities,
+    ) -> Result<ReceiveReport, ObjectReceiveError> {
         // save peer address for reporting/debugging purposes
-        let peer_address = socket
-            .peer_addr()
-            .map_err(|err| ObjectReceiveError::ConnectionError(None, err))?;
+        let peer_address = &self.peer_addr;
         let num_objects = self.object_ids.len();
         info!("receiving {} objects from {}", num_objects, peer_address);
 
@@ -83,42 +158,99 @@ impl ObjectReceiver {
         let status = socket
             .read_u8()
             .await
-            .map_err(|err| ObjectReceiveError::ConnectionError(Some(peer_address), err))?;
+            .map_err(|err| ObjectReceiveError::ConnectionError(Some(peer_address.clone()), err))?;
         if status != status_codes::BEGIN {
-            return Err(ObjectReceiveError::PeerError(peer_address, status));
+            return Err(ObjectReceiveError::PeerError(peer_address.clone(), status));
         }
 
         // receive objects one-by-one, and save them to the local plasma store.
         let plasma_object_ids = map_object_ids(&self.object_ids);
-        let mut bytes_received = 0;
+        let mut report = ReceiveReport::default();
+
+        // if the transfer does not complete - either because of an error, or because the task
+        // was cancelled (e.g. the server was shut down) - the guard deletes objects which have
+        // been received so far (if cleanup is enabled); the object being received is aborted
+        // by receive_object() unless it has been sealed
+        let mut cleanup = CleanupGuard {
+            plasma_client: &self.plasma_client,
+            object_ids: &plasma_object_ids,
+            first: 0,
+            num_objects: 0,
+        };
+
         for (i, oid) in plasma_object_ids.iter().enumerate() {
-            match receive_object(&self.plasma_client, oid, socket, peer_address).await {
+            if !self.cleanup_on_error {
+                cleanup.first = i;
+            }
+            cleanup.num_objects = i + 1;
+            let result = tokio::select! {
+                result = receive_object(
+                    &self.plasma_client,
+                    oid,
+                    socket,
+                    peer_address,
+                    capabilities,
+                    self.size_limits,
+                    self.read_timeout,
+                    self.budget.as_ref(),
+                    self.verify_after_seal,
+                ) => result,
+                _ = self.cancel.cancelled() => {
+                    info!("receiving objects from {} was cancelled", peer_address);
+                    Err(ObjectReceiveError::TransferCancelled(peer_address.clone()))
+                }
+            };
+            match result {
                 Ok(ob) => {
                     debug!("received object {} from {}", ob, peer_address);
-                    bytes_received += ob.size();
+                    report.bytes_received += ob.size();
+                    self.metrics.object_received(ob.size());
+                    report.received.push(self.object_ids[i]);
+
+                    // the object has already been sealed, so failing to release it does not
+                    // affect the transfer; the error is logged, and the object stays in use by
+                    // this client until the connection to the store is re-established
+                    if let Err(err) = ob.release() {
+                        check_store_error(&self.healthy, &err);
+                        warn!(
+                            "failed to release object received from {}: {}",
+                            peer_address, err
+                        );
+                    }
                 }
                 Err(err) => {
-                    // try to return to pre-request state by deleting already received objects;
-                    // if the delete fails, just swallow the error
-                    let _ = self
-                        .plasma_client
-                        .delete_many(&plasma_object_ids[..(i + 1)]);
-                    return Err(err);
+                    if let ObjectReceiveError::StoreError(_, store_err) = &err {
+                        check_store_error(&self.healthy, store_err);
+                    }
+                    report.failed = Some((self.object_ids[i], err));
+                    if !self.cleanup_on_error {
+                        self.track_expiry(&report.received);
+                    }
+                    return Ok(report);
                 }
             };
         }
+        cleanup.num_objects = 0;
+        self.track_expiry(&report.received);
 
         // all objects have been received - so, remove them from the receiving set
         info!(
             "received {} objects ({} bytes) from {}",
-            num_objects, bytes_received, peer_address
+            num_objects, report.bytes_received, peer_address
         );
-        Ok(())
+        Ok(report)
     }
 
     // HELPER METHODS
     // --------------------------------------------------------------------------------------------
 
+    /// Starts tracking expiry of the specified received objects, if objects expire.
+    fn track_expiry(&self, object_ids: &[ObjectId]) {
+        if let Some(expiry) = &self.expiry {
+            expiry.track(object_ids);
+        }
+    }
+
     /// Adds all IDs from `object_ids` into the set of objects which are currently being received;
     /// if any of the IDs is already in the list, this will return an error.
     fn add_to_receiving(&self) -> Result<(), ObjectReceiveError> {
@@ -136,7 +268,7 @@ impl ObjectReceiver {
 
         if !duplicates.is_empty() {
             return Err(ObjectReceiveError::AlreadyReceiving(
-                self.peer_addr,
+                self.peer_addr.clone(),
                 duplicates,
             ));
         }
@@ -147,7 +279,7 @@ impl ObjectReceiver {
     }
 }
 
-impl Drop for ObjectReceiver {
+impl<S: ObjectStore> Drop for ObjectReceiver<S> {
     /// When the receiver is dropped, we need to remove all receiver objects from the receiving set.
     /// We do it here because the receiving set needs to be cleared regardless of whether there were
     /// errors or not.
@@ -163,79 +295,345 @@ impl Drop for ObjectReceiver {
     }
 }
 
+// RECEIVE REPORT
+// ================================================================================================
+
+/// Describes the outcome of receiving objects from a peer.
+#[derive(Debug, Default)]
+pub struct ReceiveReport {
+    /// IDs of objects which were received and saved into the local plasma store, in the order
+    /// in which they were received.
+    pub received: Vec<ObjectId>,
+
+    /// ID of the object which could not be received together with the reason for the failure;
+    /// objects following the failed object were not received. `None` if all objects were
+    /// received successfully.
+    pub failed: Option<(ObjectId, ObjectReceiveError)>,
+
+    /// Number of bytes of object data and metadata of the objects which were received.
+    pub bytes_received: usize,
+}
+
+impl ReceiveReport {
+    /// Returns an error if receiving of any of the objects failed.
+    pub fn into_result(self) -> Result<(), ObjectReceiveError> {
+        match self.failed {
+            Some((_, err)) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+// CLEANUP GUARD
+// ================================================================================================
+
+/// Deletes objects in the `first..num_objects` range from the local plasma store when dropped;
+/// this is used to return to pre-request state when receiving of objects does not complete.
+struct CleanupGuard<'a, S: ObjectStore> {
+    plasma_client: &'a S,
+    object_ids: &'a [plasma_store::ObjectId],
+    first: usize,
+    num_objects: usize,
+}
+
+impl<'a, S: ObjectStore> Drop for CleanupGuard<'a, S> {
+    fn drop(&mut self) {
+        // if the delete fails, just swallow the error
+        if self.first < self.num_objects {
+            let _ = self
+                .plasma_client
+                .delete_many(&self.object_ids[self.first..self.num_objects]);
+        }
+    }
+}
+
+// ABORT GUARD
+// ================================================================================================
+
+/// Aborts an unsealed object in the local plasma store when dropped, unless the object has been
+/// taken out of the guard; otherwise, an object which was not received in full would stay in
+/// the store unsealed, and could not be deleted or received again.
+struct AbortGuard<O: StoredObject> {
+    ob: Option<O>,
+}
+
+impl<O: StoredObject> AbortGuard<O> {
+    /// Returns a reference to the guarded object.
+    fn object(&mut self) -> &mut O {
+        self.ob.as_mut().unwrap()
+    }
+
+    /// Takes the object out of the guard; the object will not be aborted.
+    fn into_inner(mut self) -> O {
+        self.ob.take().unwrap()
+    }
+}
+
+impl<O: StoredObject> Drop for AbortGuard<O> {
+    fn drop(&mut self) {
+        if let Some(ob) = self.ob.as_mut() {
+            if let Err(err) = ob.abort_in_place() {
+                warn!("failed to abort unsealed object {}: {}", ob.id(), err);
+            }
+        }
+    }
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
 
 /// Reads a single object from the socket and saves it under the specified 'oid'
-/// into the local plasma store.
-#[allow(clippy::needless_lifetimes)]
-async fn receive_object<'a>(
-    pc: &'a PlasmaClient,
+/// into the local plasma store. The object is expected to be written as described in
+/// `utils::write_object()` for the specified capabilities; if checksums are enabled, the object
+/// is verified against the checksum before being sealed. Objects exceeding the specified size
+/// limits are rejected before they are created in the store. Every read from the socket is
+/// subject to the specified read timeout, if any.
+///
+/// If a byte budget is specified, the object is read from the socket only once the declared
+/// size of the object has been acquired from the budget; the budget is released once the
+/// object is sealed or receiving of the object fails.
+///
+/// If `verify_after_seal` is set, the object is read back from the store once it is sealed,
+/// and a `StoreError` is returned if it does not match the received object.
+///
+/// If receiving fails before the object is sealed, or the returned future is dropped, the
+/// unsealed object is aborted.
+#[allow(clippy::needless_lifetimes, clippy::too_many_arguments)]
+async fn receive_object<'a, S: ObjectStore, R: AsyncRead + Unpin>(
+    pc: &'a S,
     oid: &plasma_store::ObjectId,
-    socket: &mut TcpStream,
-    from_peer: SocketAddr,
-) -> Result<ObjectBuffer<'a>, ObjectReceiveError> {
+    socket: &mut R,
+    from_peer: &PeerAddr,
+    capabilities: Capabilities,
+    size_limits: SizeLimits,
+    read_timeout: Option<Duration>,
+    budget: Option<&Arc<ByteBudget>>,
+    verify_after_seal: bool,
+) -> Result<S::Object<'a>, ObjectReceiveError> {
     // read the header to determine size of object data and metadata
-    let (meta_size, data_size) = read_object_header(socket, from_peer).await?;
+    let (meta_size, data_size) = with_read_timeout(read_timeout, read_object_header(socket))
+        .await
+        .map_err(|err| ObjectReceiveError::ConnectionError(Some(from_peer.clone()), err))?;
+    let codec = with_read_timeout(read_timeout, Codec::read_from(socket, capabilities))
+        .await
+        .map_err(|err| ObjectReceiveError::ConnectionError(Some(from_peer.clone()), err))?;
+    let checksum = if capabilities.checksums() {
+        let checksum = with_read_timeout(read_timeout, socket.read_u32_le())
+            .await
+            .map_err(|err| ObjectReceiveError::ConnectionError(Some(from_peer.clone()), err))?;
+        Some(checksum)
+    } else {
+        None
+    };
 
     // make sure data size is not zero
     if data_size == 0 {
         let oid = oid.to_bytes().try_into().unwrap();
-        return Err(ObjectReceiveError::ZeroLengthObjectData(from_peer, oid));
+        return Err(ObjectReceiveError::ZeroLengthObjectData(
+            from_peer.clone(),
+            oid,
+        ));
     }
 
     // make sure data size does not exceed the allowed limit
-    if data_size as u64 > MAX_DATA_SIZE {
+    if data_size as u64 > size_limits.max_data_size {
         let oid = oid.to_bytes().try_into().unwrap();
         return Err(ObjectReceiveError::ObjectDataTooLarge(
-            from_peer, oid, data_size,
+            from_peer.clone(),
+            oid,
+            data_size,
         ));
     }
 
     // make sure data size does not exceed the allowed limit
-    if meta_size as u64 > MAX_META_SIZE {
+    if meta_size as u64 > size_limits.max_meta_size {
         let oid = oid.to_bytes().try_into().unwrap();
         return Err(ObjectReceiveError::ObjectMetaTooLarge(
-            from_peer, oid, meta_size,
+            from_peer.clone(),
+            oid,
+            meta_size,
         ));
     }
 
+    // wait until there is enough budget for the buffers of the object; compressed data is
+    // decompressed chunk by chunk directly into the object buffer, so it needs no budget
+    let declared_size = (meta_size + data_size) as u64;
+    let _permit = match budget {
+        Some(budget) => {
+            if budget.available() < declared_size {
+                debug!(
+                    "object {} from {} is waiting for {} bytes of receive budget",
+                    oid, from_peer, declared_size
+                );
+            }
+            Some(budget.acquire(declared_size).await)
+        }
+        None => None,
+    };
+
     // read the metadata from the socket and save it into a vector
     let mut meta_buf = vec![0u8; meta_size];
-    socket
-        .read_exact(&mut meta_buf)
+    with_read_timeout(read_timeout, socket.read_exact(&mut meta_buf))
         .await
-        .map_err(|err| ObjectReceiveError::ConnectionError(Some(from_peer), err))?;
+        .map_err(|err| ObjectReceiveError::ConnectionError(Some(from_peer.clone()), err))?;
 
-    // create object in the plasma store
-    let mut ob = pc
-        .create(oid.clone(), data_size, &meta_buf)
-        .map_err(|err| ObjectReceiveError::StoreError(from_peer, err))?;
+    // create object in the plasma store; until the object is sealed, the guard aborts it if
+    // receiving fails or this future is dropped (e.g. because the transfer was cancelled)
+    let ob = create_object(pc, oid, data_size, &meta_buf, from_peer)
+        .map_err(|err| ObjectReceiveError::StoreError(from_peer.clone(), err))?;
+    let mut guard = AbortGuard { ob: Some(ob) };
 
-    // read object data from the socket and save it into the object buffer
-    let data_buf = ob.data_mut();
-    socket
-        .read_exact(data_buf)
+    // read object data from the socket chunk-by-chunk directly into the object buffer; if
+    // the data was compressed, every chunk is decompressed into the object buffer
+    let data_buf = guard
+        .object()
+        .data_mut()
+        .map_err(|err| ObjectReceiveError::StoreError(from_peer.clone(), err))?;
+    read_data(socket, data_buf, codec == Codec::Lz4, read_timeout)
         .await
-        .map_err(|err| ObjectReceiveError::ConnectionError(Some(from_peer), err))?;
+        .map_err(|err| ObjectReceiveError::ConnectionError(Some(from_peer.clone()), err))?;
+
+    // make sure the object was not corrupted in transit; the unsealed object is aborted by
+    // the guard. The checksum of the received object is also needed to verify the object
+    // once it is sealed
+    let received_checksum = if checksum.is_some() || verify_after_seal {
+        Some(object_checksum(&meta_buf, data_buf))
+    } else {
+        None
+    };
+    if let Some(checksum) = checksum {
+        if received_checksum != Some(checksum) {
+            let oid = oid.to_bytes().try_into().unwrap();
+            return Err(ObjectReceiveError::ChecksumMismatch(from_peer.clone(), oid));
+        }
+    }
 
     // seal the object to make it available to other clients
-    ob.seal()
-        .map_err(|err| ObjectReceiveError::StoreError(from_peer, err))?;
+    guard
+        .object()
+        .seal()
+        .map_err(|err| ObjectReceiveError::StoreError(from_peer.clone(), err))?;
+    let ob = guard.into_inner();
+
+    // make sure the object which landed in the store is the object which was received; the
+    // sealed object is deleted by the caller if it is not
+    if let Some(checksum) = received_checksum.filter(|_| verify_after_seal) {
+        verify_sealed_object(pc, oid, meta_size, data_size, checksum)
+            .map_err(|err| ObjectReceiveError::StoreError(from_peer.clone(), err))?;
+    }
 
     Ok(ob)
 }
 
-/// Breaks object header into metadata size (lower 16 bits) and data size (upper 48 bits).
-async fn read_object_header(
-    socket: &mut TcpStream,
-    from_peer: SocketAddr,
-) -> Result<(usize, usize), ObjectReceiveError> {
-    let header = socket
-        .read_u64_le()
-        .await
-        .map_err(|err| ObjectReceiveError::ConnectionError(Some(from_peer), err))?;
-    let meta_size = (header as u16) as usize;
-    let data_size = (header >> 16) as usize;
-    Ok((meta_size, data_size))
+/// Reads the sealed object back from the store, and makes sure that it matches the object
+/// with the specified sizes and checksum.
+fn verify_sealed_object<S: ObjectStore>(
+    pc: &S,
+    oid: &plasma_store::ObjectId,
+    meta_size: usize,
+    data_size: usize,
+    checksum: u32,
+) -> Result<(), PlasmaError> {
+    match pc.get(oid.clone(), Some(Duration::from_millis(0)))? {
+        Some(ob) => compare_sealed_object(ob.meta(), ob.data(), meta_size, data_size, checksum),
+        None => Err(PlasmaError::HashMismatch(String::from(
+            "object not found in the store",
+        ))),
+    }
+}
+
+/// Compares metadata and data of an object read from the store against the sizes and the
+/// checksum of the received object; returns `PlasmaError::HashMismatch` describing the first
+/// difference found.
+pub fn compare_sealed_object(
+    meta: &[u8],
+    data: &[u8],
+    meta_size: usize,
+    data_size: usize,
+    checksum: u32,
+) -> Result<(), PlasmaError> {
+    if meta.len() != meta_size || data.len() != data_size {
+        return Err(PlasmaError::HashMismatch(format!(
+            "{} bytes of metadata and {} bytes of data, but {} and {} bytes were received",
+            meta.len(),
+            data.len(),
+            meta_size,
+            data_size
+        )));
+    }
+    let actual = object_checksum(meta, data);
+    if actual != checksum {
+        return Err(PlasmaError::HashMismatch(format!(
+            "checksum {:08x}, but received object has checksum {:08x}",
+            actual, checksum
+        )));
+    }
+    Ok(())
+}
+
+/// Fills the buffer with object data read from the socket in chunks of at most DATA_CHUNK_SIZE
+/// bytes, as `utils::read_data_chunked()` does; if `compressed` is set, every chunk is read and
+/// decompressed as `utils::read_data_compressed()` does. Every chunk must be read within the
+/// specified timeout, if any.
+async fn read_data<R: AsyncRead + Unpin>(
+    socket: &mut R,
+    buf: &mut [u8],
+    compressed: bool,
+    read_timeout: Option<Duration>,
+) -> std::io::Result<()> {
+    let mut scratch = Vec::new();
+    for chunk in buf.chunks_mut(DATA_CHUNK_SIZE) {
+        if compressed {
+            with_read_timeout(
+                read_timeout,
+                read_compressed_chunk(socket, chunk, &mut scratch),
+            )
+            .await?;
+        } else {
+            with_read_timeout(read_timeout, socket.read_exact(chunk)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Drives the read to completion; if the read does not complete within the specified timeout,
+/// a `TimedOut` error is returned instead. If `timeout` is `None`, waits for the read
+/// indefinitely.
+async fn with_read_timeout<T, F>(timeout: Option<Duration>, read: F) -> std::io::Result<T>
+where
+    F: Future<Output = std::io::Result<T>>,
+{
+    match timeout {
+        Some(timeout) => time::timeout(timeout, read).await.unwrap_or_else(|_| {
+            Err(std::io::Error::new(
+                ErrorKind::TimedOut,
+                format!("no data received within {} ms", timeout.as_millis()),
+            ))
+        }),
+        None => read.await,
+    }
+}
+
+/// Creates an object in the plasma store; if the store does not have enough memory for the
+/// object, asks the store to evict enough objects to make room for it and retries once.
+fn create_object<'a, S: ObjectStore>(
+    pc: &'a S,
+    oid: &plasma_store::ObjectId,
+    data_size: usize,
+    meta: &[u8],
+    from_peer: &PeerAddr,
+) -> Result<S::Object<'a>, PlasmaError> {
+    match pc.create(oid.clone(), data_size, meta) {
+        Err(PlasmaError::OutOfMemory(reason)) => {
+            let num_bytes = data_size + meta.len();
+            warn!(
+                "plasma store is full while receiving object {} from {} ({}); evicting {} bytes",
+                oid, from_peer, reason, num_bytes
+            );
+            let num_bytes_evicted = pc.evict(num_bytes)?;
+            debug!("evicted {} bytes from plasma store", num_bytes_evicted);
+            pc.create(oid.clone(), data_size, meta)
+        }
+        result => result,
+    }
 }
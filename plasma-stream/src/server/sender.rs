@@ -4,25 +4,32 @@
 // LICENSE file in the root directory of this source tree.
 
 use super::{
-    errors::ObjectSendError, status_codes, utils::map_object_ids, ObjectId, MAX_DATA_SIZE,
+    errors::ObjectSendError,
+    handshake::Capabilities,
+    status_codes,
+    store::{check_store_error, ensure_healthy, SizeLimits},
+    utils::{map_object_ids, write_object},
+    Metrics, ObjectId, ObjectStore, PeerAddr, StoredObject, TransferEstimate, MAX_DATA_SIZE,
     MAX_META_SIZE,
 };
-use plasma_store::{ObjectBuffer, PlasmaClient};
+use plasma_store::PlasmaClient;
+use plasma_stream::{ManifestEntry, TransferManifest};
+use rustc_hash::FxHashSet;
 use std::{
-    collections::HashSet,
     convert::TryInto,
-    net::SocketAddr,
-    sync::{Arc, Mutex},
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
 };
-use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info};
 
 // OBJECT SENDER
 // ================================================================================================
 
-pub struct ObjectSender {
+pub struct ObjectSender<S: ObjectStore = PlasmaClient> {
     /// Address of the peer to which the objects will be sent.
-    pub peer_addr: SocketAddr,
+    pub peer_addr: PeerAddr,
 
     /// IDs for object to be sent by this sender.
     pub object_ids: Vec<ObjectId>,
@@ -31,19 +38,40 @@ pub struct ObjectSender {
     pub delete_after_send: bool,
 
     /// Reference to the plasma store client.
-    pub plasma_client: Arc<PlasmaClient>,
+    pub plasma_client: S,
 
-    /// Maximum time allocated to retrieving objects from the plasma store.
-    pub timeout_ms: i64,
+    /// Health flag of the plasma store connection shared across all senders and receivers.
+    pub healthy: Arc<AtomicBool>,
+
+    /// Maximum time allocated to waiting for objects to be sealed in the plasma store; `None`
+    /// means waiting indefinitely.
+    pub timeout: Option<Duration>,
 
     /// Reference to a set of objects currently scheduled for deletion across all senders.
-    pub deleting: Arc<Mutex<HashSet<ObjectId>>>,
+    pub deleting: Arc<Mutex<FxHashSet<ObjectId>>>,
+
+    /// Maximum sizes of data and metadata of objects which can be sent.
+    pub size_limits: SizeLimits,
+
+    /// Cancels the transfer when cancelled; cancelling the parent token held by the store
+    /// cancels this token as well.
+    pub cancel: CancellationToken,
+
+    /// Counters describing activity of the server.
+    pub metrics: Arc<Metrics>,
+
+    /// If set, no objects are sent; instead, a `TransferEstimate` describing what would have
+    /// been sent is written into the socket.
+    pub dry_run: bool,
 }
 
-impl ObjectSender {
+impl<S: ObjectStore> ObjectSender<S> {
     /// Reads objects from the local plasma store and sends them into the specified socket. If
     /// `delete_after_send` = true, it'll try to delete the objects from the store after they
     /// are sent. However, deletion of the objects from the local store is not guaranteed.
+    /// Optional protocol features negotiated for the connection (e.g. checksums, compression)
+    /// determine how objects are written into the socket, and whether a manifest of the objects
+    /// is written before them. Returns the number of bytes of object data and metadata sent.
     ///
     /// Will return an error if:
     /// * Any of the requested objects are scheduled for deletion.
@@ -51,37 +79,70 @@ impl ObjectSender {
     /// * There was some kind of error retrieving objects from the Plasma Store.
     /// * Any of the requested objects exceed data and metadata size limits.
     /// * Writing objects into the socket fails for some reason; this error may happen after
-    ///   some objects have already been written into the socket.
-    pub async fn run(&self, socket: &mut TcpStream) -> Result<(), ObjectSendError> {
-        // try to send objects and handle any resulting errors
-        if let Err(err) = self.send_objects(socket).await {
-            // errors which can happen only before any objects are sent will have a response code
-            if let Some(response_code) = err.response_code() {
-                // if we couldn't send a response code for some reason, there isn't much
-                // else we can do - so, just ignore the error
-                let _result = socket.write_u8(response_code).await;
+    ///   some objects have already been written into the socket, in which case the error
+    ///   reports which objects were written before the failure.
+    /// * The transfer is cancelled via the `cancel` token; an object which is being written
+    ///   when the token is cancelled is abandoned, and the error reports which objects were
+    ///   written before that.
+    ///
+    /// In dry-run mode, objects which are missing from the store or exceed size limits do not
+    /// cause an error, but are listed in the estimate written into the socket instead; no
+    /// object data is sent, so 0 is returned.
+    pub async fn run<W: AsyncWrite + Unpin>(
+        &self,
+        socket: &mut W,
+        capabilities: Capabilities,
+    ) -> Result<usize, ObjectSendError> {
+        // try to send objects (or an estimate) and handle any resulting errors
+        let result = if self.dry_run {
+            self.send_estimate(socket).await.map(|_| 0)
+        } else {
+            self.send_objects(socket, capabilities).await
+        };
+        match result {
+            Ok(bytes_sent) => Ok(bytes_sent),
+            Err(err) => {
+                // errors which can happen only before any objects are sent will have a
+                // response code
+                if let Some(response_code) = err.response_code() {
+                    // if we couldn't send a response code for some reason, there isn't much
+                    // else we can do - so, just ignore the error
+                    if socket.write_u8(response_code).await.is_ok() {
+                        let _result = socket.flush().await;
+                    }
+                }
+                Err(err)
             }
-            return Err(err);
         }
-        Ok(())
     }
 
     // HELPER METHODS
     // --------------------------------------------------------------------------------------------
 
     /// Does the actual work described for the `run()` method above.
-    async fn send_objects(&self, socket: &mut TcpStream) -> Result<(), ObjectSendError> {
+    async fn send_objects<W: AsyncWrite + Unpin>(
+        &self,
+        socket: &mut W,
+        capabilities: Capabilities,
+    ) -> Result<usize, ObjectSendError> {
         // save peer address for reporting/debugging purposes
         let num_objects = self.object_ids.len();
         info!("sending {} objects to {}", num_objects, self.peer_addr);
 
+        // fail fast if connection to the plasma store has been lost
+        ensure_healthy(&self.healthy)
+            .map_err(|err| ObjectSendError::StoreError(self.peer_addr.clone(), err))?;
+
         // make sure none of the objects to be sent are currently scheduled for deletion;
         // if delete_after_send = true and none of the objects are scheduled for deletion,
         // this will also add the object IDs to the set of objects scheduled for deletion
         self.check_deleting()?;
 
-        // get all objects from the plasma store; this also ensures that all requested
-        // objects exist locally
+        // get all objects from the plasma store with a single request; this also ensures that
+        // all requested objects exist locally. Retrieved objects are mapped into memory rather
+        // than copied, so object data is actually read from the store only as it is written
+        // into the socket; thus, there is no need to prefetch the next object while the
+        // current one is being sent.
         let plasma_object_ids = map_object_ids(&self.object_ids);
         let objects = self.get_objects(&plasma_object_ids)?;
 
@@ -90,23 +151,49 @@ impl ObjectSender {
         // discovering that some other objects cannot be sent
         self.check_object_sizes(&objects)?;
 
-        // send a flag indicating that we are about to begin sending objects, and then,
-        // one-by-one, write objects into the socket
+        // send a flag indicating that we are about to begin sending objects, followed by a
+        // manifest of the objects if the peer asked for it, and then, one-by-one, write objects
+        // into the socket
         socket
             .write_u8(status_codes::BEGIN)
             .await
-            .map_err(|err| ObjectSendError::ConnectionError(Some(self.peer_addr), err))?;
+            .map_err(|err| ObjectSendError::ConnectionError(Some(self.peer_addr.clone()), err))?;
+        if capabilities.manifest() {
+            build_manifest(&self.object_ids, &objects)
+                .write_into(socket)
+                .await
+                .map_err(|err| {
+                    ObjectSendError::ConnectionError(Some(self.peer_addr.clone()), err)
+                })?;
+        }
 
         let mut bytes_sent = 0;
-        for ob in objects.iter() {
-            match send_object(ob, socket).await {
+        for (i, ob) in objects.iter().enumerate() {
+            let result = tokio::select! {
+                result = send_object(ob, socket, capabilities) => result,
+                _ = self.cancel.cancelled() => {
+                    info!("sending objects to {} was cancelled", self.peer_addr);
+                    return Err(ObjectSendError::TransferCancelled(
+                        self.peer_addr.clone(),
+                        self.object_ids[..i].to_vec(),
+                    ));
+                }
+            };
+            match result {
                 Ok(()) => {
                     debug!("sent object {} to {}", ob, self.peer_addr);
                     bytes_sent += ob.size();
+                    self.metrics.object_sent(ob.size());
                 }
                 Err(err) => {
-                    // if there was an error sending an object, abort the entire operation
-                    return Err(ObjectSendError::ConnectionError(Some(self.peer_addr), err));
+                    // if there was an error sending an object, abort the entire operation, but
+                    // report which objects were written so that the transfer can be resumed
+                    return Err(ObjectSendError::ObjectsPartiallySent {
+                        peer: self.peer_addr.clone(),
+                        sent: self.object_ids[..i].to_vec(),
+                        failed_at: self.object_ids[i],
+                        source: err,
+                    });
                 }
             }
         }
@@ -121,11 +208,58 @@ impl ObjectSender {
         // any object which is in use by other clients.
         if self.delete_after_send {
             if let Err(err) = self.plasma_client.delete_many(&plasma_object_ids) {
+                check_store_error(&self.healthy, &err);
                 error!("error while deleting objects from plasma store: {}", err);
             }
         }
 
-        Ok(())
+        Ok(bytes_sent)
+    }
+
+    /// Does the actual work described for the `run()` method above in dry-run mode.
+    async fn send_estimate<W: AsyncWrite + Unpin>(
+        &self,
+        socket: &mut W,
+    ) -> Result<TransferEstimate, ObjectSendError> {
+        info!(
+            "estimating transfer of {} objects to {}",
+            self.object_ids.len(),
+            self.peer_addr
+        );
+
+        // perform the same checks as when sending objects, but record missing and oversized
+        // (or empty) objects instead of failing on them
+        ensure_healthy(&self.healthy)
+            .map_err(|err| ObjectSendError::StoreError(self.peer_addr.clone(), err))?;
+        self.check_deleting()?;
+        let plasma_object_ids = map_object_ids(&self.object_ids);
+        let (objects, missing) = self.find_objects(&plasma_object_ids)?;
+
+        let mut estimate = TransferEstimate {
+            num_objects: objects.len(),
+            missing,
+            ..TransferEstimate::default()
+        };
+        for ob in objects.iter() {
+            estimate.total_size += ob.size() as u64;
+            if self.check_object_size(ob).is_err() {
+                estimate
+                    .oversized
+                    .push(ob.id().to_bytes().try_into().unwrap());
+            }
+        }
+
+        // write the estimate into the socket; no object data is sent
+        let write_estimate = async {
+            socket.write_u8(status_codes::BEGIN).await?;
+            estimate.write_into(socket).await?;
+            socket.flush().await
+        };
+        write_estimate
+            .await
+            .map_err(|err| ObjectSendError::ConnectionError(Some(self.peer_addr.clone()), err))?;
+
+        Ok(estimate)
     }
 
     /// Checks if any of the IDs in `object_ids` are in the deleting set, and if they are,
@@ -146,7 +280,7 @@ impl ObjectSender {
         // if they were, return an error
         if !in_deleting.is_empty() {
             return Err(ObjectSendError::ObjectDeletionScheduled(
-                self.peer_addr,
+                self.peer_addr.clone(),
                 in_deleting,
             ));
         }
@@ -159,27 +293,42 @@ impl ObjectSender {
         Ok(())
     }
 
-    /// Makes sure that none of the objects in the list is too big (both for data and metadata)
-    fn check_object_sizes(&self, objects: &[ObjectBuffer<'_>]) -> Result<(), ObjectSendError> {
+    /// Makes sure that none of the objects in the list is too big (both for data and metadata),
+    /// and that all of them have data; objects without data would be rejected by the receiver
+    fn check_object_sizes(&self, objects: &[S::Object<'_>]) -> Result<(), ObjectSendError> {
         for ob in objects {
-            let meta_size = ob.meta().len();
-            if meta_size as u64 > MAX_META_SIZE {
-                let oid: ObjectId = ob.id().to_bytes().try_into().unwrap();
-                return Err(ObjectSendError::ObjectMetaTooLarge(
-                    self.peer_addr,
-                    oid,
-                    meta_size,
-                ));
-            }
-            let data_size = ob.data().len();
-            if data_size as u64 > MAX_DATA_SIZE {
-                let oid: ObjectId = ob.id().to_bytes().try_into().unwrap();
-                return Err(ObjectSendError::ObjectDataTooLarge(
-                    self.peer_addr,
-                    oid,
-                    data_size,
-                ));
-            }
+            self.check_object_size(ob)?;
+        }
+        Ok(())
+    }
+
+    /// Makes sure that neither data nor metadata of the object exceed size limits, and that the
+    /// object has data; zero-length metadata is allowed.
+    fn check_object_size(&self, ob: &S::Object<'_>) -> Result<(), ObjectSendError> {
+        let meta_size = ob.meta().len();
+        if meta_size as u64 > self.size_limits.max_meta_size {
+            let oid: ObjectId = ob.id().to_bytes().try_into().unwrap();
+            return Err(ObjectSendError::ObjectMetaTooLarge(
+                self.peer_addr.clone(),
+                oid,
+                meta_size,
+            ));
+        }
+        let data_size = ob.data().len();
+        if data_size as u64 > self.size_limits.max_data_size {
+            let oid: ObjectId = ob.id().to_bytes().try_into().unwrap();
+            return Err(ObjectSendError::ObjectDataTooLarge(
+                self.peer_addr.clone(),
+                oid,
+                data_size,
+            ));
+        }
+        if data_size == 0 {
+            let oid: ObjectId = ob.id().to_bytes().try_into().unwrap();
+            return Err(ObjectSendError::ZeroLengthObjectData(
+                self.peer_addr.clone(),
+                oid,
+            ));
         }
         Ok(())
     }
@@ -191,8 +340,28 @@ impl ObjectSender {
     fn get_objects(
         &self,
         object_ids: &[plasma_store::ObjectId],
-    ) -> Result<Vec<ObjectBuffer>, ObjectSendError> {
-        match self.plasma_client.get_many(&object_ids, self.timeout_ms) {
+    ) -> Result<Vec<S::Object<'_>>, ObjectSendError> {
+        let (objects, missing) = self.find_objects(object_ids)?;
+
+        // if any of the objects were not found, return an error
+        if !missing.is_empty() {
+            return Err(ObjectSendError::ObjectsNotFound(
+                self.peer_addr.clone(),
+                missing,
+            ));
+        }
+
+        Ok(objects)
+    }
+
+    /// Retrieves the specified objects from the local plasma store; returns the objects which
+    /// were found together with IDs of objects which were not. An error is returned only if
+    /// there was some error retrieving objects from the store.
+    fn find_objects(
+        &self,
+        object_ids: &[plasma_store::ObjectId],
+    ) -> Result<(Vec<S::Object<'_>>, Vec<ObjectId>), ObjectSendError> {
+        match self.plasma_client.get_many(object_ids, self.timeout) {
             Ok(objects) => {
                 // check if any of the objects were returned as None, and record corresponding
                 // IDs in a separate vector
@@ -204,20 +373,17 @@ impl ObjectSender {
                         None => missing.push(self.object_ids[i]),
                     }
                 }
-
-                // if any of the objects were not found, return an error
-                if !missing.is_empty() {
-                    return Err(ObjectSendError::ObjectsNotFound(self.peer_addr, missing));
-                }
-
-                Ok(result)
+                Ok((result, missing))
+            }
+            Err(err) => {
+                check_store_error(&self.healthy, &err);
+                Err(ObjectSendError::StoreError(self.peer_addr.clone(), err))
             }
-            Err(err) => Err(ObjectSendError::StoreError(self.peer_addr, err)),
         }
     }
 }
 
-impl Drop for ObjectSender {
+impl<S: ObjectStore> Drop for ObjectSender<S> {
     /// When the sender is dropped, we may need to remove all sender objects from the deleting set.
     /// We do it here because the deleting set needs to be cleared regardless of whether there were
     /// errors or not.
@@ -238,27 +404,42 @@ impl Drop for ObjectSender {
 // HELPER FUNCTIONS
 // ================================================================================================
 
-/// Writes the object into the socket; the object is written as follows:
-/// * first object header (data and meta size) is written as u64
-/// * then, object metadata is written,
-/// * and finally, object data buffer is written
-async fn send_object(ob: &ObjectBuffer<'_>, socket: &mut TcpStream) -> std::io::Result<()> {
-    // Write object header into the socket. The object header consists of a 16-bit value
-    // describing the size of the metadata, and a 48-bit value describing the size of that
-    // data. Thus, object metadata is limited to at most 64 KB, while object data can be
-    // potentially as larger as 256 TB (though MAX_DATA_SIZE imposes 16 TB limit).
+/// Returns a manifest listing the specified objects in the order in which they are sent;
+/// `object_ids` must be IDs of the objects in the same order.
+fn build_manifest<O: StoredObject>(object_ids: &[ObjectId], objects: &[O]) -> TransferManifest {
+    let entries = object_ids
+        .iter()
+        .zip(objects)
+        .map(|(oid, ob)| {
+            let (meta, data) = ob.parts();
+            ManifestEntry::new(*oid, meta, data)
+        })
+        .collect();
+    TransferManifest::new(entries)
+}
+
+/// Writes the object into the socket and flushes the socket; see `utils::write_object()` for the
+/// description of how the object is written. If compression is enabled, the codec is chosen per
+/// object: a codec hint in object metadata is honored (e.g. data of objects whose metadata
+/// starts with `codec=none;` is not compressed), and data of other objects is compressed with
+/// LZ4. The object is sent only once the socket has been flushed, so that objects left in a
+/// write buffer are not counted as sent.
+async fn send_object<O: StoredObject, W: AsyncWrite + Unpin>(
+    ob: &O,
+    socket: &mut W,
+    capabilities: Capabilities,
+) -> std::io::Result<()> {
     // asserts are OK here because we check object sizes beforehand, and asserts should
     // never fail
-    let meta_size = ob.meta().len() as u64;
-    assert!(meta_size <= MAX_META_SIZE, "object metadata is too large");
-    let data_size = ob.data().len() as u64;
-    assert!(data_size <= MAX_DATA_SIZE, "object data is too large");
-    let header = meta_size | (data_size << 16);
-    socket.write_u64_le(header).await?;
-
-    // write both data and metadata into the socket
-    socket.write_all(ob.meta()).await?;
-    socket.write_all(ob.data()).await?;
-
-    Ok(())
+    let (meta, data) = ob.parts();
+    assert!(
+        meta.len() as u64 <= MAX_META_SIZE,
+        "object metadata is too large"
+    );
+    assert!(
+        data.len() as u64 <= MAX_DATA_SIZE,
+        "object data is too large"
+    );
+    write_object(meta, data, capabilities, socket).await?;
+    socket.flush().await
 }
@@ -3,75 +3,526 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
-use super::{ObjectId, ObjectReceiver, ObjectSender};
-use plasma_store::PlasmaClient;
+use super::{
+    errors::ObjectDeleteError, utils::map_object_ids, ByteBudget, ConnectionPool, ExpiryTracker,
+    Metrics, ObjectId, ObjectReceiver, ObjectSender, ObjectStore, PeerAddr, MAX_DATA_SIZE,
+    MAX_META_SIZE,
+};
+use plasma_store::{ObjectState, PlasmaClient, PlasmaError};
+use rustc_hash::FxHashSet;
 use std::{
-    collections::HashSet,
-    net::SocketAddr,
-    sync::{Arc, Mutex},
+    convert::TryInto,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
 };
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
 
 // OBJECT STORE WRAPPER
 // ================================================================================================
 
+/// Wraps a client of the local object store and keeps state shared by all senders and receivers.
+/// The server runs against the Plasma Store; other `ObjectStore` implementations can be plugged
+/// in via `from_client()`.
 #[derive(Debug, Clone)]
-pub struct Store {
-    /// Connection to the Plasma Store. We put it into an Arc because it can be accessed from
-    /// multiple threads concurrently, and we don't want to clone the connection for each thread.
-    plasma_client: Arc<PlasmaClient>,
+pub struct Store<S = PlasmaClient> {
+    /// Path to the UNIX domain socket of the Plasma Store; used to reconnect to the store.
+    plasma_socket: String,
+
+    /// Number of attempts to make when reconnecting to the Plasma Store.
+    plasma_connect_retries: u32,
+
+    /// Connection to the Plasma Store. Cloning the client shares the same connection, so all
+    /// senders and receivers use a single connection. The connection is replaced when the
+    /// store is reconnected.
+    plasma_client: Arc<RwLock<S>>,
 
-    /// Maximum time allocated to retrieving objects from the store.
-    timeout_ms: i64,
+    /// Set to false when an operation against the Plasma Store fails because the connection
+    /// to the store was lost. While the store is unhealthy, senders and receivers fail fast.
+    healthy: Arc<AtomicBool>,
+
+    /// Maximum time allocated to waiting for objects requested via COPY to be sealed in the
+    /// store; `None` means waiting indefinitely.
+    copy_timeout: Option<Duration>,
+
+    /// Maximum time allocated to waiting for objects requested via TAKE to be sealed in the
+    /// store; `None` means waiting indefinitely.
+    take_timeout: Option<Duration>,
 
     /// A set of IDs for objects which are in the process of being received. This is used to
     /// make sure two separate requests don't try to receive the same object.
-    // TODO: use non-cryptographic hashing
-    receiving: Arc<Mutex<HashSet<ObjectId>>>,
+    receiving: Arc<Mutex<FxHashSet<ObjectId>>>,
 
     /// A set of IDs for objects which are scheduled to be deleted. This is used to make sure
     /// two separate requests don't try to delete the same object from the store.
-    // TODO: use non-cryptographic hashing
-    deleting: Arc<Mutex<HashSet<ObjectId>>>,
+    deleting: Arc<Mutex<FxHashSet<ObjectId>>>,
+
+    /// Limits on sizes of objects sent and received; enforced by all senders and receivers.
+    size_limits: SizeLimits,
+
+    /// Maximum time receivers wait for a single read of object data from a peer to complete;
+    /// `None` means waiting indefinitely.
+    receive_timeout: Option<Duration>,
+
+    /// Tracks deadlines of objects received from peers if objects expire after a time-to-live;
+    /// `None` means that objects are kept in the store until they are deleted explicitly.
+    expiry: Option<Arc<ExpiryTracker>>,
+
+    /// Limits the total size of objects being received at the same time by all receivers;
+    /// `None` means that the size is not limited.
+    receive_budget: Option<Arc<ByteBudget>>,
+
+    /// Whether receivers read every object back from the Plasma Store after sealing it to
+    /// verify that it matches the received object.
+    verify_received: bool,
+
+    /// Idle outbound connections to peers which can be reused by subsequent peer requests;
+    /// `None` means that a new connection is opened for every peer request.
+    connection_pool: Option<Arc<ConnectionPool>>,
+
+    /// Cancels all in-flight transfers when cancelled; every sender and receiver is given a
+    /// child of this token so that individual transfers can be cancelled as well.
+    cancel: CancellationToken,
+
+    /// Counters describing activity of the server; shared with all senders and receivers.
+    metrics: Arc<Metrics>,
 }
 
 impl Store {
-    pub fn new(plasma_client: PlasmaClient, timeout_ms: i64) -> Self {
+    /// Connects to the Plasma Store at the specified socket and returns a new store wrapper.
+    pub fn new(
+        plasma_socket: &str,
+        plasma_connect_retries: u32,
+        copy_timeout: Option<Duration>,
+        take_timeout: Option<Duration>,
+    ) -> Result<Self, PlasmaError> {
+        let plasma_client = PlasmaClient::new(plasma_socket, plasma_connect_retries)?;
+        Ok(Store::from_client(
+            plasma_client,
+            plasma_socket,
+            plasma_connect_retries,
+            copy_timeout,
+            take_timeout,
+        ))
+    }
+}
+
+impl<S: ObjectStore> Store<S> {
+    /// Returns a new store wrapper around the specified client of the object store; the socket
+    /// and the number of connection attempts are used to reconnect to the store if connection
+    /// to it is lost.
+    pub fn from_client(
+        plasma_client: S,
+        plasma_socket: &str,
+        plasma_connect_retries: u32,
+        copy_timeout: Option<Duration>,
+        take_timeout: Option<Duration>,
+    ) -> Self {
         Store {
-            plasma_client: Arc::new(plasma_client),
-            timeout_ms,
-            receiving: Arc::new(Mutex::new(HashSet::new())),
-            deleting: Arc::new(Mutex::new(HashSet::new())),
+            plasma_socket: String::from(plasma_socket),
+            plasma_connect_retries,
+            plasma_client: Arc::new(RwLock::new(plasma_client)),
+            healthy: Arc::new(AtomicBool::new(true)),
+            copy_timeout,
+            take_timeout,
+            receiving: Arc::new(Mutex::new(FxHashSet::default())),
+            deleting: Arc::new(Mutex::new(FxHashSet::default())),
+            size_limits: SizeLimits::default(),
+            receive_timeout: None,
+            expiry: None,
+            receive_budget: None,
+            verify_received: false,
+            connection_pool: None,
+            cancel: CancellationToken::new(),
+            metrics: Arc::new(Metrics::default()),
+        }
+    }
+
+    /// Sets limits on sizes of objects sent and received by senders and receivers built by
+    /// this store; by default, objects are limited only by the protocol.
+    pub fn with_size_limits(mut self, size_limits: SizeLimits) -> Self {
+        self.size_limits = size_limits;
+        self
+    }
+
+    /// Sets the maximum time receivers built by this store wait for a single read of object
+    /// data from a peer to complete; by default, receivers wait indefinitely.
+    pub fn with_receive_timeout(mut self, receive_timeout: Option<Duration>) -> Self {
+        self.receive_timeout = receive_timeout;
+        self
+    }
+
+    /// Sets the time-to-live of objects received by receivers built by this store; expired
+    /// objects are deleted by `expire_objects()`. By default, objects do not expire.
+    pub fn with_object_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.expiry = ttl.map(|ttl| Arc::new(ExpiryTracker::new(ttl)));
+        self
+    }
+
+    /// Sets the maximum total size in bytes of objects which receivers built by this store can
+    /// receive at the same time; by default, the size is not limited.
+    pub fn with_receive_budget(mut self, max_bytes: Option<u64>) -> Self {
+        self.receive_budget = max_bytes.map(|max_bytes| Arc::new(ByteBudget::new(max_bytes)));
+        self
+    }
+
+    /// Sets whether receivers built by this store read every object back from the Plasma Store
+    /// after sealing it to verify that it matches the received object; by default, objects
+    /// are not verified.
+    pub fn with_verify_received(mut self, verify_received: bool) -> Self {
+        self.verify_received = verify_received;
+        self
+    }
+
+    /// Sets the maximum number of idle connections kept for every peer so that subsequent peer
+    /// requests to the same peer can reuse them, and the maximum time a connection can stay
+    /// idle; by default, or if `max_per_peer` is 0, connections to peers are not reused.
+    pub fn with_connection_pool(mut self, max_per_peer: usize, max_idle: Duration) -> Self {
+        self.connection_pool = match max_per_peer {
+            0 => None,
+            _ => Some(Arc::new(ConnectionPool::new(max_per_peer, max_idle))),
+        };
+        self
+    }
+
+    /// Returns the pool of idle connections to peers, if connections to peers are reused.
+    pub fn connection_pool(&self) -> Option<&Arc<ConnectionPool>> {
+        self.connection_pool.as_ref()
+    }
+
+    /// Returns the tracker of object deadlines, if objects in this store expire.
+    pub fn expiry(&self) -> Option<&Arc<ExpiryTracker>> {
+        self.expiry.as_ref()
+    }
+
+    /// Returns counters describing activity of the server.
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    /// Cancels all in-flight transfers of senders and receivers built by this store. Senders and
+    /// receivers built after this call will fail as soon as they start transferring objects.
+    pub fn cancel_transfers(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Returns false if connection to the Plasma Store was lost and the store has not been
+    /// reconnected since.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::SeqCst)
+    }
+
+    /// If connection to the Plasma Store was lost, tries to reconnect to the store. Object
+    /// senders and receivers built after a successful reconnect will use the new connection.
+    ///
+    /// Returns an error if the store is unhealthy and reconnecting fails.
+    pub fn reconnect_if_unhealthy(&self) -> Result<(), PlasmaError> {
+        if self.is_healthy() {
+            return Ok(());
+        }
+
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        let mut plasma_client = self.plasma_client.write().unwrap();
+
+        // the store may have been reconnected while we were waiting for the lock
+        if self.is_healthy() {
+            return Ok(());
+        }
+
+        match S::connect(&self.plasma_socket, self.plasma_connect_retries) {
+            Ok(client) => {
+                *plasma_client = client;
+                self.healthy.store(true, Ordering::SeqCst);
+                info!("reconnected to plasma store at {}", self.plasma_socket);
+                Ok(())
+            }
+            Err(err) => {
+                error!("failed to reconnect to plasma store: {}", err);
+                Err(err)
+            }
         }
     }
 
-    /// Returns a new ObjectSender for sending objects with the specified IDs.
+    /// Checks that the Plasma Store responds to requests. Store capacity is cached by the
+    /// client, so memory usage is queried instead as it requires a round trip to the store.
+    ///
+    /// Returns an error if the store is unhealthy or does not respond; in the latter case, the
+    /// store is marked as unhealthy if the connection to it was lost.
+    pub fn check_health(&self) -> Result<(), PlasmaError> {
+        ensure_healthy(&self.healthy)?;
+        match self.plasma_client().memory_usage() {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                check_store_error(&self.healthy, &err);
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns a new ObjectSender for sending objects with the specified IDs. If `timeout` is
+    /// provided, the sender will use it to wait for objects in the plasma store; otherwise, if
+    /// the objects are to be deleted after they are sent, TAKE timeout is used, and COPY
+    /// timeout is used in all other cases.
     pub fn build_sender(
         &self,
-        peer_addr: SocketAddr,
+        peer_addr: PeerAddr,
         object_ids: Vec<ObjectId>,
         delete_after_send: bool,
-    ) -> ObjectSender {
+        timeout: Option<Duration>,
+    ) -> ObjectSender<S> {
         ObjectSender {
             peer_addr,
             object_ids,
             delete_after_send,
-            plasma_client: self.plasma_client.clone(),
-            timeout_ms: self.timeout_ms,
+            plasma_client: self.plasma_client(),
+            healthy: self.healthy.clone(),
+            timeout: timeout.or(if delete_after_send {
+                self.take_timeout
+            } else {
+                self.copy_timeout
+            }),
             deleting: self.deleting.clone(),
+            size_limits: self.size_limits,
+            cancel: self.cancel.child_token(),
+            metrics: self.metrics.clone(),
+            dry_run: false,
         }
     }
 
-    /// Returns a new ObjectReceiver for receiving objects with the specified IDs.
+    /// Returns a new ObjectReceiver for receiving objects with the specified IDs. If
+    /// `cleanup_on_error` is set, objects received before a failure are deleted from the store.
     pub fn build_receiver(
         &self,
-        peer_addr: SocketAddr,
+        peer_addr: PeerAddr,
         object_ids: Vec<ObjectId>,
-    ) -> ObjectReceiver {
+        cleanup_on_error: bool,
+    ) -> ObjectReceiver<S> {
         ObjectReceiver {
             peer_addr,
             object_ids,
-            plasma_client: self.plasma_client.clone(),
+            plasma_client: self.plasma_client(),
+            healthy: self.healthy.clone(),
             receiving: self.receiving.clone(),
+            cleanup_on_error,
+            size_limits: self.size_limits,
+            read_timeout: self.receive_timeout,
+            expiry: self.expiry.clone(),
+            budget: self.receive_budget.clone(),
+            verify_after_seal: self.verify_received,
+            cancel: self.cancel.child_token(),
+            metrics: self.metrics.clone(),
+        }
+    }
+
+    /// Returns IDs of all sealed objects currently held in the Plasma Store.
+    pub fn list_objects(&self) -> Result<Vec<ObjectId>, PlasmaError> {
+        let objects = match self.plasma_client().list() {
+            Ok(objects) => objects,
+            Err(err) => {
+                check_store_error(&self.healthy, &err);
+                return Err(err);
+            }
+        };
+        Ok(objects
+            .into_iter()
+            .filter(|info| info.state == ObjectState::Sealed)
+            .map(|info| info.id.to_bytes().try_into().unwrap())
+            .collect())
+    }
+
+    /// Checks which of the specified objects are held in the Plasma Store; the returned flags
+    /// are in the same order as `object_ids`.
+    pub fn contains_objects(&self, object_ids: &[ObjectId]) -> Result<Vec<bool>, PlasmaError> {
+        let plasma_object_ids = map_object_ids(object_ids);
+        let in_store = match self.plasma_client().contains_many(&plasma_object_ids) {
+            Ok(in_store) => in_store,
+            Err(err) => {
+                check_store_error(&self.healthy, &err);
+                return Err(err);
+            }
+        };
+        Ok(plasma_object_ids
+            .iter()
+            .map(|oid| in_store.contains(oid))
+            .collect())
+    }
+
+    /// Deletes the specified objects from the Plasma Store on behalf of the specified peer.
+    ///
+    /// While the objects are being deleted, they are added to the set of objects scheduled for
+    /// deletion so that concurrent requests for the same objects fail fast. Will return an error
+    /// if any of the objects are already scheduled for deletion (e.g. by an in-flight TAKE
+    /// request). Plasma store silently skips objects which are in use by other clients, so
+    /// deletion of the objects is not guaranteed.
+    pub fn delete_objects(
+        &self,
+        peer_addr: &PeerAddr,
+        object_ids: &[ObjectId],
+    ) -> Result<(), ObjectDeleteError> {
+        // fail fast if connection to the plasma store has been lost
+        ensure_healthy(&self.healthy)
+            .map_err(|err| ObjectDeleteError::StoreError(peer_addr.clone(), err))?;
+
+        // mark the objects as scheduled for deletion, unless some of them already are;
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        {
+            let mut deleting = self.deleting.lock().unwrap();
+            let in_deleting: Vec<ObjectId> = object_ids
+                .iter()
+                .filter(|oid| deleting.contains(*oid))
+                .copied()
+                .collect();
+            if !in_deleting.is_empty() {
+                return Err(ObjectDeleteError::ObjectDeletionScheduled(
+                    peer_addr.clone(),
+                    in_deleting,
+                ));
+            }
+            deleting.extend(object_ids.iter());
+        }
+
+        let result = self
+            .plasma_client()
+            .delete_many(&map_object_ids(object_ids))
+            .map_err(|err| {
+                check_store_error(&self.healthy, &err);
+                ObjectDeleteError::StoreError(peer_addr.clone(), err)
+            });
+
+        // regardless of the outcome, the objects are no longer scheduled for deletion
+        let mut deleting = self.deleting.lock().unwrap();
+        for oid in object_ids.iter() {
+            deleting.remove(oid);
+        }
+
+        result
+    }
+
+    /// Deletes objects whose time-to-live has elapsed from the Plasma Store, and returns the
+    /// number of deleted objects.
+    ///
+    /// Deletion is best-effort: objects which are scheduled for deletion by other requests (e.g.
+    /// by an in-flight TAKE request) are skipped, and Plasma store silently skips objects which
+    /// are in use by other clients. Deleting of skipped objects is retried after the check
+    /// interval of the expiry tracker.
+    pub fn expire_objects(&self) -> Result<usize, PlasmaError> {
+        let expiry = match &self.expiry {
+            Some(expiry) => expiry,
+            None => return Ok(0),
+        };
+
+        // fail fast if connection to the plasma store has been lost; objects stay tracked
+        // until the next attempt
+        ensure_healthy(&self.healthy)?;
+        let now = Instant::now();
+        let retry_at = now + expiry.check_interval();
+        let expired = expiry.take_expired(now);
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        // mark expired objects as scheduled for deletion, except for the objects which already
+        // are; `unwrap()` is OK here because no thread will panic wile holding the lock
+        let expired: Vec<ObjectId> = {
+            let mut deleting = self.deleting.lock().unwrap();
+            let (scheduled, expired): (Vec<ObjectId>, Vec<ObjectId>) =
+                expired.into_iter().partition(|oid| deleting.contains(oid));
+            expiry.track_until(&scheduled, retry_at);
+            deleting.extend(expired.iter());
+            expired
+        };
+
+        // delete the objects and check which of them remained in the store
+        let plasma_client = self.plasma_client();
+        let plasma_object_ids = map_object_ids(&expired);
+        let result = plasma_client
+            .delete_many(&plasma_object_ids)
+            .and_then(|_| plasma_client.contains_many(&plasma_object_ids));
+
+        // regardless of the outcome, the objects are no longer scheduled for deletion
+        {
+            let mut deleting = self.deleting.lock().unwrap();
+            for oid in expired.iter() {
+                deleting.remove(oid);
+            }
+        }
+
+        match result {
+            Ok(in_store) => {
+                let remaining: Vec<ObjectId> = expired
+                    .iter()
+                    .zip(plasma_object_ids.iter())
+                    .filter(|(_, plasma_oid)| in_store.contains(plasma_oid))
+                    .map(|(oid, _)| *oid)
+                    .collect();
+                expiry.track_until(&remaining, retry_at);
+                Ok(expired.len() - remaining.len())
+            }
+            Err(err) => {
+                check_store_error(&self.healthy, &err);
+                expiry.track_until(&expired, retry_at);
+                Err(err)
+            }
         }
     }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+
+    /// Returns the current connection to the Plasma Store.
+    fn plasma_client(&self) -> S {
+        // `unwrap()` is OK here because no thread will panic wile holding the lock
+        self.plasma_client.read().unwrap().clone()
+    }
+}
+
+// SIZE LIMITS
+// ================================================================================================
+
+/// Maximum sizes of data and metadata of objects sent and received by the server. The limits
+/// cannot exceed `MAX_DATA_SIZE` and `MAX_META_SIZE`, which are imposed by the object header
+/// layout of the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimits {
+    /// Maximum size of object data in bytes.
+    pub max_data_size: u64,
+    /// Maximum size of object metadata in bytes.
+    pub max_meta_size: u64,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        SizeLimits {
+            max_data_size: MAX_DATA_SIZE,
+            max_meta_size: MAX_META_SIZE,
+        }
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Marks the store as unhealthy if the specified error indicates that connection to the
+/// Plasma Store was lost.
+pub fn check_store_error(healthy: &AtomicBool, err: &PlasmaError) {
+    if let PlasmaError::StoreDisconnected(_) = err {
+        if healthy.swap(false, Ordering::SeqCst) {
+            error!("lost connection to plasma store: {}", err);
+        }
+    }
+}
+
+/// Returns an error if connection to the Plasma Store was lost; this allows requests to fail
+/// fast rather than being attempted against a dead connection.
+pub fn ensure_healthy(healthy: &AtomicBool) -> Result<(), PlasmaError> {
+    if healthy.load(Ordering::SeqCst) {
+        Ok(())
+    } else {
+        Err(PlasmaError::StoreDisconnected(String::from(
+            "store is unavailable until reconnected",
+        )))
+    }
 }
@@ -0,0 +1,2572 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use super::*;
+use plasma_store::{
+    MemoryUsage, ObjectInfo, ObjectState, PlasmaClient, PlasmaError, StatusCode, DIGEST_SIZE,
+};
+use plasma_stream::{
+    errors::{ClientError, PeerResult},
+    handshake::Capabilities,
+    Client,
+};
+use rustc_hash::FxHashMap;
+use std::{
+    convert::TryInto,
+    fmt,
+    pin::Pin,
+    process::{Child, Command},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    thread,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter},
+    net::{TcpListener, TcpStream, UnixListener},
+    sync::{broadcast, mpsc, Semaphore},
+};
+use tracing::level_filters::LevelFilter;
+use tracing_test::traced_test;
+
+// CONSTANTS
+// ================================================================================================
+
+const PLASMA_SOCKET: &str = "/tmp/plasma";
+const PLASMA_RESTART_SOCKET: &str = "/tmp/plasma-restart";
+const PLASMA_EVICT_SOCKET: &str = "/tmp/plasma-evict";
+const PLASMA_PUSH_SOCKET: &str = "/tmp/plasma-push";
+const TIMEOUT: Duration = Duration::from_millis(1000);
+
+// CREDENTIALS TESTS
+// ================================================================================================
+
+#[test]
+fn credentials_read_from_file() {
+    let credentials_dir = std::env::temp_dir().join("plasma-stream-credentials");
+    std::fs::create_dir_all(&credentials_dir).unwrap();
+    std::fs::write(credentials_dir.join("test-token"), "secret\n").unwrap();
+
+    let value = credentials::read_credential(&credentials_dir, "test-token").unwrap();
+    assert_eq!(Some(String::from("secret")), value);
+
+    // missing credentials should be skipped
+    let value = credentials::read_credential(&credentials_dir, "missing").unwrap();
+    assert_eq!(None, value);
+
+    std::fs::remove_dir_all(credentials_dir).unwrap();
+}
+
+// LOGGING TESTS
+// ================================================================================================
+
+#[test]
+fn subscriber_uses_log_level_option() {
+    // the default level should be used unless another level is requested
+    let options = ServerOptions::from_iter(&["plasma-stream-server"]);
+    assert_eq!(Level::INFO, options.log_level);
+    let subscriber = build_subscriber(options.log_level);
+    assert_eq!(Some(LevelFilter::INFO), subscriber.max_level_hint());
+
+    let options = ServerOptions::from_iter(&["plasma-stream-server", "--log-level", "warn"]);
+    assert_eq!(Level::WARN, options.log_level);
+    let subscriber = build_subscriber(options.log_level);
+    assert_eq!(Some(LevelFilter::WARN), subscriber.max_level_hint());
+
+    // levels which are not recognized should be rejected
+    let result = ServerOptions::from_iter_safe(&["plasma-stream-server", "--log-level", "loud"]);
+    assert!(result.is_err());
+}
+
+// HANDLER TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn handler_captures_malformed_request() {
+    let (mut client, mut socket) = connect_pair().await;
+
+    // send a request with an invalid type
+    let malformed = [0xFFu8, 1, 2, 3];
+    client.write_all(&malformed).await.unwrap();
+
+    // take a snapshot of the request and make sure it cannot be parsed
+    let snapshot = handler::peek_request(&socket).await.unwrap();
+    assert_eq!(malformed.to_vec(), snapshot);
+    assert!(Request::read_from(&mut socket).await.is_err());
+
+    // capture the request and make sure the artifact contains the original bytes
+    let capture_dir = std::env::temp_dir().join("plasma-stream-captures");
+    std::fs::create_dir_all(&capture_dir).unwrap();
+    let peer_addr = socket.peer_addr().unwrap();
+    let path = handler::write_capture(&capture_dir, peer_addr, &snapshot).unwrap();
+    assert_eq!(malformed.to_vec(), std::fs::read(&path).unwrap());
+    std::fs::remove_file(path).unwrap();
+}
+
+// SENDER TESTS
+// ================================================================================================
+// tests below require plasma store server to be running on the local machine; running ignored
+// tests can be done via: cargo test -- --ignored
+
+#[tokio::test]
+#[ignore]
+async fn sender_waits_for_object_to_be_sealed() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+
+    // seal the object shortly after the request is made
+    let oid = plasma_store::ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let producer_oid = oid.clone();
+    let producer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        build_client()
+            .create_and_seal(producer_oid, &data, &[])
+            .unwrap();
+    });
+
+    // the object should be sent to the peer once it becomes available
+    let (mut peer, mut socket) = connect_pair().await;
+    let object_ids: Vec<ObjectId> = vec![oid.to_bytes().try_into().unwrap()];
+    let peer_addr = socket.peer_addr().unwrap();
+    store
+        .build_sender(peer_addr.into(), object_ids, false, None)
+        .run(&mut socket, Capabilities::none())
+        .await
+        .unwrap();
+    producer.join().unwrap();
+
+    assert_eq!(status_codes::BEGIN, peer.read_u8().await.unwrap());
+}
+
+#[tokio::test]
+#[ignore]
+async fn sender_uses_timeout_override() {
+    // the store default timeout is long enough to make the test hang if it were used
+    let long_timeout = Duration::from_secs(60);
+    let store = Store::new(PLASMA_SOCKET, 0, Some(long_timeout), Some(long_timeout)).unwrap();
+    let peer_addr: PeerAddr = "127.0.0.1:2021".parse().unwrap();
+    let object_ids = build_object_ids(1);
+
+    // without an override, the store default is used
+    let sender = store.build_sender(peer_addr.clone(), object_ids.clone(), false, None);
+    assert_eq!(Some(long_timeout), sender.timeout);
+    drop(sender);
+
+    // with an override, the sender gives up on a missing object after the overridden timeout
+    let timeout = Duration::from_millis(100);
+    let sender = store.build_sender(peer_addr, object_ids.clone(), false, Some(timeout));
+    assert_eq!(Some(timeout), sender.timeout);
+    let (_peer, mut socket) = connect_pair().await;
+    let started = std::time::Instant::now();
+    let result = sender.run(&mut socket, Capabilities::none()).await;
+    assert!(started.elapsed() < long_timeout);
+    match result {
+        Err(errors::ObjectSendError::ObjectsNotFound(_, missing)) => {
+            assert_eq!(object_ids, missing)
+        }
+        result => panic!("expected objects not found error, but got {:?}", result),
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn sender_enforces_size_limits() {
+    let size_limits = SizeLimits {
+        max_data_size: 1024 * 1024,
+        ..SizeLimits::default()
+    };
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT))
+        .unwrap()
+        .with_size_limits(size_limits);
+
+    // a 2 MB object exceeds the configured 1 MB limit
+    let object_ids = build_object_ids(1);
+    let oid = plasma_store::ObjectId::from(object_ids[0]);
+    build_client()
+        .create_and_seal(oid, &vec![1u8; 2 * 1024 * 1024], &[])
+        .unwrap();
+
+    let (mut peer, mut socket) = connect_pair().await;
+    let peer_addr = socket.peer_addr().unwrap();
+    let result = store
+        .build_sender(peer_addr.into(), object_ids.clone(), false, None)
+        .run(&mut socket, Capabilities::none())
+        .await;
+    match result {
+        Err(errors::ObjectSendError::ObjectDataTooLarge(_, oid, size)) => {
+            assert_eq!(object_ids[0], oid);
+            assert_eq!(2 * 1024 * 1024, size);
+        }
+        result => panic!("expected data too large error, but got {:?}", result),
+    }
+    assert_eq!(
+        status_codes::OB_DATA_TOO_LARGE_ERR,
+        peer.read_u8().await.unwrap()
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn sender_rejects_zero_length_data() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+
+    // zero-length metadata is allowed, but an object without data would be rejected by the
+    // receiver, so the sender should reject it before sending anything
+    let object_ids = build_object_ids(1);
+    let oid = plasma_store::ObjectId::from(object_ids[0]);
+    let pc = build_client();
+    pc.create_and_seal(oid.clone(), &[], &[1u8, 2]).unwrap();
+
+    let (mut peer, mut socket) = connect_pair().await;
+    let peer_addr = socket.peer_addr().unwrap();
+    let result = store
+        .build_sender(peer_addr.into(), object_ids.clone(), false, None)
+        .run(&mut socket, Capabilities::none())
+        .await;
+    match result {
+        Err(errors::ObjectSendError::ZeroLengthObjectData(_, oid)) => {
+            assert_eq!(object_ids[0], oid);
+        }
+        result => panic!("expected zero-length data error, but got {:?}", result),
+    }
+    assert_eq!(
+        status_codes::OB_DATA_ZERO_LENGTH_ERR,
+        peer.read_u8().await.unwrap()
+    );
+    pc.delete(&oid).unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn sender_dry_run_reports_estimate() {
+    let size_limits = SizeLimits {
+        max_data_size: 1024,
+        ..SizeLimits::default()
+    };
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT))
+        .unwrap()
+        .with_size_limits(size_limits);
+
+    // put two objects into the store, one of which exceeds the size limit; the third object
+    // is not in the store
+    let object_ids = build_object_ids(3);
+    let pc = build_client();
+    pc.create_and_seal(object_ids[0].into(), &[1u8; 100], &[1, 2])
+        .unwrap();
+    pc.create_and_seal(object_ids[1].into(), &[2u8; 2048], &[])
+        .unwrap();
+
+    let (mut peer, mut socket) = connect_pair().await;
+    let peer_addr = socket.peer_addr().unwrap();
+    let mut sender = store.build_sender(peer_addr.into(), object_ids.clone(), false, None);
+    sender.dry_run = true;
+    let bytes_sent = sender.run(&mut socket, Capabilities::none()).await.unwrap();
+    assert_eq!(0, bytes_sent);
+    drop(socket);
+
+    // the response should consist of BEGIN followed by the estimate, and nothing else
+    let mut response = Vec::new();
+    peer.read_to_end(&mut response).await.unwrap();
+    assert_eq!(status_codes::BEGIN, response[0]);
+    let mut reader = &response[1..];
+    let estimate = TransferEstimate::read_from(&mut reader).await.unwrap();
+    assert!(reader.is_empty(), "object data should not be sent");
+    let expected = TransferEstimate {
+        num_objects: 2,
+        total_size: 102 + 2048,
+        missing: vec![object_ids[2]],
+        oversized: vec![object_ids[1]],
+    };
+    assert_eq!(expected, estimate);
+
+    // the objects should remain in the store
+    assert!(pc.contains(&object_ids[0].into()).unwrap());
+    assert!(pc.contains(&object_ids[1].into()).unwrap());
+}
+
+#[tokio::test]
+#[ignore]
+async fn sender_reports_partial_progress() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+    let pc = build_client();
+
+    let object_ids = build_object_ids(3);
+    let plasma_object_ids = utils::map_object_ids(&object_ids);
+    let data = [1u8; 16];
+    for oid in plasma_object_ids.iter() {
+        pc.create_and_seal(oid.clone(), &data, &[]).unwrap();
+    }
+
+    // the connection accepts the BEGIN flag and the first object, and then fails; without
+    // capabilities, an object is written as an 8-byte header followed by its data
+    let peer_addr: PeerAddr = "127.0.0.1:2021".parse().unwrap();
+    let mut socket = FailingWriter {
+        remaining: 1 + 8 + data.len(),
+    };
+    let result = store
+        .build_sender(peer_addr, object_ids.clone(), false, None)
+        .run(&mut socket, Capabilities::none())
+        .await;
+    match result {
+        Err(errors::ObjectSendError::ObjectsPartiallySent {
+            sent, failed_at, ..
+        }) => {
+            assert_eq!(object_ids[..1].to_vec(), sent);
+            assert_eq!(object_ids[1], failed_at);
+        }
+        result => panic!("expected partial send error, but got {:?}", result),
+    }
+
+    pc.delete_many(&plasma_object_ids).unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn sender_flushes_every_object() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+    let pc = build_client();
+
+    let source_ids = build_object_ids(2);
+    let plasma_source_ids = utils::map_object_ids(&source_ids);
+    for (i, oid) in plasma_source_ids.iter().enumerate() {
+        pc.create_and_seal(oid.clone(), &vec![1u8; 100 * (i + 1)], &[1, 2])
+            .unwrap();
+    }
+
+    // writes are buffered, so nothing reaches the receiver unless the sender flushes; the
+    // buffer is large enough to hold all of the objects
+    let (mut peer, socket) = connect_pair().await;
+    let peer_addr: PeerAddr = peer.peer_addr().unwrap().into();
+    let mut socket = BufWriter::with_capacity(64 * 1024, socket);
+    let target_ids = build_object_ids(2);
+    let sender = store.build_sender(peer_addr.clone(), source_ids, false, None);
+    let receiver = store.build_receiver(peer_addr, target_ids.clone(), true);
+    receiver.prepare().unwrap();
+    let (sent, report) = tokio::join!(
+        sender.run(&mut socket, Capabilities::none()),
+        tokio::time::timeout(TIMEOUT, receiver.run(&mut peer, Capabilities::none()))
+    );
+    let bytes_sent = sent.unwrap();
+    report.unwrap().unwrap().into_result().unwrap();
+
+    // the receiver should get exactly the bytes counted by the sender
+    let plasma_target_ids = utils::map_object_ids(&target_ids);
+    let bytes_received: usize = plasma_target_ids
+        .iter()
+        .map(|oid| pc.get(oid.clone(), Some(TIMEOUT)).unwrap().unwrap().size())
+        .sum();
+    assert_eq!(bytes_sent, bytes_received);
+
+    pc.delete_many(&plasma_source_ids).unwrap();
+    pc.delete_many(&plasma_target_ids).unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_deletes_objects_after_take() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // put an object into the store
+    let pc = build_client();
+    let oid = plasma_store::ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+
+    // serve a single connection
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let handler_store = store.clone();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let limit_connections = Arc::new(Semaphore::new(0));
+        let (notify_shutdown, _) = broadcast::channel(1);
+        let (shutdown_complete, _) = mpsc::channel(1);
+        let mut handler = Handler::new(
+            socket.into(),
+            handler_store,
+            limit_connections,
+            None,
+            None,
+            Capabilities::all(),
+            ConnectRetry::default(),
+            None,
+            true,
+            None,
+            ConnectionLimits::default(),
+            Shutdown::new(notify_shutdown.subscribe()),
+            shutdown_complete,
+        );
+        handler.run().await.unwrap();
+    });
+
+    // take the object and make sure it was received correctly
+    let mut client = Client::connect(address).await.unwrap();
+    let object_ids: Vec<ObjectId> = vec![oid.to_bytes().try_into().unwrap()];
+    let objects = client.take(&object_ids).await.unwrap();
+    assert_eq!(vec![(object_ids[0], meta.to_vec(), data.to_vec())], objects);
+
+    // once the connection is closed, the object should have been deleted from the store
+    drop(client);
+    server.await.unwrap();
+    assert!(!pc.contains(&oid).unwrap());
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_copies_objects_over_unix_socket() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // put an object into the store
+    let pc = build_client();
+    let oid = plasma_store::ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+
+    // serve a single connection accepted over a Unix socket
+    let path = std::env::temp_dir().join(format!("plasma-stream-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let handler_store = store.clone();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let limit_connections = Arc::new(Semaphore::new(0));
+        let (notify_shutdown, _) = broadcast::channel(1);
+        let (shutdown_complete, _) = mpsc::channel(1);
+        let mut handler = Handler::new(
+            socket.into(),
+            handler_store,
+            limit_connections,
+            None,
+            None,
+            Capabilities::all(),
+            ConnectRetry::default(),
+            None,
+            true,
+            None,
+            ConnectionLimits::default(),
+            Shutdown::new(notify_shutdown.subscribe()),
+            shutdown_complete,
+        );
+        handler.run().await.unwrap();
+    });
+
+    // copy the object and make sure it was received correctly and is still in the store
+    let mut client = Client::connect_unix(&path).await.unwrap();
+    let object_ids: Vec<ObjectId> = vec![oid.to_bytes().try_into().unwrap()];
+    let objects = client.copy(&object_ids).await.unwrap();
+    assert_eq!(vec![(object_ids[0], meta.to_vec(), data.to_vec())], objects);
+
+    drop(client);
+    server.await.unwrap();
+    assert!(pc.contains(&oid).unwrap());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_completes_transfer_on_shutdown() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // put a large object into the store so that the transfer is still in progress when the
+    // shutdown signal is sent
+    let pc = build_client();
+    let oid = plasma_store::ObjectId::rand();
+    let data = vec![7u8; 4_000_000];
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+
+    let (mut client, socket) = connect_pair().await;
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel::<()>(1);
+    let mut handler = Handler::new(
+        socket.into(),
+        store,
+        Arc::new(Semaphore::new(0)),
+        None,
+        None,
+        Capabilities::all(),
+        ConnectRetry::default(),
+        None,
+        true,
+        None,
+        ConnectionLimits::default(),
+        Shutdown::new(notify_shutdown.subscribe()),
+        shutdown_complete_tx,
+    );
+    let server = tokio::spawn(async move { handler.run().await });
+    handshake::initiate(&mut client, Capabilities::none(), None)
+        .await
+        .unwrap();
+
+    // start the transfer and wait until the handler begins sending the object
+    let object_ids: Vec<ObjectId> = vec![oid.to_bytes().try_into().unwrap()];
+    Request::Copy(object_ids)
+        .write_into(&mut client)
+        .await
+        .unwrap();
+    assert_eq!(status_codes::BEGIN, client.read_u8().await.unwrap());
+
+    // signal shutdown while the transfer is in progress
+    drop(notify_shutdown);
+
+    // the transfer should still complete
+    let (meta_size, data_size) = utils::read_object_header(&mut client).await.unwrap();
+    let mut received_meta = vec![0u8; meta_size];
+    client.read_exact(&mut received_meta).await.unwrap();
+    let mut received_data = vec![0u8; data_size];
+    client.read_exact(&mut received_data).await.unwrap();
+    assert_eq!(meta.to_vec(), received_meta);
+    assert_eq!(data, received_data);
+
+    // the handler should stop after the current request without waiting for the next one,
+    // and the shutdown completion channel should close once the handler is dropped
+    server.await.unwrap().unwrap();
+    assert_eq!(None, shutdown_complete_rx.recv().await);
+
+    pc.delete(&oid).unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_lists_objects() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // put three objects into the store
+    let pc = build_client();
+    let object_ids = build_object_ids(3);
+    let plasma_object_ids = utils::map_object_ids(&object_ids);
+    for oid in plasma_object_ids.iter() {
+        pc.create_and_seal(oid.clone(), &[1u8, 2, 3, 4], &[])
+            .unwrap();
+    }
+
+    // serve a single connection
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (notify_shutdown, _) = broadcast::channel(1);
+        let (shutdown_complete, _) = mpsc::channel(1);
+        let mut handler = Handler::new(
+            socket.into(),
+            store,
+            Arc::new(Semaphore::new(0)),
+            None,
+            None,
+            Capabilities::all(),
+            ConnectRetry::default(),
+            None,
+            true,
+            None,
+            ConnectionLimits::default(),
+            Shutdown::new(notify_shutdown.subscribe()),
+            shutdown_complete,
+        );
+        handler.run().await.unwrap();
+    });
+
+    // the listed objects should include all three objects; the store may hold other objects
+    // as well
+    let mut client = Client::connect(address).await.unwrap();
+    let listed = client.list().await.unwrap();
+    for oid in object_ids.iter() {
+        assert!(listed.contains(oid));
+    }
+
+    drop(client);
+    server.await.unwrap();
+    pc.delete_many(&plasma_object_ids).unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_checks_contained_objects() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // put every other object into the store
+    let pc = build_client();
+    let object_ids = build_object_ids(4);
+    let plasma_object_ids = utils::map_object_ids(&object_ids);
+    for oid in plasma_object_ids.iter().step_by(2) {
+        pc.create_and_seal(oid.clone(), &[1u8, 2, 3, 4], &[])
+            .unwrap();
+    }
+
+    let (mut client, socket) = connect_pair().await;
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let (shutdown_complete, _) = mpsc::channel(1);
+    let mut handler = Handler::new(
+        socket.into(),
+        store,
+        Arc::new(Semaphore::new(0)),
+        None,
+        None,
+        Capabilities::all(),
+        ConnectRetry::default(),
+        None,
+        true,
+        None,
+        ConnectionLimits::default(),
+        Shutdown::new(notify_shutdown.subscribe()),
+        shutdown_complete,
+    );
+    let server = tokio::spawn(async move { handler.run().await });
+    handshake::initiate(&mut client, Capabilities::none(), None)
+        .await
+        .unwrap();
+
+    // the response should be a bitmap in the order of the requested objects
+    Request::Contains(object_ids)
+        .write_into(&mut client)
+        .await
+        .unwrap();
+    assert_eq!(status_codes::BEGIN, client.read_u8().await.unwrap());
+    assert_eq!(0b0000_0101, client.read_u8().await.unwrap());
+
+    drop(client);
+    server.await.unwrap().unwrap();
+    pc.delete(&plasma_object_ids[0]).unwrap();
+    pc.delete(&plasma_object_ids[2]).unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_deletes_objects() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // put two objects into the store
+    let pc = build_client();
+    let object_ids = build_object_ids(2);
+    let plasma_object_ids = utils::map_object_ids(&object_ids);
+    for oid in plasma_object_ids.iter() {
+        pc.create_and_seal(oid.clone(), &[1u8, 2, 3, 4], &[])
+            .unwrap();
+    }
+
+    // serve a single connection
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (notify_shutdown, _) = broadcast::channel(1);
+        let (shutdown_complete, _) = mpsc::channel(1);
+        let mut handler = Handler::new(
+            socket.into(),
+            store,
+            Arc::new(Semaphore::new(0)),
+            None,
+            None,
+            Capabilities::all(),
+            ConnectRetry::default(),
+            None,
+            true,
+            None,
+            ConnectionLimits::default(),
+            Shutdown::new(notify_shutdown.subscribe()),
+            shutdown_complete,
+        );
+        handler.run().await.unwrap();
+    });
+
+    // delete the objects; no object data should be transferred
+    let mut client = Client::connect(address).await.unwrap();
+    client.delete(&object_ids).await.unwrap();
+    for oid in plasma_object_ids.iter() {
+        assert!(!pc.contains(oid).unwrap());
+    }
+
+    drop(client);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_closes_idle_connection() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // open a connection which never sends anything
+    let (mut client, socket) = connect_pair().await;
+    let limit_connections = Arc::new(Semaphore::new(0));
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let (shutdown_complete, _) = mpsc::channel(1);
+    let mut handler = Handler::new(
+        socket.into(),
+        store,
+        limit_connections.clone(),
+        None,
+        Some(Duration::from_millis(100)),
+        Capabilities::all(),
+        ConnectRetry::default(),
+        None,
+        true,
+        None,
+        ConnectionLimits::default(),
+        Shutdown::new(notify_shutdown.subscribe()),
+        shutdown_complete,
+    );
+    let server = tokio::spawn(async move { handler.run().await });
+
+    // the handler should close the connection without an error once the idle timeout expires,
+    // and the connection permit should be released
+    tokio::time::timeout(Duration::from_secs(1), server)
+        .await
+        .expect("idle connection was not closed")
+        .unwrap()
+        .unwrap();
+    assert_eq!(0, client.read(&mut [0u8; 1]).await.unwrap());
+    assert_eq!(1, limit_connections.available_permits());
+}
+
+// RECEIVER TESTS
+// ================================================================================================
+// tests below require plasma store server to be running on the local machine
+
+#[tokio::test]
+#[ignore]
+async fn receiver_reports_partial_progress() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+    let pc = build_client();
+
+    // when partial results are retained, received objects should stay in the store
+    let object_ids = build_object_ids(3);
+    let report = receive_with_failure(&store, &object_ids, false).await;
+    assert_eq!(object_ids[..2].to_vec(), report.received);
+    match report.failed {
+        Some((oid, errors::ObjectReceiveError::ZeroLengthObjectData(_, _))) => {
+            assert_eq!(object_ids[2], oid)
+        }
+        failed => panic!("expected zero length data error, but got {:?}", failed),
+    }
+    let plasma_object_ids = utils::map_object_ids(&object_ids);
+    assert!(pc.contains(&plasma_object_ids[0]).unwrap());
+    assert!(pc.contains(&plasma_object_ids[1]).unwrap());
+    assert!(!pc.contains(&plasma_object_ids[2]).unwrap());
+    pc.delete_many(&plasma_object_ids[..2]).unwrap();
+
+    // when cleanup is enabled, received objects should be deleted from the store
+    let object_ids = build_object_ids(3);
+    let report = receive_with_failure(&store, &object_ids, true).await;
+    assert_eq!(object_ids[..2].to_vec(), report.received);
+    assert!(report.failed.is_some());
+    for oid in utils::map_object_ids(&object_ids).iter() {
+        assert!(!pc.contains(oid).unwrap());
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn receiver_rejects_corrupted_object() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+    let pc = build_client();
+
+    let (mut peer, mut socket) = connect_pair().await;
+    let object_ids = build_object_ids(1);
+    let receiver = store.build_receiver(
+        socket.peer_addr().unwrap().into(),
+        object_ids.clone(),
+        false,
+    );
+    receiver.prepare().unwrap();
+
+    // send an object with a byte of data corrupted after the checksum was computed
+    let meta = [1u8, 2, 3, 4];
+    let mut data = [5u8; 8];
+    let checksum = utils::object_checksum(&meta, &data);
+    data[3] ^= 0xFF;
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    peer.write_u64_le(4u64 | (8u64 << 16)).await.unwrap();
+    peer.write_u32_le(checksum).await.unwrap();
+    peer.write_all(&meta).await.unwrap();
+    peer.write_all(&data).await.unwrap();
+
+    // the object should be rejected and should not remain in the store; the object was written
+    // without compression, so only checksums are enabled
+    let capabilities = Capabilities::from_bits(Capabilities::CHECKSUMS);
+    let report = receiver.run(&mut socket, capabilities).await.unwrap();
+    assert!(report.received.is_empty());
+    match report.failed {
+        Some((oid, errors::ObjectReceiveError::ChecksumMismatch(_, _))) => {
+            assert_eq!(object_ids[0], oid)
+        }
+        failed => panic!("expected checksum mismatch error, but got {:?}", failed),
+    }
+    let plasma_oid = plasma_store::ObjectId::from(object_ids[0]);
+    assert!(!pc.contains(&plasma_oid).unwrap());
+}
+
+#[tokio::test]
+#[ignore]
+async fn receiver_enforces_size_limits() {
+    let size_limits = SizeLimits {
+        max_data_size: 1024 * 1024,
+        ..SizeLimits::default()
+    };
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT))
+        .unwrap()
+        .with_size_limits(size_limits);
+    let pc = build_client();
+
+    let (mut peer, mut socket) = connect_pair().await;
+    let object_ids = build_object_ids(1);
+    let receiver = store.build_receiver(
+        socket.peer_addr().unwrap().into(),
+        object_ids.clone(),
+        false,
+    );
+    receiver.prepare().unwrap();
+
+    // announce a 2 MB object; it should be rejected based on the header alone
+    let data_size = 2 * 1024 * 1024u64;
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    peer.write_u64_le(data_size << 16).await.unwrap();
+
+    let report = receiver
+        .run(&mut socket, Capabilities::none())
+        .await
+        .unwrap();
+    assert!(report.received.is_empty());
+    match report.failed {
+        Some((oid, errors::ObjectReceiveError::ObjectDataTooLarge(_, _, size))) => {
+            assert_eq!(object_ids[0], oid);
+            assert_eq!(data_size as usize, size);
+        }
+        failed => panic!("expected data too large error, but got {:?}", failed),
+    }
+    let plasma_oid = plasma_store::ObjectId::from(object_ids[0]);
+    assert!(!pc.contains(&plasma_oid).unwrap());
+}
+
+#[tokio::test]
+#[ignore]
+async fn receiver_cleans_up_cancelled_transfer() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+    let pc = build_client();
+
+    let (mut peer, mut socket) = connect_pair().await;
+    let object_ids = build_object_ids(2);
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), object_ids.clone(), true);
+    receiver.prepare().unwrap();
+
+    // send the first object in full, but only a part of the second object, and keep the
+    // connection open so that the transfer stalls
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    peer.write_u64_le(4u64 | (8u64 << 16)).await.unwrap();
+    peer.write_all(&[1u8; 12]).await.unwrap();
+    peer.write_u64_le(4u64 | (8u64 << 16)).await.unwrap();
+    peer.write_all(&[1u8; 6]).await.unwrap();
+
+    // cancel the transfer while the second object is being received
+    let cancel = receiver.cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        cancel.cancel();
+    });
+
+    let report = receiver
+        .run(&mut socket, Capabilities::none())
+        .await
+        .unwrap();
+    assert_eq!(vec![object_ids[0]], report.received);
+    match report.failed {
+        Some((oid, errors::ObjectReceiveError::TransferCancelled(_))) => {
+            assert_eq!(object_ids[1], oid)
+        }
+        failed => panic!("expected transfer cancelled error, but got {:?}", failed),
+    }
+
+    // both the received object and the partially received object should have been deleted
+    for oid in utils::map_object_ids(&object_ids) {
+        assert!(!pc.contains(&oid).unwrap());
+    }
+    drop(peer);
+}
+
+#[tokio::test]
+async fn receiver_aborts_partially_received_objects() {
+    let memory_store = MemoryStore::default();
+    let store = Store::from_client(memory_store.clone(), "memory", 0, None, None);
+    let object_ids = build_object_ids(1);
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2, 3, 4];
+
+    // the peer goes away in the middle of the object data
+    let (mut peer, mut socket) = connect_pair().await;
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), object_ids.clone(), true);
+    receiver.prepare().unwrap();
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    peer.write_u64_le(4u64 | (8u64 << 16)).await.unwrap();
+    peer.write_all(&[1u8; 6]).await.unwrap();
+    drop(peer);
+    let report = receiver
+        .run(&mut socket, Capabilities::none())
+        .await
+        .unwrap();
+    assert!(matches!(
+        report.failed,
+        Some((_, errors::ObjectReceiveError::ConnectionError(_, _)))
+    ));
+    assert!(memory_store.list().unwrap().is_empty());
+    drop(receiver);
+
+    // the transfer is cancelled in the middle of the object data
+    let (mut peer, mut socket) = connect_pair().await;
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), object_ids.clone(), true);
+    receiver.prepare().unwrap();
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    peer.write_u64_le(4u64 | (8u64 << 16)).await.unwrap();
+    peer.write_all(&[1u8; 6]).await.unwrap();
+    let cancel = receiver.cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        cancel.cancel();
+    });
+    let report = receiver
+        .run(&mut socket, Capabilities::none())
+        .await
+        .unwrap();
+    assert!(matches!(
+        report.failed,
+        Some((_, errors::ObjectReceiveError::TransferCancelled(_)))
+    ));
+    assert!(memory_store.list().unwrap().is_empty());
+    drop(receiver);
+    drop(peer);
+
+    // since nothing was left behind, the object can be received again
+    let (mut peer, mut socket) = connect_pair().await;
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), object_ids.clone(), true);
+    receiver.prepare().unwrap();
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    utils::write_object(&meta, &data, Capabilities::none(), &mut peer)
+        .await
+        .unwrap();
+    let report = receiver.run(&mut socket, Capabilities::none()).await;
+    report.unwrap().into_result().unwrap();
+    let oid = plasma_store::ObjectId::from(object_ids[0]);
+    let ob = memory_store.get(oid, None).unwrap().unwrap();
+    assert_eq!((&meta[..], &data[..]), ob.parts());
+}
+
+#[tokio::test]
+#[ignore]
+async fn receiver_times_out_stalled_peer() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT))
+        .unwrap()
+        .with_receive_timeout(Some(Duration::from_millis(100)));
+    let pc = build_client();
+
+    let (mut peer, mut socket) = connect_pair().await;
+    let object_ids = build_object_ids(2);
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), object_ids.clone(), true);
+    receiver.prepare().unwrap();
+
+    // send the first object in full, but only the header of the second object, and keep the
+    // connection open so that the transfer stalls
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    peer.write_u64_le(4u64 | (8u64 << 16)).await.unwrap();
+    peer.write_all(&[1u8; 12]).await.unwrap();
+    peer.write_u64_le(4u64 | (8u64 << 16)).await.unwrap();
+
+    let report = receiver
+        .run(&mut socket, Capabilities::none())
+        .await
+        .unwrap();
+    assert_eq!(vec![object_ids[0]], report.received);
+    match report.failed {
+        Some((oid, errors::ObjectReceiveError::ConnectionError(_, err))) => {
+            assert_eq!(object_ids[1], oid);
+            assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+        }
+        failed => panic!("expected connection error, but got {:?}", failed),
+    }
+
+    // both the received object and the partially received object should have been deleted
+    for oid in utils::map_object_ids(&object_ids) {
+        assert!(!pc.contains(&oid).unwrap());
+    }
+    drop(peer);
+}
+
+#[tokio::test]
+#[ignore]
+async fn receiver_receives_large_object() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+
+    // put a 100 MB object into the store; the object spans many data chunks
+    let pc = build_client();
+    let source_oid = plasma_store::ObjectId::rand();
+    let data: Vec<u8> = (0..100_000_000u32).map(|i| (i % 251) as u8).collect();
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(source_oid.clone(), &data, &meta)
+        .unwrap();
+
+    // send the object from one end of the connection and receive it under a different ID
+    // on the other end
+    let (mut peer, mut socket) = connect_pair().await;
+    let sender = store.build_sender(
+        peer.peer_addr().unwrap().into(),
+        vec![source_oid.to_bytes().try_into().unwrap()],
+        false,
+        None,
+    );
+    let target_ids = build_object_ids(1);
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), target_ids.clone(), true);
+    receiver.prepare().unwrap();
+    // manifests are requested only by clients, so they are not negotiated between servers
+    let capabilities = Capabilities::from_bits(Capabilities::CHECKSUMS | Capabilities::COMPRESSION);
+    let (sent, report) = tokio::join!(
+        sender.run(&mut socket, capabilities),
+        receiver.run(&mut peer, capabilities)
+    );
+    sent.unwrap();
+    report.unwrap().into_result().unwrap();
+
+    // make sure the received object is identical to the original one
+    let target_oid = plasma_store::ObjectId::from(target_ids[0]);
+    let ob = pc.get(target_oid.clone(), Some(TIMEOUT)).unwrap().unwrap();
+    assert!(data == ob.data());
+    assert_eq!(&meta, ob.meta());
+    drop(ob);
+
+    pc.delete(&source_oid).unwrap();
+    pc.delete(&target_oid).unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn receiver_evicts_objects_when_store_is_full() {
+    // start a dedicated 10 MB plasma store and nearly fill it with sealed objects
+    let mut plasma_store = start_plasma_store(PLASMA_EVICT_SOCKET);
+    let store = Store::new(PLASMA_EVICT_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+    let pc = PlasmaClient::new(PLASMA_EVICT_SOCKET, 0).unwrap();
+    let filler = vec![7u8; 1_000_000];
+    for _ in 0..9 {
+        let oid = plasma_store::ObjectId::rand();
+        pc.create_and_seal(oid, &filler, &[]).unwrap();
+    }
+
+    // receive an object which does not fit into the remaining space
+    let (mut peer, mut socket) = connect_pair().await;
+    let object_ids = build_object_ids(1);
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), object_ids.clone(), true);
+    receiver.prepare().unwrap();
+    let data = vec![5u8; 2_000_000];
+    let meta = [1u8, 2, 3, 4];
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    let (written, report) = tokio::join!(
+        utils::write_object(&meta, &data, Capabilities::none(), &mut peer),
+        receiver.run(&mut socket, Capabilities::none())
+    );
+    written.unwrap();
+    report.unwrap().into_result().unwrap();
+
+    // the object should be in the store
+    let oid = plasma_store::ObjectId::from(object_ids[0]);
+    let ob = pc.get(oid, Some(TIMEOUT)).unwrap().unwrap();
+    assert!(data == ob.data());
+    drop(ob);
+
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+}
+
+#[test]
+fn receiver_verification_detects_mismatch() {
+    let meta = [1u8, 2];
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let checksum = utils::object_checksum(&meta, &data);
+    receiver::compare_sealed_object(&meta, &data, meta.len(), data.len(), checksum).unwrap();
+
+    // the object in the store is shorter than the received one
+    let result = receiver::compare_sealed_object(&meta, &data[..7], 2, 8, checksum);
+    assert!(matches!(result, Err(PlasmaError::HashMismatch(_))));
+
+    // the object in the store has the same length, but different content
+    let corrupted = [1u8, 2, 3, 4, 5, 6, 7, 9];
+    let result = receiver::compare_sealed_object(&meta, &corrupted, 2, 8, checksum);
+    assert!(matches!(result, Err(PlasmaError::HashMismatch(_))));
+}
+
+#[tokio::test]
+#[ignore]
+async fn receiver_verifies_sealed_objects() {
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT))
+        .unwrap()
+        .with_verify_received(true);
+    let object_ids = build_object_ids(2);
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2];
+
+    let (mut peer, mut socket) = connect_pair().await;
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), object_ids.clone(), true);
+    receiver.prepare().unwrap();
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    for _ in 0..2 {
+        utils::write_object(&meta, &data, Capabilities::all(), &mut peer)
+            .await
+            .unwrap();
+    }
+    let report = receiver.run(&mut socket, Capabilities::all()).await;
+    report.unwrap().into_result().unwrap();
+
+    // verified objects should remain in the store
+    let pc = build_client();
+    let plasma_object_ids = utils::map_object_ids(&object_ids);
+    assert_eq!(2, pc.contains_many(&plasma_object_ids).unwrap().len());
+    pc.delete_many(&plasma_object_ids).unwrap();
+}
+
+#[tokio::test]
+async fn byte_budget_waits_for_release() {
+    let budget = Arc::new(ByteBudget::new(1000));
+    let first = budget.acquire(800).await;
+    assert_eq!(200, budget.available());
+
+    // the second acquisition has to wait until the first permit is released
+    let mut second = Box::pin(budget.acquire(800));
+    let acquired = tokio::time::timeout(Duration::from_millis(50), &mut second).await;
+    assert!(acquired.is_err());
+    drop(first);
+    let second = tokio::time::timeout(TIMEOUT, second).await.unwrap();
+    assert_eq!(200, budget.available());
+    drop(second);
+
+    // acquisitions larger than the budget take up the entire budget
+    let whole = budget.acquire(5000).await;
+    assert_eq!(0, budget.available());
+    drop(whole);
+    assert_eq!(1000, budget.available());
+}
+
+#[tokio::test]
+#[ignore]
+async fn receiver_budget_serializes_large_objects() {
+    // the budget is large enough for only one of the objects at a time
+    let data = vec![1u8; 1_000_000];
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT))
+        .unwrap()
+        .with_receive_budget(Some(1_500_000));
+    let object_ids = build_object_ids(2);
+
+    let (mut first_peer, mut first_socket) = connect_pair().await;
+    let (mut second_peer, mut second_socket) = connect_pair().await;
+    let peer_addr: PeerAddr = first_socket.peer_addr().unwrap().into();
+    let first = store.build_receiver(peer_addr.clone(), object_ids[..1].to_vec(), true);
+    let second = store.build_receiver(peer_addr, object_ids[1..].to_vec(), true);
+    first.prepare().unwrap();
+    second.prepare().unwrap();
+
+    // the first peer sends only a part of the object data, so the first receiver holds its
+    // part of the budget while waiting for the rest of the data; without capabilities, an
+    // object is written as an 8-byte header followed by its metadata and data
+    let header = 4u64 | (data.len() as u64) << 16;
+    first_peer.write_u8(status_codes::BEGIN).await.unwrap();
+    first_peer.write_u64_le(header).await.unwrap();
+    first_peer.write_all(&[1u8; 4]).await.unwrap();
+    first_peer.write_all(&data[..1000]).await.unwrap();
+    let first_run = tokio::spawn(async move {
+        first
+            .run(&mut first_socket, Capabilities::none())
+            .await
+            .and_then(receiver::ReceiveReport::into_result)
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // the second object is sent in full, but cannot be received until the first one is
+    second_peer.write_u8(status_codes::BEGIN).await.unwrap();
+    second_peer.write_u64_le(header).await.unwrap();
+    second_peer.write_all(&[1u8; 4]).await.unwrap();
+    let second_peer_data = data.clone();
+    let second_write = tokio::spawn(async move {
+        second_peer.write_all(&second_peer_data).await.unwrap();
+        second_peer
+    });
+    let mut second_run = Box::pin(second.run(&mut second_socket, Capabilities::none()));
+    let received = tokio::time::timeout(Duration::from_millis(100), &mut second_run).await;
+    assert!(received.is_err());
+
+    // once the first object is complete, the second one should be received as well
+    first_peer.write_all(&data[1000..]).await.unwrap();
+    first_run.await.unwrap().unwrap();
+    let report = tokio::time::timeout(TIMEOUT, second_run).await.unwrap();
+    report.unwrap().into_result().unwrap();
+    second_write.await.unwrap();
+
+    let pc = build_client();
+    pc.delete_many(&utils::map_object_ids(&object_ids)).unwrap();
+}
+
+// EXPIRY TESTS
+// ================================================================================================
+
+#[test]
+fn expiry_tracker_takes_expired_objects() {
+    let tracker = ExpiryTracker::new(Duration::from_millis(100));
+    assert_eq!(Duration::from_millis(100), tracker.check_interval());
+    let object_ids = build_object_ids(3);
+    let now = std::time::Instant::now();
+    tracker.track_until(&object_ids[..1], now + Duration::from_millis(20));
+    tracker.track_until(&object_ids[1..2], now);
+    tracker.track_until(&object_ids[2..], now + Duration::from_millis(10));
+
+    // objects are returned in the order of their deadlines, and only once
+    assert_eq!(vec![object_ids[1]], tracker.take_expired(now));
+    assert!(tracker.take_expired(now).is_empty());
+    assert_eq!(
+        vec![object_ids[2], object_ids[0]],
+        tracker.take_expired(now + Duration::from_millis(20))
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn store_deletes_expired_objects() {
+    let ttl = Duration::from_millis(100);
+    let store = Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT))
+        .unwrap()
+        .with_object_ttl(Some(ttl));
+    let object_ids = build_object_ids(2);
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2];
+
+    // receive two objects from a peer
+    let (mut peer, mut socket) = connect_pair().await;
+    let receiver =
+        store.build_receiver(socket.peer_addr().unwrap().into(), object_ids.clone(), true);
+    receiver.prepare().unwrap();
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    for _ in 0..2 {
+        utils::write_object(&meta, &data, Capabilities::none(), &mut peer)
+            .await
+            .unwrap();
+    }
+    let report = receiver.run(&mut socket, Capabilities::none()).await;
+    report.unwrap().into_result().unwrap();
+    assert_eq!(0, store.expire_objects().unwrap());
+
+    // once the objects expire, only the object which is not in use should be deleted
+    let pc = build_client();
+    let oids: Vec<plasma_store::ObjectId> = object_ids.iter().map(|&oid| oid.into()).collect();
+    let ob = pc.get(oids[1].clone(), Some(TIMEOUT)).unwrap().unwrap();
+    tokio::time::sleep(ttl).await;
+    assert_eq!(1, store.expire_objects().unwrap());
+    assert!(!pc.contains(&oids[0]).unwrap());
+    assert!(pc.contains(&oids[1]).unwrap());
+
+    // once the object is released, deleting it should be retried
+    drop(ob);
+    assert_eq!(0, store.expire_objects().unwrap());
+    tokio::time::sleep(ttl).await;
+    assert_eq!(1, store.expire_objects().unwrap());
+    assert!(!pc.contains(&oids[1]).unwrap());
+}
+
+// DISPATCHER TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn dispatcher_retries_peer_connection() {
+    // reserve a port and release it so that the first connection attempt is refused
+    let address = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    // start listening on the port after the first attempt has failed, but before the retry
+    let peer = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let listener = TcpListener::bind(address).await.unwrap();
+        listener.accept().await.unwrap();
+    });
+
+    let retry = ConnectRetry {
+        max_retries: 3,
+        initial_backoff: Duration::from_millis(200),
+    };
+    let socket = dispatcher::connect_with_retry(&address.into(), 1, retry)
+        .await
+        .unwrap();
+    assert_eq!(PeerAddr::from(address), socket.peer_addr().unwrap());
+    peer.await.unwrap();
+}
+
+#[tokio::test]
+async fn dispatcher_gives_up_on_peer_connection() {
+    let address = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let retry = ConnectRetry {
+        max_retries: 2,
+        initial_backoff: Duration::from_millis(10),
+    };
+    let result = dispatcher::connect_with_retry(&address.into(), 1, retry).await;
+    assert!(matches!(
+        result,
+        Err(errors::SyncError::PeerConnectionFailed(peer, 1, _)) if peer == PeerAddr::from(address)
+    ));
+}
+
+#[tokio::test]
+async fn dispatcher_sets_keepalive() {
+    let (socket, _) = connect_pair().await;
+    let interval = Duration::from_secs(5);
+    dispatcher::set_keepalive(&socket, interval).unwrap();
+
+    let socket = socket2::SockRef::from(&socket);
+    assert!(socket.keepalive().unwrap());
+    assert_eq!(interval, socket.tcp_keepalive_time().unwrap());
+    assert_eq!(interval, socket.tcp_keepalive_interval().unwrap());
+}
+
+#[tokio::test]
+async fn dispatcher_connection_error_describes_peer_request() {
+    let address = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let retry = ConnectRetry {
+        max_retries: 0,
+        initial_backoff: Duration::from_millis(10),
+    };
+    let err = dispatcher::connect_with_retry(&address.into(), 3, retry)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains(&address.to_string()), "{}", err);
+    assert!(err.contains("3 object(s)"), "{}", err);
+}
+
+#[tokio::test]
+#[ignore]
+async fn dispatcher_first_wins_falls_back_to_next_peer() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+    let oid = plasma_store::ObjectId::rand();
+    let object_id: ObjectId = oid.to_bytes().try_into().unwrap();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2];
+
+    // the first peer refuses connections
+    let unreachable = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    // the second peer serves the object
+    let serving = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let serving_address = serving.local_addr().unwrap();
+    let peer = tokio::spawn(async move {
+        let (mut socket, _) = serving.accept().await.unwrap();
+        let capabilities = handshake::accept(&mut socket, Capabilities::all(), None)
+            .await
+            .unwrap()
+            .unwrap();
+        match Request::read_from(&mut socket).await.unwrap().unwrap() {
+            Request::Copy(object_ids) => assert_eq!(vec![object_id], object_ids),
+            request => panic!("expected a COPY request, but received {}", request),
+        }
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        utils::write_object(&meta, &data, capabilities, &mut socket)
+            .await
+            .unwrap();
+    });
+
+    // the third peer should never be contacted because the object has already been received
+    let redundant = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let redundant_address = redundant.local_addr().unwrap();
+
+    let (client, server) = serve_connection(store).await;
+    let mut client = client.unwrap();
+    let requests = vec![unreachable, serving_address, redundant_address]
+        .into_iter()
+        .map(|from| PeerRequest::Copy {
+            from: from.into(),
+            objects: vec![object_id],
+        })
+        .collect();
+    client.sync_first_wins(requests).await.unwrap();
+    peer.await.unwrap();
+
+    let accepted = tokio::time::timeout(Duration::from_millis(100), redundant.accept()).await;
+    assert!(accepted.is_err());
+    let pc = build_client();
+    assert!(pc.contains(&oid).unwrap());
+
+    drop(client);
+    server.await.unwrap().unwrap();
+    pc.delete(&oid).unwrap();
+}
+
+#[test]
+fn dispatcher_coalesces_requests_to_same_peer() {
+    let first: PeerAddr = "127.0.0.1:2021".parse().unwrap();
+    let second: PeerAddr = "127.0.0.1:2022".parse().unwrap();
+    let object_ids = build_object_ids(4);
+    let requests = vec![
+        PeerRequest::Copy {
+            from: first.clone(),
+            objects: vec![object_ids[0]],
+        },
+        PeerRequest::Copy {
+            from: second.clone(),
+            objects: vec![object_ids[1]],
+        },
+        PeerRequest::Take {
+            from: first.clone(),
+            objects: vec![object_ids[2]],
+        },
+        PeerRequest::Copy {
+            from: first.clone(),
+            objects: vec![object_ids[3]],
+        },
+    ];
+
+    // only the COPY requests for the first peer should be merged
+    let (merged, merged_into) = dispatcher::coalesce(requests);
+    assert_eq!(vec![0, 1, 2, 0], merged_into);
+    assert_eq!(3, merged.len());
+    assert!(matches!(&merged[0], PeerRequest::Copy { from, objects }
+        if from == &first && objects == &vec![object_ids[0], object_ids[3]]));
+    assert!(matches!(&merged[1], PeerRequest::Copy { from, .. } if from == &second));
+    assert!(matches!(&merged[2], PeerRequest::Take { from, .. } if from == &first));
+}
+
+#[tokio::test]
+#[ignore]
+async fn dispatcher_opens_one_connection_per_peer() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+    let oids = [
+        plasma_store::ObjectId::rand(),
+        plasma_store::ObjectId::rand(),
+    ];
+    let object_ids: Vec<ObjectId> = oids
+        .iter()
+        .map(|oid| oid.to_bytes().try_into().unwrap())
+        .collect();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2];
+
+    // the peer expects both objects to be requested over a single connection
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let expected = object_ids.clone();
+    let peer = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let capabilities = handshake::accept(&mut socket, Capabilities::all(), None)
+            .await
+            .unwrap()
+            .unwrap();
+        match Request::read_from(&mut socket).await.unwrap().unwrap() {
+            Request::Copy(object_ids) => assert_eq!(expected, object_ids),
+            request => panic!("expected a COPY request, but received {}", request),
+        }
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        for _ in 0..2 {
+            utils::write_object(&meta, &data, capabilities, &mut socket)
+                .await
+                .unwrap();
+        }
+
+        // no other connection should be opened to the peer
+        let accepted = tokio::time::timeout(Duration::from_millis(100), listener.accept()).await;
+        assert!(accepted.is_err());
+    });
+
+    let (client, server) = serve_connection(store).await;
+    let mut client = client.unwrap();
+    let requests = object_ids
+        .iter()
+        .map(|object_id| PeerRequest::Copy {
+            from: address.into(),
+            objects: vec![*object_id],
+        })
+        .collect();
+    client.sync(requests).await.unwrap();
+    peer.await.unwrap();
+
+    let pc = build_client();
+    for oid in oids.iter() {
+        assert!(pc.contains(oid).unwrap());
+        pc.delete(oid).unwrap();
+    }
+
+    drop(client);
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn dispatcher_reuses_pooled_connection() {
+    let store = Arc::new(
+        Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT))
+            .unwrap()
+            .with_connection_pool(1, Duration::from_secs(60)),
+    );
+    let oids = [
+        plasma_store::ObjectId::rand(),
+        plasma_store::ObjectId::rand(),
+    ];
+    let object_ids: Vec<ObjectId> = oids
+        .iter()
+        .map(|oid| oid.to_bytes().try_into().unwrap())
+        .collect();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2];
+
+    // the peer expects both COPY requests to be sent over a single connection
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let expected = object_ids.clone();
+    let peer = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let capabilities = handshake::accept(&mut socket, Capabilities::all(), None)
+            .await
+            .unwrap()
+            .unwrap();
+        for object_id in expected {
+            match Request::read_from(&mut socket).await.unwrap().unwrap() {
+                Request::Copy(object_ids) => assert_eq!(vec![object_id], object_ids),
+                request => panic!("expected a COPY request, but received {}", request),
+            }
+            socket.write_u8(status_codes::BEGIN).await.unwrap();
+            utils::write_object(&meta, &data, capabilities, &mut socket)
+                .await
+                .unwrap();
+        }
+
+        // no other connection should be opened to the peer
+        let accepted = tokio::time::timeout(Duration::from_millis(100), listener.accept()).await;
+        assert!(accepted.is_err());
+    });
+
+    let (client, server) = serve_connection(store.clone()).await;
+    let mut client = client.unwrap();
+    for object_id in object_ids.iter() {
+        let request = PeerRequest::Copy {
+            from: address.into(),
+            objects: vec![*object_id],
+        };
+        client.sync(vec![request]).await.unwrap();
+    }
+    peer.await.unwrap();
+    assert_eq!(
+        1,
+        store.connection_pool().unwrap().num_idle(&address.into())
+    );
+
+    let pc = build_client();
+    for oid in oids.iter() {
+        assert!(pc.contains(oid).unwrap());
+        pc.delete(oid).unwrap();
+    }
+
+    drop(client);
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn dispatcher_receives_objects_into_memory_store() {
+    let memory_store = MemoryStore::default();
+    let store = Arc::new(Store::from_client(
+        memory_store.clone(),
+        "memory",
+        0,
+        Some(TIMEOUT),
+        Some(TIMEOUT),
+    ));
+    let object_ids = build_object_ids(2);
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2];
+
+    // the peer serves both objects over a single connection
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let expected = object_ids.clone();
+    let peer = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let capabilities = handshake::accept(&mut socket, Capabilities::all(), None)
+            .await
+            .unwrap()
+            .unwrap();
+        match Request::read_from(&mut socket).await.unwrap().unwrap() {
+            Request::Copy(object_ids) => assert_eq!(expected, object_ids),
+            request => panic!("expected a COPY request, but received {}", request),
+        }
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        for _ in 0..2 {
+            utils::write_object(&meta, &data, capabilities, &mut socket)
+                .await
+                .unwrap();
+        }
+    });
+
+    let dispatcher = Dispatcher {
+        store: store.clone(),
+        capabilities: Capabilities::all(),
+        connect_retry: ConnectRetry::default(),
+        auth_token: None,
+        nodelay: true,
+        keepalive: None,
+    };
+    let (mut client, server) = connect_pair().await;
+    let request = PeerRequest::Copy {
+        from: address.into(),
+        objects: object_ids.clone(),
+    };
+    let bytes_transferred = dispatcher
+        .run(vec![request], &mut server.into())
+        .await
+        .unwrap();
+    peer.await.unwrap();
+    assert_eq!(status_codes::SUCCESS, client.read_u8().await.unwrap());
+    assert_eq!(2 * (meta.len() + data.len()) as u64, bytes_transferred);
+
+    // the objects should be sealed in the store with the data sent by the peer
+    assert_eq!(
+        vec![true, true],
+        store.contains_objects(&object_ids).unwrap()
+    );
+    for object_id in object_ids.iter() {
+        let oid = plasma_store::ObjectId::from(*object_id);
+        let ob = memory_store.get(oid, None).unwrap().unwrap();
+        assert_eq!((&meta[..], &data[..]), ob.parts());
+    }
+}
+
+#[tokio::test]
+async fn dispatcher_sync_after_prewarm_opens_no_new_connections() {
+    let memory_store = MemoryStore::default();
+    let store = Arc::new(
+        Store::from_client(memory_store, "memory", 0, Some(TIMEOUT), Some(TIMEOUT))
+            .with_connection_pool(1, Duration::from_secs(60)),
+    );
+    let object_id = build_object_ids(1)[0];
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2];
+
+    // the peer serves the object over the connection opened by the prewarm request, and hands
+    // the listener back so that connections opened afterwards can be detected
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let capabilities = handshake::accept(&mut socket, Capabilities::all(), None)
+            .await
+            .unwrap()
+            .unwrap();
+        match Request::read_from(&mut socket).await.unwrap().unwrap() {
+            Request::Copy(object_ids) => assert_eq!(vec![object_id], object_ids),
+            request => panic!("expected a COPY request, but received {}", request),
+        }
+        socket.write_u8(status_codes::BEGIN).await.unwrap();
+        utils::write_object(&meta, &data, capabilities, &mut socket)
+            .await
+            .unwrap();
+        (listener, socket)
+    });
+
+    let dispatcher = Dispatcher {
+        store: store.clone(),
+        capabilities: Capabilities::all(),
+        connect_retry: ConnectRetry::default(),
+        auth_token: None,
+        nodelay: true,
+        keepalive: None,
+    };
+    let (mut client, server) = connect_pair().await;
+    let mut server = server.into();
+
+    // the prewarmed connection should be kept in the pool
+    dispatcher
+        .prewarm(vec![address.into()], &mut server)
+        .await
+        .unwrap();
+    assert_eq!(status_codes::SUCCESS, client.read_u8().await.unwrap());
+    let pool = store.connection_pool().unwrap();
+    assert_eq!(1, pool.num_idle(&address.into()));
+
+    // the SYNC request should reuse the prewarmed connection
+    let request = PeerRequest::Copy {
+        from: address.into(),
+        objects: vec![object_id],
+    };
+    dispatcher.run(vec![request], &mut server).await.unwrap();
+    assert_eq!(status_codes::SUCCESS, client.read_u8().await.unwrap());
+    let (listener, _socket) = peer.await.unwrap();
+    let accepted = tokio::time::timeout(Duration::from_millis(100), listener.accept()).await;
+    assert!(accepted.is_err());
+    assert_eq!(vec![true], store.contains_objects(&[object_id]).unwrap());
+}
+
+#[tokio::test]
+#[ignore]
+async fn dispatcher_pushes_objects_to_peer() {
+    let mut peer_plasma_store = start_plasma_store(PLASMA_PUSH_SOCKET);
+    let oids = [
+        plasma_store::ObjectId::rand(),
+        plasma_store::ObjectId::rand(),
+    ];
+    let object_ids: Vec<ObjectId> = oids
+        .iter()
+        .map(|oid| oid.to_bytes().try_into().unwrap())
+        .collect();
+    let pc = build_client();
+    for oid in oids.iter() {
+        pc.create_and_seal(oid.clone(), &[1u8, 2, 3, 4], &[1u8, 2])
+            .unwrap();
+    }
+
+    // the peer is served by another handler backed by its own plasma store
+    let peer_store =
+        Arc::new(Store::new(PLASMA_PUSH_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer_address = listener.local_addr().unwrap();
+    let peer = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (notify_shutdown, _) = broadcast::channel(1);
+        let (shutdown_complete, _) = mpsc::channel(1);
+        let mut handler = Handler::new(
+            socket.into(),
+            peer_store,
+            Arc::new(Semaphore::new(0)),
+            None,
+            None,
+            Capabilities::all(),
+            ConnectRetry::default(),
+            None,
+            true,
+            None,
+            ConnectionLimits::default(),
+            Shutdown::new(notify_shutdown.subscribe()),
+            shutdown_complete,
+        );
+        handler.run().await
+    });
+
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+    let (client, server) = serve_connection(store).await;
+    let mut client = client.unwrap();
+    let requests = vec![PeerRequest::Push {
+        to: peer_address.into(),
+        objects: object_ids,
+    }];
+    client.sync(requests).await.unwrap();
+    peer.await.unwrap().unwrap();
+
+    // the objects should be in both stores
+    let peer_pc = PlasmaClient::new(PLASMA_PUSH_SOCKET, 0).unwrap();
+    for oid in oids.iter() {
+        assert!(peer_pc.contains(oid).unwrap());
+        assert!(pc.contains(oid).unwrap());
+        pc.delete(oid).unwrap();
+    }
+
+    drop(client);
+    server.await.unwrap().unwrap();
+    peer_plasma_store.kill().unwrap();
+    peer_plasma_store.wait().unwrap();
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_ping_reports_store_health() {
+    let mut plasma_store = start_plasma_store(PLASMA_RESTART_SOCKET);
+    let store =
+        Arc::new(Store::new(PLASMA_RESTART_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // while the plasma store is running, the server should report itself as healthy
+    let (client, server) = serve_connection(store.clone()).await;
+    client.unwrap().ping().await.unwrap();
+    server.await.unwrap().unwrap();
+
+    // once the plasma store is gone, the health check should fail
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+    let (client, server) = serve_connection(store.clone()).await;
+    let result = client.unwrap().ping().await;
+    assert!(matches!(
+        result,
+        Err(ClientError::PingError(PeerResult::PlasmaStoreError))
+    ));
+    assert!(server.await.unwrap().is_err());
+    assert!(!store.is_healthy());
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_enforces_request_limit() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+    let limits = ConnectionLimits {
+        max_requests: Some(2),
+        ..ConnectionLimits::default()
+    };
+    let (client, server) = serve_connection_with_limits(store, limits).await;
+    let mut client = client.unwrap();
+
+    // the first two requests are processed, and the third one is rejected
+    client.ping().await.unwrap();
+    client.ping().await.unwrap();
+    assert!(matches!(
+        client.ping().await,
+        Err(ClientError::PingError(PeerResult::ConnectionLimitExceeded))
+    ));
+
+    // the connection is closed after the rejected request
+    server.await.unwrap().unwrap();
+    assert!(matches!(
+        client.ping().await,
+        Err(ClientError::ConnectionError(_, _))
+    ));
+}
+
+#[tokio::test]
+#[ignore]
+async fn handler_counts_pushed_bytes_towards_byte_limit() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+    let oid = plasma_store::ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    let meta = [1u8, 2];
+
+    let (mut client, socket) = connect_pair().await;
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let (shutdown_complete, _) = mpsc::channel(1);
+    let limits = ConnectionLimits {
+        max_bytes: Some(10),
+        ..ConnectionLimits::default()
+    };
+    let mut handler = Handler::new(
+        socket.into(),
+        store,
+        Arc::new(Semaphore::new(0)),
+        None,
+        None,
+        Capabilities::all(),
+        ConnectRetry::default(),
+        None,
+        true,
+        None,
+        limits,
+        Shutdown::new(notify_shutdown.subscribe()),
+        shutdown_complete,
+    );
+    let server = tokio::spawn(async move { handler.run().await });
+    let capabilities = handshake::initiate(&mut client, Capabilities::none(), None)
+        .await
+        .unwrap();
+
+    // push a single object which uses up the entire byte budget of the connection
+    let object_ids: Vec<ObjectId> = vec![oid.to_bytes().try_into().unwrap()];
+    Request::Push(object_ids)
+        .write_into(&mut client)
+        .await
+        .unwrap();
+    assert_eq!(status_codes::SUCCESS, client.read_u8().await.unwrap());
+    client.write_u8(status_codes::BEGIN).await.unwrap();
+    utils::write_object(&meta, &data, capabilities, &mut client)
+        .await
+        .unwrap();
+    assert_eq!(status_codes::SUCCESS, client.read_u8().await.unwrap());
+
+    // the next request should be rejected, and the connection closed
+    Request::Ping.write_into(&mut client).await.unwrap();
+    assert_eq!(
+        status_codes::CONNECTION_LIMIT_EXCEEDED,
+        client.read_u8().await.unwrap()
+    );
+    server.await.unwrap().unwrap();
+
+    let pc = build_client();
+    assert!(pc.contains(&oid).unwrap());
+    pc.delete(&oid).unwrap();
+}
+
+#[tokio::test]
+#[traced_test]
+#[ignore]
+async fn handler_nests_peer_spans_within_request_spans() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+    let unreachable = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    // request an object from a peer which refuses connections
+    let (client, server) = serve_connection(store).await;
+    let mut client = client.unwrap();
+    let object_id: ObjectId = plasma_store::ObjectId::rand()
+        .to_bytes()
+        .try_into()
+        .unwrap();
+    let requests = vec![PeerRequest::Copy {
+        from: unreachable.into(),
+        objects: vec![object_id],
+    }];
+    assert!(client.sync(requests).await.is_err());
+    drop(client);
+    server.await.unwrap().unwrap();
+
+    // the peer error should be logged within the peer span, which is nested in the span of the
+    // SYNC request, which in turn is nested in the span of the connection
+    let peer_span = format!("peer{{peer={} objects=1}}", unreachable);
+    logs_assert(|lines: &[&str]| {
+        let line = lines
+            .iter()
+            .find(|line| line.contains(&peer_span))
+            .ok_or_else(|| format!("no events logged within {}", peer_span))?;
+        let connection = line.find("connection{peer=127.0.0.1:");
+        let request = line.find("request{id=0 kind=\"SYNC\"}");
+        let peer = line.find(&peer_span);
+        match (connection, request, peer) {
+            (Some(c), Some(r), Some(p)) if c < r && r < p => Ok(()),
+            _ => Err(format!("spans are not nested correctly: {}", line)),
+        }
+    });
+}
+
+// CONNECTION POOL TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn connection_pool_reuses_live_connections() {
+    let pool = ConnectionPool::new(1, Duration::from_secs(60));
+    let (client, server) = connect_pair().await;
+    let (other_client, _other_server) = connect_pair().await;
+    let peer = PeerAddr::from(client.peer_addr().unwrap());
+
+    // once the pool is full, the least recently used connection is closed
+    pool.put(peer.clone(), other_client.into(), Capabilities::none());
+    pool.put(peer.clone(), client.into(), Capabilities::all());
+    assert_eq!(1, pool.num_idle(&peer));
+    let (socket, capabilities) = pool.take(&peer).unwrap();
+    assert_eq!(Capabilities::all(), capabilities);
+    assert_eq!(0, pool.num_idle(&peer));
+    assert!(pool.take(&peer).is_none());
+
+    // connections closed by the peer are not reused
+    pool.put(peer.clone(), socket, capabilities);
+    drop(server);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(pool.take(&peer).is_none());
+    assert_eq!(0, pool.num_idle(&peer));
+}
+
+#[tokio::test]
+async fn connection_pool_discards_expired_connections() {
+    let pool = ConnectionPool::new(1, Duration::from_millis(10));
+    let (client, _server) = connect_pair().await;
+    let peer = PeerAddr::from(client.peer_addr().unwrap());
+
+    pool.put(peer.clone(), client.into(), Capabilities::all());
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(pool.take(&peer).is_none());
+}
+
+// LISTENER TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn listener_rejects_connection_when_busy() {
+    // no permits are available, so the connection should be rejected after the grace period
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let limit_connections = Semaphore::new(0);
+        let grace_period = Duration::from_millis(50);
+        listener::admit(socket.into(), &limit_connections, grace_period).await
+    });
+
+    let result = Client::connect(address).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::HandshakeError(
+            errors::HandshakeError::ServerBusy
+        ))
+    ));
+    assert!(server.await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn listener_admits_connection_when_permit_is_released() {
+    let (_client, server) = connect_pair().await;
+
+    // a permit released within the grace period should be given to the waiting connection
+    let limit_connections = Arc::new(Semaphore::new(0));
+    let releaser = limit_connections.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        releaser.add_permits(1);
+    });
+
+    let admitted = listener::admit(server.into(), &limit_connections, TIMEOUT).await;
+    assert!(admitted.is_some());
+    assert_eq!(0, limit_connections.available_permits());
+}
+
+#[tokio::test]
+#[ignore]
+async fn listener_rejects_busy_connections_without_blocking() {
+    // reserve a port for the server and start accepting connections on it
+    let address = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let port = address.port().to_string();
+    let grace_period = Duration::from_millis(500);
+    let options = ServerOptions::from_iter(&[
+        "plasma-stream-server",
+        "--port",
+        port.as_str(),
+        "--max-connections",
+        "1",
+        "--busy-grace-period",
+        "500",
+        "--plasma-socket",
+        PLASMA_SOCKET,
+    ]);
+    let mut listener = Listener::new(options).await.unwrap();
+    let server = tokio::spawn(async move { listener.start().await });
+
+    // the first client takes the only connection permit
+    let first = Client::connect(address).await.unwrap();
+
+    // a client which resets the connection while waiting for a permit should not take the
+    // server down
+    let socket = TcpStream::connect(address).await.unwrap();
+    socket.set_zero_linger().unwrap();
+    drop(socket);
+
+    // clients over the limit should wait for a permit at the same time, and thus should all be
+    // rejected after a single grace period
+    let started = std::time::Instant::now();
+    let clients: Vec<_> = (0..3)
+        .map(|_| tokio::spawn(Client::connect(address)))
+        .collect();
+    for client in clients {
+        assert!(matches!(
+            client.await.unwrap(),
+            Err(ClientError::HandshakeError(
+                errors::HandshakeError::ServerBusy
+            ))
+        ));
+    }
+    assert!(started.elapsed() < 2 * grace_period);
+
+    // once the first client goes away, the server should admit new connections
+    drop(first);
+    let mut client = Client::connect(address).await.unwrap();
+    client.ping().await.unwrap();
+    assert!(!server.is_finished());
+    server.abort();
+}
+
+// METRICS TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn metrics_endpoint_serves_counters() {
+    let metrics = Arc::new(Metrics::default());
+    metrics.object_sent(16);
+    metrics.object_sent(4);
+    metrics.connection_opened();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    metrics::spawn_endpoint(listener, metrics.clone());
+
+    let response = http_get(address, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("\nplasma_stream_objects_sent_total 2\n"));
+    assert!(response.contains("\nplasma_stream_bytes_sent_total 20\n"));
+    assert!(response.contains("\nplasma_stream_active_connections 1\n"));
+    assert!(response.contains("# TYPE plasma_stream_active_connections gauge\n"));
+
+    // counters updated after the first scrape should be reflected in the next one
+    metrics.connection_closed();
+    let response = http_get(address, "/metrics").await;
+    assert!(response.contains("\nplasma_stream_active_connections 0\n"));
+
+    // other paths should not be served
+    let response = http_get(address, "/").await;
+    assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+}
+
+#[tokio::test]
+#[ignore]
+async fn metrics_count_transferred_objects() {
+    let store = Arc::new(Store::new(PLASMA_SOCKET, 0, Some(TIMEOUT), Some(TIMEOUT)).unwrap());
+
+    // put an object into the store
+    let pc = build_client();
+    let oid = plasma_store::ObjectId::rand();
+    let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let meta = [1u8, 2, 3, 4];
+    pc.create_and_seal(oid.clone(), &data, &meta).unwrap();
+
+    // expose metrics of the store
+    let metrics_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let metrics_address = metrics_listener.local_addr().unwrap();
+    metrics::spawn_endpoint(metrics_listener, store.metrics().clone());
+
+    // serve a single connection and copy the object over it
+    let (client, socket) = connect_pair().await;
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let (shutdown_complete, _) = mpsc::channel(1);
+    let mut handler = Handler::new(
+        socket.into(),
+        store.clone(),
+        Arc::new(Semaphore::new(0)),
+        None,
+        None,
+        Capabilities::all(),
+        ConnectRetry::default(),
+        None,
+        true,
+        None,
+        ConnectionLimits::default(),
+        Shutdown::new(notify_shutdown.subscribe()),
+        shutdown_complete,
+    );
+    let server = tokio::spawn(async move { handler.run().await.unwrap() });
+    let mut client = Client::from_stream(client, Default::default())
+        .await
+        .unwrap();
+    let object_ids: Vec<ObjectId> = vec![oid.to_bytes().try_into().unwrap()];
+    client.copy(&object_ids).await.unwrap();
+
+    // while the connection is open, it should be reported as active
+    let response = http_get(metrics_address, "/metrics").await;
+    assert!(response.contains("\nplasma_stream_objects_sent_total 1\n"));
+    assert!(response.contains("\nplasma_stream_bytes_sent_total 20\n"));
+    assert!(response.contains("\nplasma_stream_active_connections 1\n"));
+
+    drop(client);
+    server.await.unwrap();
+    let response = http_get(metrics_address, "/metrics").await;
+    assert!(response.contains("\nplasma_stream_active_connections 0\n"));
+
+    pc.delete(&oid).unwrap();
+}
+
+// STORE TESTS
+// ================================================================================================
+// tests below start and stop their own plasma store server; this requires plasma-store-server
+// executable to be on the PATH
+
+#[tokio::test]
+#[ignore]
+async fn store_reconnects_after_plasma_store_restart() {
+    let mut plasma_store = start_plasma_store(PLASMA_RESTART_SOCKET);
+    let store = Store::new(PLASMA_RESTART_SOCKET, 10, Some(TIMEOUT), Some(TIMEOUT)).unwrap();
+    assert!(store.is_healthy());
+
+    // stop the plasma store; the next request should detect that the connection was lost
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+
+    let (_peer, mut socket) = connect_pair().await;
+    let peer_addr = socket.peer_addr().unwrap();
+    let oid: ObjectId = plasma_store::ObjectId::rand()
+        .to_bytes()
+        .try_into()
+        .unwrap();
+    let result = store
+        .build_sender(peer_addr.into(), vec![oid], false, None)
+        .run(&mut socket, Capabilities::none())
+        .await;
+    assert!(matches!(
+        result,
+        Err(errors::ObjectSendError::StoreError(
+            _,
+            PlasmaError::StoreDisconnected(_)
+        ))
+    ));
+    assert!(!store.is_healthy());
+
+    // while the store is down, requests should fail fast without reconnecting
+    assert!(store.reconnect_if_unhealthy().is_err());
+    assert!(!store.is_healthy());
+
+    // restart the plasma store and make sure the store recovers
+    let mut plasma_store = start_plasma_store(PLASMA_RESTART_SOCKET);
+    store.reconnect_if_unhealthy().unwrap();
+    assert!(store.is_healthy());
+
+    // requests should now be served by the new connection
+    let (_peer, mut socket) = connect_pair().await;
+    let result = store
+        .build_sender(peer_addr.into(), vec![oid], false, None)
+        .run(&mut socket, Capabilities::none())
+        .await;
+    assert!(matches!(
+        result,
+        Err(errors::ObjectSendError::ObjectsNotFound(_, _))
+    ));
+
+    plasma_store.kill().unwrap();
+    plasma_store.wait().unwrap();
+}
+
+// IN-MEMORY OBJECT STORE
+// ================================================================================================
+
+/// An `ObjectStore` which keeps objects in memory, so that server internals can be tested
+/// without a running Plasma Store; all clones of the store share the same objects. Objects are
+/// never evicted, and retrieving objects does not wait for them to be sealed. Same as in Plasma
+/// Store, unsealed objects are not deleted; they can only be aborted.
+#[derive(Debug, Clone, Default)]
+struct MemoryStore {
+    objects: Arc<Mutex<FxHashMap<ObjectId, MemoryObject>>>,
+}
+
+/// An object held in a `MemoryStore`; objects retrieved from or created in the store are
+/// copies, and created objects are written back into the store when they are sealed.
+#[derive(Debug, Clone)]
+struct MemoryObject {
+    id: plasma_store::ObjectId,
+    meta: Vec<u8>,
+    data: Vec<u8>,
+    sealed: bool,
+    store: Option<MemoryStore>,
+}
+
+impl ObjectStore for MemoryStore {
+    type Object<'a> = MemoryObject;
+
+    fn connect(socket: &str, _num_retries: u32) -> std::result::Result<Self, PlasmaError> {
+        Err(PlasmaError::StoreDisconnected(format!(
+            "cannot reconnect to in-memory store {}",
+            socket
+        )))
+    }
+
+    fn get(
+        &self,
+        oid: plasma_store::ObjectId,
+        _timeout: Option<Duration>,
+    ) -> std::result::Result<Option<MemoryObject>, PlasmaError> {
+        let key: ObjectId = oid.to_bytes().try_into().unwrap();
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.get(&key).filter(|ob| ob.sealed).cloned())
+    }
+
+    fn get_many(
+        &self,
+        object_ids: &[plasma_store::ObjectId],
+        timeout: Option<Duration>,
+    ) -> std::result::Result<Vec<Option<MemoryObject>>, PlasmaError> {
+        object_ids
+            .iter()
+            .map(|oid| ObjectStore::get(self, oid.clone(), timeout))
+            .collect()
+    }
+
+    fn create(
+        &self,
+        oid: plasma_store::ObjectId,
+        data_size: usize,
+        meta: &[u8],
+    ) -> std::result::Result<MemoryObject, PlasmaError> {
+        let key: ObjectId = oid.to_bytes().try_into().unwrap();
+        let mut objects = self.objects.lock().unwrap();
+        if objects.contains_key(&key) {
+            return Err(PlasmaError::AlreadyExists);
+        }
+        let ob = MemoryObject {
+            id: oid,
+            meta: meta.to_vec(),
+            data: vec![0u8; data_size],
+            sealed: false,
+            store: None,
+        };
+        objects.insert(key, ob.clone());
+        Ok(MemoryObject {
+            store: Some(self.clone()),
+            ..ob
+        })
+    }
+
+    fn evict(&self, _num_bytes: usize) -> std::result::Result<usize, PlasmaError> {
+        Ok(0)
+    }
+
+    fn contains_many(
+        &self,
+        object_ids: &[plasma_store::ObjectId],
+    ) -> std::result::Result<Vec<plasma_store::ObjectId>, PlasmaError> {
+        let objects = self.objects.lock().unwrap();
+        Ok(object_ids
+            .iter()
+            .filter(|oid| {
+                let key: ObjectId = oid.to_bytes().try_into().unwrap();
+                matches!(objects.get(&key), Some(ob) if ob.sealed)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn delete_many(
+        &self,
+        object_ids: &[plasma_store::ObjectId],
+    ) -> std::result::Result<(), PlasmaError> {
+        let mut objects = self.objects.lock().unwrap();
+        for oid in object_ids {
+            let key: ObjectId = oid.to_bytes().try_into().unwrap();
+            if matches!(objects.get(&key), Some(ob) if ob.sealed) {
+                objects.remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> std::result::Result<Vec<ObjectInfo>, PlasmaError> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects
+            .values()
+            .map(|ob| ObjectInfo {
+                id: ob.id.clone(),
+                data_size: ob.data.len(),
+                metadata_size: ob.meta.len(),
+                state: if ob.sealed {
+                    ObjectState::Sealed
+                } else {
+                    ObjectState::Created
+                },
+                digest: [0u8; DIGEST_SIZE],
+            })
+            .collect())
+    }
+
+    fn memory_usage(&self) -> std::result::Result<MemoryUsage, PlasmaError> {
+        let objects = self.objects.lock().unwrap();
+        Ok(MemoryUsage {
+            capacity_bytes: usize::MAX,
+            used_bytes: objects.values().map(StoredObject::size).sum(),
+            num_objects: objects.len(),
+        })
+    }
+}
+
+impl StoredObject for MemoryObject {
+    fn id(&self) -> &plasma_store::ObjectId {
+        &self.id
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn meta(&self) -> &[u8] {
+        &self.meta
+    }
+
+    fn data_mut(&mut self) -> std::result::Result<&mut [u8], PlasmaError> {
+        match self.sealed {
+            true => Err(PlasmaError::NotMutable),
+            false => Ok(&mut self.data),
+        }
+    }
+
+    fn seal(&mut self) -> std::result::Result<(), PlasmaError> {
+        let store = self.store.take().ok_or(PlasmaError::AlreadySealed)?;
+        self.sealed = true;
+        let key: ObjectId = self.id.to_bytes().try_into().unwrap();
+        let mut objects = store.objects.lock().unwrap();
+        match objects.get_mut(&key) {
+            Some(ob) => {
+                *ob = MemoryObject {
+                    store: None,
+                    ..self.clone()
+                };
+                Ok(())
+            }
+            None => Err(PlasmaError::UnknownError(
+                StatusCode::KeyError,
+                format!("object {} is not in the store", self.id.to_hex()),
+            )),
+        }
+    }
+
+    fn release(self) -> std::result::Result<(), PlasmaError> {
+        Ok(())
+    }
+
+    fn abort_in_place(&mut self) -> std::result::Result<(), PlasmaError> {
+        let store = self.store.take().ok_or(PlasmaError::NotMutable)?;
+        let key: ObjectId = self.id.to_bytes().try_into().unwrap();
+        store.objects.lock().unwrap().remove(&key);
+        Ok(())
+    }
+}
+
+impl fmt::Display for MemoryObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+fn build_client() -> PlasmaClient {
+    PlasmaClient::new(PLASMA_SOCKET, 0).unwrap()
+}
+
+/// Starts a plasma store server listening on the specified socket.
+fn start_plasma_store(socket: &str) -> Child {
+    let child = Command::new("plasma-store-server")
+        .args(["-m", "10000000", "-s", socket])
+        .spawn()
+        .unwrap();
+    // give the server some time to start listening on the socket
+    thread::sleep(Duration::from_millis(500));
+    child
+}
+
+/// Receives the specified objects from a peer which sends all but the last object correctly
+/// and then sends a zero-length object instead of the last one.
+async fn receive_with_failure(
+    store: &Store,
+    object_ids: &[ObjectId],
+    cleanup_on_error: bool,
+) -> receiver::ReceiveReport {
+    let (mut peer, mut socket) = connect_pair().await;
+    let peer_addr = socket.peer_addr().unwrap().into();
+    let receiver = store.build_receiver(peer_addr, object_ids.to_vec(), cleanup_on_error);
+    receiver.prepare().unwrap();
+
+    peer.write_u8(status_codes::BEGIN).await.unwrap();
+    for _ in 1..object_ids.len() {
+        let header = 4u64 | (8u64 << 16);
+        peer.write_u64_le(header).await.unwrap();
+        peer.write_all(&[1u8; 12]).await.unwrap();
+    }
+    peer.write_u64_le(0).await.unwrap();
+
+    receiver
+        .run(&mut socket, Capabilities::none())
+        .await
+        .unwrap()
+}
+
+fn build_object_ids(n: usize) -> Vec<ObjectId> {
+    (0..n)
+        .map(|_| {
+            plasma_store::ObjectId::rand()
+                .to_bytes()
+                .try_into()
+                .unwrap()
+        })
+        .collect()
+}
+
+/// A writer which accepts the specified number of bytes and fails all writes afterwards; this
+/// emulates a connection which is closed by the peer in the middle of a transfer.
+struct FailingWriter {
+    remaining: usize,
+}
+
+impl AsyncWrite for FailingWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        if self.remaining == 0 {
+            return Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into()));
+        }
+        let n = buf.len().min(self.remaining);
+        self.remaining -= n;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Connects a client to a handler which serves a single connection using the specified store.
+/// The returned handle resolves to the result of running the handler.
+async fn serve_connection(
+    store: Arc<Store>,
+) -> (
+    std::result::Result<Client, ClientError>,
+    tokio::task::JoinHandle<plasma_stream::Result<()>>,
+) {
+    serve_connection_with_limits(store, ConnectionLimits::default()).await
+}
+
+/// Same as `serve_connection()`, but the connection is subject to the specified limits.
+async fn serve_connection_with_limits(
+    store: Arc<Store>,
+    limits: ConnectionLimits,
+) -> (
+    std::result::Result<Client, ClientError>,
+    tokio::task::JoinHandle<plasma_stream::Result<()>>,
+) {
+    let (client, socket) = connect_pair().await;
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let (shutdown_complete, _) = mpsc::channel(1);
+    let mut handler = Handler::new(
+        socket.into(),
+        store,
+        Arc::new(Semaphore::new(0)),
+        None,
+        None,
+        Capabilities::all(),
+        ConnectRetry::default(),
+        None,
+        true,
+        None,
+        limits,
+        Shutdown::new(notify_shutdown.subscribe()),
+        shutdown_complete,
+    );
+    let server = tokio::spawn(async move { handler.run().await });
+    let client = Client::from_stream(client, Default::default()).await;
+    (client, server)
+}
+
+/// Sends an HTTP GET request for the specified path and returns the entire response.
+async fn http_get(address: std::net::SocketAddr, path: &str) -> String {
+    let mut socket = TcpStream::connect(address).await.unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+    socket.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    socket.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+/// Returns a pair of connected sockets; the first socket is the client side of the connection.
+async fn connect_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (client, server)
+}
@@ -0,0 +1,694 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use super::*;
+use handshake::{AuthToken, Capabilities};
+use std::{convert::TryInto, io::ErrorKind, net::SocketAddr};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+// PEER REQUEST TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn peer_request_ipv4_roundtrip() {
+    let addresses = [
+        "127.0.0.1:2021",
+        "192.168.1.1:2022",
+        "10.0.0.255:80",
+        "1.2.3.4:65535",
+    ];
+    for address in addresses.iter() {
+        let from: SocketAddr = address.parse().unwrap();
+        assert_eq!(PeerAddr::from(from), roundtrip_peer_addr(from.into()).await);
+    }
+}
+
+#[tokio::test]
+async fn peer_request_ipv6_roundtrip() {
+    let addresses = [
+        "[::1]:2021",
+        "[2001:db8::ff00:42:8329]:2022",
+        "[fe80::1]:65535",
+    ];
+    for address in addresses.iter() {
+        let from: SocketAddr = address.parse().unwrap();
+        assert_eq!(PeerAddr::from(from), roundtrip_peer_addr(from.into()).await);
+    }
+}
+
+#[tokio::test]
+async fn peer_request_hostname_roundtrip() {
+    let from: PeerAddr = "localhost:2021".parse().unwrap();
+    assert_eq!(PeerAddr::Host(String::from("localhost"), 2021), from);
+    assert_eq!(from, roundtrip_peer_addr(from.clone()).await);
+
+    // numeric addresses should still be parsed as socket addresses
+    let from: PeerAddr = "127.0.0.1:2021".parse().unwrap();
+    assert!(matches!(from, PeerAddr::Socket(_)));
+
+    // addresses without a port should be rejected
+    assert!("localhost".parse::<PeerAddr>().is_err());
+}
+
+#[tokio::test]
+async fn peer_request_unix_socket_roundtrip() {
+    let from: PeerAddr = "unix:/tmp/plasma-stream.sock".parse().unwrap();
+    assert_eq!(PeerAddr::Unix("/tmp/plasma-stream.sock".into()), from);
+    assert_eq!("unix:/tmp/plasma-stream.sock", from.to_string());
+    assert_eq!(from, roundtrip_peer_addr(from.clone()).await);
+
+    // empty paths and paths which do not fit into a socket address should be rejected
+    assert!("unix:".parse::<PeerAddr>().is_err());
+    let path = format!("unix:/{}", "a".repeat(107));
+    assert!(path.parse::<PeerAddr>().is_err());
+}
+
+#[tokio::test]
+async fn peer_addr_resolve_localhost() {
+    let addr = PeerAddr::Host(String::from("localhost"), 2021);
+    let resolved = addr.resolve().await.unwrap();
+    assert!(resolved.ip().is_loopback());
+    assert_eq!(2021, resolved.port());
+
+    // hostnames which cannot be resolved should result in an error
+    let addr = PeerAddr::Host(String::from("plasma-stream.invalid"), 2021);
+    assert!(addr.resolve().await.is_err());
+}
+
+// HANDSHAKE TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn handshake_matching_version() {
+    let (mut client, mut server) = connect_pair().await;
+    let server = tokio::spawn(async move {
+        handshake::accept(&mut server, Capabilities::all(), None)
+            .await
+            .unwrap()
+    });
+    let negotiated = handshake::initiate(&mut client, Capabilities::all(), None)
+        .await
+        .unwrap();
+    assert_eq!(Capabilities::all(), negotiated);
+    assert_eq!(Some(Capabilities::all()), server.await.unwrap());
+}
+
+#[tokio::test]
+async fn handshake_capabilities_fallback() {
+    // a server which does not support checksums should not enable them for the connection
+    let (mut client, mut server) = connect_pair().await;
+    let server = tokio::spawn(async move {
+        handshake::accept(&mut server, Capabilities::none(), None)
+            .await
+            .unwrap()
+    });
+    let negotiated = handshake::initiate(&mut client, Capabilities::all(), None)
+        .await
+        .unwrap();
+    assert!(!negotiated.checksums());
+    assert_eq!(Some(Capabilities::none()), server.await.unwrap());
+}
+
+#[tokio::test]
+async fn handshake_version_mismatch() {
+    let (mut client, mut server) = connect_pair().await;
+    let server =
+        tokio::spawn(
+            async move { handshake::accept(&mut server, Capabilities::all(), None).await },
+        );
+
+    // a client speaking a different version of the protocol should get a distinct status code
+    // followed by the version spoken by the server, rather than a parse failure
+    client.write_u8(PROTOCOL_VERSION + 1).await.unwrap();
+    assert_eq!(
+        status_codes::PROTOCOL_VERSION_MISMATCH,
+        client.read_u8().await.unwrap()
+    );
+    assert_eq!(PROTOCOL_VERSION, client.read_u8().await.unwrap());
+
+    match server.await.unwrap() {
+        Err(errors::HandshakeError::VersionMismatch { local, remote }) => {
+            assert_eq!(PROTOCOL_VERSION, local);
+            assert_eq!(PROTOCOL_VERSION + 1, remote);
+        }
+        result => panic!("expected version mismatch, but got {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn handshake_version_mismatch_reported_to_client() {
+    let (mut client, mut server) = connect_pair().await;
+
+    // respond to the handshake as a server speaking a newer version of the protocol would
+    let server = tokio::spawn(async move {
+        assert_eq!(PROTOCOL_VERSION, server.read_u8().await.unwrap());
+        server
+            .write_all(&[
+                status_codes::PROTOCOL_VERSION_MISMATCH,
+                PROTOCOL_VERSION + 1,
+            ])
+            .await
+            .unwrap();
+    });
+
+    let result = handshake::initiate(&mut client, Capabilities::all(), None).await;
+    assert!(matches!(
+        result,
+        Err(errors::HandshakeError::VersionMismatch { local, remote })
+            if local == PROTOCOL_VERSION && remote == PROTOCOL_VERSION + 1
+    ));
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn handshake_auth_token_accepted() {
+    let token = AuthToken::new([7; AUTH_TOKEN_BYTES]);
+    let server_token = token.clone();
+    let (mut client, mut server) = connect_pair().await;
+    let server = tokio::spawn(async move {
+        handshake::accept(&mut server, Capabilities::all(), Some(&server_token))
+            .await
+            .unwrap()
+    });
+    let negotiated = handshake::initiate(&mut client, Capabilities::all(), Some(&token))
+        .await
+        .unwrap();
+    assert_eq!(Capabilities::all(), negotiated);
+    assert_eq!(Some(Capabilities::all()), server.await.unwrap());
+}
+
+#[tokio::test]
+async fn handshake_auth_token_rejected() {
+    let server_token = AuthToken::new([7; AUTH_TOKEN_BYTES]);
+    for client_token in [None, Some(AuthToken::new([8; AUTH_TOKEN_BYTES]))] {
+        let (mut client, mut server) = connect_pair().await;
+        let server_token = server_token.clone();
+        let server = tokio::spawn(async move {
+            handshake::accept(&mut server, Capabilities::all(), Some(&server_token)).await
+        });
+
+        // both sides should report the failure, and the client should get a distinct error
+        let result =
+            handshake::initiate(&mut client, Capabilities::all(), client_token.as_ref()).await;
+        assert!(matches!(result, Err(errors::HandshakeError::AuthFailed)));
+        assert!(matches!(
+            server.await.unwrap(),
+            Err(errors::HandshakeError::AuthFailed)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn handshake_auth_token_ignored_when_not_required() {
+    let token = AuthToken::new([7; AUTH_TOKEN_BYTES]);
+    let (mut client, mut server) = connect_pair().await;
+    let server = tokio::spawn(async move {
+        handshake::accept(&mut server, Capabilities::all(), None)
+            .await
+            .unwrap()
+    });
+    handshake::initiate(&mut client, Capabilities::all(), Some(&token))
+        .await
+        .unwrap();
+    assert_eq!(Some(Capabilities::all()), server.await.unwrap());
+}
+
+#[test]
+fn auth_token_from_str() {
+    let hex = "0102030405060708091011121314151617181920212223242526272829303132";
+    let token: AuthToken = hex.parse().unwrap();
+    assert!(token.matches(&hex::decode(hex).unwrap().try_into().unwrap()));
+    assert!(!token.matches(&[0; AUTH_TOKEN_BYTES]));
+
+    // tokens of wrong length or with non-hex characters should be rejected
+    assert!("0102".parse::<AuthToken>().is_err());
+    assert!(hex.replace('0', "g").parse::<AuthToken>().is_err());
+
+    // the token should not be exposed via debug output
+    assert_eq!("AuthToken(..)", format!("{:?}", token));
+}
+
+// REQUEST TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn request_large_object_id_list_roundtrip() {
+    // build a list which does not fit into a 16-bit length prefix
+    let object_ids: Vec<ObjectId> = (0..100_000u32)
+        .map(|i| {
+            let mut oid = [0u8; OBJECT_ID_BYTES];
+            oid[..4].copy_from_slice(&i.to_le_bytes());
+            oid
+        })
+        .collect();
+
+    let (mut client, mut server) = connect_pair().await;
+    let request = Request::Copy(object_ids.clone());
+    request.validate().unwrap();
+
+    // write the request concurrently so that it does not block on a full socket buffer
+    let writer = tokio::spawn(async move { request.write_into(&mut client).await.unwrap() });
+    match Request::read_from(&mut server).await.unwrap().unwrap() {
+        Request::Copy(decoded) => assert_eq!(object_ids, decoded),
+        request => panic!("expected a COPY request, but received {}", request),
+    }
+    writer.await.unwrap();
+}
+
+#[tokio::test]
+async fn request_object_id_list_too_long() {
+    // write only the header of a COPY request which claims to have too many object IDs
+    let (mut client, mut server) = connect_pair().await;
+    client.write_u8(2).await.unwrap();
+    client
+        .write_u32_le(MAX_OBJECT_ID_LIST_LEN as u32 + 1)
+        .await
+        .unwrap();
+
+    // the request should be rejected without waiting for the object IDs
+    let err = Request::read_from(&mut server).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<errors::RequestError>(),
+        Some(errors::RequestError::ObjectIdListTooLong(_))
+    ));
+}
+
+#[test]
+fn request_object_id_list_from_bytes() {
+    // this test does not touch sockets, so it can also be run under Miri to check that the
+    // conversion does not cause undefined behavior: cargo +nightly miri test object_id_list
+    let mut bytes = Vec::with_capacity(OBJECT_ID_BYTES * 3 + 7);
+    for i in 0..3u8 {
+        bytes.extend_from_slice(&[i; OBJECT_ID_BYTES]);
+    }
+    let object_ids = request::to_object_ids(&bytes);
+    assert_eq!(3, object_ids.len());
+    for (i, oid) in object_ids.iter().enumerate() {
+        assert_eq!([i as u8; OBJECT_ID_BYTES], *oid);
+    }
+    drop(object_ids);
+
+    // incomplete object IDs should be ignored
+    bytes.extend_from_slice(&[0xFF; 7]);
+    assert_eq!(3, request::to_object_ids(&bytes).len());
+}
+
+#[test]
+fn request_sync_duplicate_object_ids() {
+    let build_requests = || {
+        vec![
+            PeerRequest::Copy {
+                from: "127.0.0.1:2022".parse().unwrap(),
+                objects: vec![[1u8; OBJECT_ID_BYTES], [2u8; OBJECT_ID_BYTES]],
+            },
+            PeerRequest::Take {
+                from: "127.0.0.1:2023".parse().unwrap(),
+                objects: vec![[1u8; OBJECT_ID_BYTES]],
+            },
+        ]
+    };
+
+    // duplicates across peer requests are rejected only by strict SYNC requests
+    assert!(matches!(
+        Request::Sync(build_requests()).validate(),
+        Err(errors::RequestError::DuplicateObjectIds)
+    ));
+    Request::SyncFirstWins(build_requests()).validate().unwrap();
+
+    // duplicates within a single peer request are rejected by both
+    let requests = vec![PeerRequest::Copy {
+        from: "127.0.0.1:2022".parse().unwrap(),
+        objects: vec![[1u8; OBJECT_ID_BYTES], [1u8; OBJECT_ID_BYTES]],
+    }];
+    assert!(matches!(
+        Request::SyncFirstWins(requests).validate(),
+        Err(errors::RequestError::DuplicateObjectIds)
+    ));
+}
+
+#[tokio::test]
+async fn request_sync_first_wins_roundtrip() {
+    let (mut client, mut server) = connect_pair().await;
+    let request = Request::SyncFirstWins(vec![
+        PeerRequest::Copy {
+            from: "127.0.0.1:2022".parse().unwrap(),
+            objects: vec![[1u8; OBJECT_ID_BYTES]],
+        },
+        PeerRequest::Copy {
+            from: "127.0.0.1:2023".parse().unwrap(),
+            objects: vec![[1u8; OBJECT_ID_BYTES]],
+        },
+    ]);
+    request.write_into(&mut client).await.unwrap();
+
+    match Request::read_from(&mut server).await.unwrap().unwrap() {
+        Request::SyncFirstWins(requests) => {
+            assert_eq!(2, requests.len());
+            for request in requests.iter() {
+                assert_eq!(&[[1u8; OBJECT_ID_BYTES]], request.incoming_objects());
+            }
+        }
+        request => panic!(
+            "expected a SYNC FIRST WINS request, but received {}",
+            request
+        ),
+    }
+}
+
+#[tokio::test]
+async fn request_sync_push_roundtrip() {
+    let (mut client, mut server) = connect_pair().await;
+    let object_ids = vec![[1u8; OBJECT_ID_BYTES], [2u8; OBJECT_ID_BYTES]];
+    let to: PeerAddr = "127.0.0.1:2022".parse().unwrap();
+    let request = Request::Sync(vec![PeerRequest::Push {
+        to: to.clone(),
+        objects: object_ids.clone(),
+    }]);
+    request.validate().unwrap();
+    request.write_into(&mut client).await.unwrap();
+
+    match Request::read_from(&mut server).await.unwrap().unwrap() {
+        Request::Sync(requests) => {
+            assert_eq!(1, requests.len());
+            assert!(requests[0].incoming_objects().is_empty());
+            assert_eq!(&object_ids[..], requests[0].objects());
+            assert_eq!(&to, requests[0].peer());
+        }
+        request => panic!("expected a SYNC request, but received {}", request),
+    }
+
+    // PUSH peer requests are not allowed in SYNC FIRST WINS requests
+    let requests = vec![PeerRequest::Push {
+        to,
+        objects: object_ids,
+    }];
+    assert!(matches!(
+        Request::SyncFirstWins(requests).validate(),
+        Err(errors::RequestError::UnexpectedPushRequest)
+    ));
+}
+
+#[test]
+fn request_display_truncates_object_ids() {
+    let object_ids = (0..65_536u32)
+        .map(|i| {
+            let mut oid = [0u8; OBJECT_ID_BYTES];
+            oid[..4].copy_from_slice(&i.to_be_bytes());
+            oid
+        })
+        .collect::<Vec<_>>();
+    let expected_ids = (0..5u32)
+        .map(|i| format!("\"{:08x}{}\"", i, "0".repeat(32)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let request = Request::Copy(object_ids.clone());
+    assert_eq!(
+        format!("COPY [{}, ... (65536 total)]", expected_ids),
+        request.to_string()
+    );
+
+    let request = Request::Sync(vec![PeerRequest::Take {
+        from: "127.0.0.1:2022".parse().unwrap(),
+        objects: object_ids,
+    }]);
+    assert_eq!(
+        format!(
+            "SYNC\nTAKE 127.0.0.1:2022 [{}, ... (65536 total)]",
+            expected_ids
+        ),
+        request.to_string()
+    );
+
+    // the verbose formatter lists all IDs
+    let verbose = request.verbose().to_string();
+    assert_eq!(65_536, verbose.matches('"').count() / 2);
+    assert!(!verbose.contains("total"));
+
+    // short lists are displayed in full
+    let request = Request::Delete(vec![[1u8; OBJECT_ID_BYTES]]);
+    assert_eq!(
+        format!("DELETE [\"{}\"]", "01".repeat(20)),
+        request.to_string()
+    );
+}
+
+#[tokio::test]
+async fn request_estimate_roundtrip() {
+    let (mut client, mut server) = connect_pair().await;
+    let object_ids = vec![[1u8; OBJECT_ID_BYTES], [2u8; OBJECT_ID_BYTES]];
+    let request = Request::Estimate(object_ids.clone());
+    assert!(request.validate().is_ok());
+    request.write_into(&mut client).await.unwrap();
+
+    match Request::read_from(&mut server).await.unwrap().unwrap() {
+        Request::Estimate(requested) => assert_eq!(object_ids, requested),
+        request => panic!("expected an ESTIMATE request, but received {}", request),
+    }
+}
+
+#[tokio::test]
+async fn request_prewarm_roundtrip() {
+    let (mut client, mut server) = connect_pair().await;
+    let peers: Vec<PeerAddr> = vec![
+        "127.0.0.1:2022".parse().unwrap(),
+        "plasma-2:2021".parse().unwrap(),
+        "unix:/tmp/plasma-stream.sock".parse().unwrap(),
+    ];
+    let request = Request::Prewarm(peers.clone());
+    request.validate().unwrap();
+    request.write_into(&mut client).await.unwrap();
+
+    match Request::read_from(&mut server).await.unwrap().unwrap() {
+        Request::Prewarm(requested) => assert_eq!(peers, requested),
+        request => panic!("expected a PREWARM request, but received {}", request),
+    }
+
+    // the peer list must not be empty
+    assert!(matches!(
+        Request::Prewarm(vec![]).validate(),
+        Err(errors::RequestError::PeerListTooShort)
+    ));
+}
+
+// TRANSFER ESTIMATE TESTS
+// ================================================================================================
+
+#[tokio::test]
+async fn transfer_estimate_roundtrip() {
+    let estimate = TransferEstimate {
+        num_objects: 3,
+        total_size: 5_000_000_000,
+        missing: vec![[1u8; OBJECT_ID_BYTES]],
+        oversized: vec![[2u8; OBJECT_ID_BYTES], [3u8; OBJECT_ID_BYTES]],
+    };
+    assert!(!estimate.is_transferable());
+
+    let mut buf = Vec::new();
+    estimate.write_into(&mut buf).await.unwrap();
+    let mut reader = &buf[..];
+    assert_eq!(
+        estimate,
+        TransferEstimate::read_from(&mut reader).await.unwrap()
+    );
+    assert!(reader.is_empty());
+}
+
+// UTILS TESTS
+// ================================================================================================
+
+#[test]
+fn bitmap_roundtrip() {
+    let flags = vec![
+        true, false, false, true, true, false, true, false, true, true,
+    ];
+    let bitmap = utils::to_bitmap(&flags);
+    assert_eq!(vec![0b0101_1001u8, 0b0000_0011], bitmap);
+    assert_eq!(flags, utils::from_bitmap(&bitmap, flags.len()));
+}
+
+#[tokio::test]
+async fn compressed_data_roundtrip() {
+    // the first chunk compresses well, the second one does not, and the last (partial) chunk
+    // compresses well again
+    let mut data = vec![0u8; 2 * DATA_CHUNK_SIZE + 1000];
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for byte in data[DATA_CHUNK_SIZE..2 * DATA_CHUNK_SIZE].iter_mut() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        *byte = state as u8;
+    }
+
+    let mut wire = Vec::new();
+    utils::write_data_compressed(&data, &mut wire)
+        .await
+        .unwrap();
+
+    // every chunk should be prefixed with its size on the wire; incompressible chunks should be
+    // sent as is
+    let first_size = u32::from_le_bytes(wire[..4].try_into().unwrap()) as usize;
+    assert!(first_size < DATA_CHUNK_SIZE / 10);
+    let second = &wire[4 + first_size..];
+    let second_size = u32::from_le_bytes(second[..4].try_into().unwrap()) as usize;
+    assert_eq!(DATA_CHUNK_SIZE, second_size);
+    assert_eq!(
+        &data[DATA_CHUNK_SIZE..2 * DATA_CHUNK_SIZE],
+        &second[4..4 + second_size]
+    );
+    let third = &second[4 + second_size..];
+    let third_size = u32::from_le_bytes(third[..4].try_into().unwrap()) as usize;
+    assert!(third_size < 1000);
+    assert_eq!(4 + third_size, third.len());
+
+    let mut reader = &wire[..];
+    let mut received = vec![0u8; data.len()];
+    utils::read_data_compressed(&mut reader, &mut received)
+        .await
+        .unwrap();
+    assert_eq!(data, received);
+    assert!(reader.is_empty());
+
+    // a chunk which claims to be larger on the wire than the chunk itself should be rejected
+    let mut wire = (DATA_CHUNK_SIZE as u32 + 1).to_le_bytes().to_vec();
+    wire.extend_from_slice(&[0u8; 16]);
+    let mut reader = &wire[..];
+    let result = utils::read_data_compressed(&mut reader, &mut received).await;
+    assert_eq!(ErrorKind::InvalidData, result.unwrap_err().kind());
+}
+
+#[tokio::test]
+async fn object_codecs_follow_hints() {
+    let compressible = vec![7u8; 100_000];
+    let mut incompressible = vec![0u8; 100_000];
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for byte in incompressible.iter_mut() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        *byte = state as u8;
+    }
+
+    // objects without a hint, or with a hint naming an unknown codec, are compressed with LZ4
+    let objects = [
+        (b"arrow".to_vec(), compressible.clone(), Codec::Lz4),
+        (
+            b"codec=none;arrow".to_vec(),
+            incompressible.clone(),
+            Codec::None,
+        ),
+        (b"codec=none;".to_vec(), compressible.clone(), Codec::None),
+        (b"codec=lz4;".to_vec(), incompressible.clone(), Codec::Lz4),
+        (b"codec=zstd;".to_vec(), compressible.clone(), Codec::Lz4),
+    ];
+    let capabilities = Capabilities::all();
+    let mut wire = Vec::new();
+    for (meta, data, _) in objects.iter() {
+        utils::write_object(meta, data, capabilities, &mut wire)
+            .await
+            .unwrap();
+    }
+
+    // every object should carry the codec chosen for it, and should be decoded accordingly
+    let mut reader = &wire[..];
+    for (meta, data, expected) in objects.iter() {
+        let (meta_size, data_size) = utils::read_object_header(&mut reader).await.unwrap();
+        let codec = Codec::read_from(&mut reader, capabilities).await.unwrap();
+        assert_eq!(*expected, codec);
+        let checksum = reader.read_u32_le().await.unwrap();
+
+        let mut received_meta = vec![0u8; meta_size];
+        reader.read_exact(&mut received_meta).await.unwrap();
+        let mut received_data = vec![0u8; data_size];
+        let wire_size = reader.len();
+        match codec {
+            Codec::Lz4 => utils::read_data_compressed(&mut reader, &mut received_data).await,
+            Codec::None => utils::read_data_chunked(&mut reader, &mut received_data).await,
+        }
+        .unwrap();
+        let wire_size = wire_size - reader.len();
+        assert_eq!(meta, &received_meta);
+        assert_eq!(data, &received_data);
+        assert_eq!(utils::object_checksum(meta, data), checksum);
+
+        // data which is not compressed should be sent as is
+        match codec {
+            Codec::Lz4 if data == &compressible => assert!(wire_size < data.len() / 10),
+            Codec::Lz4 => assert!(wire_size > data.len()),
+            Codec::None => assert_eq!(data.len(), wire_size),
+        }
+    }
+    assert!(reader.is_empty());
+
+    // without compression, hints are ignored and data of all objects is sent as is
+    let capabilities = Capabilities::from_bits(Capabilities::CODECS);
+    for (meta, data, _) in objects.iter() {
+        let mut wire = Vec::new();
+        utils::write_object(meta, data, capabilities, &mut wire)
+            .await
+            .unwrap();
+        assert_eq!(Codec::None as u8, wire[8]);
+        assert_eq!(9 + meta.len() + data.len(), wire.len());
+    }
+
+    // without codecs, data hinted not to be compressed should be framed as compressed data,
+    // with every chunk sent as is
+    let capabilities = Capabilities::from_bits(Capabilities::COMPRESSION);
+    let (meta, data, _) = &objects[2];
+    let mut wire = Vec::new();
+    utils::write_object(meta, data, capabilities, &mut wire)
+        .await
+        .unwrap();
+    assert_eq!(8 + meta.len() + 4 + data.len(), wire.len());
+    let mut reader = &wire[8 + meta.len()..];
+    assert_eq!(
+        Codec::Lz4,
+        Codec::read_from(&mut reader, capabilities).await.unwrap()
+    );
+    let mut received = vec![0u8; data.len()];
+    utils::read_data_compressed(&mut reader, &mut received)
+        .await
+        .unwrap();
+    assert_eq!(data, &received);
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Writes a peer request with the specified address into a socket, reads it back from the
+/// other end of the connection, and returns the address of the decoded request.
+async fn roundtrip_peer_addr(from: PeerAddr) -> PeerAddr {
+    let (mut client, mut server) = connect_pair().await;
+    let objects = vec![[1u8; OBJECT_ID_BYTES]];
+    let request = PeerRequest::Copy {
+        from,
+        objects: objects.clone(),
+    };
+    request.write_into(&mut client).await.unwrap();
+
+    match PeerRequest::read_from(&mut server).await.unwrap() {
+        PeerRequest::Copy {
+            from,
+            objects: decoded,
+        } => {
+            assert_eq!(objects, decoded);
+            from
+        }
+        request => panic!("expected a COPY peer request, but received {}", request),
+    }
+}
+
+/// Returns a pair of connected sockets; the first socket is the client side of the connection.
+async fn connect_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (client, server)
+}
@@ -3,10 +3,263 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
+use crate::{handshake::Capabilities, Codec, ObjectId, DATA_CHUNK_SIZE, OBJECT_ID_BYTES};
+use std::io::{Error, ErrorKind};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
 /// Converts a list of 20-byte arrays into plasma store object IDs.
 pub fn map_object_ids(object_ids: &[crate::ObjectId]) -> Vec<plasma_store::ObjectId> {
     object_ids
         .iter()
-        .map(|oid| plasma_store::ObjectId::new(*oid))
+        .map(|oid| plasma_store::ObjectId::from(*oid))
+        .collect()
+}
+
+/// Reads an object header from the specified socket and returns sizes of object metadata and
+/// data (in that order). The object header consists of a 16-bit value describing the size of
+/// the metadata, and a 48-bit value describing the size of the data.
+pub async fn read_object_header<R: AsyncRead + Unpin>(
+    socket: &mut R,
+) -> std::io::Result<(usize, usize)> {
+    let header = socket.read_u64_le().await?;
+    let meta_size = (header as u16) as usize;
+    let data_size = (header >> 16) as usize;
+    Ok((meta_size, data_size))
+}
+
+/// Writes an object into the specified socket; the object is written as follows:
+/// * first object header (data and meta size) is written as u64,
+/// * if codecs are enabled, the codec of object data is written as u8,
+/// * if checksums are enabled, CRC32 checksum of metadata and data is written as u32,
+/// * then, object metadata is written,
+/// * and finally, object data is written in chunks of at most DATA_CHUNK_SIZE bytes.
+///
+/// When compression is enabled, every chunk of data is compressed separately and is preceded
+/// by its size as sent over the wire (see `write_data_compressed()`); so, neither side holds
+/// more than a single compressed chunk at a time. Data of objects whose metadata carries a
+/// codec hint is encoded as hinted (see `Codec::select()`); if codecs are not enabled, data of
+/// objects hinted not to be compressed is still framed as compressed data, but every chunk is
+/// sent as is. Metadata and data sizes are expected to be within protocol limits.
+pub async fn write_object<W: AsyncWrite + Unpin>(
+    meta: &[u8],
+    data: &[u8],
+    capabilities: Capabilities,
+    socket: &mut W,
+) -> std::io::Result<()> {
+    // the object header consists of a 16-bit value describing the size of the metadata, and
+    // a 48-bit value describing the size of the (uncompressed) data
+    let header = meta.len() as u64 | ((data.len() as u64) << 16);
+    socket.write_u64_le(header).await?;
+
+    let codec = Codec::select(meta, capabilities);
+    if capabilities.codecs() {
+        socket.write_u8(codec as u8).await?;
+    }
+
+    if capabilities.checksums() {
+        socket.write_u32_le(object_checksum(meta, data)).await?;
+    }
+
+    socket.write_all(meta).await?;
+    match codec {
+        Codec::Lz4 => write_data_compressed(data, socket).await,
+        Codec::None if capabilities.compression() && !capabilities.codecs() => {
+            write_data_uncompressed(data, socket).await
+        }
+        Codec::None => write_data_chunked(data, socket).await,
+    }
+}
+
+/// Writes object data into the specified socket in chunks of at most DATA_CHUNK_SIZE bytes,
+/// compressing every chunk separately with LZ4. Every chunk is preceded by its size as sent
+/// over the wire (as u32); chunks which do not compress well are sent as is, which is indicated
+/// by the size on the wire being equal to the size of the chunk.
+///
+/// Chunks are compressed one at a time right before they are written, so only a single
+/// compressed chunk is held in memory, and the transfer can be cancelled between chunks.
+pub async fn write_data_compressed<W: AsyncWrite + Unpin>(
+    data: &[u8],
+    socket: &mut W,
+) -> std::io::Result<()> {
+    let max_chunk_size = data.len().min(DATA_CHUNK_SIZE);
+    let mut compressed = vec![0u8; lz4_flex::block::get_maximum_output_size(max_chunk_size)];
+    for chunk in data.chunks(DATA_CHUNK_SIZE) {
+        match compress_chunk(chunk, &mut compressed) {
+            Some(size) => {
+                socket.write_u32_le(size as u32).await?;
+                socket.write_all(&compressed[..size]).await?;
+            }
+            None => {
+                socket.write_u32_le(chunk.len() as u32).await?;
+                socket.write_all(chunk).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes object data into the specified socket framed in the same way as by
+/// `write_data_compressed()`, but with every chunk sent as is.
+async fn write_data_uncompressed<W: AsyncWrite + Unpin>(
+    data: &[u8],
+    socket: &mut W,
+) -> std::io::Result<()> {
+    for chunk in data.chunks(DATA_CHUNK_SIZE) {
+        socket.write_u32_le(chunk.len() as u32).await?;
+        socket.write_all(chunk).await?;
+    }
+    Ok(())
+}
+
+/// Fills the buffer with object data written via `write_data_compressed()`; every chunk is
+/// decompressed directly into the buffer at its offset.
+pub async fn read_data_compressed<R: AsyncRead + Unpin>(
+    socket: &mut R,
+    buf: &mut [u8],
+) -> std::io::Result<()> {
+    let mut scratch = Vec::new();
+    for chunk in buf.chunks_mut(DATA_CHUNK_SIZE) {
+        read_compressed_chunk(socket, chunk, &mut scratch).await?;
+    }
+    Ok(())
+}
+
+/// Reads a single chunk of object data written via `write_data_compressed()` into the specified
+/// buffer, which must be exactly the size of the chunk. Compressed chunks are read into
+/// `scratch` first, and then decompressed into the buffer.
+pub async fn read_compressed_chunk<R: AsyncRead + Unpin>(
+    socket: &mut R,
+    chunk: &mut [u8],
+    scratch: &mut Vec<u8>,
+) -> std::io::Result<()> {
+    // compressed chunks are never larger than the original chunks
+    let wire_size = socket.read_u32_le().await? as usize;
+    if wire_size > chunk.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "compressed chunk of object data is {} bytes, but the chunk is {} bytes",
+                wire_size,
+                chunk.len()
+            ),
+        ));
+    }
+    if wire_size == chunk.len() {
+        socket.read_exact(chunk).await?;
+        return Ok(());
+    }
+    scratch.resize(wire_size, 0);
+    socket.read_exact(scratch).await?;
+    decompress_data(scratch, chunk)
+}
+
+/// Compresses a chunk of object data with LZ4 into the specified buffer, which must be large
+/// enough for the worst case; returns the size of the compressed chunk, or `None` if the
+/// compressed chunk would not be smaller than the original chunk.
+fn compress_chunk(chunk: &[u8], buf: &mut [u8]) -> Option<usize> {
+    match lz4_flex::block::compress_into(chunk, buf) {
+        Ok(size) if size < chunk.len() => Some(size),
+        _ => None,
+    }
+}
+
+/// Decompresses a chunk compressed via `write_data_compressed()` into the specified buffer;
+/// returns an error if the data is malformed or does not decompress into exactly `buf.len()`
+/// bytes.
+pub fn decompress_data(compressed: &[u8], buf: &mut [u8]) -> std::io::Result<()> {
+    match lz4_flex::block::decompress_into(compressed, buf) {
+        Ok(size) if size == buf.len() => Ok(()),
+        Ok(size) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "decompressed object data is {} bytes, but {} bytes were expected",
+                size,
+                buf.len()
+            ),
+        )),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, err)),
+    }
+}
+
+/// Computes a CRC32 checksum of object metadata followed by object data; when checksums are
+/// negotiated for a connection, this checksum is sent right after the object header.
+pub fn object_checksum(meta: &[u8], data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(meta);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Writes object data into the specified socket in chunks of at most DATA_CHUNK_SIZE bytes;
+/// this bounds the amount of data handed to the socket at once and provides a cancellation
+/// point between chunks.
+pub async fn write_data_chunked<W: AsyncWrite + Unpin>(
+    data: &[u8],
+    socket: &mut W,
+) -> std::io::Result<()> {
+    for chunk in data.chunks(DATA_CHUNK_SIZE) {
+        socket.write_all(chunk).await?;
+    }
+    Ok(())
+}
+
+/// Fills the buffer with object data read from the specified socket in chunks of at most
+/// DATA_CHUNK_SIZE bytes; each chunk is written directly into the buffer at its offset.
+pub async fn read_data_chunked<R: AsyncRead + Unpin>(
+    socket: &mut R,
+    buf: &mut [u8],
+) -> std::io::Result<()> {
+    for chunk in buf.chunks_mut(DATA_CHUNK_SIZE) {
+        socket.read_exact(chunk).await?;
+    }
+    Ok(())
+}
+
+/// Writes a list of object IDs into the specified socket. Number of object IDs is written into
+/// the socket first (as u32), followed by the actual object IDs. This is the same layout as for
+/// object ID lists in requests, but unlike those, which are capped at `MAX_OBJECT_ID_LIST_LEN`
+/// (2^20) entries, lists written this way are not capped; this is intended for responses such
+/// as the list of objects held by a server.
+pub async fn write_object_ids<W: AsyncWrite + Unpin>(
+    object_ids: &[ObjectId],
+    socket: &mut W,
+) -> std::io::Result<()> {
+    socket.write_u32_le(object_ids.len() as u32).await?;
+    for oid in object_ids.iter() {
+        socket.write_all(oid).await?;
+    }
+    Ok(())
+}
+
+/// Reads a list of object IDs written into the socket via `write_object_ids()`.
+pub async fn read_object_ids<R: AsyncRead + Unpin>(
+    socket: &mut R,
+) -> std::io::Result<Vec<ObjectId>> {
+    let num_ids = socket.read_u32_le().await? as usize;
+    let mut object_ids = Vec::new();
+    for _ in 0..num_ids {
+        let mut oid = [0u8; OBJECT_ID_BYTES];
+        socket.read_exact(&mut oid).await?;
+        object_ids.push(oid);
+    }
+    Ok(object_ids)
+}
+
+/// Packs a list of flags into a bitmap; the flag at index `i` is stored in bit `i % 8` of byte
+/// `i / 8` of the bitmap.
+pub fn to_bitmap(flags: &[bool]) -> Vec<u8> {
+    let mut bitmap = vec![0u8; flags.len().div_ceil(8)];
+    for (i, &flag) in flags.iter().enumerate() {
+        if flag {
+            bitmap[i / 8] |= 1 << (i % 8);
+        }
+    }
+    bitmap
+}
+
+/// Unpacks the first `num_flags` flags from a bitmap created via `to_bitmap()`.
+pub fn from_bitmap(bitmap: &[u8], num_flags: usize) -> Vec<bool> {
+    (0..num_flags)
+        .map(|i| bitmap[i / 8] & (1 << (i % 8)) != 0)
         .collect()
 }